@@ -0,0 +1,862 @@
+// Keyframe animation of transforms and colors.
+//
+// A Track holds keys for one value, sorted by time, and samples them at any
+// time with step, linear or cubic Hermite interpolation. Linear interpolation
+// of rotations is a slerp along the shorter arc, and cubic rotations are
+// normalized, as glTF defines. Outside its keys a track loops, ping-pongs or
+// clamps. An AnimationClip groups the tracks that animate one object.
+//
+// Clips are loaded from a small text format, one directive per line and '#'
+// starting a comment:
+//
+//   track <target> <interpolation> <wrap>
+//   key <time> <value> [<in tangent> <out tangent>]
+//
+// Targets are translation, rotation, scale and color, interpolations step,
+// linear, slerp and cubic, and wraps loop, ping-pong and clamp. Values have 3
+// numbers for translations and scales, 4 for rotations (a quaternion as
+// x y z w) and 4 for colors (r g b a). Cubic keys also give their incoming and
+// outgoing tangents, per second. 'slerp' is only accepted for rotations, where
+// it is the same as 'linear'.
+use crate::transform::Transform;
+use cgmath::*;
+use std::{
+	error::Error,
+	fmt,
+	fs,
+	io,
+	ops::Mul,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Interpolation
+{
+	// Holds each key's value until the next key.
+	Step,
+	Linear,
+	// Hermite spline through the values with each key's tangents.
+	CubicHermite,
+}
+
+// What a track does before its first and after its last key.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Wrap
+{
+	Loop,
+	// Plays forwards, then backwards.
+	PingPong,
+	Clamp,
+}
+
+impl Wrap
+{
+	// Maps any time into `start..=end`.
+	pub fn apply(self, time : f32, start : f32, end : f32) -> f32
+	{
+		let duration = end - start;
+		if duration <= 0.0
+		{
+			return start;
+		}
+
+		match self
+		{
+			Wrap::Loop => start + (time - start).rem_euclid(duration),
+			Wrap::PingPong =>
+			{
+				let phase = (time - start).rem_euclid(2.0 * duration);
+				start + duration - (phase - duration).abs()
+			}
+			Wrap::Clamp => time.max(start).min(end),
+		}
+	}
+}
+
+// Values that tracks can interpolate.
+pub trait Animatable: Copy + Zero + Mul<f32, Output = Self>
+{
+	fn lerp(a : Self, b : Self, s : f32) -> Self;
+
+	// The Hermite spline from `p0` to `p1`, with tangents scaled to the length
+	// of the segment.
+	fn hermite(p0 : Self, m0 : Self, p1 : Self, m1 : Self, s : f32) -> Self;
+}
+
+fn hermite_vector<V : VectorSpace<Scalar = f32>>(p0 : V, m0 : V, p1 : V, m1 : V, s : f32) -> V
+{
+	let (s2, s3) = (s * s, s * s * s);
+	p0 * (2.0 * s3 - 3.0 * s2 + 1.0) + m0 * (s3 - 2.0 * s2 + s) + p1 * (-2.0 * s3 + 3.0 * s2) + m1 * (s3 - s2)
+}
+
+impl Animatable for Vector3<f32>
+{
+	fn lerp(a : Self, b : Self, s : f32) -> Self
+	{
+		a.lerp(b, s)
+	}
+
+	fn hermite(p0 : Self, m0 : Self, p1 : Self, m1 : Self, s : f32) -> Self
+	{
+		hermite_vector(p0, m0, p1, m1, s)
+	}
+}
+
+impl Animatable for Vector4<f32>
+{
+	fn lerp(a : Self, b : Self, s : f32) -> Self
+	{
+		a.lerp(b, s)
+	}
+
+	fn hermite(p0 : Self, m0 : Self, p1 : Self, m1 : Self, s : f32) -> Self
+	{
+		hermite_vector(p0, m0, p1, m1, s)
+	}
+}
+
+impl Animatable for Quaternion<f32>
+{
+	fn lerp(a : Self, b : Self, s : f32) -> Self
+	{
+		// q and -q are the same rotation, take the one closer to a.
+		let sign = a.dot(b).signum();
+		a.slerp(b * sign, s).normalize()
+	}
+
+	fn hermite(p0 : Self, m0 : Self, p1 : Self, m1 : Self, s : f32) -> Self
+	{
+		hermite_vector(p0, m0, p1, m1, s).normalize()
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Keyframe<T>
+{
+	pub time :        f32,
+	pub value :       T,
+	// Only used by cubic interpolation, in value units per second.
+	pub in_tangent :  T,
+	pub out_tangent : T,
+}
+
+impl<T : Animatable> Keyframe<T>
+{
+	pub fn new(time : f32, value : T) -> Self
+	{
+		Keyframe {
+			time :        time,
+			value :       value,
+			in_tangent :  T::zero(),
+			out_tangent : T::zero(),
+		}
+	}
+
+	pub fn cubic(time : f32, value : T, in_tangent : T, out_tangent : T) -> Self
+	{
+		Keyframe {
+			time :        time,
+			value :       value,
+			in_tangent :  in_tangent,
+			out_tangent : out_tangent,
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TrackError
+{
+	NoKeys,
+	// The key's time is not finite or not after the previous key's.
+	TimeNotIncreasing
+	{
+		key : usize,
+	},
+}
+
+impl fmt::Display for TrackError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			TrackError::NoKeys => write!(f, "track has no keys"),
+			TrackError::TimeNotIncreasing {
+				key,
+			} => write!(f, "key {} is not after the previous key", key),
+		}
+	}
+}
+
+impl Error for TrackError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T>
+{
+	interpolation : Interpolation,
+	wrap :          Wrap,
+	keys :          Vec<Keyframe<T>>,
+}
+
+#[allow(dead_code)]
+impl<T : Animatable> Track<T>
+{
+	pub fn new(interpolation : Interpolation, wrap : Wrap, keys : Vec<Keyframe<T>>) -> Result<Self, TrackError>
+	{
+		if keys.is_empty()
+		{
+			return Err(TrackError::NoKeys);
+		}
+		for (n, key) in keys.iter().enumerate()
+		{
+			if !key.time.is_finite() || (n > 0 && key.time <= keys[n - 1].time)
+			{
+				return Err(TrackError::TimeNotIncreasing {
+					key : n,
+				});
+			}
+		}
+
+		Ok(Track {
+			interpolation : interpolation,
+			wrap :          wrap,
+			keys :          keys,
+		})
+	}
+
+	pub fn interpolation(&self) -> Interpolation
+	{
+		self.interpolation
+	}
+
+	pub fn wrap(&self) -> Wrap
+	{
+		self.wrap
+	}
+
+	pub fn keys(&self) -> &[Keyframe<T>]
+	{
+		&self.keys
+	}
+
+	pub fn start(&self) -> f32
+	{
+		self.keys[0].time
+	}
+
+	pub fn end(&self) -> f32
+	{
+		self.keys[self.keys.len() - 1].time
+	}
+
+	pub fn sample(&self, time : f32) -> T
+	{
+		let time = self.wrap.apply(time, self.start(), self.end());
+
+		// The first key after `time`; the wrapped time is never before the first.
+		let next = self.keys.iter().position(|key| key.time > time).unwrap_or(self.keys.len());
+		if next == 0 || next == self.keys.len()
+		{
+			return self.keys[next.max(1) - 1].value;
+		}
+
+		let (a, b) = (&self.keys[next - 1], &self.keys[next]);
+		let length = b.time - a.time;
+		let s = (time - a.time) / length;
+		match self.interpolation
+		{
+			Interpolation::Step => a.value,
+			Interpolation::Linear => T::lerp(a.value, b.value, s),
+			Interpolation::CubicHermite =>
+			{
+				T::hermite(a.value, a.out_tangent * length, b.value, b.in_tangent * length, s)
+			}
+		}
+	}
+}
+
+// Tracks animating one object. Parts without a track keep their base values.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AnimationClip
+{
+	pub translation : Option<Track<Vector3<f32>>>,
+	pub rotation :    Option<Track<Quaternion<f32>>>,
+	pub scale :       Option<Track<Vector3<f32>>>,
+	pub color :       Option<Track<Vector4<f32>>>,
+}
+
+#[allow(dead_code)]
+impl AnimationClip
+{
+	pub fn new() -> Self
+	{
+		Self::default()
+	}
+
+	// A constant spin about `axis`, looping every full turn.
+	pub fn spin(axis : Vector3<f32>, degrees_per_second : f32) -> Self
+	{
+		// Thirds of a turn, since slerp takes the shorter arc between keys.
+		let period = 360.0 / degrees_per_second.abs();
+		let keys = (0..4)
+			.map(|n| {
+				let angle = Deg(120.0 * n as f32 * degrees_per_second.signum());
+				Keyframe::new(period * n as f32 / 3.0, Quaternion::from_axis_angle(axis.normalize(), angle))
+			})
+			.collect();
+
+		AnimationClip {
+			rotation : Some(Track::new(Interpolation::Linear, Wrap::Loop, keys).unwrap()),
+			..Self::default()
+		}
+	}
+
+	// The time of the last key of the longest track.
+	pub fn duration(&self) -> f32
+	{
+		[
+			self.translation.as_ref().map(Track::end),
+			self.rotation.as_ref().map(Track::end),
+			self.scale.as_ref().map(Track::end),
+			self.color.as_ref().map(Track::end),
+		]
+		.iter()
+		.filter_map(|&end| end)
+		.fold(0.0, f32::max)
+	}
+
+	pub fn sample_transform(&self, time : f32, base : &Transform) -> Transform
+	{
+		Transform {
+			translation : self.translation.as_ref().map_or(base.translation, |track| track.sample(time)),
+			rotation :    self.rotation.as_ref().map_or(base.rotation, |track| track.sample(time)),
+			scale :       self.scale.as_ref().map_or(base.scale, |track| track.sample(time)),
+		}
+	}
+
+	pub fn sample_color(&self, time : f32) -> Option<Vector4<f32>>
+	{
+		self.color.as_ref().map(|track| track.sample(time))
+	}
+}
+
+#[derive(Debug)]
+pub enum AnimationError
+{
+	Io
+	{
+		path :  PathBuf,
+		error : io::Error,
+	},
+	Parse
+	{
+		file :    String,
+		line :    usize,
+		details : String,
+	},
+}
+
+impl fmt::Display for AnimationError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			AnimationError::Io {
+				path,
+				error,
+			} => write!(f, "{}: {}", path.display(), error),
+			AnimationError::Parse {
+				file,
+				line,
+				details,
+			} => write!(f, "{}:{}: {}", file, line, details),
+		}
+	}
+}
+
+impl Error for AnimationError {}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum TrackTarget
+{
+	Translation,
+	Rotation,
+	Scale,
+	Color,
+}
+
+// A track being parsed, with the line of each key for error messages.
+struct PendingTrack
+{
+	target :        TrackTarget,
+	interpolation : Interpolation,
+	wrap :          Wrap,
+	line :          usize,
+	keys :          Vec<(usize, f32, Vec<f32>)>,
+}
+
+fn build_track<T : Animatable>(
+	file : &str, pending : &PendingTrack, value : impl Fn(&[f32]) -> T,
+) -> Result<Track<T>, AnimationError>
+{
+	let width = pending.keys.first().map_or(0, |(_, _, values)| values.len());
+	let width = if pending.interpolation == Interpolation::CubicHermite
+	{
+		width / 3
+	}
+	else
+	{
+		width
+	};
+	let keys = pending
+		.keys
+		.iter()
+		.map(|(_, time, values)| match pending.interpolation
+		{
+			Interpolation::CubicHermite => Keyframe::cubic(
+				*time,
+				value(&values[..width]),
+				value(&values[width..2 * width]),
+				value(&values[2 * width..]),
+			),
+			_ => Keyframe::new(*time, value(values)),
+		})
+		.collect();
+
+	Track::new(pending.interpolation, pending.wrap, keys).map_err(|error| {
+		let line = match error
+		{
+			TrackError::NoKeys => pending.line,
+			TrackError::TimeNotIncreasing {
+				key,
+			} => pending.keys[key].0,
+		};
+		AnimationError::Parse {
+			file :    file.to_string(),
+			line :    line,
+			details : error.to_string(),
+		}
+	})
+}
+
+fn finish_track(file : &str, pending : PendingTrack, clip : &mut AnimationClip) -> Result<(), AnimationError>
+{
+	let vector3 = |v : &[f32]| Vector3::new(v[0], v[1], v[2]);
+	let vector4 = |v : &[f32]| Vector4::new(v[0], v[1], v[2], v[3]);
+	match pending.target
+	{
+		TrackTarget::Translation => clip.translation = Some(build_track(file, &pending, vector3)?),
+		TrackTarget::Rotation =>
+		{
+			clip.rotation = Some(build_track(file, &pending, |v| Quaternion::new(v[3], v[0], v[1], v[2]))?)
+		}
+		TrackTarget::Scale => clip.scale = Some(build_track(file, &pending, vector3)?),
+		TrackTarget::Color => clip.color = Some(build_track(file, &pending, vector4)?),
+	}
+	Ok(())
+}
+
+// Parses the contents of an animation file. `file` is only used in error
+// messages.
+pub fn parse_animation(file : &str, source : &str) -> Result<AnimationClip, AnimationError>
+{
+	let mut clip = AnimationClip::new();
+	let mut seen : Vec<TrackTarget> = Vec::new();
+	let mut pending : Option<PendingTrack> = None;
+
+	for (n, text) in source.lines().enumerate()
+	{
+		let line = n + 1;
+		let error = |details : String| AnimationError::Parse {
+			file :    file.to_string(),
+			line :    line,
+			details : details,
+		};
+
+		let text = text.split('#').next().unwrap_or("");
+		let tokens : Vec<&str> = text.split_whitespace().collect();
+		match tokens.first()
+		{
+			None => (),
+			Some(&"track") =>
+			{
+				if tokens.len() != 4
+				{
+					return Err(error(format!("'track' expects 3 words, found {}", tokens.len() - 1)));
+				}
+				let target = match tokens[1]
+				{
+					"translation" => TrackTarget::Translation,
+					"rotation" => TrackTarget::Rotation,
+					"scale" => TrackTarget::Scale,
+					"color" => TrackTarget::Color,
+					other => return Err(error(format!("unknown track '{}'", other))),
+				};
+				let interpolation = match tokens[2]
+				{
+					"step" => Interpolation::Step,
+					"linear" => Interpolation::Linear,
+					"slerp" if target == TrackTarget::Rotation => Interpolation::Linear,
+					"slerp" => return Err(error(format!("'slerp' only applies to rotations, not {}", tokens[1]))),
+					"cubic" => Interpolation::CubicHermite,
+					other => return Err(error(format!("unknown interpolation '{}'", other))),
+				};
+				let wrap = match tokens[3]
+				{
+					"loop" => Wrap::Loop,
+					"ping-pong" => Wrap::PingPong,
+					"clamp" => Wrap::Clamp,
+					other => return Err(error(format!("unknown wrap mode '{}'", other))),
+				};
+				if seen.contains(&target)
+				{
+					return Err(error(format!("more than one {} track", tokens[1])));
+				}
+				seen.push(target);
+
+				if let Some(previous) = pending.take()
+				{
+					finish_track(file, previous, &mut clip)?;
+				}
+				pending = Some(PendingTrack {
+					target :        target,
+					interpolation : interpolation,
+					wrap :          wrap,
+					line :          line,
+					keys :          Vec::new(),
+				});
+			}
+			Some(&"key") =>
+			{
+				let track = match pending.as_mut()
+				{
+					Some(track) => track,
+					None => return Err(error("'key' appears before any 'track'".to_string())),
+				};
+
+				let mut numbers = Vec::with_capacity(tokens.len() - 1);
+				for token in &tokens[1..]
+				{
+					match token.parse::<f32>()
+					{
+						Ok(value) if value.is_finite() => numbers.push(value),
+						_ => return Err(error(format!("'key' has an invalid number '{}'", token))),
+					}
+				}
+
+				let width = match track.target
+				{
+					TrackTarget::Translation | TrackTarget::Scale => 3,
+					TrackTarget::Rotation | TrackTarget::Color => 4,
+				};
+				let expected = 1 + if track.interpolation == Interpolation::CubicHermite
+				{
+					3 * width
+				}
+				else
+				{
+					width
+				};
+				if numbers.len() != expected
+				{
+					return Err(error(format!("'key' expects {} numbers, found {}", expected, numbers.len())));
+				}
+				track.keys.push((line, numbers[0], numbers[1..].to_vec()));
+			}
+			Some(other) => return Err(error(format!("unknown directive '{}'", other))),
+		}
+	}
+
+	if let Some(last) = pending.take()
+	{
+		finish_track(file, last, &mut clip)?;
+	}
+	Ok(clip)
+}
+
+// Loads an animation file from disk.
+#[allow(dead_code)]
+pub fn load_animation(path : &Path) -> Result<AnimationClip, AnimationError>
+{
+	let source = fs::read_to_string(path).map_err(|error| AnimationError::Io {
+		path :  path.to_path_buf(),
+		error : error,
+	})?;
+	parse_animation(&path.display().to_string(), &source)
+}
+
+#[cfg(test)]
+mod animation_tests
+{
+	use crate::{
+		animation::{
+			parse_animation,
+			AnimationClip,
+			AnimationError,
+			Interpolation,
+			Keyframe,
+			Track,
+			TrackError,
+			Wrap,
+		},
+		transform::Transform,
+	};
+	use cgmath::*;
+
+	const EPSILON : f32 = 1.0e-5;
+
+	fn assert_near(expected : Vector3<f32>, actual : Vector3<f32>)
+	{
+		assert!((expected - actual).magnitude() < EPSILON, "expected {:?}, got {:?}", expected, actual);
+	}
+
+	fn assert_rotation_near(expected : Quaternion<f32>, actual : Quaternion<f32>)
+	{
+		assert!(expected.dot(actual).abs() > 1.0 - EPSILON, "expected {:?}, got {:?}", expected, actual);
+	}
+
+	// From 0 at t = 1 to 10 at t = 2 to 0 at t = 4, along x.
+	fn track(interpolation : Interpolation, wrap : Wrap) -> Track<Vector3<f32>>
+	{
+		let keys = vec![
+			Keyframe::new(1.0, Vector3::new(0.0, 0.0, 0.0)),
+			Keyframe::new(2.0, Vector3::new(10.0, 0.0, 0.0)),
+			Keyframe::new(4.0, Vector3::new(0.0, 0.0, 0.0)),
+		];
+		Track::new(interpolation, wrap, keys).unwrap()
+	}
+
+	fn x(track : &Track<Vector3<f32>>, time : f32) -> f32
+	{
+		track.sample(time).x
+	}
+
+	#[test]
+	fn test_interpolation()
+	{
+		let step = track(Interpolation::Step, Wrap::Clamp);
+		assert_eq!(0.0, x(&step, 1.5));
+		assert_eq!(10.0, x(&step, 2.0));
+		assert_eq!(10.0, x(&step, 3.9));
+
+		let linear = track(Interpolation::Linear, Wrap::Clamp);
+		assert!((x(&linear, 1.5) - 5.0).abs() < EPSILON);
+		assert!((x(&linear, 3.0) - 5.0).abs() < EPSILON);
+		assert_eq!(10.0, x(&linear, 2.0));
+
+		// With zero tangents the spline eases in and out of every key.
+		let cubic = track(Interpolation::CubicHermite, Wrap::Clamp);
+		assert!((x(&cubic, 1.5) - 5.0).abs() < EPSILON);
+		assert!((x(&cubic, 1.25) - 10.0 * 0.15625).abs() < EPSILON);
+		assert_eq!(10.0, x(&cubic, 2.0));
+	}
+
+	#[test]
+	fn test_cubic_tangents()
+	{
+		// Tangents matching a straight line reproduce it exactly.
+		let velocity = Vector3::new(2.0, -1.0, 0.5);
+		let keys = vec![
+			Keyframe::cubic(0.0, Vector3::zero(), velocity, velocity),
+			Keyframe::cubic(0.5, velocity * 0.5, velocity, velocity),
+			Keyframe::cubic(2.0, velocity * 2.0, velocity, velocity),
+		];
+		let track = Track::new(Interpolation::CubicHermite, Wrap::Clamp, keys).unwrap();
+		for &time in &[0.1, 0.25, 0.7, 1.3, 1.99]
+		{
+			assert_near(velocity * time, track.sample(time));
+		}
+	}
+
+	#[test]
+	fn test_wrap_modes()
+	{
+		let clamp = track(Interpolation::Linear, Wrap::Clamp);
+		assert_eq!(0.0, x(&clamp, -5.0));
+		assert_eq!(0.0, x(&clamp, 100.0));
+
+		// One cycle is 3 seconds long, starting at t = 1.
+		let looped = track(Interpolation::Linear, Wrap::Loop);
+		assert!((x(&looped, 1.5 + 3.0) - 5.0).abs() < EPSILON);
+		assert!((x(&looped, 1.5 - 6.0) - 5.0).abs() < EPSILON);
+		assert!((x(&looped, 3.0 + 30.0) - 5.0).abs() < EPSILON);
+
+		// Ping-pong mirrors every other cycle.
+		let ping_pong = track(Interpolation::Linear, Wrap::PingPong);
+		assert!((x(&ping_pong, 4.5) - 2.5).abs() < EPSILON);
+		assert!((x(&ping_pong, 6.0) - 10.0).abs() < EPSILON);
+		assert!((x(&ping_pong, 7.5) - 5.0).abs() < EPSILON);
+		assert!((x(&ping_pong, 0.5) - 5.0).abs() < EPSILON);
+
+		// A single key is constant.
+		let single =
+			Track::new(Interpolation::Linear, Wrap::Loop, vec![Keyframe::new(1.0, Vector3::unit_y())]).unwrap();
+		assert_near(Vector3::unit_y(), single.sample(-3.0));
+	}
+
+	#[test]
+	fn test_slerp()
+	{
+		let keys = vec![
+			Keyframe::new(0.0, Quaternion::one()),
+			Keyframe::new(1.0, Quaternion::from_angle_y(Deg(90.0))),
+			// The same rotation as 180 degrees, from the other side.
+			Keyframe::new(2.0, -Quaternion::from_angle_y(Deg(180.0))),
+		];
+		let track = Track::new(Interpolation::Linear, Wrap::Clamp, keys).unwrap();
+		assert_rotation_near(Quaternion::from_angle_y(Deg(30.0)), track.sample(1.0 / 3.0));
+		assert_rotation_near(Quaternion::from_angle_y(Deg(135.0)), track.sample(1.5));
+
+		let rotation = track.sample(0.75);
+		assert!((rotation.magnitude() - 1.0).abs() < EPSILON);
+	}
+
+	#[test]
+	fn test_invalid_tracks()
+	{
+		let empty : Vec<Keyframe<Vector3<f32>>> = Vec::new();
+		assert_eq!(Err(TrackError::NoKeys), Track::new(Interpolation::Linear, Wrap::Loop, empty));
+
+		let keys = vec![Keyframe::new(0.0, Vector3::zero()), Keyframe::new(0.0, Vector3::unit_x())];
+		assert_eq!(
+			Err(TrackError::TimeNotIncreasing {
+				key : 1,
+			}),
+			Track::new(Interpolation::Linear, Wrap::Loop, keys)
+		);
+	}
+
+	#[test]
+	fn test_spin()
+	{
+		let spin = AnimationClip::spin(Vector3::unit_y(), 90.0);
+		assert_eq!(4.0, spin.duration());
+		let base = Transform::from_translation(Vector3::new(0.0, 1.0, 0.0));
+		for &time in &[0.0, 0.3, 1.0, 2.5, 3.9, 4.1, 9.0]
+		{
+			let transform = spin.sample_transform(time, &base);
+			assert_near(base.translation, transform.translation);
+			assert_rotation_near(Quaternion::from_angle_y(Deg(90.0 * time)), transform.rotation);
+		}
+
+		let backwards = AnimationClip::spin(Vector3::unit_x(), -45.0);
+		assert_rotation_near(Quaternion::from_angle_x(Deg(-45.0)), backwards.sample_transform(1.0, &base).rotation);
+	}
+
+	#[test]
+	fn test_parse_animation()
+	{
+		let source = "# bob and pulse\n\
+		              track translation linear ping-pong\n\
+		              key 0  0 0 0\n\
+		              key 1  0 2 0   # top\n\
+		              \n\
+		              track rotation slerp loop\n\
+		              key 0  0 0 0 1\n\
+		              key 2  0 1 0 0\n\
+		              track scale cubic clamp\n\
+		              key 0  1 1 1  0 0 0  1 1 1\n\
+		              key 1  2 2 2  1 1 1  0 0 0\n\
+		              track color step clamp\n\
+		              key 0  1 0 0 1\n\
+		              key 0.5  0 0 1 1\n";
+		let clip = parse_animation("test.anim", source).unwrap();
+
+		let translation = clip.translation.as_ref().unwrap();
+		assert_eq!(Wrap::PingPong, translation.wrap());
+		assert_near(Vector3::new(0.0, 1.0, 0.0), translation.sample(1.5));
+
+		let rotation = clip.rotation.as_ref().unwrap();
+		assert_eq!(Interpolation::Linear, rotation.interpolation());
+		assert_rotation_near(Quaternion::from_angle_y(Deg(90.0)), rotation.sample(1.0));
+
+		let scale = clip.scale.as_ref().unwrap();
+		assert_eq!(Interpolation::CubicHermite, scale.interpolation());
+		assert_eq!(Vector3::new(1.0, 1.0, 1.0), scale.keys()[1].in_tangent);
+		assert_near(Vector3::new(2.0, 2.0, 2.0), scale.sample(5.0));
+
+		assert_eq!(Some(Vector4::new(0.0, 0.0, 1.0, 1.0)), clip.sample_color(0.75));
+		assert_eq!(2.0, clip.duration());
+	}
+
+	fn parse_error(source : &str) -> (usize, String)
+	{
+		match parse_animation("test.anim", source)
+		{
+			Err(AnimationError::Parse {
+				line,
+				details,
+				..
+			}) => (line, details),
+			other => panic!("expected a parse error, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn test_parse_errors_report_lines()
+	{
+		assert_eq!((1, "'key' appears before any 'track'".to_string()), parse_error("key 0 0 0 0\n"));
+		assert_eq!((2, "unknown track 'size'".to_string()), parse_error("\ntrack size linear loop\n"));
+		assert_eq!((1, "unknown interpolation 'smooth'".to_string()), parse_error("track scale smooth loop\n"));
+		assert_eq!((1, "unknown wrap mode 'bounce'".to_string()), parse_error("track scale linear bounce\n"));
+		assert_eq!((1, "'track' expects 3 words, found 2".to_string()), parse_error("track scale linear\n"));
+		assert_eq!(
+			(1, "'slerp' only applies to rotations, not color".to_string()),
+			parse_error("track color slerp loop\n")
+		);
+		assert_eq!(
+			(2, "'key' expects 5 numbers, found 4".to_string()),
+			parse_error("track rotation slerp loop\nkey 0 0 0 1\n")
+		);
+		assert_eq!(
+			(2, "'key' expects 10 numbers, found 4".to_string()),
+			parse_error("track scale cubic loop\nkey 0 1 1 1\n")
+		);
+		assert_eq!(
+			(2, "'key' has an invalid number 'nan'".to_string()),
+			parse_error("track scale step loop\nkey nan 1 1 1\n")
+		);
+		assert_eq!(
+			(3, "key 1 is not after the previous key".to_string()),
+			parse_error("track scale step loop\nkey 1 1 1 1\nkey 0.5 2 2 2\n")
+		);
+		assert_eq!((1, "track has no keys".to_string()), parse_error("track scale step loop\ntrack color step loop\n"));
+		assert_eq!(
+			(3, "more than one scale track".to_string()),
+			parse_error("track scale step loop\nkey 0 1 1 1\ntrack scale step loop\n")
+		);
+		assert_eq!((1, "unknown directive 'keys'".to_string()), parse_error("keys 0 1 1 1\n"));
+	}
+
+	#[test]
+	fn test_load_fixture()
+	{
+		let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("animation").join("bob.anim");
+		let clip = crate::animation::load_animation(&path).unwrap();
+		assert_eq!(2.0, clip.duration());
+		assert_eq!(None, clip.scale);
+
+		// Tracks wrap separately: translation cycles every 2 seconds, rotation every 4.
+		let transform = clip.sample_transform(2.5, &Transform::identity());
+		assert_near(Vector3::new(0.0, 0.25, 0.0), transform.translation);
+		assert_rotation_near(Quaternion::from_angle_y(Deg(67.5)), transform.rotation);
+		assert_eq!(Some(Vector4::new(1.0, 0.0, 0.0, 1.0)), clip.sample_color(5.0));
+	}
+
+	#[test]
+	fn test_missing_file()
+	{
+		let result = crate::animation::load_animation(std::path::Path::new("does/not/exist.anim"));
+		match result
+		{
+			Err(AnimationError::Io {
+				..
+			}) => (),
+			other => panic!("expected an io error, got {:?}", other),
+		}
+	}
+}
@@ -0,0 +1,246 @@
+// A look-at camera with a projection that can be switched at runtime.
+//
+// The view is left-handed, as everywhere else in the renderer. The
+// orthographic projection covers the same area at the target distance as the
+// perspective one, so switching keeps whatever is around the target in frame
+// at about the same size.
+//
+// Depth can be reversed, and the perspective far plane dropped, for more depth
+// precision in large scenes. Projections use the D3D12 clip space convention.
+use crate::transforms::{
+	self,
+	ClipSpaceConvention,
+	DepthRange,
+	Handedness,
+};
+use cgmath::*;
+use serde::{
+	Deserialize,
+	Serialize,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Projection
+{
+	Perspective,
+	Orthographic,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DepthMode
+{
+	// Near to the low end of the depth range, tested with LESS and cleared to 1.
+	Standard,
+	// Near at 1 and far at 0, tested with GREATER and cleared to 0.
+	Reversed,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera
+{
+	pub eye :          Point3<f32>,
+	pub target :       Point3<f32>,
+	pub up :           Vector3<f32>,
+	// Vertical field of view of the perspective projection.
+	pub fovy :         Rad<f32>,
+	pub aspect :       f32,
+	pub near :         f32,
+	pub far :          f32,
+	pub projection :   Projection,
+	pub depth :        DepthMode,
+	// Ignores `far` for perspective projections, so nothing is ever too far
+	// away to draw. Orthographic projections always use `far`.
+	pub infinite_far : bool,
+}
+
+impl Camera
+{
+	pub fn view(&self) -> Matrix4<f32>
+	{
+		transforms::look_at_lh(self.eye, self.target, self.up)
+	}
+
+	pub fn projection_matrix(&self) -> Matrix4<f32>
+	{
+		let convention = self.convention();
+		match self.projection
+		{
+			Projection::Perspective if self.infinite_far =>
+			{
+				transforms::perspective_infinite(self.fovy, self.aspect, self.near, convention)
+			}
+			Projection::Perspective => transforms::perspective(
+				PerspectiveFov {
+					fovy :   self.fovy,
+					aspect : self.aspect,
+					near :   self.near,
+					far :    self.far,
+				},
+				convention,
+			),
+			Projection::Orthographic =>
+			{
+				let height = 2.0 * (self.target - self.eye).magnitude() * (self.fovy / 2.0).tan();
+				transforms::ortho(height * self.aspect, height, self.near, self.far, convention)
+			}
+		}
+	}
+
+	pub fn convention(&self) -> ClipSpaceConvention
+	{
+		let depth_range = match self.depth
+		{
+			DepthMode::Standard => DepthRange::ZeroToOne,
+			DepthMode::Reversed => DepthRange::OneToZero,
+		};
+		ClipSpaceConvention::new(Handedness::Left, depth_range)
+	}
+
+	// The depth range of `projection_matrix`, for culling.
+	pub fn depth_range(&self) -> DepthRange
+	{
+		self.convention().depth_range
+	}
+
+	pub fn view_projection(&self) -> Matrix4<f32>
+	{
+		self.projection_matrix() * self.view()
+	}
+
+	pub fn toggle_projection(&mut self)
+	{
+		self.projection = match self.projection
+		{
+			Projection::Perspective => Projection::Orthographic,
+			Projection::Orthographic => Projection::Perspective,
+		};
+	}
+}
+
+#[cfg(test)]
+mod camera_tests
+{
+	use crate::{
+		camera::{
+			Camera,
+			DepthMode,
+			Projection,
+		},
+		geometry::{
+			frustum::{
+				Containment,
+				Frustum,
+			},
+			processing::BoundingSphere,
+		},
+		transforms::DepthRange,
+	};
+	use cgmath::*;
+
+	fn camera() -> Camera
+	{
+		Camera {
+			eye :          Point3::new(0.0, 1.0, -4.0),
+			target :       Point3::new(0.0, 1.0, 0.0),
+			up :           Vector3::unit_y(),
+			fovy :         Rad(std::f32::consts::FRAC_PI_2),
+			aspect :       2.0,
+			near :         0.1,
+			far :          100.0,
+			projection :   Projection::Perspective,
+			depth :        DepthMode::Standard,
+			infinite_far : false,
+		}
+	}
+
+	fn to_ndc(camera : &Camera, point : Point3<f32>) -> Vector3<f32>
+	{
+		let clip = camera.view_projection() * point.to_homogeneous();
+		clip.truncate() / clip.w
+	}
+
+	#[test]
+	fn test_toggle_keeps_target_plane()
+	{
+		let mut camera = camera();
+		// On the plane through the target, both projections agree.
+		let points = [Point3::new(4.0, 1.0, 0.0), Point3::new(-2.0, 3.0, 0.0), Point3::new(8.0, -3.0, 0.0)];
+		let perspective : Vec<_> = points.iter().map(|&p| to_ndc(&camera, p)).collect();
+
+		camera.toggle_projection();
+		assert_eq!(Projection::Orthographic, camera.projection);
+		for (&point, expected) in points.iter().zip(perspective)
+		{
+			let ndc = to_ndc(&camera, point);
+			assert!((ndc.truncate() - expected.truncate()).magnitude() < 1.0e-5, "{:?}", point);
+		}
+		// The corner of the view at the target distance.
+		assert!((to_ndc(&camera, Point3::new(8.0, 5.0, 0.0)).truncate() - Vector2::new(1.0, 1.0)).magnitude() < 1.0e-5);
+
+		camera.toggle_projection();
+		assert_eq!(Projection::Perspective, camera.projection);
+	}
+
+	#[test]
+	fn test_orthographic_ignores_distance()
+	{
+		let mut camera = camera();
+		camera.projection = Projection::Orthographic;
+		let near = to_ndc(&camera, Point3::new(2.0, 2.0, -2.0));
+		let far = to_ndc(&camera, Point3::new(2.0, 2.0, 30.0));
+		assert!((near.truncate() - far.truncate()).magnitude() < 1.0e-6);
+		assert!(near.z < far.z);
+	}
+
+	#[test]
+	fn test_depth_modes()
+	{
+		// Every combination keeps near and far in order within its depth range,
+		// and culls with it consistently.
+		let mut camera = camera();
+		for &projection in &[Projection::Perspective, Projection::Orthographic]
+		{
+			for &depth in &[DepthMode::Standard, DepthMode::Reversed]
+			{
+				for &infinite_far in &[false, true]
+				{
+					camera.projection = projection;
+					camera.depth = depth;
+					camera.infinite_far = infinite_far;
+					let at = |distance : f32| to_ndc(&camera, camera.eye + Vector3::new(0.0, 0.0, distance)).z;
+
+					let (near, middle, far) = (at(camera.near), at(10.0), at(camera.far));
+					match depth
+					{
+						DepthMode::Standard => assert!(near < middle && middle < far),
+						DepthMode::Reversed => assert!(near > middle && middle > far),
+					}
+					let near_depth = match camera.depth_range()
+					{
+						DepthRange::ZeroToOne => 0.0,
+						DepthRange::MinusOneToOne => -1.0,
+						DepthRange::OneToZero => 1.0,
+					};
+					assert!((near - near_depth).abs() < 1.0e-5);
+
+					let frustum = Frustum::from_matrix(camera.view_projection(), camera.depth_range());
+					let point = |distance : f32| BoundingSphere {
+						center : (camera.eye + Vector3::new(0.0, 0.0, distance)).to_vec(),
+						radius : 0.01,
+					};
+					assert_eq!(Containment::Inside, frustum.test_sphere(&point(10.0)));
+					assert_eq!(Containment::Outside, frustum.test_sphere(&point(0.05)));
+					let beyond = if infinite_far && projection == Projection::Perspective
+					{
+						Containment::Inside
+					}
+					else
+					{
+						Containment::Outside
+					};
+					assert_eq!(beyond, frustum.test_sphere(&point(200.0)));
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,774 @@
+// Scripted camera flythroughs along splines, for demos and reproducible
+// benchmarks.
+//
+// The eye follows a Catmull-Rom spline through its points, or a chain of cubic
+// Bezier segments given as anchor, control, control, anchor, control, ...
+// points. The camera looks at a point moving along a second spline of the
+// same kind, or turns through orientation keys. Playback moves at constant
+// speed: time maps to a distance along the curve through an arc length table,
+// rather than to the spline parameter, which would speed up wherever the
+// points are far apart.
+//
+// Paths are loaded from a small text format, one directive per line and '#'
+// starting a comment:
+//
+//   spline <catmull-rom|bezier>
+//   duration <seconds>
+//   wrap <loop|ping-pong|clamp>
+//   point <x> <y> <z>
+//   target <x> <y> <z>
+//   orientation <time> <x> <y> <z> <w>
+//
+// 'wrap' is optional and defaults to clamp. A path has either target points or
+// orientation keys, which are quaternions interpolated with slerp.
+use crate::{
+	animation::{
+		Interpolation,
+		Keyframe,
+		Track,
+		TrackError,
+		Wrap,
+	},
+	geometry::ColoredVertex,
+	transforms,
+};
+use cgmath::*;
+use std::{
+	error::Error,
+	fmt,
+	fs,
+	io,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+// Arc length table resolution. Enough to keep the speed within a percent on
+// curves as tight as camera paths use.
+const SAMPLES_PER_SEGMENT : usize = 64;
+
+// Debug line colors and the number of view directions drawn along a path.
+const PATH_COLOR : Vector4<f32> = Vector4::new(1.0, 0.9, 0.2, 1.0);
+const VIEW_COLOR : Vector4<f32> = Vector4::new(0.2, 0.9, 1.0, 1.0);
+const DEBUG_VIEW_COUNT : usize = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SplineKind
+{
+	// Passes through every point, with the end points repeated.
+	CatmullRom,
+	// Cubic segments through every third point, the others pulling the curve.
+	Bezier,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SplineError
+{
+	NoPoints,
+	// Bezier splines need 3n + 1 points.
+	BezierPointCount
+	{
+		count : usize,
+	},
+}
+
+impl fmt::Display for SplineError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			SplineError::NoPoints => write!(f, "spline has no points"),
+			SplineError::BezierPointCount {
+				count,
+			} => write!(f, "bezier spline needs 3n + 1 points, found {}", count),
+		}
+	}
+}
+
+impl Error for SplineError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spline
+{
+	kind :    SplineKind,
+	points :  Vec<Vector3<f32>>,
+	// Distance along the curve at every 1 / SAMPLES_PER_SEGMENT of the
+	// parameter.
+	lengths : Vec<f32>,
+}
+
+#[allow(dead_code)]
+impl Spline
+{
+	pub fn new(kind : SplineKind, points : Vec<Vector3<f32>>) -> Result<Self, SplineError>
+	{
+		if points.is_empty()
+		{
+			return Err(SplineError::NoPoints);
+		}
+		if kind == SplineKind::Bezier && points.len() % 3 != 1
+		{
+			return Err(SplineError::BezierPointCount {
+				count : points.len(),
+			});
+		}
+
+		let mut spline = Spline {
+			kind :    kind,
+			points :  points,
+			lengths : vec![0.0],
+		};
+		let samples = spline.segment_count() * SAMPLES_PER_SEGMENT;
+		let mut previous = spline.point_at(0.0);
+		for n in 1..=samples
+		{
+			let point = spline.point_at(n as f32 / SAMPLES_PER_SEGMENT as f32);
+			let length = spline.lengths[n - 1] + (point - previous).magnitude();
+			spline.lengths.push(length);
+			previous = point;
+		}
+		Ok(spline)
+	}
+
+	pub fn kind(&self) -> SplineKind
+	{
+		self.kind
+	}
+
+	pub fn points(&self) -> &[Vector3<f32>]
+	{
+		&self.points
+	}
+
+	pub fn segment_count(&self) -> usize
+	{
+		match self.kind
+		{
+			SplineKind::CatmullRom => self.points.len() - 1,
+			SplineKind::Bezier => self.points.len() / 3,
+		}
+	}
+
+	pub fn length(&self) -> f32
+	{
+		self.lengths[self.lengths.len() - 1]
+	}
+
+	// The point at parameter `u`, from 0 at the start to `segment_count` at the
+	// end, with each segment spanning 1.
+	pub fn point_at(&self, u : f32) -> Vector3<f32>
+	{
+		let segments = self.segment_count();
+		if segments == 0
+		{
+			return self.points[0];
+		}
+
+		let u = u.max(0.0).min(segments as f32);
+		let segment = (u.floor() as usize).min(segments - 1);
+		let t = u - segment as f32;
+		let (t2, t3) = (t * t, t * t * t);
+		match self.kind
+		{
+			SplineKind::CatmullRom =>
+			{
+				let p = |n : isize| self.points[(segment as isize + n).max(0).min(segments as isize) as usize];
+				let (p0, p1, p2, p3) = (p(-1), p(0), p(1), p(2));
+				(p1 * 2.0
+					+ (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+					+ (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+					* 0.5
+			}
+			SplineKind::Bezier =>
+			{
+				let p = &self.points[3 * segment..3 * segment + 4];
+				let s = 1.0 - t;
+				p[0] * (s * s * s) + p[1] * (3.0 * s * s * t) + p[2] * (3.0 * s * t2) + p[3] * t3
+			}
+		}
+	}
+
+	// The point `distance` along the curve, clamped to its ends.
+	pub fn point_at_distance(&self, distance : f32) -> Vector3<f32>
+	{
+		self.point_at(self.parameter_at_distance(distance))
+	}
+
+	fn parameter_at_distance(&self, distance : f32) -> f32
+	{
+		let next = self.lengths.iter().position(|&length| length > distance);
+		let next = match next
+		{
+			Some(0) => return 0.0,
+			Some(next) => next,
+			None => return self.segment_count() as f32,
+		};
+
+		let (before, after) = (self.lengths[next - 1], self.lengths[next]);
+		let fraction = (distance - before) / (after - before);
+		(next as f32 - 1.0 + fraction) / SAMPLES_PER_SEGMENT as f32
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathOrientation
+{
+	// Looks at the point as far along this spline as the eye is along its own.
+	LookAt(Spline),
+	// Turns through rotations of +z forward and +y up, keyed in seconds.
+	Keys(Track<Quaternion<f32>>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraPose
+{
+	pub eye :    Point3<f32>,
+	pub target : Point3<f32>,
+	pub up :     Vector3<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraPath
+{
+	pub position :    Spline,
+	pub orientation : PathOrientation,
+	// Seconds from one end of the path to the other.
+	pub duration :    f32,
+	pub wrap :        Wrap,
+}
+
+#[allow(dead_code)]
+impl CameraPath
+{
+	pub fn pose(&self, time : f32) -> CameraPose
+	{
+		let time = self.wrap.apply(time, 0.0, self.duration);
+		let progress = if self.duration > 0.0
+		{
+			time / self.duration
+		}
+		else
+		{
+			0.0
+		};
+		let eye = Point3::from_vec(self.position.point_at_distance(progress * self.position.length()));
+
+		match &self.orientation
+		{
+			PathOrientation::LookAt(targets) => CameraPose {
+				eye :    eye,
+				target : Point3::from_vec(targets.point_at_distance(progress * targets.length())),
+				up :     Vector3::unit_y(),
+			},
+			PathOrientation::Keys(keys) =>
+			{
+				let rotation = keys.sample(time);
+				CameraPose {
+					eye :    eye,
+					target : eye + rotation.rotate_vector(Vector3::unit_z()),
+					up :     rotation.rotate_vector(Vector3::unit_y()),
+				}
+			}
+		}
+	}
+
+	pub fn view(&self, time : f32) -> Matrix4<f32>
+	{
+		let pose = self.pose(time);
+		transforms::look_at_lh(pose.eye, pose.target, pose.up)
+	}
+
+	// A line list previewing the path: the curve itself, and where the camera
+	// looks from evenly spaced times along it.
+	pub fn debug_lines(&self) -> Vec<ColoredVertex>
+	{
+		let mut lines = Vec::new();
+
+		let samples = (self.position.segment_count() * SAMPLES_PER_SEGMENT).max(1);
+		for n in 0..samples
+		{
+			let u = |n : usize| n as f32 / SAMPLES_PER_SEGMENT as f32;
+			lines.push(ColoredVertex::new(self.position.point_at(u(n)), PATH_COLOR));
+			lines.push(ColoredVertex::new(self.position.point_at(u(n + 1)), PATH_COLOR));
+		}
+
+		let view_length = (self.position.length() / DEBUG_VIEW_COUNT as f32).max(0.1);
+		for n in 0..=DEBUG_VIEW_COUNT
+		{
+			let pose = self.pose(self.duration * n as f32 / DEBUG_VIEW_COUNT as f32);
+			let forward = (pose.target - pose.eye).normalize() * view_length;
+			lines.push(ColoredVertex::new(pose.eye.to_vec(), VIEW_COLOR));
+			lines.push(ColoredVertex::new(pose.eye.to_vec() + forward, VIEW_COLOR));
+		}
+
+		lines
+	}
+}
+
+#[derive(Debug)]
+pub enum CameraPathError
+{
+	Io
+	{
+		path :  PathBuf,
+		error : io::Error,
+	},
+	Parse
+	{
+		file :    String,
+		line :    usize,
+		details : String,
+	},
+	// Problems with the file as a whole, like a missing duration.
+	Invalid
+	{
+		file :    String,
+		details : String,
+	},
+}
+
+impl fmt::Display for CameraPathError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			CameraPathError::Io {
+				path,
+				error,
+			} => write!(f, "{}: {}", path.display(), error),
+			CameraPathError::Parse {
+				file,
+				line,
+				details,
+			} => write!(f, "{}:{}: {}", file, line, details),
+			CameraPathError::Invalid {
+				file,
+				details,
+			} => write!(f, "{}: {}", file, details),
+		}
+	}
+}
+
+impl Error for CameraPathError {}
+
+fn parse_numbers(tokens : &[&str], count : usize) -> Result<Vec<f32>, String>
+{
+	if tokens.len() != count + 1
+	{
+		return Err(format!("'{}' expects {} numbers, found {}", tokens[0], count, tokens.len() - 1));
+	}
+
+	let mut numbers = Vec::with_capacity(count);
+	for token in &tokens[1..]
+	{
+		match token.parse::<f32>()
+		{
+			Ok(value) if value.is_finite() => numbers.push(value),
+			_ => return Err(format!("'{}' has an invalid number '{}'", tokens[0], token)),
+		}
+	}
+	Ok(numbers)
+}
+
+// Parses the contents of a camera path file. `file` is only used in error
+// messages.
+pub fn parse_camera_path(file : &str, source : &str) -> Result<CameraPath, CameraPathError>
+{
+	let mut kind = None;
+	let mut duration = None;
+	let mut wrap = Wrap::Clamp;
+	let mut points = Vec::new();
+	let mut targets = Vec::new();
+	// With the line of each key, for error messages.
+	let mut orientations : Vec<(usize, Keyframe<Quaternion<f32>>)> = Vec::new();
+
+	for (n, text) in source.lines().enumerate()
+	{
+		let line = n + 1;
+		let error = |details : String| CameraPathError::Parse {
+			file :    file.to_string(),
+			line :    line,
+			details : details,
+		};
+
+		let text = text.split('#').next().unwrap_or("");
+		let tokens : Vec<&str> = text.split_whitespace().collect();
+		let word = |expected : &str| match tokens.len()
+		{
+			2 => Ok(tokens[1]),
+			found => Err(error(format!("'{}' expects {}, found {} words", tokens[0], expected, found - 1))),
+		};
+		match tokens.first()
+		{
+			None => (),
+			Some(&"spline") =>
+			{
+				kind = Some(match word("a spline kind")?
+				{
+					"catmull-rom" => SplineKind::CatmullRom,
+					"bezier" => SplineKind::Bezier,
+					other => return Err(error(format!("unknown spline kind '{}'", other))),
+				})
+			}
+			Some(&"duration") =>
+			{
+				let seconds = parse_numbers(&tokens, 1).map_err(error)?[0];
+				if seconds <= 0.0
+				{
+					return Err(error(format!("duration must be positive, found {}", seconds)));
+				}
+				duration = Some(seconds);
+			}
+			Some(&"wrap") =>
+			{
+				wrap = match word("a wrap mode")?
+				{
+					"loop" => Wrap::Loop,
+					"ping-pong" => Wrap::PingPong,
+					"clamp" => Wrap::Clamp,
+					other => return Err(error(format!("unknown wrap mode '{}'", other))),
+				}
+			}
+			Some(&"point") =>
+			{
+				let v = parse_numbers(&tokens, 3).map_err(error)?;
+				points.push(Vector3::new(v[0], v[1], v[2]));
+			}
+			Some(&"target") =>
+			{
+				let v = parse_numbers(&tokens, 3).map_err(error)?;
+				targets.push(Vector3::new(v[0], v[1], v[2]));
+			}
+			Some(&"orientation") =>
+			{
+				let v = parse_numbers(&tokens, 5).map_err(error)?;
+				orientations.push((line, Keyframe::new(v[0], Quaternion::new(v[4], v[1], v[2], v[3]).normalize())));
+			}
+			Some(other) => return Err(error(format!("unknown directive '{}'", other))),
+		}
+	}
+
+	let invalid = |details : String| CameraPathError::Invalid {
+		file :    file.to_string(),
+		details : details,
+	};
+	let kind = kind.ok_or_else(|| invalid("missing 'spline'".to_string()))?;
+	let duration = duration.ok_or_else(|| invalid("missing 'duration'".to_string()))?;
+	let position = Spline::new(kind, points).map_err(|error| invalid(format!("points: {}", error)))?;
+
+	let orientation = match (targets.is_empty(), orientations.is_empty())
+	{
+		(false, true) =>
+		{
+			PathOrientation::LookAt(Spline::new(kind, targets).map_err(|error| invalid(format!("targets: {}", error)))?)
+		}
+		(true, false) =>
+		{
+			let keys = orientations.iter().map(|(_, key)| *key).collect();
+			let track = Track::new(Interpolation::Linear, Wrap::Clamp, keys).map_err(|error| match error
+			{
+				TrackError::TimeNotIncreasing {
+					key,
+				} => CameraPathError::Parse {
+					file :    file.to_string(),
+					line :    orientations[key].0,
+					details : format!("orientation key {} is not after the previous key", key),
+				},
+				TrackError::NoKeys => invalid(error.to_string()),
+			})?;
+			PathOrientation::Keys(track)
+		}
+		(true, true) => return Err(invalid("needs 'target' points or 'orientation' keys".to_string())),
+		(false, false) => return Err(invalid("has both 'target' points and 'orientation' keys".to_string())),
+	};
+
+	Ok(CameraPath {
+		position :    position,
+		orientation : orientation,
+		duration :    duration,
+		wrap :        wrap,
+	})
+}
+
+// Loads a camera path file from disk.
+#[allow(dead_code)]
+pub fn load_camera_path(path : &Path) -> Result<CameraPath, CameraPathError>
+{
+	let source = fs::read_to_string(path).map_err(|error| CameraPathError::Io {
+		path :  path.to_path_buf(),
+		error : error,
+	})?;
+	parse_camera_path(&path.display().to_string(), &source)
+}
+
+#[cfg(test)]
+mod camera_path_tests
+{
+	use crate::{
+		animation::{
+			Interpolation,
+			Keyframe,
+			Track,
+			Wrap,
+		},
+		camera_path::{
+			parse_camera_path,
+			CameraPath,
+			CameraPathError,
+			PathOrientation,
+			Spline,
+			SplineError,
+			SplineKind,
+		},
+		geometry::Vertex,
+		transforms,
+	};
+	use cgmath::*;
+
+	const EPSILON : f32 = 1.0e-4;
+
+	fn assert_near(expected : Vector3<f32>, actual : Vector3<f32>)
+	{
+		assert!((expected - actual).magnitude() < EPSILON, "expected {:?}, got {:?}", expected, actual);
+	}
+
+	// Points unevenly spaced along a bend, so the spline parameter does not
+	// move at constant speed.
+	fn points() -> Vec<Vector3<f32>>
+	{
+		vec![
+			Vector3::new(0.0, 0.0, 0.0),
+			Vector3::new(1.0, 0.0, 0.0),
+			Vector3::new(3.0, 0.0, 2.0),
+			Vector3::new(4.0, 1.0, 5.0),
+		]
+	}
+
+	#[test]
+	fn test_catmull_rom_passes_through_points()
+	{
+		let spline = Spline::new(SplineKind::CatmullRom, points()).unwrap();
+		assert_eq!(3, spline.segment_count());
+		for (n, &point) in points().iter().enumerate()
+		{
+			assert_near(point, spline.point_at(n as f32));
+		}
+		// Clamped outside the curve.
+		assert_near(points()[0], spline.point_at(-1.0));
+		assert_near(points()[3], spline.point_at(7.0));
+
+		// A straight line through evenly spaced points stays on it.
+		let line =
+			Spline::new(SplineKind::CatmullRom, (0..4).map(|n| Vector3::new(n as f32, 0.0, 0.0)).collect()).unwrap();
+		assert_near(Vector3::new(1.25, 0.0, 0.0), line.point_at(1.25));
+		assert!((line.length() - 3.0).abs() < EPSILON);
+	}
+
+	#[test]
+	fn test_bezier()
+	{
+		let spline = Spline::new(SplineKind::Bezier, points()).unwrap();
+		assert_eq!(1, spline.segment_count());
+		assert_near(points()[0], spline.point_at(0.0));
+		assert_near(points()[3], spline.point_at(1.0));
+		// (a + 3b + 3c + d) / 8 at the middle.
+		let expected = (points()[0] + points()[1] * 3.0 + points()[2] * 3.0 + points()[3]) / 8.0;
+		assert_near(expected, spline.point_at(0.5));
+
+		assert_eq!(
+			Err(SplineError::BezierPointCount {
+				count : 3,
+			}),
+			Spline::new(SplineKind::Bezier, points()[..3].to_vec())
+		);
+		assert_eq!(Err(SplineError::NoPoints), Spline::new(SplineKind::CatmullRom, Vec::new()));
+
+		// A single point is a valid, constant spline.
+		let single = Spline::new(SplineKind::Bezier, vec![Vector3::unit_y()]).unwrap();
+		assert_eq!(0.0, single.length());
+		assert_near(Vector3::unit_y(), single.point_at_distance(3.0));
+	}
+
+	#[test]
+	fn test_constant_speed()
+	{
+		for &kind in &[SplineKind::CatmullRom, SplineKind::Bezier]
+		{
+			let spline = Spline::new(kind, points()).unwrap();
+			let steps = 100;
+			let step = spline.length() / steps as f32;
+			let positions : Vec<Vector3<f32>> =
+				(0..=steps).map(|n| spline.point_at_distance(step * n as f32)).collect();
+			for pair in positions.windows(2)
+			{
+				let distance = (pair[1] - pair[0]).magnitude();
+				assert!((distance - step).abs() < 0.01 * step, "{:?}: {} instead of {}", kind, distance, step);
+			}
+			assert_near(points()[3], positions[steps]);
+		}
+	}
+
+	#[test]
+	fn test_look_at_path()
+	{
+		let path = CameraPath {
+			position :    Spline::new(SplineKind::CatmullRom, points()).unwrap(),
+			orientation : PathOrientation::LookAt(Spline::new(SplineKind::CatmullRom, vec![Vector3::zero()]).unwrap()),
+			duration :    10.0,
+			wrap :        Wrap::Loop,
+		};
+
+		let start = path.pose(0.0);
+		assert_near(points()[0], start.eye.to_vec());
+		let end = path.pose(9.999);
+		assert!((end.eye.to_vec() - points()[3]).magnitude() < 0.01);
+		assert_near(start.eye.to_vec(), path.pose(10.0).eye.to_vec());
+
+		// Views come from look_at_lh, with the target straight ahead.
+		let pose = path.pose(6.0);
+		assert_eq!(transforms::look_at_lh(pose.eye, pose.target, pose.up), path.view(6.0));
+		let ahead = path.view(6.0) * pose.target.to_homogeneous();
+		assert!(ahead.x.abs() < EPSILON && ahead.y.abs() < EPSILON && ahead.z > 0.0);
+	}
+
+	#[test]
+	fn test_orientation_keys()
+	{
+		let keys = vec![Keyframe::new(0.0, Quaternion::one()), Keyframe::new(4.0, Quaternion::from_angle_y(Deg(90.0)))];
+		let path = CameraPath {
+			position :    Spline::new(SplineKind::CatmullRom, points()).unwrap(),
+			orientation : PathOrientation::Keys(Track::new(Interpolation::Linear, Wrap::Clamp, keys).unwrap()),
+			duration :    4.0,
+			wrap :        Wrap::Clamp,
+		};
+
+		let pose = path.pose(2.0);
+		let forward = (pose.target - pose.eye).normalize();
+		assert_near(Quaternion::from_angle_y(Deg(45.0)).rotate_vector(Vector3::unit_z()), forward);
+		assert_near(Vector3::unit_y(), pose.up);
+
+		let pose = path.pose(100.0);
+		assert_near(Vector3::unit_x(), pose.target - pose.eye);
+	}
+
+	#[test]
+	fn test_debug_lines()
+	{
+		let path = parse_camera_path(
+			"test.path",
+			"spline bezier\nduration 2\npoint 0 0 0\npoint 0 1 0\npoint 1 1 0\npoint 1 0 0\ntarget 0 0 5\n",
+		)
+		.unwrap();
+		let lines = path.debug_lines();
+		assert_eq!(0, lines.len() % 2);
+		// The curve starts and ends at the end points.
+		assert_near(Vector3::zero(), lines[0].position());
+		let view_lines = 2 * 17;
+		assert_near(Vector3::unit_x(), lines[lines.len() - view_lines - 1].position());
+		// Views point at the target.
+		let (from, to) = (lines[lines.len() - 2].position(), lines[lines.len() - 1].position());
+		assert!((to - from).normalize().dot((Vector3::new(0.0, 0.0, 5.0) - from).normalize()) > 1.0 - EPSILON);
+	}
+
+	#[test]
+	fn test_parse_camera_path()
+	{
+		let source = "# orbit the origin\n\
+		              spline catmull-rom\n\
+		              duration 8\n\
+		              wrap ping-pong\n\
+		              point 0 1 -4\n\
+		              point 4 1 0\n\
+		              point 0 1 4   # halfway\n\
+		              orientation 0  0 0 0 1\n\
+		              orientation 8  0 1 0 0\n";
+		let path = parse_camera_path("test.path", source).unwrap();
+		assert_eq!(SplineKind::CatmullRom, path.position.kind());
+		assert_eq!(3, path.position.points().len());
+		assert_eq!(8.0, path.duration);
+		assert_eq!(Wrap::PingPong, path.wrap);
+		match &path.orientation
+		{
+			PathOrientation::Keys(keys) => assert_eq!(2, keys.keys().len()),
+			other => panic!("expected orientation keys, got {:?}", other),
+		}
+		assert_near(Vector3::new(0.0, 1.0, 4.0), path.pose(8.0).eye.to_vec());
+		assert_near(Vector3::new(0.0, 1.0, -4.0), path.pose(16.0).eye.to_vec());
+	}
+
+	fn parse_error(source : &str) -> (usize, String)
+	{
+		match parse_camera_path("test.path", source)
+		{
+			Err(CameraPathError::Parse {
+				line,
+				details,
+				..
+			}) => (line, details),
+			other => panic!("expected a parse error, got {:?}", other),
+		}
+	}
+
+	fn invalid(source : &str) -> String
+	{
+		match parse_camera_path("test.path", source)
+		{
+			Err(CameraPathError::Invalid {
+				details,
+				..
+			}) => details,
+			other => panic!("expected an invalid path, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn test_parse_errors()
+	{
+		assert_eq!((1, "unknown spline kind 'b-spline'".to_string()), parse_error("spline b-spline\n"));
+		assert_eq!((2, "'spline' expects a spline kind, found 0 words".to_string()), parse_error("\nspline\n"));
+		assert_eq!((1, "unknown wrap mode 'bounce'".to_string()), parse_error("wrap bounce\n"));
+		assert_eq!((1, "'point' expects 3 numbers, found 2".to_string()), parse_error("point 0 1\n"));
+		assert_eq!((1, "'target' has an invalid number 'x'".to_string()), parse_error("target x 0 0\n"));
+		assert_eq!((1, "duration must be positive, found 0".to_string()), parse_error("duration 0\n"));
+		assert_eq!((1, "unknown directive 'speed'".to_string()), parse_error("speed 2\n"));
+		assert_eq!(
+			(3, "orientation key 1 is not after the previous key".to_string()),
+			parse_error("spline bezier\norientation 1 0 0 0 1\norientation 1 0 1 0 0\nduration 1\npoint 0 0 0\n")
+		);
+
+		assert_eq!("missing 'spline'", invalid("duration 1\npoint 0 0 0\ntarget 0 0 1\n"));
+		assert_eq!("missing 'duration'", invalid("spline bezier\npoint 0 0 0\ntarget 0 0 1\n"));
+		assert_eq!("points: spline has no points", invalid("spline bezier\nduration 1\ntarget 0 0 1\n"));
+		assert_eq!(
+			"targets: bezier spline needs 3n + 1 points, found 2",
+			invalid("spline bezier\nduration 1\npoint 0 0 0\ntarget 0 0 1\ntarget 0 0 2\n")
+		);
+		assert_eq!("needs 'target' points or 'orientation' keys", invalid("spline bezier\nduration 1\npoint 0 0 0\n"));
+		assert_eq!(
+			"has both 'target' points and 'orientation' keys",
+			invalid("spline bezier\nduration 1\npoint 0 0 0\ntarget 0 0 1\norientation 0 0 0 0 1\n")
+		);
+	}
+
+	#[test]
+	fn test_load_fixture()
+	{
+		let path =
+			std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("camera_path").join("flyby.path");
+		let path = crate::camera_path::load_camera_path(&path).unwrap();
+		assert_eq!(SplineKind::Bezier, path.position.kind());
+		assert_eq!(Wrap::Loop, path.wrap);
+		assert!(path.position.length() > 0.0);
+
+		match crate::camera_path::load_camera_path(std::path::Path::new("does/not/exist.path"))
+		{
+			Err(CameraPathError::Io {
+				..
+			}) => (),
+			other => panic!("expected an io error, got {:?}", other),
+		}
+	}
+}
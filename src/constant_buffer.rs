@@ -0,0 +1,230 @@
+// Constant buffer structs that match HLSL cbuffer packing.
+//
+// HLSL packs cbuffer members into 16-byte registers: a member never straddles
+// a register boundary, and matrices, arrays and structs always start on a new
+// register. Array elements are each padded to a full register, so only arrays
+// of 16-byte types have the same stride in Rust. D3D12 additionally requires
+// constant buffer views to be 256-byte aligned.
+//
+// `constant_buffer!` declares a struct with `#[repr(C, align(256))]`, which
+// pads it to the CBV alignment, and checks at compile time that every field
+// sits at the offset HLSL would give it. Where it doesn't, add an explicit
+// padding field or reorder the fields.
+use cgmath::*;
+
+pub const REGISTER_SIZE : usize = 16;
+
+// D3D12_CONSTANT_BUFFER_DATA_PLACEMENT_ALIGNMENT
+pub const CONSTANT_BUFFER_ALIGNMENT : usize = 256;
+
+// Types that can be members of a cbuffer.
+pub trait HlslType
+{
+	const SIZE : usize;
+
+	// Matrices, arrays and structs always start on a new register.
+	const STARTS_REGISTER : bool;
+}
+
+impl HlslType for f32
+{
+	const SIZE : usize = 4;
+	const STARTS_REGISTER : bool = false;
+}
+
+impl HlslType for u32
+{
+	const SIZE : usize = 4;
+	const STARTS_REGISTER : bool = false;
+}
+
+impl HlslType for i32
+{
+	const SIZE : usize = 4;
+	const STARTS_REGISTER : bool = false;
+}
+
+impl HlslType for Vector2<f32>
+{
+	const SIZE : usize = 8;
+	const STARTS_REGISTER : bool = false;
+}
+
+impl HlslType for Vector3<f32>
+{
+	const SIZE : usize = 12;
+	const STARTS_REGISTER : bool = false;
+}
+
+impl HlslType for Vector4<f32>
+{
+	const SIZE : usize = 16;
+	const STARTS_REGISTER : bool = false;
+}
+
+impl HlslType for Matrix4<f32>
+{
+	const SIZE : usize = 64;
+	const STARTS_REGISTER : bool = true;
+}
+
+impl<T : HlslType, const N: usize> HlslType for [T; N]
+{
+	const SIZE : usize = if T::SIZE % REGISTER_SIZE == 0
+	{
+		N * T::SIZE
+	}
+	else
+	{
+		panic!("cbuffer arrays pad every element to 16 bytes, use an array of 16-byte elements instead")
+	};
+	const STARTS_REGISTER : bool = true;
+}
+
+// Offset HLSL gives a member of `size` bytes that follows a member ending at
+// `end`.
+pub const fn hlsl_offset(end : usize, size : usize, starts_register : bool) -> usize
+{
+	let used = end % REGISTER_SIZE;
+	if used != 0 && (starts_register || used + size > REGISTER_SIZE)
+	{
+		end - used + REGISTER_SIZE
+	}
+	else
+	{
+		end
+	}
+}
+
+// Declares a constant buffer struct:
+//
+//     constant_buffer! {
+//         pub struct SceneConstantBuffer
+//         {
+//             pub view_projection : Matrix4<f32>,
+//             pub light_direction : Vector3<f32>,
+//             pub light_intensity : f32,
+//         }
+//     }
+//
+// Fields that HLSL would place at a different offset fail to compile.
+#[macro_export]
+macro_rules! constant_buffer {
+	(
+		$(#[$meta:meta])*
+		$visibility:vis struct $name:ident
+		{
+			$($field_visibility:vis $field:ident : $field_type:ty),* $(,)?
+		}
+	) => {
+		$(#[$meta])*
+		#[repr(C, align(256))]
+		#[derive(Debug, Copy, Clone)]
+		$visibility struct $name
+		{
+			$($field_visibility $field : $field_type),*
+		}
+
+		#[allow(unused_imports, unused_mut, unused_assignments)]
+		const _ : () = {
+			use $crate::constant_buffer::HlslType;
+
+			let mut end = 0;
+			$(
+				let offset = $crate::constant_buffer::hlsl_offset(
+					end,
+					<$field_type as HlslType>::SIZE,
+					<$field_type as HlslType>::STARTS_REGISTER,
+				);
+				assert!(
+					offset == std::mem::offset_of!($name, $field),
+					concat!(
+						"`",
+						stringify!($name),
+						"::",
+						stringify!($field),
+						"` is not where HLSL cbuffer packing puts it, add padding before it"
+					)
+				);
+				end = offset + <$field_type as HlslType>::SIZE;
+			)*
+			assert!(std::mem::size_of::<$name>() % $crate::constant_buffer::CONSTANT_BUFFER_ALIGNMENT == 0);
+		};
+	};
+}
+
+#[cfg(test)]
+mod constant_buffer_tests
+{
+	use crate::constant_buffer::{
+		hlsl_offset,
+		HlslType,
+		CONSTANT_BUFFER_ALIGNMENT,
+	};
+	use cgmath::*;
+	use std::mem;
+
+	crate::constant_buffer! {
+		#[allow(dead_code)]
+		struct Lighting
+		{
+			direction : Vector3<f32>,
+			intensity : f32,
+			color : Vector2<f32>,
+			_padding : Vector2<f32>,
+			transforms : [Matrix4<f32>; 2],
+			ambient : Vector4<f32>,
+			count : u32,
+		}
+	}
+
+	// Offsets of a sequence of members, as HLSL packs them.
+	fn pack(members : &[(usize, bool)]) -> Vec<usize>
+	{
+		let mut end = 0;
+		members
+			.iter()
+			.map(|&(size, starts_register)| {
+				let offset = hlsl_offset(end, size, starts_register);
+				end = offset + size;
+				offset
+			})
+			.collect()
+	}
+
+	fn member<T : HlslType>() -> (usize, bool)
+	{
+		(T::SIZE, T::STARTS_REGISTER)
+	}
+
+	#[test]
+	fn test_hlsl_packing_rules()
+	{
+		// float3 then float share a register.
+		assert_eq!(vec![0, 12], pack(&[member::<Vector3<f32>>(), member::<f32>()]));
+		// float then float3 too, but float2 then float3 would straddle.
+		assert_eq!(vec![0, 4], pack(&[member::<f32>(), member::<Vector3<f32>>()]));
+		assert_eq!(vec![0, 16], pack(&[member::<Vector2<f32>>(), member::<Vector3<f32>>()]));
+		// Two float2s fill a register exactly.
+		assert_eq!(vec![0, 8, 16], pack(&[member::<Vector2<f32>>(), member::<Vector2<f32>>(), member::<f32>()]));
+		// Matrices and arrays start a new register.
+		assert_eq!(vec![0, 16], pack(&[member::<f32>(), member::<Matrix4<f32>>()]));
+		assert_eq!(vec![0, 16, 48], pack(&[member::<f32>(), member::<[Vector4<f32>; 2]>(), member::<f32>()]));
+		// A float4 after a float must move to the next register.
+		assert_eq!(vec![0, 16], pack(&[member::<f32>(), member::<Vector4<f32>>()]));
+	}
+
+	#[test]
+	fn test_constant_buffer_layout()
+	{
+		assert_eq!(0, mem::offset_of!(Lighting, direction));
+		assert_eq!(12, mem::offset_of!(Lighting, intensity));
+		assert_eq!(16, mem::offset_of!(Lighting, color));
+		assert_eq!(32, mem::offset_of!(Lighting, transforms));
+		assert_eq!(160, mem::offset_of!(Lighting, ambient));
+		assert_eq!(176, mem::offset_of!(Lighting, count));
+
+		assert_eq!(CONSTANT_BUFFER_ALIGNMENT, mem::align_of::<Lighting>());
+		assert_eq!(256, mem::size_of::<Lighting>());
+	}
+}
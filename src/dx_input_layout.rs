@@ -0,0 +1,85 @@
+extern crate winapi;
+
+use crate::vertex_layout::{
+	VertexFormat,
+	VertexLayout,
+};
+use std::ffi::CString;
+use winapi::{
+	shared::dxgiformat,
+	um::d3d12,
+};
+
+fn dxgi_format(format : VertexFormat) -> dxgiformat::DXGI_FORMAT
+{
+	match format
+	{
+		VertexFormat::Float => dxgiformat::DXGI_FORMAT_R32_FLOAT,
+		VertexFormat::Float2 => dxgiformat::DXGI_FORMAT_R32G32_FLOAT,
+		VertexFormat::Float3 => dxgiformat::DXGI_FORMAT_R32G32B32_FLOAT,
+		VertexFormat::Float4 => dxgiformat::DXGI_FORMAT_R32G32B32A32_FLOAT,
+	}
+}
+
+// D3D12 input layout built from the VertexLayout of each vertex stream. Owns
+// the semantic name strings the element descs point to, so it must outlive
+// any D3D12_INPUT_LAYOUT_DESC taken from it.
+pub struct InputLayout
+{
+	semantic_names : Vec<CString>,
+	element_descs :  Vec<d3d12::D3D12_INPUT_ELEMENT_DESC>,
+}
+
+impl InputLayout
+{
+	pub fn new() -> Self
+	{
+		Self {
+			semantic_names : Vec::new(),
+			element_descs :  Vec::new(),
+		}
+	}
+
+	// Adds a stream of per-vertex data of type V in `input_slot`.
+	pub fn per_vertex<V : VertexLayout>(self, input_slot : u32) -> Self
+	{
+		self.with_stream::<V>(input_slot, d3d12::D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA, 0)
+	}
+
+	// Adds a stream of per-instance data of type V in `input_slot`, advancing
+	// one element every `step_rate` instances.
+	pub fn per_instance<V : VertexLayout>(self, input_slot : u32, step_rate : u32) -> Self
+	{
+		self.with_stream::<V>(input_slot, d3d12::D3D12_INPUT_CLASSIFICATION_PER_INSTANCE_DATA, step_rate)
+	}
+
+	fn with_stream<V : VertexLayout>(
+		mut self, input_slot : u32, classification : d3d12::D3D12_INPUT_CLASSIFICATION, step_rate : u32,
+	) -> Self
+	{
+		for element in V::elements()
+		{
+			// The CString's buffer stays put when the Vec grows.
+			let semantic_name = CString::new(element.semantic_name).unwrap();
+			self.element_descs.push(d3d12::D3D12_INPUT_ELEMENT_DESC {
+				SemanticName : semantic_name.as_ptr(),
+				SemanticIndex : element.semantic_index,
+				Format : dxgi_format(element.format),
+				InputSlot : input_slot,
+				AlignedByteOffset : element.offset as u32,
+				InputSlotClass : classification,
+				InstanceDataStepRate : step_rate,
+			});
+			self.semantic_names.push(semantic_name);
+		}
+		self
+	}
+
+	pub fn desc(&self) -> d3d12::D3D12_INPUT_LAYOUT_DESC
+	{
+		d3d12::D3D12_INPUT_LAYOUT_DESC {
+			pInputElementDescs : self.element_descs.as_ptr(),
+			NumElements :        self.element_descs.len() as u32,
+		}
+	}
+}
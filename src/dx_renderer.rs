@@ -1,12 +1,52 @@
 // extern crate d3d12_rs;
 extern crate winapi;
 use crate::{
+	camera::{
+		Camera,
+		DepthMode,
+		Projection,
+	},
+	camera_path::CameraPath,
 	dx_descriptor_handles::{
 		CD3D12_CPU_DESCRIPTOR_HANDLE,
 		CD3D12_GPU_DESCRIPTOR_HANDLE,
 	},
-	geometry::*,
-	transforms,
+	dx_input_layout::InputLayout,
+	geometry::{
+		bvh::{
+			Bvh,
+			MeshHit,
+		},
+		frustum::{
+			CullingStats,
+			Frustum,
+		},
+		processing::{
+			bounding_sphere,
+			BoundingSphere,
+		},
+		ray::Ray,
+		*,
+	},
+	hot_reload::{
+		MeshUpdate,
+		SceneDiff,
+	},
+	instancing::InstanceData,
+	scene::{
+		Draw,
+		MeshId,
+		ObjectId,
+		Scene,
+		SceneError,
+	},
+	scene_file::{
+		self,
+		SceneDescription,
+		SceneFileError,
+	},
+	transforms::Viewport,
+	vertex_layout::VertexLayout,
 	weak_ptr::WeakPtr,
 	win_window,
 };
@@ -58,6 +98,7 @@ use std::{
 		OsStrExt,
 		OsStringExt,
 	},
+	path::Path,
 	ptr,
 	string::String,
 };
@@ -68,12 +109,50 @@ const G_WIDTH : u32 = 1280;
 const G_HEIGHT : u32 = 720;
 const FOVY : f32 = 90.0;
 const ASPECT_RATIO : f32 = G_WIDTH as f32 / G_HEIGHT as f32;
+const DEPTH_FORMAT : dxgiformat::DXGI_FORMAT = dxgiformat::DXGI_FORMAT_D32_FLOAT;
 
-struct MatrixConstantBuffer
-{
+// Slots of the constant buffer, each with its own CBV in the descriptor heap.
+// Visible scene objects take the first slots, in draw order. Objects past
+// MAX_DRAWN_OBJECTS are not drawn and are counted in FrameStats::over_limit.
+const MAX_DRAWN_OBJECTS : usize = 256;
+const DEBUG_LINE_CONSTANTS : usize = MAX_DRAWN_OBJECTS;
+const INSTANCE_CONSTANTS : usize = MAX_DRAWN_OBJECTS + 1;
+const CONSTANT_BUFFER_SLOTS : usize = MAX_DRAWN_OBJECTS + 2;
+
+crate::constant_buffer! {
 	#[allow(dead_code)]
-	mvp_transform : cgmath::Matrix4<f32>,
-	_padding :      [u8; 192],
+	struct MatrixConstantBuffer
+	{
+		mvp_transform : cgmath::Matrix4<f32>,
+		// Multiplies the vertex colors.
+		tint : cgmath::Vector4<f32>,
+		// Triangle of the mesh drawn highlighted, -1 for none.
+		highlighted_triangle : i32,
+	}
+}
+
+// Counters for the last frame, reported alongside the frame rate.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct FrameStats
+{
+	pub culling :    CullingStats,
+	// Visible objects left undrawn because every constant buffer slot was taken.
+	pub over_limit : u32,
+}
+
+// A mesh uploaded for drawing. The CPU copy is kept for picking and culling.
+struct GpuMesh
+{
+	name : String,
+	vertex_buffer : WeakPtr<d3d12::ID3D12Resource>,
+	vertex_buffer_view : d3d12::D3D12_VERTEX_BUFFER_VIEW,
+	index_buffer : WeakPtr<d3d12::ID3D12Resource>,
+	index_buffer_view : d3d12::D3D12_INDEX_BUFFER_VIEW,
+	index_count : u32,
+	mesh : Mesh<ColoredVertex>,
+	bvh : Bvh,
+	// Model space bounds of the mesh.
+	bounds : BoundingSphere,
 }
 
 #[allow(dead_code)]
@@ -89,22 +168,74 @@ pub struct Renderer
 	rtv_descriptor_heap : WeakPtr<d3d12::ID3D12DescriptorHeap>,
 	rtv_descriptor_size : u32,
 	cbv_descriptor_heap : WeakPtr<d3d12::ID3D12DescriptorHeap>,
+	cbv_descriptor_size : u32,
+	dsv_descriptor_heap : WeakPtr<d3d12::ID3D12DescriptorHeap>,
+	depth_buffer : WeakPtr<d3d12::ID3D12Resource>,
 	command_allocators : [WeakPtr<d3d12::ID3D12CommandAllocator>; G_MAX_FRAME_COUNT],
 	command_list : WeakPtr<d3d12::ID3D12GraphicsCommandList>,
 	render_targets : [WeakPtr<d3d12::ID3D12Resource>; G_MAX_FRAME_COUNT],
 	root_signature : WeakPtr<d3d12::ID3D12RootSignature>,
 	pipeline_state : WeakPtr<d3d12::ID3D12PipelineState>,
+	// The same shaders drawing line lists, for debug lines.
+	line_pipeline_state : WeakPtr<d3d12::ID3D12PipelineState>,
+	// Takes per-instance transforms and tints from a second vertex stream.
+	instanced_pipeline_state : WeakPtr<d3d12::ID3D12PipelineState>,
 	frame_count : u32,
 	frame_index : usize,
-	vertex_buffer : WeakPtr<d3d12::ID3D12Resource>,
-	vertex_buffer_view : d3d12::D3D12_VERTEX_BUFFER_VIEW,
 	constant_buffer : WeakPtr<d3d12::ID3D12Resource>,
 	constant_buffer_gpu_handle : CD3D12_GPU_DESCRIPTOR_HANDLE,
 	p_cbv_data : *mut MatrixConstantBuffer,
 	fence : WeakPtr<d3d12::ID3D12Fence>,
 	fence_values : [u64; G_MAX_FRAME_COUNT],
 	fence_event : HANDLE,
+	// Replaced resources the GPU may still be reading, each with the fence
+	// value after which it is free.
+	retired_resources : Vec<(u64, WeakPtr<d3d12::ID3D12Resource>)>,
 	timer : std::time::Instant,
+	camera : Camera,
+	meshes : Vec<GpuMesh>,
+	scene : Scene,
+	// Objects in view as of the last update, in constant buffer slot order, and
+	// the matrices they were drawn with, so picks hit what is on screen.
+	draws : Vec<Draw>,
+	view : Matrix4<f32>,
+	projection : Matrix4<f32>,
+	picked : Option<(ObjectId, usize)>,
+	frame_stats : FrameStats,
+	// Scripted camera flythrough, previewed as debug lines. The camera only
+	// follows it while `follow_camera_path` is set.
+	camera_path : Option<CameraPath>,
+	follow_camera_path : bool,
+	debug_line_buffer : WeakPtr<d3d12::ID3D12Resource>,
+	debug_line_buffer_view : d3d12::D3D12_VERTEX_BUFFER_VIEW,
+	debug_line_vertex_count : u32,
+	// Copies of one mesh drawn in a single instanced draw.
+	instanced_mesh : Option<MeshId>,
+	instance_buffer : WeakPtr<d3d12::ID3D12Resource>,
+	instance_buffer_view : d3d12::D3D12_VERTEX_BUFFER_VIEW,
+	instance_count : u32,
+	// Background color, from the scene file.
+	clear_color : [f32; 4],
+}
+
+// Depth test that keeps the nearest fragment for a depth mode.
+fn depth_comparison_func(depth : DepthMode) -> d3d12::D3D12_COMPARISON_FUNC
+{
+	match depth
+	{
+		DepthMode::Standard => d3d12::D3D12_COMPARISON_FUNC_LESS,
+		DepthMode::Reversed => d3d12::D3D12_COMPARISON_FUNC_GREATER,
+	}
+}
+
+// Depth buffer clear value for a depth mode, the far end of its range.
+fn depth_clear_value(depth : DepthMode) -> f32
+{
+	match depth
+	{
+		DepthMode::Standard => d3d12::D3D12_MAX_DEPTH,
+		DepthMode::Reversed => d3d12::D3D12_MIN_DEPTH,
+	}
 }
 
 fn to_wchar(str : &str) -> Vec<u16>
@@ -117,6 +248,65 @@ fn to_cstring(str : &str) -> CString
 	CString::new(str).unwrap()
 }
 
+// Compiles one entry point of an HLSL file, panicking with the compiler's
+// errors if it fails.
+fn compile_shader(shader_path : &[u16], entry_point : &str, target : &str) -> WeakPtr<d3dcommon::ID3DBlob>
+{
+	let compile_flags = if cfg!(debug_assertions)
+	{
+		D3DCOMPILE_DEBUG | D3DCOMPILE_SKIP_OPTIMIZATION
+	}
+	else
+	{
+		0
+	};
+
+	let shader_entry_point = to_cstring(entry_point);
+	let shader_compiler_target = to_cstring(target);
+
+	let mut shader_blob = WeakPtr::<d3dcommon::ID3DBlob>::null();
+	let mut shader_error = WeakPtr::<d3dcommon::ID3DBlob>::null();
+
+	unsafe {
+		let hr_shader_compile = D3DCompileFromFile(
+			shader_path.as_ptr(),
+			ptr::null() as _,
+			ptr::null_mut() as _,
+			shader_entry_point.as_ptr(),
+			shader_compiler_target.as_ptr(),
+			compile_flags,
+			0,
+			shader_blob.mut_void() as *mut *mut d3dcommon::ID3DBlob,
+			shader_error.mut_void() as *mut *mut d3dcommon::ID3DBlob,
+		);
+
+		if !winerror::SUCCEEDED(hr_shader_compile)
+		{
+			let error_result = CString::from(CStr::from_ptr(shader_error.GetBufferPointer() as *const i8));
+			let error_result_str = error_result.to_str().unwrap();
+
+			assert!(
+				winerror::SUCCEEDED(hr_shader_compile),
+				"Failed to compile shader {0}. HRESULT: 0x{1:x} ; path: {2} ; Error {3} ; Shader Blob Error {4}",
+				entry_point,
+				hr_shader_compile,
+				String::from_utf16(shader_path).unwrap(),
+				std::io::Error::from_raw_os_error(hr_shader_compile),
+				error_result_str
+			);
+		}
+
+		assert!(
+			!shader_blob.is_null(),
+			"Failed to create shader {0}. path: {1}",
+			entry_point,
+			String::from_utf16(shader_path).unwrap()
+		);
+	}
+
+	shader_blob
+}
+
 impl Renderer
 {
 	pub fn new() -> Self
@@ -167,22 +357,55 @@ impl Renderer
 			rtv_descriptor_heap : WeakPtr::<d3d12::ID3D12DescriptorHeap>::null(),
 			rtv_descriptor_size : 0,
 			cbv_descriptor_heap : WeakPtr::<d3d12::ID3D12DescriptorHeap>::null(),
+			cbv_descriptor_size : 0,
+			dsv_descriptor_heap : WeakPtr::<d3d12::ID3D12DescriptorHeap>::null(),
+			depth_buffer : WeakPtr::<d3d12::ID3D12Resource>::null(),
 			command_allocators : [WeakPtr::<d3d12::ID3D12CommandAllocator>::null(); G_MAX_FRAME_COUNT],
 			command_list : WeakPtr::<d3d12::ID3D12GraphicsCommandList>::null(),
 			render_targets : [WeakPtr::null(); G_MAX_FRAME_COUNT],
 			root_signature : WeakPtr::<d3d12::ID3D12RootSignature>::null(),
 			pipeline_state : WeakPtr::<d3d12::ID3D12PipelineState>::null(),
+			line_pipeline_state : WeakPtr::<d3d12::ID3D12PipelineState>::null(),
+			instanced_pipeline_state : WeakPtr::<d3d12::ID3D12PipelineState>::null(),
 			frame_count : frame_count,
 			frame_index : 0,
-			vertex_buffer : WeakPtr::<d3d12::ID3D12Resource>::null(),
-			vertex_buffer_view : unsafe { mem::zeroed() },
 			constant_buffer : WeakPtr::<d3d12::ID3D12Resource>::null(),
 			constant_buffer_gpu_handle : CD3D12_GPU_DESCRIPTOR_HANDLE::new(),
 			p_cbv_data : ptr::null_mut(),
 			fence : WeakPtr::<d3d12::ID3D12Fence>::null(),
 			fence_values : [0; G_MAX_FRAME_COUNT],
 			fence_event : ptr::null_mut(),
+			retired_resources : Vec::new(),
 			timer : std::time::Instant::now(),
+			camera : Camera {
+				eye :          Point3::new(0.0, 0.66, -2.5),
+				target :       Point3::new(0.0, 0.66, 0.0),
+				up :           Vector3::unit_y(),
+				fovy :         Rad(FOVY.to_radians()),
+				aspect :       ASPECT_RATIO,
+				near :         0.1,
+				far :          100.0,
+				projection :   Projection::Perspective,
+				depth :        DepthMode::Standard,
+				infinite_far : false,
+			},
+			meshes : Vec::new(),
+			scene : Scene::new(),
+			draws : Vec::new(),
+			view : Matrix4::identity(),
+			projection : Matrix4::identity(),
+			picked : None,
+			frame_stats : FrameStats::default(),
+			camera_path : None,
+			follow_camera_path : false,
+			debug_line_buffer : WeakPtr::<d3d12::ID3D12Resource>::null(),
+			debug_line_buffer_view : unsafe { mem::zeroed() },
+			debug_line_vertex_count : 0,
+			instanced_mesh : None,
+			instance_buffer : WeakPtr::<d3d12::ID3D12Resource>::null(),
+			instance_buffer_view : unsafe { mem::zeroed() },
+			instance_count : 0,
+			clear_color : [0.0, 0.2, 0.4, 1.0],
 		}
 	}
 
@@ -395,7 +618,7 @@ impl Renderer
 		let mut cbv_descriptor_heap = WeakPtr::<d3d12::ID3D12DescriptorHeap>::null();
 		let cbv_descriptor_heap_desc = d3d12::D3D12_DESCRIPTOR_HEAP_DESC {
 			Type :           d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
-			NumDescriptors : 1,
+			NumDescriptors : CONSTANT_BUFFER_SLOTS as u32,
 			Flags :          d3d12::D3D12_DESCRIPTOR_HEAP_FLAG_SHADER_VISIBLE,
 			NodeMask :       G_SINGLE_NODEMASK,
 		};
@@ -412,6 +635,8 @@ impl Renderer
 			cbv_descriptor_heap_hr
 		);
 		self.cbv_descriptor_heap = cbv_descriptor_heap;
+		self.cbv_descriptor_size =
+			unsafe { self.device.GetDescriptorHandleIncrementSize(d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV) };
 		unsafe {
 			let buffer_name : String = String::from("cbv descriptor heap");
 			let buffer_size = u32::try_from(buffer_name.len()).unwrap();
@@ -605,125 +830,26 @@ impl Renderer
 
 		self.root_signature = root_signature;
 
-		let compile_flags = if cfg!(debug_assertions)
-		{
-			D3DCOMPILE_DEBUG | D3DCOMPILE_SKIP_OPTIMIZATION
-		}
-		else
-		{
-			0
-		};
-
 		let shader_path = to_wchar("D:\\Repo\\rust\\rust_raytracer\\src\\shaders.hlsl");
+		let vertex_shader_blob = compile_shader(&shader_path, "VSMain", "vs_5_0");
+		let instanced_vertex_shader_blob = compile_shader(&shader_path, "VSInstanced", "vs_5_0");
+		let pixel_shader_blob = compile_shader(&shader_path, "PSMain", "ps_5_0");
 
-		let vertex_shader_entry_point = to_cstring("VSMain");
-		let vertex_shader_compiler_target = to_cstring("vs_5_0");
-
-		let pixel_shader_entry_point = to_cstring("PSMain");
-		let pixel_shader_compiler_target = to_cstring("ps_5_0");
-
-		let mut vertex_shader_blob = WeakPtr::<d3dcommon::ID3DBlob>::null();
-		let mut vertex_shader_error = WeakPtr::<d3dcommon::ID3DBlob>::null();
-		let mut pixel_shader_blob = WeakPtr::<d3dcommon::ID3DBlob>::null();
-		let mut pixel_shader_error = WeakPtr::<d3dcommon::ID3DBlob>::null();
-
-		unsafe {
-			let hr_vertex_shader_compile = D3DCompileFromFile(
-				shader_path.as_ptr(),
-				ptr::null() as _,
-				ptr::null_mut() as _,
-				vertex_shader_entry_point.as_ptr(),
-				vertex_shader_compiler_target.as_ptr(),
-				compile_flags,
-				0,
-				vertex_shader_blob.mut_void() as *mut *mut d3dcommon::ID3DBlob,
-				vertex_shader_error.mut_void() as *mut *mut d3dcommon::ID3DBlob,
-			);
-
-			if !winerror::SUCCEEDED(hr_vertex_shader_compile)
-			{
-				let error_result = CString::from(CStr::from_ptr(vertex_shader_error.GetBufferPointer() as *const i8));
-				let error_result_str = error_result.to_str().unwrap();
-
-				assert!(
-					winerror::SUCCEEDED(hr_vertex_shader_compile),
-					"Failed to compile vertex shader. HRESULT: 0x{0:x} ; path: {1} ; Error {2} ; Shader Blob Error {3}",
-					hr_vertex_shader_compile,
-					String::from_utf16(&shader_path).unwrap(),
-					std::io::Error::from_raw_os_error(hr_vertex_shader_compile),
-					error_result_str
-				);
-			}
-
-			assert!(
-				!vertex_shader_blob.is_null(),
-				"Failed to create vertex shader. path: {0}",
-				String::from_utf16(&shader_path).unwrap()
-			);
-
-			let hr_pixel_shader_compile = D3DCompileFromFile(
-				shader_path.as_ptr(),
-				ptr::null() as _,
-				ptr::null_mut() as _,
-				pixel_shader_entry_point.as_ptr(),
-				pixel_shader_compiler_target.as_ptr(),
-				compile_flags,
-				0,
-				pixel_shader_blob.mut_void() as *mut *mut d3dcommon::ID3DBlob,
-				pixel_shader_error.mut_void() as *mut *mut d3dcommon::ID3DBlob,
-			);
-
-			if !winerror::SUCCEEDED(hr_pixel_shader_compile)
-			{
-				let error_result = CString::from(CStr::from_ptr(pixel_shader_error.GetBufferPointer() as *const i8));
-				let error_result_str = error_result.to_str().unwrap();
-
-				assert!(
-					winerror::SUCCEEDED(hr_pixel_shader_compile),
-					"Failed to compile pixel shader. HRESULT: 0x{0:x} ; path: {1} ; Error {2} ; Shader Blob Error {3}",
-					hr_pixel_shader_compile,
-					String::from_utf16(&shader_path).unwrap(),
-					std::io::Error::from_raw_os_error(hr_pixel_shader_compile),
-					error_result_str
-				);
-			}
-
-			assert!(
-				!pixel_shader_blob.is_null(),
-				"Failed to create pixel shader. path: {0}",
-				String::from_utf16(&shader_path).unwrap()
-			);
-		}
-
-		let position_semantic = to_cstring("POSITION");
-		let color_semnatic = to_cstring("COLOR");
-
-		let input_element_descs : [d3d12::D3D12_INPUT_ELEMENT_DESC; 2] = [
-			d3d12::D3D12_INPUT_ELEMENT_DESC {
-				SemanticName : position_semantic.as_ptr(),
-				SemanticIndex : 0,
-				Format : dxgiformat::DXGI_FORMAT_R32G32B32_FLOAT,
-				InputSlot : 0,
-				AlignedByteOffset : 0,
-				InputSlotClass : d3d12::D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA,
-				InstanceDataStepRate : 0,
-			},
-			d3d12::D3D12_INPUT_ELEMENT_DESC {
-				SemanticName : color_semnatic.as_ptr(),
-				SemanticIndex : 0,
-				Format : dxgiformat::DXGI_FORMAT_R32G32B32_FLOAT,
-				InputSlot : 0,
-				AlignedByteOffset : 12,
-				InputSlotClass : d3d12::D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA,
-				InstanceDataStepRate : 0,
-			},
-		];
+		let input_layout = InputLayout::new().per_vertex::<ColoredVertex>(0);
+		// Instanced draws add a second stream with one InstanceData per copy.
+		let instanced_input_layout =
+			InputLayout::new().per_vertex::<ColoredVertex>(0).per_instance::<InstanceData>(1, 1);
 
 		let vertex_shader = d3d12::D3D12_SHADER_BYTECODE {
 			BytecodeLength :  unsafe { vertex_shader_blob.GetBufferSize() },
 			pShaderBytecode : unsafe { vertex_shader_blob.GetBufferPointer() },
 		};
 
+		let instanced_vertex_shader = d3d12::D3D12_SHADER_BYTECODE {
+			BytecodeLength :  unsafe { instanced_vertex_shader_blob.GetBufferSize() },
+			pShaderBytecode : unsafe { instanced_vertex_shader_blob.GetBufferPointer() },
+		};
+
 		let pixel_shader = d3d12::D3D12_SHADER_BYTECODE {
 			BytecodeLength :  unsafe { pixel_shader_blob.GetBufferSize() },
 			pShaderBytecode : unsafe { pixel_shader_blob.GetBufferPointer() },
@@ -769,10 +895,12 @@ impl Renderer
 			StencilFunc :        0,
 		};
 
+		// The compare function follows the camera's depth mode, like the depth
+		// buffer's clear value.
 		let depth_stencil_state_desc = d3d12::D3D12_DEPTH_STENCIL_DESC {
-			DepthEnable :      FALSE,
-			DepthWriteMask :   d3d12::D3D12_DEPTH_WRITE_MASK_ZERO,
-			DepthFunc :        d3d12::D3D12_COMPARISON_FUNC_NEVER,
+			DepthEnable :      TRUE,
+			DepthWriteMask :   d3d12::D3D12_DEPTH_WRITE_MASK_ALL,
+			DepthFunc :        depth_comparison_func(self.camera.depth),
 			StencilEnable :    FALSE,
 			StencilReadMask :  0,
 			StencilWriteMask : 0,
@@ -811,15 +939,12 @@ impl Renderer
 			SampleMask : UINT::max_value(),
 			RasterizerState : default_rasterizer_state,
 			DepthStencilState : depth_stencil_state_desc,
-			InputLayout : d3d12::D3D12_INPUT_LAYOUT_DESC {
-				pInputElementDescs : input_element_descs.as_ptr(),
-				NumElements :        input_element_descs.len() as u32,
-			},
+			InputLayout : input_layout.desc(),
 			IBStripCutValue : d3d12::D3D12_INDEX_BUFFER_STRIP_CUT_VALUE_DISABLED,
 			PrimitiveTopologyType : d3d12::D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
 			NumRenderTargets : self.frame_count,
 			RTVFormats : default_rtv_formats,
-			DSVFormat : dxgiformat::DXGI_FORMAT_UNKNOWN,
+			DSVFormat : DEPTH_FORMAT,
 			SampleDesc : dxgitype::DXGI_SAMPLE_DESC {
 				Count :   1,
 				Quality : 0,
@@ -849,6 +974,62 @@ impl Renderer
 		}
 		self.pipeline_state = pipeline;
 
+		// Debug lines reuse the shaders, drawn as line lists without culling.
+		let line_pso_desc = d3d12::D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+			RasterizerState : d3d12::D3D12_RASTERIZER_DESC {
+				CullMode : d3d12::D3D12_CULL_MODE_NONE,
+				..default_rasterizer_state
+			},
+			PrimitiveTopologyType : d3d12::D3D12_PRIMITIVE_TOPOLOGY_TYPE_LINE,
+			..pso_desc
+		};
+
+		let mut line_pipeline = WeakPtr::<d3d12::ID3D12PipelineState>::null();
+		unsafe {
+			let hr_gpstate = self.device.CreateGraphicsPipelineState(
+				&line_pso_desc,
+				&d3d12::ID3D12PipelineState::uuidof(),
+				line_pipeline.mut_void(),
+			);
+
+			assert!(winerror::SUCCEEDED(hr_gpstate), "Failed to create line pipeline state. 0x{:x}", hr_gpstate);
+
+			let buffer_name : String = String::from("line pipeline state");
+			let buffer_size = u32::try_from(buffer_name.len()).unwrap();
+			line_pipeline.SetPrivateData(
+				&d3dcommon::WKPDID_D3DDebugObjectName,
+				buffer_size,
+				buffer_name.as_ptr() as *mut _,
+			);
+		}
+		self.line_pipeline_state = line_pipeline;
+
+		let instanced_pso_desc = d3d12::D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+			VS : instanced_vertex_shader,
+			InputLayout : instanced_input_layout.desc(),
+			..pso_desc
+		};
+
+		let mut instanced_pipeline = WeakPtr::<d3d12::ID3D12PipelineState>::null();
+		unsafe {
+			let hr_gpstate = self.device.CreateGraphicsPipelineState(
+				&instanced_pso_desc,
+				&d3d12::ID3D12PipelineState::uuidof(),
+				instanced_pipeline.mut_void(),
+			);
+
+			assert!(winerror::SUCCEEDED(hr_gpstate), "Failed to create instanced pipeline state. 0x{:x}", hr_gpstate);
+
+			let buffer_name : String = String::from("instanced pipeline state");
+			let buffer_size = u32::try_from(buffer_name.len()).unwrap();
+			instanced_pipeline.SetPrivateData(
+				&d3dcommon::WKPDID_D3DDebugObjectName,
+				buffer_size,
+				buffer_name.as_ptr() as *mut _,
+			);
+		}
+		self.instanced_pipeline_state = instanced_pipeline;
+
 		// Create the Command List
 		let mut command_list = WeakPtr::<d3d12::ID3D12GraphicsCommandList>::null();
 		unsafe {
@@ -873,99 +1054,102 @@ impl Renderer
 		}
 		self.command_list = command_list;
 
-		// Create Triangle Assets
-		// Upload to Vertex Buffer.
+		// Create the depth buffer, cleared to the far end of the depth mode's
+		// range.
 		{
-			let mut triangle_vertices = sample_colored_tetrahedron_vertices();
-			let triangle_vertices_size = std::mem::size_of_val(&triangle_vertices);
-			let triangle_vertices_size_u32 =
-				u32::try_from(triangle_vertices_size).expect("Failed Type Conversion: usize -> u32");
-			let vertex_size = std::mem::size_of_val(&triangle_vertices[0]);
-			let vertex_size_u32 = u32::try_from(vertex_size).expect("Failed Type Conversion: usize -> u32");
+			let mut dsv_descriptor_heap = WeakPtr::<d3d12::ID3D12DescriptorHeap>::null();
+			let dsv_descriptor_heap_desc = d3d12::D3D12_DESCRIPTOR_HEAP_DESC {
+				Type :           d3d12::D3D12_DESCRIPTOR_HEAP_TYPE_DSV,
+				NumDescriptors : 1,
+				Flags :          d3d12::D3D12_DESCRIPTOR_HEAP_FLAG_NONE,
+				NodeMask :       G_SINGLE_NODEMASK,
+			};
+			let dsv_descriptor_heap_hr = unsafe {
+				self.device.CreateDescriptorHeap(
+					&dsv_descriptor_heap_desc,
+					&d3d12::ID3D12DescriptorHeap::uuidof(),
+					dsv_descriptor_heap.mut_void(),
+				)
+			};
+			assert!(
+				winerror::SUCCEEDED(dsv_descriptor_heap_hr),
+				"error on depth stencil descriptor heap creation 0x{:x}",
+				dsv_descriptor_heap_hr
+			);
+			self.dsv_descriptor_heap = dsv_descriptor_heap;
 
 			let default_heap_properties = d3d12::D3D12_HEAP_PROPERTIES {
-				Type : d3d12::D3D12_HEAP_TYPE_UPLOAD,
+				Type : d3d12::D3D12_HEAP_TYPE_DEFAULT,
 				CPUPageProperty : d3d12::D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
 				MemoryPoolPreference : d3d12::D3D12_MEMORY_POOL_UNKNOWN,
 				CreationNodeMask : G_SINGLE_NODEMASK,
 				VisibleNodeMask : G_SINGLE_NODEMASK,
 			};
 
-			let vertex_buffer_resource_desc = d3d12::D3D12_RESOURCE_DESC {
-				Dimension : d3d12::D3D12_RESOURCE_DIMENSION_BUFFER,
+			let depth_buffer_resource_desc = d3d12::D3D12_RESOURCE_DESC {
+				Dimension : d3d12::D3D12_RESOURCE_DIMENSION_TEXTURE2D,
 				Alignment : 0,
-				Width : triangle_vertices_size as u64,
-				Height : 1,
+				Width : G_WIDTH as u64,
+				Height : G_HEIGHT,
 				DepthOrArraySize : 1,
 				MipLevels : 1,
-				Format : dxgiformat::DXGI_FORMAT_UNKNOWN,
+				Format : DEPTH_FORMAT,
 				SampleDesc : dxgitype::DXGI_SAMPLE_DESC {
 					Count :   1,
 					Quality : 0,
 				},
-				Layout : d3d12::D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
-				Flags : d3d12::D3D12_RESOURCE_FLAG_NONE,
+				Layout : d3d12::D3D12_TEXTURE_LAYOUT_UNKNOWN,
+				Flags : d3d12::D3D12_RESOURCE_FLAG_ALLOW_DEPTH_STENCIL,
 			};
 
-			let mut vertex_buffer = WeakPtr::<d3d12::ID3D12Resource>::null();
+			let mut depth_clear = d3d12::D3D12_CLEAR_VALUE {
+				Format : DEPTH_FORMAT,
+				..unsafe { mem::zeroed() }
+			};
+			*unsafe { depth_clear.u.DepthStencil_mut() } = d3d12::D3D12_DEPTH_STENCIL_VALUE {
+				Depth :   depth_clear_value(self.camera.depth),
+				Stencil : 0,
+			};
 
+			let mut depth_buffer = WeakPtr::<d3d12::ID3D12Resource>::null();
 			unsafe {
-				let hr_create_committed_resource = self.device.CreateCommittedResource(
+				let hr_create_depth_buffer = self.device.CreateCommittedResource(
 					&default_heap_properties,
 					d3d12::D3D12_HEAP_FLAG_NONE,
-					&vertex_buffer_resource_desc,
-					d3d12::D3D12_RESOURCE_STATE_GENERIC_READ,
-					ptr::null() as _,
+					&depth_buffer_resource_desc,
+					d3d12::D3D12_RESOURCE_STATE_DEPTH_WRITE,
+					&depth_clear,
 					&d3d12::ID3D12Resource::uuidof(),
-					vertex_buffer.mut_void(),
+					depth_buffer.mut_void(),
 				);
 
 				assert!(
-					winerror::SUCCEEDED(hr_create_committed_resource),
-					"Failed to create vertex buffer. 0x{:x}",
-					hr_create_committed_resource
+					winerror::SUCCEEDED(hr_create_depth_buffer),
+					"Failed to create depth buffer. 0x{:x}",
+					hr_create_depth_buffer
 				);
 
-				let buffer_name : String = String::from("triangle vertex buffer");
+				let buffer_name : String = String::from("depth buffer");
 				let buffer_size = u32::try_from(buffer_name.len()).unwrap();
-				vertex_buffer.SetPrivateData(
+				depth_buffer.SetPrivateData(
 					&d3dcommon::WKPDID_D3DDebugObjectName,
 					buffer_size,
 					buffer_name.as_ptr() as *mut _,
 				);
-			}
 
-			let mut p_vertex_data_begin = ptr::null_mut::<winapi::ctypes::c_void>();
-
-			let read_range = d3d12::D3D12_RANGE {
-				Begin : 0,
-				End :   0,
-			};
-			unsafe {
-				let hr_map = vertex_buffer.Map(0, &read_range, &mut p_vertex_data_begin);
-				assert!(winerror::SUCCEEDED(hr_map), "Failed to map vertex buffer. 0x{:x}", hr_map);
-				assert!(!p_vertex_data_begin.is_null(), "Failed to map vertex buffer. 0x{:x}", hr_map);
-
-				std::ptr::copy_nonoverlapping(
-					triangle_vertices.as_mut_ptr(),
-					p_vertex_data_begin as *mut ColoredVertex,
-					triangle_vertices.len(),
+				self.device.CreateDepthStencilView(
+					depth_buffer.as_mut_ptr(),
+					ptr::null(),
+					self.dsv_descriptor_heap.GetCPUDescriptorHandleForHeapStart(),
 				);
-
-				vertex_buffer.Unmap(0, ptr::null());
 			}
-
-			self.vertex_buffer = vertex_buffer;
-			self.vertex_buffer_view = d3d12::D3D12_VERTEX_BUFFER_VIEW {
-				BufferLocation : unsafe { self.vertex_buffer.GetGPUVirtualAddress() },
-				SizeInBytes :    triangle_vertices_size_u32,
-				StrideInBytes :  vertex_size_u32,
-			};
+			self.depth_buffer = depth_buffer;
 		}
 
 		// Create constant buffer.
 		{
-			let constant_buffer_size = std::mem::size_of::<MatrixConstantBuffer>(); // CB size is required to be 256-byte aligned.
+			let slot_size = std::mem::size_of::<MatrixConstantBuffer>(); // Padded to 256 bytes by constant_buffer!
+			let constant_buffer_size = slot_size * CONSTANT_BUFFER_SLOTS;
 
 			let default_heap_properties = d3d12::D3D12_HEAP_PROPERTIES {
 				Type : d3d12::D3D12_HEAP_TYPE_UPLOAD,
@@ -1019,15 +1203,22 @@ impl Renderer
 				);
 			}
 
-			// Describe and create a constant buffer view.
+			// Describe and create one constant buffer view per slot.
 			unsafe {
-				let cbv_desc = d3d12::D3D12_CONSTANT_BUFFER_VIEW_DESC {
-					BufferLocation : constant_buffer.GetGPUVirtualAddress(),
-					SizeInBytes :    u32::try_from(constant_buffer_size).unwrap(),
-				};
-
-				self.device
-					.CreateConstantBufferView(&cbv_desc, self.cbv_descriptor_heap.GetCPUDescriptorHandleForHeapStart());
+				for slot in 0..CONSTANT_BUFFER_SLOTS
+				{
+					let cbv_desc = d3d12::D3D12_CONSTANT_BUFFER_VIEW_DESC {
+						BufferLocation : constant_buffer.GetGPUVirtualAddress() + (slot * slot_size) as u64,
+						SizeInBytes :    u32::try_from(slot_size).unwrap(),
+					};
+
+					let cbv_handle = CD3D12_CPU_DESCRIPTOR_HANDLE::from_offset(
+						&self.cbv_descriptor_heap.GetCPUDescriptorHandleForHeapStart(),
+						slot as i32,
+						self.cbv_descriptor_size,
+					);
+					self.device.CreateConstantBufferView(&cbv_desc, cbv_handle.0);
+				}
 
 				let cbv_srv_gpu_handle =
 					CD3D12_GPU_DESCRIPTOR_HANDLE::from(self.cbv_descriptor_heap.GetGPUDescriptorHandleForHeapStart());
@@ -1079,33 +1270,489 @@ impl Renderer
 		}
 	}
 
+	// Creates a buffer on the upload heap and copies `size` bytes from `data` into
+	// it. Upload heap buffers are fine for small, static geometry.
+	fn create_upload_buffer(&self, name : &str, data : *const u8, size : usize) -> WeakPtr<d3d12::ID3D12Resource>
+	{
+		let upload_heap_properties = d3d12::D3D12_HEAP_PROPERTIES {
+			Type : d3d12::D3D12_HEAP_TYPE_UPLOAD,
+			CPUPageProperty : d3d12::D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
+			MemoryPoolPreference : d3d12::D3D12_MEMORY_POOL_UNKNOWN,
+			CreationNodeMask : G_SINGLE_NODEMASK,
+			VisibleNodeMask : G_SINGLE_NODEMASK,
+		};
+
+		let buffer_resource_desc = d3d12::D3D12_RESOURCE_DESC {
+			Dimension : d3d12::D3D12_RESOURCE_DIMENSION_BUFFER,
+			Alignment : 0,
+			Width : size as u64,
+			Height : 1,
+			DepthOrArraySize : 1,
+			MipLevels : 1,
+			Format : dxgiformat::DXGI_FORMAT_UNKNOWN,
+			SampleDesc : dxgitype::DXGI_SAMPLE_DESC {
+				Count :   1,
+				Quality : 0,
+			},
+			Layout : d3d12::D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+			Flags : d3d12::D3D12_RESOURCE_FLAG_NONE,
+		};
+
+		let mut buffer = WeakPtr::<d3d12::ID3D12Resource>::null();
+
+		unsafe {
+			let hr_create_committed_resource = self.device.CreateCommittedResource(
+				&upload_heap_properties,
+				d3d12::D3D12_HEAP_FLAG_NONE,
+				&buffer_resource_desc,
+				d3d12::D3D12_RESOURCE_STATE_GENERIC_READ,
+				ptr::null() as _,
+				&d3d12::ID3D12Resource::uuidof(),
+				buffer.mut_void(),
+			);
+
+			assert!(
+				winerror::SUCCEEDED(hr_create_committed_resource),
+				"Failed to create {}. 0x{:x}",
+				name,
+				hr_create_committed_resource
+			);
+
+			let buffer_name : String = String::from(name);
+			let buffer_size = u32::try_from(buffer_name.len()).unwrap();
+			buffer.SetPrivateData(&d3dcommon::WKPDID_D3DDebugObjectName, buffer_size, buffer_name.as_ptr() as *mut _);
+		}
+
+		let mut p_data_begin = ptr::null_mut::<winapi::ctypes::c_void>();
+
+		// We do not intend to read from this resource on the CPU.
+		let read_range = d3d12::D3D12_RANGE {
+			Begin : 0,
+			End :   0,
+		};
+		unsafe {
+			let hr_map = buffer.Map(0, &read_range, &mut p_data_begin);
+			assert!(winerror::SUCCEEDED(hr_map), "Failed to map {}. 0x{:x}", name, hr_map);
+			assert!(!p_data_begin.is_null(), "Failed to map {}. 0x{:x}", name, hr_map);
+
+			std::ptr::copy_nonoverlapping(data, p_data_begin as *mut u8, size);
+
+			buffer.Unmap(0, ptr::null());
+		}
+
+		buffer
+	}
+
+	// Uploads a mesh for scene objects to reference.
+	pub fn add_mesh(&mut self, name : &str, mesh : Mesh<ColoredVertex>) -> MeshId
+	{
+		let vertices_size_u32 =
+			u32::try_from(mesh.vertices_size_in_bytes()).expect("Failed Type Conversion: usize -> u32");
+		let vertex_size_u32 = u32::try_from(ColoredVertex::stride()).expect("Failed Type Conversion: usize -> u32");
+		let indices_size_u32 =
+			u32::try_from(mesh.indices.size_in_bytes()).expect("Failed Type Conversion: usize -> u32");
+		let index_format = match mesh.indices
+		{
+			Indices::U16(_) => dxgiformat::DXGI_FORMAT_R16_UINT,
+			Indices::U32(_) => dxgiformat::DXGI_FORMAT_R32_UINT,
+		};
+
+		let vertex_buffer = self.create_upload_buffer(
+			&format!("{} vertex buffer", name),
+			mesh.vertices.as_ptr() as *const u8,
+			mesh.vertices_size_in_bytes(),
+		);
+		let index_buffer = self.create_upload_buffer(
+			&format!("{} index buffer", name),
+			mesh.indices.as_ptr(),
+			mesh.indices.size_in_bytes(),
+		);
+
+		let vertex_buffer_view = d3d12::D3D12_VERTEX_BUFFER_VIEW {
+			BufferLocation : unsafe { vertex_buffer.GetGPUVirtualAddress() },
+			SizeInBytes :    vertices_size_u32,
+			StrideInBytes :  vertex_size_u32,
+		};
+		let index_buffer_view = d3d12::D3D12_INDEX_BUFFER_VIEW {
+			BufferLocation : unsafe { index_buffer.GetGPUVirtualAddress() },
+			SizeInBytes :    indices_size_u32,
+			Format :         index_format,
+		};
+
+		self.meshes.push(GpuMesh {
+			name : name.to_string(),
+			vertex_buffer : vertex_buffer,
+			vertex_buffer_view : vertex_buffer_view,
+			index_buffer : index_buffer,
+			index_buffer_view : index_buffer_view,
+			index_count : u32::try_from(mesh.indices.len()).expect("Failed Type Conversion: usize -> u32"),
+			bvh : Bvh::new(&mesh),
+			bounds : bounding_sphere(&mesh).expect("The mesh has no vertices."),
+			mesh : mesh,
+		});
+		MeshId(self.meshes.len() - 1)
+	}
+
+	// Replaces the meshes, objects, camera and clear color with those of a
+	// scene description. Mesh and animation files are relative to `directory`.
+	// Everything is loaded before anything is replaced, so on failure the
+	// current scene stays up.
+	pub fn load_scene(&mut self, description : &SceneDescription, directory : &Path) -> Result<(), SceneFileError>
+	{
+		let meshes = scene_file::load_meshes(description, directory)?;
+		let mesh_ids : Vec<MeshId> = (0..meshes.len()).map(MeshId).collect();
+		let scene = scene_file::build_scene(description, &mesh_ids, directory)?;
+
+		self.set_instances(MeshId(0), &[]);
+		let old_meshes : Vec<GpuMesh> = self.meshes.drain(..).collect();
+		for mesh in old_meshes
+		{
+			self.retire_mesh(mesh);
+		}
+
+		for (mesh, description) in meshes.into_iter().zip(&description.meshes)
+		{
+			self.add_mesh(&description.name, mesh);
+		}
+		self.scene = scene;
+		self.draws.clear();
+		self.picked = None;
+		description.camera.apply(&mut self.camera);
+		let color = description.clear_color;
+		self.clear_color = [color.x, color.y, color.z, color.w];
+		Ok(())
+	}
+
+	// Applies a changed description of the scene in use. Only the meshes the
+	// diff says to load are uploaded, and the objects are only rebuilt when
+	// they changed, so objects added while running survive edits to mesh
+	// files. On failure nothing changes.
+	pub fn reload_scene(
+		&mut self, description : &SceneDescription, diff : &SceneDiff, directory : &Path,
+	) -> Result<(), SceneFileError>
+	{
+		let mut loaded = Vec::new();
+		for (mesh, update) in description.meshes.iter().zip(&diff.meshes)
+		{
+			loaded.push(match update
+			{
+				MeshUpdate::Keep(_) => None,
+				MeshUpdate::Load => Some(scene_file::load_mesh(&mesh.source, directory)?),
+			});
+		}
+		let scene = if diff.rebuild_objects
+		{
+			let mesh_ids : Vec<MeshId> = (0..description.meshes.len()).map(MeshId).collect();
+			Some(scene_file::build_scene(description, &mesh_ids, directory)?)
+		}
+		else
+		{
+			None
+		};
+
+		let instanced_mesh = self.instanced_mesh.map(|mesh| self.meshes[mesh.0].name.clone());
+		let mut old_meshes : Vec<Option<GpuMesh>> = self.meshes.drain(..).map(Some).collect();
+		for ((mesh, update), loaded) in description.meshes.iter().zip(&diff.meshes).zip(loaded)
+		{
+			match (update, loaded)
+			{
+				(MeshUpdate::Keep(index), _) =>
+				{
+					self.meshes.push(old_meshes[*index].take().expect("The mesh was kept twice."))
+				}
+				(MeshUpdate::Load, Some(loaded)) =>
+				{
+					self.add_mesh(&mesh.name, loaded);
+				}
+				(MeshUpdate::Load, None) => unreachable!(),
+			}
+		}
+		for mesh in old_meshes.into_iter().flatten()
+		{
+			self.retire_mesh(mesh);
+		}
+
+		// Instances follow their mesh by name.
+		match instanced_mesh.and_then(|name| self.find_mesh(&name))
+		{
+			Some(mesh) => self.instanced_mesh = Some(mesh),
+			None => self.set_instances(MeshId(0), &[]),
+		}
+		if let Some(scene) = scene
+		{
+			self.scene = scene;
+		}
+		if diff.rebuild_objects || diff.loads_meshes()
+		{
+			// Triangle numbers and object ids may mean something else now.
+			self.draws.clear();
+			self.picked = None;
+		}
+		if diff.camera
+		{
+			description.camera.apply(&mut self.camera);
+		}
+		if diff.clear_color
+		{
+			let color = description.clear_color;
+			self.clear_color = [color.x, color.y, color.z, color.w];
+		}
+		Ok(())
+	}
+
+	// Frees the buffers of a mesh that is no longer drawn, once the GPU is done
+	// with them.
+	fn retire_mesh(&mut self, mesh : GpuMesh)
+	{
+		self.retire_resource(mesh.vertex_buffer);
+		self.retire_resource(mesh.index_buffer);
+	}
+
+	// Queues `resource` to be freed once the frames that may use it are done.
+	// Everything recorded so far has finished when the fence reaches the value
+	// of the frame being recorded.
+	fn retire_resource(&mut self, resource : WeakPtr<d3d12::ID3D12Resource>)
+	{
+		if !resource.is_null()
+		{
+			self.retired_resources.push((self.fence_values[self.frame_index], resource));
+		}
+	}
+
+	fn release_retired_resources(&mut self)
+	{
+		let completed_value = unsafe { self.fence.GetCompletedValue() };
+		self.retired_resources.retain(|&(fence_value, resource)| {
+			if fence_value > completed_value
+			{
+				return true;
+			}
+			unsafe { resource.destroy() };
+			false
+		});
+	}
+
+	pub fn find_mesh(&self, name : &str) -> Option<MeshId>
+	{
+		self.meshes.iter().position(|mesh| mesh.name == name).map(MeshId)
+	}
+
+	pub fn scene(&self) -> &Scene
+	{
+		&self.scene
+	}
+
+	// Objects can be added, moved and reparented between frames. Use
+	// remove_object to remove them, so picks are cleared.
+	pub fn scene_mut(&mut self) -> &mut Scene
+	{
+		&mut self.scene
+	}
+
+	pub fn remove_object(&mut self, object : ObjectId) -> Result<Vec<ObjectId>, SceneError>
+	{
+		let removed = self.scene.remove(object)?;
+		if self.picked.map_or(false, |(picked, _)| removed.contains(&picked))
+		{
+			self.picked = None;
+		}
+		Ok(removed)
+	}
+
+	pub fn picked_object(&self) -> Option<ObjectId>
+	{
+		self.picked.map(|(object, _)| object)
+	}
+
 	pub fn update(&mut self)
 	{
 		let time_elapsed = self.timer.elapsed().as_secs_f32();
-		let model = Matrix4::from_angle_y(Rad::from(Deg(time_elapsed * 90.0)));
-
-		let eye = Point3::new(0.0, 0.66, -2.5);
-		let target = Point3::new(0.0, 0.66, 0.0);
-		let up = Vector3::unit_y();
-		let view_lh = transforms::look_at_lh(eye, target, up);
-
-		let perspective = PerspectiveFov {
-			fovy :   cgmath::Rad(FOVY.to_radians()),
-			aspect : ASPECT_RATIO,
-			near :   0.1,
-			far :    100.0,
-		};
 
-		let proj_lh = transforms::perspective_lh(perspective);
+		if let (Some(path), true) = (&self.camera_path, self.follow_camera_path)
+		{
+			let pose = path.pose(time_elapsed);
+			self.camera.eye = pose.eye;
+			self.camera.target = pose.target;
+			self.camera.up = pose.up;
+		}
+
+		self.view = self.camera.view();
+		self.projection = self.camera.projection_matrix();
+		let view_projection = self.projection * self.view;
+
+		let frustum = Frustum::from_matrix(view_projection, self.camera.depth_range());
+		self.frame_stats = FrameStats::default();
+		self.draws.clear();
+		for draw in self.scene.draws(time_elapsed)
+		{
+			let bounds = &self.meshes[draw.mesh.0].bounds;
+			let visible = frustum.intersects_sphere(&bounds.transform(draw.world));
+			if visible && self.draws.len() == MAX_DRAWN_OBJECTS
+			{
+				self.frame_stats.over_limit += 1;
+				continue;
+			}
+			self.frame_stats.culling.record(visible);
+			if visible
+			{
+				self.draws.push(draw);
+			}
+		}
 
-		let buffer_data = MatrixConstantBuffer {
-			mvp_transform : proj_lh * view_lh * model,
-			_padding :      unsafe { std::mem::zeroed() },
+		for (slot, draw) in self.draws.iter().enumerate()
+		{
+			let highlighted_triangle = match self.picked
+			{
+				Some((object, triangle)) if object == draw.object => triangle as i32,
+				_ => -1,
+			};
+			let buffer_data = MatrixConstantBuffer {
+				mvp_transform : view_projection * draw.world,
+				tint : draw.color,
+				highlighted_triangle : highlighted_triangle,
+			};
+			unsafe {
+				std::ptr::copy_nonoverlapping(&buffer_data, self.p_cbv_data.add(slot), 1);
+			}
+		}
+
+		// Debug lines are already in world space.
+		let debug_line_data = MatrixConstantBuffer {
+			mvp_transform : view_projection,
+			tint : Vector4::new(1.0, 1.0, 1.0, 1.0),
+			highlighted_triangle : -1,
+		};
+
+		// Instances carry their own transforms and tints.
+		let instance_data = MatrixConstantBuffer {
+			mvp_transform : view_projection,
+			tint : Vector4::new(1.0, 1.0, 1.0, 1.0),
+			highlighted_triangle : -1,
 		};
 
 		unsafe {
-			std::ptr::copy_nonoverlapping(&buffer_data, self.p_cbv_data, 1);
+			std::ptr::copy_nonoverlapping(&debug_line_data, self.p_cbv_data.add(DEBUG_LINE_CONSTANTS), 1);
+			std::ptr::copy_nonoverlapping(&instance_data, self.p_cbv_data.add(INSTANCE_CONSTANTS), 1);
+		}
+	}
+
+	// Draws `instances` copies of `mesh` in one instanced draw, replacing any
+	// previous instances. An empty slice stops drawing them.
+	pub fn set_instances(&mut self, mesh : MeshId, instances : &[InstanceData])
+	{
+		// The previous instances may still be in flight.
+		self.retire_resource(self.instance_buffer);
+		self.instance_buffer = WeakPtr::<d3d12::ID3D12Resource>::null();
+
+		self.instanced_mesh = None;
+		self.instance_count = 0;
+		if instances.is_empty()
+		{
+			return;
+		}
+
+		let instances_size = instances.len() * InstanceData::stride();
+		self.instance_buffer =
+			self.create_upload_buffer("instance buffer", instances.as_ptr() as *const u8, instances_size);
+		self.instance_buffer_view = d3d12::D3D12_VERTEX_BUFFER_VIEW {
+			BufferLocation : unsafe { self.instance_buffer.GetGPUVirtualAddress() },
+			SizeInBytes :    u32::try_from(instances_size).expect("Failed Type Conversion: usize -> u32"),
+			StrideInBytes :  u32::try_from(InstanceData::stride()).expect("Failed Type Conversion: usize -> u32"),
+		};
+		self.instance_count = u32::try_from(instances.len()).expect("Failed Type Conversion: usize -> u32");
+		self.instanced_mesh = Some(mesh);
+	}
+
+	// Sets the path the camera can follow and uploads its preview lines. Call
+	// after load_assets.
+	pub fn set_camera_path(&mut self, path : CameraPath)
+	{
+		// The previous lines may still be in flight.
+		self.retire_resource(self.debug_line_buffer);
+
+		let lines = path.debug_lines();
+		let lines_size = lines.len() * ColoredVertex::stride();
+		self.debug_line_buffer =
+			self.create_upload_buffer("camera path debug lines", lines.as_ptr() as *const u8, lines_size);
+		self.debug_line_buffer_view = d3d12::D3D12_VERTEX_BUFFER_VIEW {
+			BufferLocation : unsafe { self.debug_line_buffer.GetGPUVirtualAddress() },
+			SizeInBytes :    u32::try_from(lines_size).expect("Failed Type Conversion: usize -> u32"),
+			StrideInBytes :  u32::try_from(ColoredVertex::stride()).expect("Failed Type Conversion: usize -> u32"),
+		};
+		self.debug_line_vertex_count = u32::try_from(lines.len()).expect("Failed Type Conversion: usize -> u32");
+		self.camera_path = Some(path);
+	}
+
+	// Starts or stops flying the camera along the camera path, if there is one.
+	pub fn toggle_camera_path(&mut self)
+	{
+		self.follow_camera_path = !self.follow_camera_path && self.camera_path.is_some();
+	}
+
+	// Switches the camera between perspective and orthographic.
+	pub fn toggle_projection(&mut self)
+	{
+		self.camera.toggle_projection();
+	}
+
+	// Selects reverse-Z and an infinite far plane. The depth compare function
+	// is part of the pipeline state and the depth buffer is created with its
+	// clear value, so call this before load_assets.
+	pub fn set_depth_mode(&mut self, depth : DepthMode, infinite_far : bool)
+	{
+		self.camera.depth = depth;
+		self.camera.infinite_far = infinite_far;
+	}
+
+	pub fn frame_stats(&self) -> FrameStats
+	{
+		self.frame_stats
+	}
+
+	// Picks the triangle under the cursor, given relative to the viewport from
+	// (0, 0) at the top left to (1, 1) at the bottom right. The picked triangle
+	// is highlighted until the next pick, which clears it on a miss.
+	pub fn pick(&mut self, x : f32, y : f32)
+	{
+		let viewport = Viewport {
+			x :         self.viewport.TopLeftX,
+			y :         self.viewport.TopLeftY,
+			width :     self.viewport.Width,
+			height :    self.viewport.Height,
+			min_depth : self.viewport.MinDepth,
+			max_depth : self.viewport.MaxDepth,
+		};
+		let cursor = Point2::new(viewport.x + x * viewport.width, viewport.y + y * viewport.height);
+
+		let ray = match Ray::from_cursor(cursor, self.view, self.projection, &viewport, self.camera.depth_range())
+		{
+			Some(ray) => ray,
+			None =>
+			{
+				self.picked = None;
+				return;
+			}
+		};
+
+		// Each object is hit in its model space, where its mesh hierarchy was built.
+		// Transforming the ray keeps distances along it comparable between objects.
+		let mut closest : Option<(ObjectId, MeshHit)> = None;
+		for draw in &self.draws
+		{
+			let mesh = &self.meshes[draw.mesh.0];
+			let hit = draw
+				.world
+				.invert()
+				.and_then(|inverse_world| mesh.bvh.intersect(&mesh.mesh, &ray.transform(inverse_world)));
+			if let Some(hit) = hit
+			{
+				if closest.as_ref().map_or(true, |(_, closest_hit)| hit.hit.t < closest_hit.hit.t)
+				{
+					closest = Some((draw.object, hit));
+				}
+			}
 		}
+		self.picked = closest.map(|(object, hit)| (object, hit.triangle));
 	}
 
 	pub fn render(&mut self) -> i32
@@ -1168,10 +1815,7 @@ impl Renderer
 			let mut descriptor_heaps = [self.cbv_descriptor_heap.as_mut_ptr()];
 			self.command_list.SetDescriptorHeaps(descriptor_heaps.len() as u32, descriptor_heaps.as_mut_ptr());
 			const CBV_SLOT : u32 = 0;
-			self.command_list.SetGraphicsRootDescriptorTable(
-				CBV_SLOT,
-				self.cbv_descriptor_heap.GetGPUDescriptorHandleForHeapStart(),
-			);
+			let cbv_heap_gpu_handle = self.cbv_descriptor_heap.GetGPUDescriptorHandleForHeapStart();
 
 			self.command_list.RSSetViewports(1, &self.viewport);
 			self.command_list.RSSetScissorRects(1, &self.scissor_rect);
@@ -1198,22 +1842,76 @@ impl Renderer
 				self.frame_index as i32,
 				self.rtv_descriptor_size,
 			);
-			self.command_list.OMSetRenderTargets(1, &rtv_handle.0, FALSE, ptr::null());
-
-			let clear_color : [f32; 4] = [0.0, 0.2, 0.4, 1.0];
-			self.command_list.ClearRenderTargetView(rtv_handle.0, &clear_color, 0, ptr::null());
-			self.command_list.IASetPrimitiveTopology(d3dcommon::D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
-			self.command_list.IASetVertexBuffers(0, 1, &self.vertex_buffer_view);
-			let vertex_count = 12; // TODO: Make this not hardcoded.
-			let instance_count = 1;
-			let start_vertex_location = 0;
-			let start_instance_location = 0;
-			self.command_list.DrawInstanced(
-				vertex_count,
-				instance_count,
-				start_vertex_location,
-				start_instance_location,
+			let dsv_handle = self.dsv_descriptor_heap.GetCPUDescriptorHandleForHeapStart();
+			self.command_list.OMSetRenderTargets(1, &rtv_handle.0, FALSE, &dsv_handle);
+
+			self.command_list.ClearRenderTargetView(rtv_handle.0, &self.clear_color, 0, ptr::null());
+			self.command_list.ClearDepthStencilView(
+				dsv_handle,
+				d3d12::D3D12_CLEAR_FLAG_DEPTH,
+				depth_clear_value(self.camera.depth),
+				0,
+				0,
+				ptr::null(),
 			);
+			self.command_list.IASetPrimitiveTopology(d3dcommon::D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+
+			// One draw per visible object, each with its own constants.
+			for (slot, draw) in self.draws.iter().enumerate()
+			{
+				let object_constants = CD3D12_GPU_DESCRIPTOR_HANDLE::from_offset(
+					&cbv_heap_gpu_handle,
+					slot as i32,
+					self.cbv_descriptor_size,
+				);
+				self.command_list.SetGraphicsRootDescriptorTable(CBV_SLOT, object_constants.0);
+
+				let mesh = &self.meshes[draw.mesh.0];
+				self.command_list.IASetVertexBuffers(0, 1, &mesh.vertex_buffer_view);
+				self.command_list.IASetIndexBuffer(&mesh.index_buffer_view);
+				let instance_count = 1;
+				let start_index_location = 0;
+				let base_vertex_location = 0;
+				let start_instance_location = 0;
+				self.command_list.DrawIndexedInstanced(
+					mesh.index_count,
+					instance_count,
+					start_index_location,
+					base_vertex_location,
+					start_instance_location,
+				);
+			}
+
+			if let Some(mesh) = self.instanced_mesh
+			{
+				let instance_constants = CD3D12_GPU_DESCRIPTOR_HANDLE::from_offset(
+					&cbv_heap_gpu_handle,
+					INSTANCE_CONSTANTS as i32,
+					self.cbv_descriptor_size,
+				);
+				self.command_list.SetPipelineState(self.instanced_pipeline_state.as_mut_ptr());
+				self.command_list.SetGraphicsRootDescriptorTable(CBV_SLOT, instance_constants.0);
+
+				let mesh = &self.meshes[mesh.0];
+				let vertex_buffer_views = [mesh.vertex_buffer_view, self.instance_buffer_view];
+				self.command_list.IASetVertexBuffers(0, vertex_buffer_views.len() as u32, vertex_buffer_views.as_ptr());
+				self.command_list.IASetIndexBuffer(&mesh.index_buffer_view);
+				self.command_list.DrawIndexedInstanced(mesh.index_count, self.instance_count, 0, 0, 0);
+			}
+
+			if self.debug_line_vertex_count > 0
+			{
+				let debug_line_constants = CD3D12_GPU_DESCRIPTOR_HANDLE::from_offset(
+					&cbv_heap_gpu_handle,
+					DEBUG_LINE_CONSTANTS as i32,
+					self.cbv_descriptor_size,
+				);
+				self.command_list.SetPipelineState(self.line_pipeline_state.as_mut_ptr());
+				self.command_list.SetGraphicsRootDescriptorTable(CBV_SLOT, debug_line_constants.0);
+				self.command_list.IASetPrimitiveTopology(d3dcommon::D3D_PRIMITIVE_TOPOLOGY_LINELIST);
+				self.command_list.IASetVertexBuffers(0, 1, &self.debug_line_buffer_view);
+				self.command_list.DrawInstanced(self.debug_line_vertex_count, 1, 0, 0);
+			}
 
 			let mut resource_barrier_end = d3d12::D3D12_RESOURCE_BARRIER {
 				Type : d3d12::D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
@@ -1316,5 +2014,7 @@ impl Renderer
 
 		// Ready to begin the next frame. Set the fence value for the next frame.
 		self.fence_values[next_fence_index] = current_fence_value + 1;
+
+		self.release_retired_resources();
 	}
 }
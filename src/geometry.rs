@@ -1,7 +1,29 @@
+use crate::{
+	transforms,
+	vertex_layout::{
+		VertexElement,
+		VertexLayout,
+	},
+};
 use cgmath::*;
-use crate::transforms;
+use std::collections::HashMap;
 
-#[derive(Debug, Copy, Clone)]
+pub mod bvh;
+pub mod dump;
+pub mod frustum;
+pub mod gltf;
+pub mod obj;
+pub mod optimize;
+pub mod ply;
+pub mod primitives;
+pub mod processing;
+pub mod ray;
+pub mod simplify;
+pub mod stl;
+pub mod subdivide;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct ColoredVertex
 {
 	#[allow(dead_code)]
@@ -11,6 +33,342 @@ pub struct ColoredVertex
 	color : Vector4<f32>,
 }
 
+// Index storage for a Mesh. 16-bit indices are used whenever the vertex count
+// allows it, since they halve the index buffer size.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Indices
+{
+	U16(Vec<u16>),
+	U32(Vec<u32>),
+}
+
+impl Indices
+{
+	// Picks the narrowest storage that can address `vertex_count` vertices.
+	pub fn from_u32(indices : Vec<u32>, vertex_count : usize) -> Self
+	{
+		if vertex_count <= (u16::max_value() as usize) + 1
+		{
+			Indices::U16(indices.iter().map(|&i| i as u16).collect())
+		}
+		else
+		{
+			Indices::U32(indices)
+		}
+	}
+
+	pub fn len(&self) -> usize
+	{
+		match self
+		{
+			Indices::U16(indices) => indices.len(),
+			Indices::U32(indices) => indices.len(),
+		}
+	}
+
+	#[allow(dead_code)]
+	pub fn is_empty(&self) -> bool
+	{
+		self.len() == 0
+	}
+
+	pub fn get(&self, n : usize) -> u32
+	{
+		match self
+		{
+			Indices::U16(indices) => indices[n] as u32,
+			Indices::U32(indices) => indices[n],
+		}
+	}
+
+	pub fn to_u32(&self) -> Vec<u32>
+	{
+		match self
+		{
+			Indices::U16(indices) => indices.iter().map(|&i| i as u32).collect(),
+			Indices::U32(indices) => indices.clone(),
+		}
+	}
+
+	// Size of a single index in bytes.
+	pub fn stride(&self) -> usize
+	{
+		match self
+		{
+			Indices::U16(_) => std::mem::size_of::<u16>(),
+			Indices::U32(_) => std::mem::size_of::<u32>(),
+		}
+	}
+
+	pub fn as_ptr(&self) -> *const u8
+	{
+		match self
+		{
+			Indices::U16(indices) => indices.as_ptr() as *const u8,
+			Indices::U32(indices) => indices.as_ptr() as *const u8,
+		}
+	}
+
+	pub fn size_in_bytes(&self) -> usize
+	{
+		self.len() * self.stride()
+	}
+}
+
+// Indexed triangle list. Every three indices form one triangle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh<V>
+{
+	pub vertices : Vec<V>,
+	pub indices :  Indices,
+}
+
+impl<V : Copy> Mesh<V>
+{
+	pub fn new(vertices : Vec<V>, indices : Vec<u32>) -> Self
+	{
+		assert!(indices.len() % 3 == 0, "Mesh indices must form a triangle list. len: {}", indices.len());
+		assert!(
+			indices.iter().all(|&i| (i as usize) < vertices.len()),
+			"Mesh index out of range of {} vertices",
+			vertices.len()
+		);
+
+		let indices = Indices::from_u32(indices, vertices.len());
+		Self {
+			vertices : vertices,
+			indices :  indices,
+		}
+	}
+
+	// Builds an indexed mesh from a non-indexed triangle list, sharing vertices
+	// with the same bit patterns.
+	pub fn from_triangle_soup(soup : &[V]) -> Self
+	where
+		V : VertexLayout,
+	{
+		let elements = V::elements();
+		let mut vertices : Vec<V> = Vec::new();
+		let mut indices : Vec<u32> = Vec::with_capacity(soup.len());
+		let mut shared : HashMap<Vec<u32>, u32> = HashMap::with_capacity(soup.len());
+
+		for vertex in soup
+		{
+			let index = *shared.entry(vertex_bits(vertex, &elements)).or_insert_with(|| {
+				vertices.push(*vertex);
+				(vertices.len() - 1) as u32
+			});
+			indices.push(index);
+		}
+
+		Self::new(vertices, indices)
+	}
+
+	// Expands the mesh back into a non-indexed triangle list.
+	pub fn to_triangle_soup(&self) -> Vec<V>
+	{
+		(0..self.indices.len()).map(|n| self.vertices[self.indices.get(n) as usize]).collect()
+	}
+
+	pub fn triangle_count(&self) -> usize
+	{
+		self.indices.len() / 3
+	}
+
+	pub fn triangle(&self, n : usize) -> [u32; 3]
+	{
+		[self.indices.get(n * 3), self.indices.get(n * 3 + 1), self.indices.get(n * 3 + 2)]
+	}
+
+	pub fn triangles<'a>(&'a self) -> impl Iterator<Item = [u32; 3]> + 'a
+	{
+		(0..self.triangle_count()).map(move |n| self.triangle(n))
+	}
+
+	// Converts every vertex, keeping the same topology.
+	pub fn map_vertices<U : Copy, F : Fn(&V) -> U>(&self, f : F) -> Mesh<U>
+	{
+		Mesh {
+			vertices : self.vertices.iter().map(f).collect(),
+			indices :  self.indices.clone(),
+		}
+	}
+
+	pub fn vertices_size_in_bytes(&self) -> usize
+	{
+		self.vertices.len() * std::mem::size_of::<V>()
+	}
+}
+
+// The bit patterns of a vertex's attributes, for hashing. Only the attributes
+// are read, as padding between them is never initialized.
+fn vertex_bits<V : VertexLayout>(vertex : &V, elements : &[VertexElement]) -> Vec<u32>
+{
+	let base = vertex as *const V as *const u8;
+	let mut bits = Vec::with_capacity(std::mem::size_of::<V>() / 4);
+	for element in elements
+	{
+		for word in 0..element.format.size() / 4
+		{
+			// Every vertex format is made of 32-bit floats.
+			bits.push(unsafe { std::ptr::read_unaligned(base.add(element.offset + word * 4) as *const u32) });
+		}
+	}
+	bits
+}
+
+// General purpose vertex, as produced by the procedural generators and
+// loaders.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshVertex
+{
+	pub position : Vector3<f32>,
+	pub normal :   Vector3<f32>,
+	pub uv :       Vector2<f32>,
+	pub color :    Vector4<f32>,
+}
+
+crate::impl_vertex_layout!(ColoredVertex {
+	position => "POSITION",
+	color => "COLOR",
+});
+
+crate::impl_vertex_layout!(MeshVertex {
+	position => "POSITION",
+	normal => "NORMAL",
+	uv => "TEXCOORD",
+	color => "COLOR",
+});
+
+impl ColoredVertex
+{
+	pub fn new(position : Vector3<f32>, color : Vector4<f32>) -> Self
+	{
+		ColoredVertex {
+			position : position,
+			color :    color,
+		}
+	}
+}
+
+impl From<MeshVertex> for ColoredVertex
+{
+	fn from(vertex : MeshVertex) -> Self
+	{
+		ColoredVertex {
+			position : vertex.position,
+			color :    vertex.color,
+		}
+	}
+}
+
+// Without normal and texture coordinates, which are left as zero.
+impl From<ColoredVertex> for MeshVertex
+{
+	fn from(vertex : ColoredVertex) -> Self
+	{
+		MeshVertex {
+			position : vertex.position,
+			normal :   Vector3::zero(),
+			uv :       Vector2::zero(),
+			color :    vertex.color,
+		}
+	}
+}
+
+// Vertex types that the generic mesh operations (welding, bounds, ...) can work
+// on.
+pub trait Vertex: Copy
+{
+	fn position(&self) -> Vector3<f32>;
+
+	// Copy of this vertex moved to `position`, other attributes unchanged.
+	fn with_position(&self, position : Vector3<f32>) -> Self;
+
+	// True if every attribute of `other` is within `epsilon` of this vertex.
+	fn approx_eq(&self, other : &Self, epsilon : f32) -> bool;
+
+	// Interpolates every attribute, with `t` = 0 giving this vertex.
+	fn lerp(&self, other : &Self, t : f32) -> Self;
+}
+
+fn approx_eq_components<T : Array<Element = f32>>(a : T, b : T, epsilon : f32) -> bool
+{
+	(0..T::len()).all(|n| (a[n] - b[n]).abs() <= epsilon)
+}
+
+impl Vertex for ColoredVertex
+{
+	fn position(&self) -> Vector3<f32>
+	{
+		self.position
+	}
+
+	fn with_position(&self, position : Vector3<f32>) -> Self
+	{
+		ColoredVertex {
+			position : position,
+			..*self
+		}
+	}
+
+	fn approx_eq(&self, other : &Self, epsilon : f32) -> bool
+	{
+		approx_eq_components(self.position, other.position, epsilon)
+			&& approx_eq_components(self.color, other.color, epsilon)
+	}
+
+	fn lerp(&self, other : &Self, t : f32) -> Self
+	{
+		ColoredVertex {
+			position : self.position.lerp(other.position, t),
+			color :    self.color.lerp(other.color, t),
+		}
+	}
+}
+
+impl Vertex for MeshVertex
+{
+	fn position(&self) -> Vector3<f32>
+	{
+		self.position
+	}
+
+	fn with_position(&self, position : Vector3<f32>) -> Self
+	{
+		MeshVertex {
+			position : position,
+			..*self
+		}
+	}
+
+	fn approx_eq(&self, other : &Self, epsilon : f32) -> bool
+	{
+		approx_eq_components(self.position, other.position, epsilon)
+			&& approx_eq_components(self.normal, other.normal, epsilon)
+			&& approx_eq_components(self.uv, other.uv, epsilon)
+			&& approx_eq_components(self.color, other.color, epsilon)
+	}
+
+	// The normal is interpolated linearly and renormalized.
+	fn lerp(&self, other : &Self, t : f32) -> Self
+	{
+		let mut normal = self.normal.lerp(other.normal, t);
+		if normal.magnitude2() > 0.0
+		{
+			normal = normal.normalize();
+		}
+
+		MeshVertex {
+			position : self.position.lerp(other.position, t),
+			normal :   normal,
+			uv :       self.uv.lerp(other.uv, t),
+			color :    self.color.lerp(other.color, t),
+		}
+	}
+}
+
 #[allow(dead_code)]
 const SAMPLE_ASPECT_RATIO : f32 = 1280.0 / 720.0;
 
@@ -61,25 +419,24 @@ pub fn sample_colored_triangle_vertices_eye_pre_projected(fovy : f32, aspect_rat
 
 	// Create Perspective Projection transform
 	let perspective = PerspectiveFov {
-		fovy : cgmath::Rad(fovy.to_radians()),
+		fovy :   cgmath::Rad(fovy.to_radians()),
 		aspect : aspect_ratio,
-		near : 0.1,
-		far : 100.0};
+		near :   0.1,
+		far :    100.0,
+	};
 
 	// Create the view-Projection transform
 	let proj_lh = transforms::perspective_lh(perspective);
 	let view_proj = proj_lh * view_lh;
 
-	// Sample Vertices for an Equilateral triangle (might be a little off from truly equilateral)
-	let sample_triangle_vertices : [Vector3<f32>; 3] = [
-		Vector3::new(-1.0, 0.0, 0.0),
-		Vector3::new(0.0, 4.0 / 6.0_f32.sqrt(), 0.0),
-		Vector3::new(1.0, 0.0, 0.0),
-	];
+	// Sample Vertices for an Equilateral triangle (might be a little off from truly
+	// equilateral)
+	let sample_triangle_vertices : [Vector3<f32>; 3] =
+		[Vector3::new(-1.0, 0.0, 0.0), Vector3::new(0.0, 4.0 / 6.0_f32.sqrt(), 0.0), Vector3::new(1.0, 0.0, 0.0)];
 
 	// trasform to projection space
-	let vleft  = view_proj * sample_triangle_vertices[0].extend(1.0);
-	let vtop   = view_proj * sample_triangle_vertices[1].extend(1.0);
+	let vleft = view_proj * sample_triangle_vertices[0].extend(1.0);
+	let vtop = view_proj * sample_triangle_vertices[1].extend(1.0);
 	let vright = view_proj * sample_triangle_vertices[2].extend(1.0);
 
 	// perspective divide (normally done by gpu pipeline)
@@ -145,3 +502,78 @@ pub fn sample_colored_tetrahedron_vertices() -> [ColoredVertex; 12]
 
 	return colored_vertices;
 }
+
+#[allow(dead_code)]
+pub fn sample_colored_triangle_mesh_pre_projected(aspect_ratio : f32) -> Mesh<ColoredVertex>
+{
+	Mesh::from_triangle_soup(&sample_colored_triangle_vertices_pre_projected(aspect_ratio))
+}
+
+#[allow(dead_code)]
+pub fn sample_colored_triangle_mesh_eye_pre_projected(fovy : f32, aspect_ratio : f32) -> Mesh<ColoredVertex>
+{
+	Mesh::from_triangle_soup(&sample_colored_triangle_vertices_eye_pre_projected(fovy, aspect_ratio))
+}
+
+// Sample colored tetrahedron, sharing the 4 tip vertices between its faces.
+#[allow(dead_code)]
+pub fn sample_colored_tetrahedron_mesh() -> Mesh<ColoredVertex>
+{
+	Mesh::from_triangle_soup(&sample_colored_tetrahedron_vertices())
+}
+
+#[cfg(test)]
+mod geometry_tests
+{
+	use crate::geometry::*;
+
+	#[test]
+	fn test_tetrahedron_mesh_shares_vertices()
+	{
+		let mesh = sample_colored_tetrahedron_mesh();
+		assert_eq!(4, mesh.vertices.len());
+		assert_eq!(12, mesh.indices.len());
+		assert_eq!(4, mesh.triangle_count());
+		assert_eq!(Indices::U16(vec![0, 1, 2, 3, 1, 0, 2, 1, 3, 0, 2, 3]), mesh.indices);
+	}
+
+	#[test]
+	fn test_triangle_soup_round_trip()
+	{
+		let soup = sample_colored_tetrahedron_vertices();
+		let mesh = Mesh::from_triangle_soup(&soup);
+		assert_eq!(soup.to_vec(), mesh.to_triangle_soup());
+	}
+
+	#[test]
+	fn test_triangle_soup_shares_vertices()
+	{
+		let sphere = primitives::icosphere(1.0, 3);
+		let mesh = Mesh::from_triangle_soup(&sphere.to_triangle_soup());
+		assert_eq!(sphere.vertices.len(), mesh.vertices.len());
+		assert_eq!(sphere.to_triangle_soup(), mesh.to_triangle_soup());
+
+		// Only identical bits are shared.
+		let a = ColoredVertex {
+			position : Vector3::new(0.0, 0.0, 0.0),
+			color :    Vector4::new(1.0, 1.0, 1.0, 1.0),
+		};
+		let b = ColoredVertex {
+			position : Vector3::new(-0.0, 0.0, 0.0),
+			..a
+		};
+		assert_eq!(2, Mesh::from_triangle_soup(&[a, b, a]).vertices.len());
+	}
+
+	#[test]
+	fn test_index_width()
+	{
+		let small = Indices::from_u32(vec![0, 1, 65535], 65536);
+		assert_eq!(2, small.stride());
+		assert_eq!(6, small.size_in_bytes());
+
+		let large = Indices::from_u32(vec![0, 1, 65536], 65537);
+		assert_eq!(4, large.stride());
+		assert_eq!(65536, large.get(2));
+	}
+}
@@ -0,0 +1,317 @@
+// Bounding volume hierarchy over the triangles of a mesh, to find the
+// triangle a ray hits without testing every triangle.
+//
+// Nodes are boxes around their triangles, split at the median centroid along
+// the widest axis until a few triangles are left. The tree is built for one
+// set of positions, so a mesh that is deformed needs a new one. Rigid
+// motion does not: transform the ray into model space instead.
+use super::{
+	processing::Aabb,
+	ray::{
+		Ray,
+		TriangleHit,
+	},
+	Mesh,
+	Vertex,
+};
+use cgmath::*;
+
+// Leaves hold at most this many triangles.
+const MAX_LEAF_TRIANGLES : usize = 4;
+
+#[derive(Debug, Clone)]
+struct BvhNode
+{
+	bounds : Aabb,
+	// Children for inner nodes, a range of `Bvh::triangles` for leaves.
+	kind :   BvhNodeKind,
+}
+
+#[derive(Debug, Clone)]
+enum BvhNodeKind
+{
+	Inner
+	{
+		left :  usize,
+		right : usize,
+	},
+	Leaf
+	{
+		first : usize,
+		count : usize,
+	},
+}
+
+#[derive(Debug, Clone)]
+pub struct Bvh
+{
+	nodes :     Vec<BvhNode>,
+	// Triangle numbers, grouped by leaf.
+	triangles : Vec<usize>,
+}
+
+// The closest triangle a ray hit.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshHit
+{
+	pub triangle : usize,
+	pub hit :      TriangleHit,
+}
+
+fn triangle_points<V : Vertex>(mesh : &Mesh<V>, triangle : usize) -> [Point3<f32>; 3]
+{
+	let [a, b, c] = mesh.triangle(triangle);
+	let p = |n : u32| Point3::from_vec(mesh.vertices[n as usize].position());
+	[p(a), p(b), p(c)]
+}
+
+impl Bvh
+{
+	pub fn new<V : Vertex>(mesh : &Mesh<V>) -> Self
+	{
+		let bounds : Vec<Aabb> = (0..mesh.triangle_count())
+			.map(|t| Aabb::from_points(triangle_points(mesh, t).iter().map(|p| p.to_vec())).unwrap())
+			.collect();
+		let centroids : Vec<Vector3<f32>> = bounds.iter().map(|b| b.center()).collect();
+
+		let mut bvh = Bvh {
+			nodes :     Vec::new(),
+			triangles : (0..mesh.triangle_count()).collect(),
+		};
+		if !bvh.triangles.is_empty()
+		{
+			bvh.build(0, bvh.triangles.len(), &bounds, &centroids);
+		}
+		bvh
+	}
+
+	// Adds the node for triangles[first..first + count] and returns its index.
+	fn build(&mut self, first : usize, count : usize, bounds : &[Aabb], centroids : &[Vector3<f32>]) -> usize
+	{
+		let triangles = &mut self.triangles[first..first + count];
+		let node_bounds = triangles.iter().skip(1).fold(bounds[triangles[0]], |b, &t| b.union(&bounds[t]));
+
+		let index = self.nodes.len();
+		self.nodes.push(BvhNode {
+			bounds : node_bounds,
+			kind :   BvhNodeKind::Leaf {
+				first : first,
+				count : count,
+			},
+		});
+		if count <= MAX_LEAF_TRIANGLES
+		{
+			return index;
+		}
+
+		let centroid_bounds = Aabb::from_points(triangles.iter().map(|&t| centroids[t])).unwrap();
+		let extents = centroid_bounds.extents();
+		let axis = if extents.x >= extents.y && extents.x >= extents.z
+		{
+			0
+		}
+		else if extents.y >= extents.z
+		{
+			1
+		}
+		else
+		{
+			2
+		};
+
+		let half = count / 2;
+		triangles.select_nth_unstable_by(half, |&a, &b| {
+			centroids[a][axis].partial_cmp(&centroids[b][axis]).unwrap_or(std::cmp::Ordering::Equal)
+		});
+
+		let left = self.build(first, half, bounds, centroids);
+		let right = self.build(first + half, count - half, bounds, centroids);
+		self.nodes[index].kind = BvhNodeKind::Inner {
+			left :  left,
+			right : right,
+		};
+		index
+	}
+
+	// The closest triangle of `mesh`, which must be the mesh the hierarchy was
+	// built for, that `ray` hits.
+	pub fn intersect<V : Vertex>(&self, mesh : &Mesh<V>, ray : &Ray) -> Option<MeshHit>
+	{
+		let mut closest : Option<MeshHit> = None;
+		let mut stack = Vec::new();
+		if !self.nodes.is_empty()
+		{
+			stack.push(0);
+		}
+
+		while let Some(index) = stack.pop()
+		{
+			let node = &self.nodes[index];
+			match ray.intersect_aabb(&node.bounds)
+			{
+				Some((t_enter, _)) if closest.is_none_or(|c| t_enter <= c.hit.t) => (),
+				_ => continue,
+			}
+
+			match node.kind
+			{
+				BvhNodeKind::Inner {
+					left,
+					right,
+				} =>
+				{
+					stack.push(left);
+					stack.push(right);
+				}
+				BvhNodeKind::Leaf {
+					first,
+					count,
+				} =>
+				{
+					for &triangle in &self.triangles[first..first + count]
+					{
+						let [a, b, c] = triangle_points(mesh, triangle);
+						if let Some(hit) = ray.intersect_triangle(a, b, c)
+						{
+							if closest.is_none_or(|c| hit.t < c.hit.t)
+							{
+								closest = Some(MeshHit {
+									triangle : triangle,
+									hit :      hit,
+								});
+							}
+						}
+					}
+				}
+			}
+		}
+
+		closest
+	}
+
+	pub fn depth(&self) -> usize
+	{
+		fn depth(nodes : &[BvhNode], index : usize) -> usize
+		{
+			match nodes[index].kind
+			{
+				BvhNodeKind::Inner {
+					left,
+					right,
+				} => 1 + depth(nodes, left).max(depth(nodes, right)),
+				BvhNodeKind::Leaf {
+					..
+				} => 1,
+			}
+		}
+
+		if self.nodes.is_empty()
+		{
+			0
+		}
+		else
+		{
+			depth(&self.nodes, 0)
+		}
+	}
+}
+
+// The closest triangle `ray` hits, testing every triangle. Fine for small
+// meshes, and the reference the hierarchy is tested against.
+#[allow(dead_code)]
+pub fn intersect_brute_force<V : Vertex>(mesh : &Mesh<V>, ray : &Ray) -> Option<MeshHit>
+{
+	(0..mesh.triangle_count())
+		.filter_map(|triangle| {
+			let [a, b, c] = triangle_points(mesh, triangle);
+			ray.intersect_triangle(a, b, c).map(|hit| MeshHit {
+				triangle : triangle,
+				hit :      hit,
+			})
+		})
+		.min_by(|a, b| a.hit.t.partial_cmp(&b.hit.t).unwrap())
+}
+
+#[cfg(test)]
+mod bvh_tests
+{
+	use crate::geometry::{
+		bvh::{
+			intersect_brute_force,
+			Bvh,
+		},
+		primitives::{
+			icosphere,
+			torus,
+		},
+		ray::Ray,
+		sample_colored_tetrahedron_mesh,
+		Mesh,
+	};
+	use cgmath::*;
+
+	#[test]
+	fn test_matches_brute_force()
+	{
+		let mesh = torus(2.0, 0.5, 48, 24);
+		let bvh = Bvh::new(&mesh);
+		assert!(bvh.depth() > 5);
+
+		let mut hits = 0;
+		for n in 0..400
+		{
+			// Rays from points around the torus towards points near its center.
+			let angle = n as f32 * 0.37;
+			let origin = Point3::new(angle.cos() * 5.0, (n % 7) as f32 - 3.0, angle.sin() * 5.0);
+			let target = Point3::new((n % 5) as f32 - 2.0, 0.0, (n % 3) as f32 - 1.0);
+			let ray = Ray::new(origin, target - origin);
+
+			let expected = intersect_brute_force(&mesh, &ray);
+			let actual = bvh.intersect(&mesh, &ray);
+			// Compared by distance, as rays through an edge hit two triangles.
+			assert_eq!(expected.map(|h| h.hit.t), actual.map(|h| h.hit.t), "ray {}", n);
+			hits += actual.is_some() as usize;
+		}
+		assert!(hits > 100);
+	}
+
+	#[test]
+	fn test_closest_face()
+	{
+		let mesh = icosphere(1.0, 2);
+		let bvh = Bvh::new(&mesh);
+
+		// From outside, the hit is on the near side of the sphere.
+		let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::unit_z());
+		let hit = bvh.intersect(&mesh, &ray).unwrap();
+		assert!((ray.at(hit.hit.t).z + 1.0).abs() < 0.05);
+
+		// Missing it entirely.
+		assert_eq!(None, bvh.intersect(&mesh, &Ray::new(Point3::new(0.0, 2.0, -5.0), Vector3::unit_z())));
+	}
+
+	#[test]
+	fn test_tetrahedron_faces()
+	{
+		// A ray at the center of every face, from outside, picks that face.
+		let mesh = sample_colored_tetrahedron_mesh();
+		let bvh = Bvh::new(&mesh);
+		for (n, [a, b, c]) in mesh.triangles().enumerate()
+		{
+			let p = |i : u32| Point3::from_vec(mesh.vertices[i as usize].position);
+			let center = Point3::centroid(&[p(a), p(b), p(c)]);
+			let normal = (p(b) - p(a)).cross(p(c) - p(a)).normalize();
+			let ray = Ray::new(center + normal * 3.0, -normal);
+			assert_eq!(Some(n), bvh.intersect(&mesh, &ray).map(|hit| hit.triangle));
+		}
+	}
+
+	#[test]
+	fn test_empty_mesh()
+	{
+		let mesh = Mesh::<crate::geometry::MeshVertex>::new(Vec::new(), Vec::new());
+		let bvh = Bvh::new(&mesh);
+		assert_eq!(0, bvh.depth());
+		assert_eq!(None, bvh.intersect(&mesh, &Ray::new(Point3::origin(), Vector3::unit_x())));
+	}
+}
@@ -0,0 +1,252 @@
+// Dumps meshes and vertex arrays to OBJ or PLY files, to inspect them in an
+// external viewer.
+//
+// Vertex data computed on the CPU, like the output of
+// `sample_colored_triangle_vertices_eye_pre_projected`, can be written out
+// exactly as the renderer uploads it, or after applying the transform the
+// shader would. Transformed positions are divided by w, so dumping with a
+// view-projection matrix gives normalized device coordinates. The format is
+// picked from the file extension, and vertex colors are always written.
+use super::{
+	obj::{
+		save_obj,
+		ObjError,
+	},
+	ply::{
+		save_ply,
+		PlyError,
+		PlyFormat,
+	},
+	Mesh,
+	MeshVertex,
+	Vertex,
+};
+use cgmath::*;
+use std::{
+	error::Error,
+	fmt,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DumpFormat
+{
+	Obj,
+	// ASCII, so the values can also be read directly.
+	Ply,
+}
+
+impl DumpFormat
+{
+	// From the extension of `path`, ignoring case.
+	pub fn from_path(path : &Path) -> Option<Self>
+	{
+		let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+		match extension.as_str()
+		{
+			"obj" => Some(DumpFormat::Obj),
+			"ply" => Some(DumpFormat::Ply),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum DumpError
+{
+	UnknownFormat
+	{
+		path : PathBuf,
+	},
+	Obj(ObjError),
+	Ply(PlyError),
+}
+
+impl fmt::Display for DumpError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			DumpError::UnknownFormat {
+				path,
+			} => write!(f, "{}: unknown mesh format, expected .obj or .ply", path.display()),
+			DumpError::Obj(error) => error.fmt(f),
+			DumpError::Ply(error) => error.fmt(f),
+		}
+	}
+}
+
+impl Error for DumpError {}
+
+// Applies `transform` to every position, including the divide by w. Other
+// attributes are copied unchanged.
+#[allow(dead_code)]
+pub fn transform_vertices<V : Vertex>(vertices : &[V], transform : Matrix4<f32>) -> Vec<V>
+{
+	vertices
+		.iter()
+		.map(|vertex| {
+			let p = transform * vertex.position().extend(1.0);
+			vertex.with_position(p.truncate() / p.w)
+		})
+		.collect()
+}
+
+// Writes `mesh` to `path`, as OBJ or PLY depending on the extension.
+#[allow(dead_code)]
+pub fn dump_mesh<V : Vertex + Into<MeshVertex>>(path : &Path, mesh : &Mesh<V>) -> Result<(), DumpError>
+{
+	let format = DumpFormat::from_path(path).ok_or_else(|| DumpError::UnknownFormat {
+		path : path.to_path_buf(),
+	})?;
+
+	let mesh = Mesh::new(mesh.vertices.iter().map(|&vertex| vertex.into()).collect(), mesh.indices.to_u32());
+	match format
+	{
+		DumpFormat::Obj => save_obj(path, &mesh).map_err(DumpError::Obj),
+		DumpFormat::Ply => save_ply(path, &mesh, PlyFormat::Ascii).map_err(DumpError::Ply),
+	}
+}
+
+// Writes a triangle list after applying `transform`, as the vertex shader
+// would. Normals do not survive a projective transform, so they are written
+// as zero.
+#[allow(dead_code)]
+pub fn dump_vertices<V : Vertex + Into<MeshVertex>>(
+	path : &Path, vertices : &[V], transform : Matrix4<f32>,
+) -> Result<(), DumpError>
+{
+	let vertices : Vec<MeshVertex> = transform_vertices(vertices, transform)
+		.into_iter()
+		.map(|vertex| MeshVertex {
+			normal : Vector3::zero(),
+			..vertex.into()
+		})
+		.collect();
+	let indices = (0..vertices.len() as u32).collect();
+	dump_mesh(path, &Mesh::new(vertices, indices))
+}
+
+#[cfg(test)]
+mod dump_tests
+{
+	use crate::geometry::{
+		dump::{
+			dump_mesh,
+			dump_vertices,
+			transform_vertices,
+			DumpError,
+			DumpFormat,
+		},
+		obj::load_obj,
+		ply::load_ply,
+		sample_colored_tetrahedron_mesh,
+		sample_colored_triangle_vertices_eye_pre_projected,
+		ColoredVertex,
+	};
+	use cgmath::*;
+	use std::{
+		fs,
+		path::{
+			Path,
+			PathBuf,
+		},
+	};
+
+	const EPSILON : f32 = 1.0e-5;
+
+	// Fresh directory per test, so tests can run in parallel.
+	fn temp_dir(test : &str) -> PathBuf
+	{
+		let directory = std::env::temp_dir().join(format!("dump_tests_{}_{}", std::process::id(), test));
+		fs::create_dir_all(&directory).unwrap();
+		directory
+	}
+
+	#[test]
+	fn test_format_from_path()
+	{
+		assert_eq!(Some(DumpFormat::Obj), DumpFormat::from_path(Path::new("frame/mesh.obj")));
+		assert_eq!(Some(DumpFormat::Ply), DumpFormat::from_path(Path::new("mesh.PLY")));
+		assert_eq!(None, DumpFormat::from_path(Path::new("mesh.stl")));
+		assert_eq!(None, DumpFormat::from_path(Path::new("mesh")));
+
+		match dump_mesh(Path::new("mesh.txt"), &sample_colored_tetrahedron_mesh())
+		{
+			Err(DumpError::UnknownFormat {
+				..
+			}) => (),
+			_ => panic!("expected an unknown format error"),
+		}
+	}
+
+	#[test]
+	fn test_transform_divides_by_w()
+	{
+		let vertices = [ColoredVertex {
+			position : vec3(1.0, 2.0, 3.0),
+			color :    vec4(0.25, 0.5, 0.75, 1.0),
+		}];
+		// Translates by (1, 0, 0) and sets w to 2.
+		let mut transform = Matrix4::from_translation(vec3(1.0, 0.0, 0.0));
+		transform.w.w = 2.0;
+
+		let transformed = transform_vertices(&vertices, transform);
+		assert_eq!(vec3(1.0, 1.0, 1.5), transformed[0].position);
+		assert_eq!(vertices[0].color, transformed[0].color);
+	}
+
+	#[test]
+	fn test_dump_pre_projected_triangle()
+	{
+		let directory = temp_dir("pre_projected");
+		let vertices = sample_colored_triangle_vertices_eye_pre_projected(90.0, 16.0 / 9.0);
+
+		// Already projected, so dumped as is.
+		for name in ["triangle.obj", "triangle.ply"].iter()
+		{
+			let path = directory.join(name);
+			dump_vertices(&path, &vertices, Matrix4::identity()).unwrap();
+			let mesh = match DumpFormat::from_path(&path).unwrap()
+			{
+				DumpFormat::Obj => load_obj(&path).unwrap().mesh,
+				DumpFormat::Ply => load_ply(&path).unwrap(),
+			};
+
+			assert_eq!(1, mesh.triangle_count());
+			for (expected, actual) in vertices.iter().zip(mesh.to_triangle_soup())
+			{
+				assert!((expected.position - actual.position).magnitude() < EPSILON, "{}", name);
+				assert!((expected.color - actual.color).magnitude() < 1.0 / 255.0, "{}", name);
+				assert_eq!(Vector3::zero(), actual.normal);
+			}
+		}
+		fs::remove_dir_all(&directory).unwrap();
+	}
+
+	#[test]
+	fn test_dump_transformed_mesh()
+	{
+		let directory = temp_dir("transformed");
+		let path = directory.join("tetrahedron.ply");
+		let mesh = sample_colored_tetrahedron_mesh();
+		let transform = Matrix4::from_translation(vec3(0.0, 0.0, 5.0)) * Matrix4::from_scale(2.0);
+
+		dump_mesh(&path, &mesh).unwrap();
+		assert_eq!(mesh.to_triangle_soup().len(), load_ply(&path).unwrap().to_triangle_soup().len());
+
+		dump_vertices(&path, &mesh.to_triangle_soup(), transform).unwrap();
+		let loaded = load_ply(&path).unwrap();
+		for (expected, actual) in mesh.to_triangle_soup().iter().zip(loaded.to_triangle_soup())
+		{
+			let position = expected.position * 2.0 + vec3(0.0, 0.0, 5.0);
+			assert!((position - actual.position).magnitude() < EPSILON);
+			assert!((expected.color - actual.color).magnitude() < 1.0 / 255.0);
+		}
+		fs::remove_dir_all(&directory).unwrap();
+	}
+}
@@ -0,0 +1,372 @@
+// View frustum culling.
+//
+// The six planes are extracted from a view-projection matrix (Gribb and
+// Hartmann): a point is inside when its clip space x, y and z lie between -w
+// and w, and each of those inequalities is a plane in world space. Only the
+// near plane depends on the depth range of the projection, since z starts at
+// 0 for D3D style projections and at -w for GL style ones, and reverse-Z swaps
+// the near and far planes. Plane normals point into the frustum. A projection
+// without a far plane gives a degenerate far plane that culls nothing.
+use super::processing::{
+	Aabb,
+	BoundingSphere,
+};
+use crate::transforms::DepthRange;
+use cgmath::*;
+
+// Points p with `normal.dot(p) + distance == 0`, with a unit normal so the
+// result of `signed_distance` is a distance.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Plane
+{
+	pub normal :   Vector3<f32>,
+	pub distance : f32,
+}
+
+impl Plane
+{
+	// From the coefficients (a, b, c, d) of `a x + b y + c z + d = 0`. Without
+	// a normal, every point is in front of the plane.
+	pub fn from_coefficients(coefficients : Vector4<f32>) -> Self
+	{
+		let length = coefficients.truncate().magnitude();
+		if length == 0.0
+		{
+			return Plane {
+				normal :   Vector3::zero(),
+				distance : f32::INFINITY,
+			};
+		}
+		Plane {
+			normal :   coefficients.truncate() / length,
+			distance : coefficients.w / length,
+		}
+	}
+
+	// Positive on the side the normal points to.
+	pub fn signed_distance(&self, point : Vector3<f32>) -> f32
+	{
+		self.normal.dot(point) + self.distance
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Containment
+{
+	Outside,
+	Intersecting,
+	Inside,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum
+{
+	// Left, right, bottom, top, near and far.
+	pub planes : [Plane; 6],
+}
+
+impl Frustum
+{
+	// The frustum of `view_projection`, in the space the view matrix maps from,
+	// usually world space. With a model-view-projection matrix the planes are
+	// in model space instead.
+	pub fn from_matrix(view_projection : Matrix4<f32>, depth_range : DepthRange) -> Self
+	{
+		let m = view_projection;
+		let (x, y, z, w) = (m.row(0), m.row(1), m.row(2), m.row(3));
+		let (near, far) = match depth_range
+		{
+			DepthRange::ZeroToOne => (z, w - z),
+			DepthRange::MinusOneToOne => (w + z, w - z),
+			DepthRange::OneToZero => (w - z, z),
+		};
+
+		Frustum {
+			planes : [
+				Plane::from_coefficients(w + x),
+				Plane::from_coefficients(w - x),
+				Plane::from_coefficients(w + y),
+				Plane::from_coefficients(w - y),
+				Plane::from_coefficients(near),
+				Plane::from_coefficients(far),
+			],
+		}
+	}
+
+	pub fn test_sphere(&self, sphere : &BoundingSphere) -> Containment
+	{
+		let mut containment = Containment::Inside;
+		for plane in &self.planes
+		{
+			let distance = plane.signed_distance(sphere.center);
+			if distance < -sphere.radius
+			{
+				return Containment::Outside;
+			}
+			if distance < sphere.radius
+			{
+				containment = Containment::Intersecting;
+			}
+		}
+		containment
+	}
+
+	// Tests the corners nearest and farthest along each plane normal. Boxes
+	// near a corner of the frustum can be reported as intersecting while
+	// outside, which only costs a draw.
+	pub fn test_aabb(&self, aabb : &Aabb) -> Containment
+	{
+		let center = aabb.center();
+		let extents = aabb.extents();
+		let mut containment = Containment::Inside;
+		for plane in &self.planes
+		{
+			let distance = plane.signed_distance(center);
+			let radius = plane.normal.map(f32::abs).dot(extents);
+			if distance < -radius
+			{
+				return Containment::Outside;
+			}
+			if distance < radius
+			{
+				containment = Containment::Intersecting;
+			}
+		}
+		containment
+	}
+
+	pub fn intersects_sphere(&self, sphere : &BoundingSphere) -> bool
+	{
+		self.test_sphere(sphere) != Containment::Outside
+	}
+
+	pub fn intersects_aabb(&self, aabb : &Aabb) -> bool
+	{
+		self.test_aabb(aabb) != Containment::Outside
+	}
+}
+
+// Objects drawn and skipped in a frame.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct CullingStats
+{
+	pub visible : u32,
+	pub culled :  u32,
+}
+
+impl CullingStats
+{
+	pub fn record(&mut self, visible : bool)
+	{
+		if visible
+		{
+			self.visible += 1;
+		}
+		else
+		{
+			self.culled += 1;
+		}
+	}
+}
+
+#[cfg(test)]
+mod frustum_tests
+{
+	use crate::{
+		geometry::{
+			frustum::{
+				Containment,
+				CullingStats,
+				Frustum,
+			},
+			processing::{
+				Aabb,
+				BoundingSphere,
+			},
+		},
+		transforms::{
+			self,
+			ClipSpaceConvention,
+			DepthRange,
+			Handedness,
+		},
+	};
+	use cgmath::*;
+
+	const EPSILON : f32 = 1.0e-4;
+	const NEAR : f32 = 1.0;
+	const FAR : f32 = 10.0;
+
+	// Camera at the origin looking down +z, with a 90 degree field of view so
+	// the side planes are at 45 degrees.
+	fn perspective() -> PerspectiveFov<f32>
+	{
+		PerspectiveFov {
+			fovy :   Rad(std::f32::consts::FRAC_PI_2),
+			aspect : 1.0,
+			near :   NEAR,
+			far :    FAR,
+		}
+	}
+
+	fn convention(depth_range : DepthRange) -> ClipSpaceConvention
+	{
+		ClipSpaceConvention::new(Handedness::Left, depth_range)
+	}
+
+	// The same frustum in every depth range.
+	fn frustums() -> Vec<(DepthRange, Frustum)>
+	{
+		let view = transforms::look_at_lh(Point3::origin(), Point3::new(0.0, 0.0, 1.0), Vector3::unit_y());
+		[DepthRange::ZeroToOne, DepthRange::MinusOneToOne, DepthRange::OneToZero]
+			.iter()
+			.map(|&depth_range| {
+				let projection = transforms::perspective(perspective(), convention(depth_range));
+				(depth_range, Frustum::from_matrix(projection * view, depth_range))
+			})
+			.collect()
+	}
+
+	fn sphere(x : f32, y : f32, z : f32, radius : f32) -> BoundingSphere
+	{
+		BoundingSphere {
+			center : Vector3::new(x, y, z),
+			radius : radius,
+		}
+	}
+
+	fn cube(x : f32, y : f32, z : f32, half_size : f32) -> Aabb
+	{
+		let center = Vector3::new(x, y, z);
+		Aabb {
+			min : center - Vector3::new(half_size, half_size, half_size),
+			max : center + Vector3::new(half_size, half_size, half_size),
+		}
+	}
+
+	#[test]
+	fn test_planes()
+	{
+		for (depth_range, frustum) in frustums()
+		{
+			let [left, right, bottom, top, near, far] = frustum.planes;
+			let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+			assert!((left.normal - Vector3::new(diagonal, 0.0, diagonal)).magnitude() < EPSILON, "{:?}", depth_range);
+			assert!((right.normal - Vector3::new(-diagonal, 0.0, diagonal)).magnitude() < EPSILON, "{:?}", depth_range);
+			assert!((bottom.normal - Vector3::new(0.0, diagonal, diagonal)).magnitude() < EPSILON, "{:?}", depth_range);
+			assert!((top.normal - Vector3::new(0.0, -diagonal, diagonal)).magnitude() < EPSILON, "{:?}", depth_range);
+
+			// The near and far planes are where the projection puts them.
+			assert!((near.normal - Vector3::unit_z()).magnitude() < EPSILON, "{:?}", depth_range);
+			assert!((near.signed_distance(Vector3::new(0.0, 0.0, NEAR))).abs() < EPSILON, "{:?}", depth_range);
+			assert!((far.normal + Vector3::unit_z()).magnitude() < EPSILON, "{:?}", depth_range);
+			assert!((far.signed_distance(Vector3::new(0.0, 0.0, FAR))).abs() < 1.0e-3, "{:?}", depth_range);
+		}
+	}
+
+	#[test]
+	fn test_spheres()
+	{
+		for (depth_range, frustum) in frustums()
+		{
+			assert_eq!(Containment::Inside, frustum.test_sphere(&sphere(0.0, 0.0, 5.0, 1.0)), "{:?}", depth_range);
+			// Behind the camera, before the near plane and past the far plane.
+			assert_eq!(Containment::Outside, frustum.test_sphere(&sphere(0.0, 0.0, -3.0, 1.0)), "{:?}", depth_range);
+			assert_eq!(Containment::Outside, frustum.test_sphere(&sphere(0.0, 0.0, 0.4, 0.5)), "{:?}", depth_range);
+			assert_eq!(Containment::Outside, frustum.test_sphere(&sphere(0.0, 0.0, 11.5, 1.0)), "{:?}", depth_range);
+			// Beside the 45 degree planes: at z = 5 they are at x = +-5, and a
+			// sphere at x = 7 is sqrt(2) past the right one.
+			assert_eq!(Containment::Outside, frustum.test_sphere(&sphere(7.0, 0.0, 5.0, 1.0)), "{:?}", depth_range);
+			assert_eq!(
+				Containment::Intersecting,
+				frustum.test_sphere(&sphere(7.0, 0.0, 5.0, 1.5)),
+				"{:?}",
+				depth_range
+			);
+			assert_eq!(
+				Containment::Intersecting,
+				frustum.test_sphere(&sphere(0.0, -5.0, 5.0, 0.5)),
+				"{:?}",
+				depth_range
+			);
+			assert_eq!(
+				Containment::Intersecting,
+				frustum.test_sphere(&sphere(0.0, 0.0, FAR, 0.5)),
+				"{:?}",
+				depth_range
+			);
+			assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, NEAR, 0.1)), "{:?}", depth_range);
+		}
+	}
+
+	#[test]
+	fn test_aabbs()
+	{
+		for (depth_range, frustum) in frustums()
+		{
+			assert_eq!(Containment::Inside, frustum.test_aabb(&cube(0.0, 0.0, 5.0, 1.0)), "{:?}", depth_range);
+			assert_eq!(Containment::Outside, frustum.test_aabb(&cube(0.0, 0.0, -3.0, 1.0)), "{:?}", depth_range);
+			assert_eq!(Containment::Outside, frustum.test_aabb(&cube(0.0, 8.0, 5.0, 1.0)), "{:?}", depth_range);
+			assert_eq!(Containment::Outside, frustum.test_aabb(&cube(0.0, 0.0, 12.0, 1.0)), "{:?}", depth_range);
+			// Straddling the left plane and the near plane.
+			assert_eq!(Containment::Intersecting, frustum.test_aabb(&cube(-5.0, 0.0, 5.0, 1.0)), "{:?}", depth_range);
+			assert_eq!(Containment::Intersecting, frustum.test_aabb(&cube(0.0, 0.0, NEAR, 0.5)), "{:?}", depth_range);
+			// A box around the whole frustum is not inside it.
+			assert!(frustum.intersects_aabb(&cube(0.0, 0.0, 5.0, 100.0)), "{:?}", depth_range);
+		}
+	}
+
+	#[test]
+	fn test_infinite_far_plane()
+	{
+		let view = transforms::look_at_lh(Point3::origin(), Point3::new(0.0, 0.0, 1.0), Vector3::unit_y());
+		let (fovy, near) = (perspective().fovy, NEAR);
+		for &depth_range in &[DepthRange::ZeroToOne, DepthRange::MinusOneToOne, DepthRange::OneToZero]
+		{
+			let projection = transforms::perspective_infinite(fovy, 1.0, near, convention(depth_range));
+			let frustum = Frustum::from_matrix(projection * view, depth_range);
+			// Nothing is past the far plane, but the others still cull.
+			assert_eq!(Containment::Inside, frustum.test_sphere(&sphere(0.0, 0.0, 1.0e6, 1.0)));
+			assert_eq!(Containment::Inside, frustum.test_aabb(&cube(0.0, 0.0, 1.0e6, 1.0)));
+			assert_eq!(Containment::Outside, frustum.test_sphere(&sphere(0.0, 0.0, 0.4, 0.5)));
+			assert_eq!(Containment::Outside, frustum.test_sphere(&sphere(7.0, 0.0, 5.0, 1.0)));
+		}
+	}
+
+	#[test]
+	fn test_transformed_bounds()
+	{
+		// The frustum of a model-view-projection matrix is in model space, so
+		// testing untransformed bounds against it agrees with testing world
+		// space bounds against the world space frustum.
+		let view = transforms::look_at_lh(Point3::new(0.0, 1.0, -4.0), Point3::origin(), Vector3::unit_y());
+		let view_projection = transforms::perspective(perspective(), ClipSpaceConvention::D3D12) * view;
+		let world = Frustum::from_matrix(view_projection, DepthRange::ZeroToOne);
+		let bounds = sphere(0.0, 0.0, 0.0, 0.5);
+
+		for n in 0..32
+		{
+			let offset = Vector3::new(n as f32 - 16.0, (n % 5) as f32 - 2.0, (n % 7) as f32);
+			let model = Matrix4::from_translation(offset) * Matrix4::from_angle_y(Deg(n as f32 * 20.0));
+			let local = Frustum::from_matrix(view_projection * model, DepthRange::ZeroToOne);
+			assert_eq!(world.test_sphere(&bounds.transform(model)), local.test_sphere(&bounds), "offset {:?}", offset);
+		}
+	}
+
+	#[test]
+	fn test_stats()
+	{
+		let mut stats = CullingStats::default();
+		for &visible in &[true, false, true, true]
+		{
+			stats.record(visible);
+		}
+		assert_eq!(
+			CullingStats {
+				visible : 3,
+				culled :  1,
+			},
+			stats
+		);
+	}
+}
@@ -0,0 +1,936 @@
+// glTF 2.0 scene loader for .gltf (with external or embedded buffers) and .glb
+// files.
+//
+// glTF is right-handed with counter-clockwise front faces. Like the OBJ
+// loader, everything is converted on load to the left-handed conventions of
+// the geometry module: z is negated, triangle winding is reversed and node
+// transforms are mirrored to match. Texture coordinates already have v
+// pointing down, so they are kept as is.
+//
+// Only what the renderer can use is read: triangle meshes, node transforms,
+// the base color factor of each material, COLOR_0 and the translation,
+// rotation and scale channels of animations. Vertex colors are multiplied by
+// the base color factor, as the glTF spec defines. Animations become looping
+// tracks, one clip per animated node; morph target weights are skipped.
+// Nodes must form trees. Nodes outside the loaded scene are kept, but not
+// drawn.
+use super::{
+	Mesh,
+	MeshVertex,
+};
+use crate::animation::{
+	Animatable,
+	AnimationClip,
+	Interpolation,
+	Keyframe,
+	Track,
+	Wrap,
+};
+use cgmath::*;
+use std::{
+	error::Error,
+	fmt,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+// Extensions that only add information the loader can safely ignore.
+const IGNORABLE_EXTENSIONS : &[&str] =
+	&["KHR_materials_emissive_strength", "KHR_materials_unlit", "KHR_texture_transform", "KHR_lights_punctual"];
+
+#[derive(Debug)]
+pub enum GltfError
+{
+	Load
+	{
+		path :  PathBuf,
+		error : ::gltf::Error,
+	},
+	UnsupportedExtensions
+	{
+		path :       PathBuf,
+		extensions : Vec<String>,
+	},
+	Buffer
+	{
+		path :    PathBuf,
+		buffer :  usize,
+		details : String,
+	},
+	InvalidPrimitive
+	{
+		path :      PathBuf,
+		mesh :      usize,
+		primitive : usize,
+		details :   String,
+	},
+	InvalidAnimation
+	{
+		path :      PathBuf,
+		animation : usize,
+		channel :   usize,
+		details :   String,
+	},
+	// The node hierarchy is not a forest of trees.
+	InvalidNode
+	{
+		path :    PathBuf,
+		node :    usize,
+		details : String,
+	},
+}
+
+impl fmt::Display for GltfError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			GltfError::Load {
+				path,
+				error,
+			} => write!(f, "{}: {}", path.display(), error),
+			GltfError::UnsupportedExtensions {
+				path,
+				extensions,
+			} => write!(f, "{}: requires unsupported glTF extensions: {}", path.display(), extensions.join(", ")),
+			GltfError::Buffer {
+				path,
+				buffer,
+				details,
+			} => write!(f, "{}: buffer {}: {}", path.display(), buffer, details),
+			GltfError::InvalidPrimitive {
+				path,
+				mesh,
+				primitive,
+				details,
+			} => write!(f, "{}: mesh {} primitive {}: {}", path.display(), mesh, primitive, details),
+			GltfError::InvalidAnimation {
+				path,
+				animation,
+				channel,
+				details,
+			} => write!(f, "{}: animation {} channel {}: {}", path.display(), animation, channel, details),
+			GltfError::InvalidNode {
+				path,
+				node,
+				details,
+			} => write!(f, "{}: node {}: {}", path.display(), node, details),
+		}
+	}
+}
+
+impl Error for GltfError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMaterial
+{
+	pub name :       Option<String>,
+	pub base_color : Vector4<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfPrimitive
+{
+	pub mesh :     Mesh<MeshVertex>,
+	pub material : Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfMesh
+{
+	pub name :       Option<String>,
+	pub primitives : Vec<GltfPrimitive>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfNode
+{
+	pub name : Option<String>,
+	pub parent : Option<usize>,
+	pub children : Vec<usize>,
+	pub mesh : Option<usize>,
+	pub local_transform : Matrix4<f32>,
+	pub world_transform : Matrix4<f32>,
+	// False for nodes outside the loaded scene. They have no world transform
+	// and are not drawn.
+	pub in_scene : bool,
+}
+
+// The tracks of one animation that move a node, relative to its parent like
+// `GltfNode::local_transform`.
+#[derive(Debug, Clone)]
+pub struct GltfNodeAnimation
+{
+	pub node : usize,
+	pub clip : AnimationClip,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfAnimation
+{
+	pub name :  Option<String>,
+	pub nodes : Vec<GltfNodeAnimation>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfScene
+{
+	pub meshes :      Vec<GltfMesh>,
+	pub materials :   Vec<GltfMaterial>,
+	pub nodes :       Vec<GltfNode>,
+	pub roots :       Vec<usize>,
+	pub animations :  Vec<GltfAnimation>,
+	pub diagnostics : Vec<String>,
+}
+
+impl GltfScene
+{
+	// Bakes every mesh instance into a single mesh in world space.
+	#[allow(dead_code)]
+	pub fn flatten(&self) -> Mesh<MeshVertex>
+	{
+		let mut vertices : Vec<MeshVertex> = Vec::new();
+		let mut indices : Vec<u32> = Vec::new();
+
+		for node in self.nodes.iter().filter(|node| node.in_scene)
+		{
+			let mesh = match node.mesh
+			{
+				Some(mesh) => &self.meshes[mesh],
+				None => continue,
+			};
+
+			let normal_transform = node.world_transform.invert().unwrap_or_else(Matrix4::identity).transpose();
+			for primitive in &mesh.primitives
+			{
+				let base = vertices.len() as u32;
+				vertices.extend(primitive.mesh.vertices.iter().map(|v| MeshVertex {
+					position : (node.world_transform * v.position.extend(1.0)).truncate(),
+					normal : (normal_transform * v.normal.extend(0.0)).truncate().normalize_or_zero(),
+					..*v
+				}));
+				indices.extend(primitive.mesh.indices.to_u32().iter().map(|i| base + i));
+			}
+		}
+
+		Mesh::new(vertices, indices)
+	}
+}
+
+trait NormalizeOrZero
+{
+	fn normalize_or_zero(self) -> Self;
+}
+
+impl NormalizeOrZero for Vector3<f32>
+{
+	fn normalize_or_zero(self) -> Self
+	{
+		if self.magnitude2() > 0.0
+		{
+			self.normalize()
+		}
+		else
+		{
+			self
+		}
+	}
+}
+
+// Mirrors a right-handed transform into left-handed space: S * m * S, with S
+// negating z.
+fn mirror_z(m : Matrix4<f32>) -> Matrix4<f32>
+{
+	let s = Matrix4::from_nonuniform_scale(1.0, 1.0, -1.0);
+	s * m * s
+}
+
+fn convert_position(p : [f32; 3]) -> Vector3<f32>
+{
+	Vector3::new(p[0], p[1], -p[2])
+}
+
+// glTF quaternions are x, y, z, w. Mirroring z reverses rotations about x and
+// y.
+fn convert_rotation(q : [f32; 4]) -> Quaternion<f32>
+{
+	Quaternion::new(q[3], -q[0], -q[1], q[2])
+}
+
+// The gltf crate's own normalized integer conversions are off for u8 data, so
+// unsigned normalized attributes are converted here.
+fn read_tex_coords(uvs : ::gltf::mesh::util::ReadTexCoords) -> Vec<Vector2<f32>>
+{
+	use ::gltf::mesh::util::ReadTexCoords;
+
+	match uvs
+	{
+		ReadTexCoords::U8(uvs) => uvs.map(|uv| Vector2::new(uv[0] as f32, uv[1] as f32) / 255.0).collect(),
+		ReadTexCoords::U16(uvs) => uvs.map(|uv| Vector2::new(uv[0] as f32, uv[1] as f32) / 65535.0).collect(),
+		ReadTexCoords::F32(uvs) => uvs.map(Vector2::from).collect(),
+	}
+}
+
+fn read_colors(colors : ::gltf::mesh::util::ReadColors) -> Vec<Vector4<f32>>
+{
+	use ::gltf::mesh::util::ReadColors;
+
+	let rgb = |r, g, b, max : f32| Vector4::new(r / max, g / max, b / max, 1.0);
+	let rgba = |r, g, b, a, max : f32| Vector4::new(r, g, b, a) / max;
+	match colors
+	{
+		ReadColors::RgbU8(colors) => colors.map(|c| rgb(c[0] as f32, c[1] as f32, c[2] as f32, 255.0)).collect(),
+		ReadColors::RgbU16(colors) => colors.map(|c| rgb(c[0] as f32, c[1] as f32, c[2] as f32, 65535.0)).collect(),
+		ReadColors::RgbF32(colors) => colors.map(|c| rgb(c[0], c[1], c[2], 1.0)).collect(),
+		ReadColors::RgbaU8(colors) =>
+		{
+			colors.map(|c| rgba(c[0] as f32, c[1] as f32, c[2] as f32, c[3] as f32, 255.0)).collect()
+		}
+		ReadColors::RgbaU16(colors) =>
+		{
+			colors.map(|c| rgba(c[0] as f32, c[1] as f32, c[2] as f32, c[3] as f32, 65535.0)).collect()
+		}
+		ReadColors::RgbaF32(colors) => colors.map(Vector4::from).collect(),
+	}
+}
+
+// Turns the index list of any triangle topology into a triangle list.
+fn triangle_list(mode : ::gltf::mesh::Mode, indices : Vec<u32>) -> Option<Vec<u32>>
+{
+	use ::gltf::mesh::Mode;
+
+	match mode
+	{
+		Mode::Triangles => Some(indices),
+		Mode::TriangleStrip => Some(
+			(2..indices.len())
+				.flat_map(|n| {
+					// Every other triangle in a strip has flipped winding.
+					if n % 2 == 0
+					{
+						vec![indices[n - 2], indices[n - 1], indices[n]]
+					}
+					else
+					{
+						vec![indices[n - 1], indices[n - 2], indices[n]]
+					}
+				})
+				.collect(),
+		),
+		Mode::TriangleFan =>
+		{
+			Some((2..indices.len()).flat_map(|n| vec![indices[0], indices[n - 1], indices[n]]).collect())
+		}
+		Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => None,
+	}
+}
+
+fn load_primitive(
+	path : &Path, primitive : &::gltf::Primitive, buffers : &[Vec<u8>], materials : &[GltfMaterial], mesh_index : usize,
+) -> Result<Mesh<MeshVertex>, GltfError>
+{
+	let invalid = |details : &str| GltfError::InvalidPrimitive {
+		path :      path.to_path_buf(),
+		mesh :      mesh_index,
+		primitive : primitive.index(),
+		details :   details.to_string(),
+	};
+
+	let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+
+	let positions : Vec<Vector3<f32>> = match reader.read_positions()
+	{
+		Some(positions) => positions.map(convert_position).collect(),
+		None => return Err(invalid("has no POSITION attribute")),
+	};
+	let count = positions.len();
+
+	let normals : Vec<Vector3<f32>> = match reader.read_normals()
+	{
+		Some(normals) => normals.map(convert_position).collect(),
+		None => vec![Vector3::zero(); count],
+	};
+	let uvs : Vec<Vector2<f32>> = match reader.read_tex_coords(0)
+	{
+		Some(uvs) => read_tex_coords(uvs),
+		None => vec![Vector2::zero(); count],
+	};
+	let colors : Vec<Vector4<f32>> = match reader.read_colors(0)
+	{
+		Some(colors) => read_colors(colors),
+		None => vec![Vector4::new(1.0, 1.0, 1.0, 1.0); count],
+	};
+	if normals.len() != count || uvs.len() != count || colors.len() != count
+	{
+		return Err(invalid("has attributes with mismatched counts"));
+	}
+
+	let base_color = match primitive.material().index()
+	{
+		Some(material) => materials[material].base_color,
+		None => Vector4::new(1.0, 1.0, 1.0, 1.0),
+	};
+
+	let vertices = (0..count)
+		.map(|n| MeshVertex {
+			position : positions[n],
+			normal :   normals[n],
+			uv :       uvs[n],
+			color :    colors[n].mul_element_wise(base_color),
+		})
+		.collect();
+
+	let indices = match reader.read_indices()
+	{
+		Some(indices) => indices.into_u32().collect::<Vec<u32>>(),
+		None => (0..count as u32).collect(),
+	};
+	if indices.iter().any(|&i| i as usize >= count)
+	{
+		return Err(invalid("has indices out of range"));
+	}
+
+	let mut indices = triangle_list(primitive.mode(), indices).expect("only triangle primitives are loaded");
+	indices.truncate(indices.len() - indices.len() % 3);
+
+	// Reverse the winding, since mirroring z flips it.
+	for triangle in indices.chunks_mut(3)
+	{
+		triangle.swap(1, 2);
+	}
+
+	Ok(Mesh::new(vertices, indices))
+}
+
+// Pairs sampler input times with output values. Cubic spline outputs hold an
+// in-tangent, a value and an out-tangent per key.
+fn animation_track<T : Animatable>(
+	times : &[f32], values : Vec<T>, interpolation : Interpolation,
+) -> Result<Track<T>, String>
+{
+	let per_key = if interpolation == Interpolation::CubicHermite
+	{
+		3
+	}
+	else
+	{
+		1
+	};
+	if values.len() != times.len() * per_key
+	{
+		return Err(format!("has {} outputs for {} inputs", values.len(), times.len()));
+	}
+
+	let keys = times
+		.iter()
+		.zip(values.chunks(per_key))
+		.map(|(&time, key)| match key
+		{
+			[in_tangent, value, out_tangent] => Keyframe::cubic(time, *value, *in_tangent, *out_tangent),
+			_ => Keyframe::new(time, key[0]),
+		})
+		.collect();
+	Track::new(interpolation, Wrap::Loop, keys).map_err(|error| error.to_string())
+}
+
+fn load_animation(
+	path : &Path, animation : &::gltf::Animation, buffers : &[Vec<u8>], diagnostics : &mut Vec<String>,
+) -> Result<GltfAnimation, GltfError>
+{
+	use ::gltf::animation::util::ReadOutputs;
+
+	let mut nodes : Vec<GltfNodeAnimation> = Vec::new();
+	for (n, channel) in animation.channels().enumerate()
+	{
+		let invalid = |details : String| GltfError::InvalidAnimation {
+			path :      path.to_path_buf(),
+			animation : animation.index(),
+			channel :   n,
+			details :   details,
+		};
+
+		let reader = channel.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+		let times : Vec<f32> = match reader.read_inputs()
+		{
+			Some(times) => times.collect(),
+			None => return Err(invalid("has no input times".to_string())),
+		};
+		let interpolation = match channel.sampler().interpolation()
+		{
+			::gltf::animation::Interpolation::Step => Interpolation::Step,
+			::gltf::animation::Interpolation::Linear => Interpolation::Linear,
+			::gltf::animation::Interpolation::CubicSpline => Interpolation::CubicHermite,
+		};
+
+		let node = channel.target().node().index();
+		let clip = match nodes.iter().position(|animated| animated.node == node)
+		{
+			Some(existing) => &mut nodes[existing].clip,
+			None =>
+			{
+				nodes.push(GltfNodeAnimation {
+					node : node,
+					clip : AnimationClip::new(),
+				});
+				&mut nodes.last_mut().unwrap().clip
+			}
+		};
+
+		match reader.read_outputs()
+		{
+			Some(ReadOutputs::Translations(translations)) =>
+			{
+				let values = translations.map(convert_position).collect();
+				clip.translation = Some(animation_track(&times, values, interpolation).map_err(invalid)?);
+			}
+			Some(ReadOutputs::Rotations(rotations)) =>
+			{
+				let values = rotations.into_f32().map(convert_rotation).collect();
+				clip.rotation = Some(animation_track(&times, values, interpolation).map_err(invalid)?);
+			}
+			Some(ReadOutputs::Scales(scales)) =>
+			{
+				let values = scales.map(Vector3::from).collect();
+				clip.scale = Some(animation_track(&times, values, interpolation).map_err(invalid)?);
+			}
+			Some(ReadOutputs::MorphTargetWeights(_)) => diagnostics.push(format!(
+				"animation {} channel {}: skipping morph target weights, they are not supported",
+				animation.index(),
+				n
+			)),
+			None => return Err(invalid("has no output values".to_string())),
+		}
+	}
+
+	// Nodes whose only channels were skipped are not animated.
+	nodes.retain(|animated| animated.clip != AnimationClip::new());
+	Ok(GltfAnimation {
+		name :  animation.name().map(String::from),
+		nodes : nodes,
+	})
+}
+
+// Reads the binary chunk of a .glb and any .bin files next to a .gltf.
+// Base64 data URIs are not supported.
+fn load_buffers(
+	path : &Path, document : &::gltf::Document, mut blob : Option<Vec<u8>>,
+) -> Result<Vec<Vec<u8>>, GltfError>
+{
+	let mut buffers = Vec::new();
+	for buffer in document.buffers()
+	{
+		let buffer_error = |details : String| GltfError::Buffer {
+			path :    path.to_path_buf(),
+			buffer :  buffer.index(),
+			details : details,
+		};
+
+		let data = match buffer.source()
+		{
+			::gltf::buffer::Source::Bin =>
+			{
+				blob.take().ok_or_else(|| buffer_error("missing GLB binary chunk".to_string()))?
+			}
+			::gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") =>
+			{
+				return Err(buffer_error(
+					"embedded data URIs are not supported, export as .glb or with a .bin".to_string(),
+				))
+			}
+			::gltf::buffer::Source::Uri(uri) =>
+			{
+				let file = path.parent().unwrap_or_else(|| Path::new("")).join(uri);
+				std::fs::read(&file).map_err(|error| buffer_error(format!("{}: {}", file.display(), error)))?
+			}
+		};
+		if data.len() < buffer.length()
+		{
+			return Err(buffer_error(format!("expected {} bytes, found {}", buffer.length(), data.len())));
+		}
+		buffers.push(data);
+	}
+
+	Ok(buffers)
+}
+
+// Loads a .gltf or .glb file. Buffers referenced by a .gltf are resolved
+// relative to its directory.
+#[allow(dead_code)]
+pub fn load_gltf(path : &Path) -> Result<GltfScene, GltfError>
+{
+	let load_error = |error| GltfError::Load {
+		path :  path.to_path_buf(),
+		error : error,
+	};
+
+	let ::gltf::Gltf {
+		document,
+		blob,
+	} = ::gltf::Gltf::open(path).map_err(load_error)?;
+
+	let mut diagnostics = Vec::new();
+
+	let unsupported : Vec<String> = document
+		.extensions_required()
+		.filter(|extension| !IGNORABLE_EXTENSIONS.contains(extension))
+		.map(|extension| extension.to_string())
+		.collect();
+	if !unsupported.is_empty()
+	{
+		return Err(GltfError::UnsupportedExtensions {
+			path :       path.to_path_buf(),
+			extensions : unsupported,
+		});
+	}
+	for extension in document.extensions_used()
+	{
+		diagnostics.push(format!("ignoring optional extension {}", extension));
+	}
+
+	let buffers = load_buffers(path, &document, blob)?;
+
+	let materials : Vec<GltfMaterial> = document
+		.materials()
+		.map(|material| {
+			if material.pbr_metallic_roughness().base_color_texture().is_some()
+			{
+				diagnostics.push(format!(
+					"material {}: base color texture is not supported, using the base color factor only",
+					material.index().unwrap_or(0)
+				));
+			}
+			GltfMaterial {
+				name :       material.name().map(String::from),
+				base_color : Vector4::from(material.pbr_metallic_roughness().base_color_factor()),
+			}
+		})
+		.collect();
+
+	let mut meshes = Vec::new();
+	for mesh in document.meshes()
+	{
+		let mut primitives = Vec::new();
+		for primitive in mesh.primitives()
+		{
+			if triangle_list(primitive.mode(), Vec::new()).is_none()
+			{
+				diagnostics.push(format!(
+					"mesh {} primitive {}: skipping {:?} primitive, only triangles are supported",
+					mesh.index(),
+					primitive.index(),
+					primitive.mode()
+				));
+				continue;
+			}
+
+			primitives.push(GltfPrimitive {
+				mesh :     load_primitive(path, &primitive, &buffers, &materials, mesh.index())?,
+				material : primitive.material().index(),
+			});
+		}
+
+		meshes.push(GltfMesh {
+			name :       mesh.name().map(String::from),
+			primitives : primitives,
+		});
+	}
+
+	let mut nodes : Vec<GltfNode> = document
+		.nodes()
+		.map(|node| GltfNode {
+			name : node.name().map(String::from),
+			parent : None,
+			children : node.children().map(|child| child.index()).collect(),
+			mesh : node.mesh().map(|mesh| mesh.index()),
+			local_transform : mirror_z(Matrix4::from(node.transform().matrix())),
+			world_transform : Matrix4::identity(),
+			in_scene : false,
+		})
+		.collect();
+	let invalid_node = |node : usize, details : String| GltfError::InvalidNode {
+		path :    path.to_path_buf(),
+		node :    node,
+		details : details,
+	};
+	for n in 0..nodes.len()
+	{
+		for child in nodes[n].children.clone()
+		{
+			if let Some(parent) = nodes[child].parent
+			{
+				return Err(invalid_node(child, format!("is a child of both node {} and node {}", parent, n)));
+			}
+			nodes[child].parent = Some(n);
+		}
+	}
+
+	// With one parent each, the nodes form trees unless following the parents
+	// up from a node comes back around to a node on the way.
+	let mut checked = vec![false; nodes.len()];
+	let mut on_path = vec![false; nodes.len()];
+	for n in 0..nodes.len()
+	{
+		let mut path_nodes = Vec::new();
+		let mut next = Some(n);
+		while let Some(node) = next.filter(|&node| !checked[node])
+		{
+			if on_path[node]
+			{
+				return Err(invalid_node(node, "is its own ancestor".to_string()));
+			}
+			on_path[node] = true;
+			path_nodes.push(node);
+			next = nodes[node].parent;
+		}
+		for node in path_nodes
+		{
+			on_path[node] = false;
+			checked[node] = true;
+		}
+	}
+
+	let roots : Vec<usize> = match document.default_scene().or_else(|| document.scenes().next())
+	{
+		Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+		None => (0..nodes.len()).filter(|&n| nodes[n].parent.is_none()).collect(),
+	};
+	if let Some(&root) = roots.iter().find(|&&root| nodes[root].parent.is_some())
+	{
+		return Err(invalid_node(root, "is a scene root but has a parent".to_string()));
+	}
+
+	// Parents are always visited before their children.
+	let mut stack : Vec<(usize, Matrix4<f32>)> = roots.iter().map(|&root| (root, Matrix4::identity())).collect();
+	while let Some((n, parent_transform)) = stack.pop()
+	{
+		let world_transform = parent_transform * nodes[n].local_transform;
+		nodes[n].world_transform = world_transform;
+		nodes[n].in_scene = true;
+		stack.extend(nodes[n].children.iter().map(|&child| (child, world_transform)));
+	}
+	for n in (0..nodes.len()).filter(|&n| !nodes[n].in_scene && nodes[n].mesh.is_some())
+	{
+		diagnostics.push(format!("node {}: skipping its mesh, the node is not part of the scene", n));
+	}
+
+	let mut animations = Vec::new();
+	for animation in document.animations()
+	{
+		animations.push(load_animation(path, &animation, &buffers, &mut diagnostics)?);
+	}
+
+	Ok(GltfScene {
+		meshes :      meshes,
+		materials :   materials,
+		nodes :       nodes,
+		roots :       roots,
+		animations :  animations,
+		diagnostics : diagnostics,
+	})
+}
+
+#[cfg(test)]
+mod gltf_tests
+{
+	use crate::{
+		animation::{
+			Interpolation,
+			Wrap,
+		},
+		geometry::gltf::{
+			load_gltf,
+			GltfError,
+			GltfScene,
+		},
+	};
+	use cgmath::*;
+	use std::path::{
+		Path,
+		PathBuf,
+	};
+
+	const EPSILON : f32 = 1.0e-5;
+
+	fn fixture(name : &str) -> PathBuf
+	{
+		Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("gltf").join(name)
+	}
+
+	fn assert_near(expected : Vector3<f32>, actual : Vector3<f32>)
+	{
+		assert!((expected - actual).magnitude() < EPSILON, "expected {:?}, got {:?}", expected, actual);
+	}
+
+	fn check_two_nodes(scene : &GltfScene)
+	{
+		assert_eq!(vec![0], scene.roots);
+		assert_eq!(2, scene.nodes.len());
+		assert_eq!(Some("root".to_string()), scene.nodes[0].name);
+		assert_eq!(Some(0), scene.nodes[1].parent);
+		assert_eq!(Some(0), scene.nodes[1].mesh);
+		assert!(scene.animations.is_empty());
+
+		assert_eq!(1, scene.materials.len());
+		assert_eq!(Vector4::new(0.5, 1.0, 1.0, 1.0), scene.materials[0].base_color);
+
+		// The points primitive is skipped with a diagnostic.
+		let mesh = &scene.meshes[0];
+		assert_eq!(2, mesh.primitives.len());
+		assert!(scene.diagnostics.iter().any(|d| d.contains("Points")));
+		assert!(scene.diagnostics.iter().any(|d| d.contains("KHR_materials_emissive_strength")));
+
+		// COLOR_0 is multiplied by the base color factor.
+		let triangle = &mesh.primitives[0].mesh;
+		assert_eq!(Some(0), mesh.primitives[0].material);
+		assert_eq!(Vector4::new(0.5, 0.0, 0.0, 1.0), triangle.vertices[0].color);
+		assert_eq!(Vector4::new(0.0, 1.0, 0.0, 1.0), triangle.vertices[1].color);
+		assert_eq!(Vector2::new(0.0, 1.0), triangle.vertices[0].uv);
+
+		// The triangle strip becomes a list, and has no material.
+		let strip = &mesh.primitives[1].mesh;
+		assert_eq!(2, strip.triangle_count());
+		assert_eq!(None, mesh.primitives[1].material);
+		assert_eq!(Vector4::new(1.0, 1.0, 1.0, 1.0), strip.vertices[0].color);
+
+		// Mirrored into left-handed space with the winding reversed, so faces
+		// still point along their normals.
+		assert_near(Vector3::new(0.0, 0.0, -1.0), triangle.vertices[0].normal);
+		for mesh in &[triangle, strip]
+		{
+			for [a, b, c] in mesh.triangles()
+			{
+				let p = |n : u32| mesh.vertices[n as usize].position;
+				assert!((p(b) - p(a)).cross(p(c) - p(a)).dot(Vector3::new(0.0, 0.0, -1.0)) > 0.0);
+			}
+		}
+
+		// root: translate z by 1 and rotate 90 degrees about y. child: scale 2.
+		// In glTF space (1, 0, 0) ends up at (0, 0, -1), so (0, 0, 1) here.
+		let world = scene.flatten();
+		assert_eq!(3 + 4, world.vertices.len());
+		assert_near(Vector3::new(0.0, 0.0, 1.0), world.vertices[1].position);
+		assert_near(Vector3::new(0.0, 2.0, -1.0), world.vertices[2].position);
+		assert_near(Vector3::new(1.0, 0.0, 0.0), world.vertices[0].normal);
+		assert_near(
+			Vector3::new(-2.0, 0.0, -1.0),
+			(scene.nodes[1].world_transform * Vector4::new(0.0, 0.0, 1.0, 1.0)).truncate(),
+		);
+	}
+
+	#[test]
+	fn test_load_gltf_with_bin()
+	{
+		check_two_nodes(&load_gltf(&fixture("two_nodes.gltf")).unwrap());
+	}
+
+	#[test]
+	fn test_load_glb()
+	{
+		check_two_nodes(&load_gltf(&fixture("two_nodes.glb")).unwrap());
+	}
+
+	#[test]
+	fn test_load_animation()
+	{
+		let scene = load_gltf(&fixture("animated.gltf")).unwrap();
+		assert_eq!(1, scene.animations.len());
+		let animation = &scene.animations[0];
+		assert_eq!(Some("wiggle".to_string()), animation.name);
+		assert_eq!(1, animation.nodes.len());
+		assert_eq!(0, animation.nodes[0].node);
+
+		// Mirrored into left-handed space like the node transforms.
+		let clip = &animation.nodes[0].clip;
+		let translation = clip.translation.as_ref().unwrap();
+		assert_eq!(Interpolation::Linear, translation.interpolation());
+		assert_eq!(Wrap::Loop, translation.wrap());
+		assert_near(Vector3::new(0.5, 1.0, -1.5), translation.sample(0.5));
+
+		// 90 degrees about y in glTF is -90 degrees here, held until the end.
+		let rotation = clip.rotation.as_ref().unwrap();
+		assert_eq!(Interpolation::Step, rotation.interpolation());
+		assert_eq!(Quaternion::one(), rotation.sample(0.9));
+		let turned = rotation.keys()[1].value;
+		assert!(turned.dot(Quaternion::from_angle_y(Deg(-90.0))) > 1.0 - EPSILON);
+
+		// Cubic outputs are split into tangents and values.
+		let scale = clip.scale.as_ref().unwrap();
+		assert_eq!(Interpolation::CubicHermite, scale.interpolation());
+		assert_eq!(2, scale.keys().len());
+		assert_eq!(Vector3::new(1.0, 0.0, 0.0), scale.keys()[0].out_tangent);
+		assert_eq!(Vector3::new(0.0, 1.0, 0.0), scale.keys()[1].in_tangent);
+		assert_near(Vector3::new(2.0, 2.0, 2.0), scale.keys()[1].value);
+		assert_eq!(None, clip.color);
+	}
+
+	#[test]
+	fn test_unsupported_required_extension()
+	{
+		match load_gltf(&fixture("unsupported.gltf"))
+		{
+			Err(
+				error @ GltfError::UnsupportedExtensions {
+					..
+				},
+			) =>
+			{
+				let message = error.to_string();
+				assert!(message.ends_with("requires unsupported glTF extensions: KHR_draco_mesh_compression"));
+			}
+			other => panic!("expected an unsupported extension error, got {:?}", other.map(|_| ())),
+		}
+	}
+
+	#[test]
+	fn test_invalid_node_hierarchies()
+	{
+		for (file, node, details) in [
+			("node_cycle.gltf", 0, "is its own ancestor"),
+			("shared_child.gltf", 2, "is a child of both node 0 and node 1"),
+		]
+		.iter()
+		{
+			match load_gltf(&fixture(file))
+			{
+				Err(
+					error @ GltfError::InvalidNode {
+						..
+					},
+				) =>
+				{
+					let message = error.to_string();
+					assert!(message.contains(&format!("node {}: {}", node, details)), "{}", message);
+				}
+				other => panic!("expected an invalid node error for {}, got {:?}", file, other.map(|_| ())),
+			}
+		}
+	}
+
+	#[test]
+	fn test_node_outside_scene()
+	{
+		// Like two_nodes.gltf, with a third node using the mesh that no scene
+		// includes.
+		let scene = load_gltf(&fixture("stray_node.gltf")).unwrap();
+		assert_eq!(3, scene.nodes.len());
+		assert!(scene.nodes[0].in_scene && scene.nodes[1].in_scene);
+		assert!(!scene.nodes[2].in_scene);
+		assert!(scene.diagnostics.iter().any(|d| d.starts_with("node 2:")), "{:?}", scene.diagnostics);
+		assert_eq!(3 + 4, scene.flatten().vertices.len());
+	}
+
+	#[test]
+	fn test_missing_file()
+	{
+		match load_gltf(&fixture("does_not_exist.gltf"))
+		{
+			Err(GltfError::Load {
+				..
+			}) => (),
+			other => panic!("expected a load error, got {:?}", other.map(|_| ())),
+		}
+	}
+}
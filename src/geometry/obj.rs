@@ -0,0 +1,818 @@
+// Wavefront OBJ/MTL loader and OBJ writer.
+//
+// OBJ is right-handed with counter-clockwise front faces and texture v
+// pointing up. Everything is converted on load to the conventions used by the
+// rest of the geometry module: left-handed (z is negated), triangles wound so
+// (b - a) x (c - a) faces outwards, and texture v pointing down.
+//
+// Vertex colors come from the common `v x y z r g b` extension when present,
+// otherwise from the diffuse color of the active material. Normals that are
+// missing from the file are left as zero. The writer converts back and always
+// writes vertex colors with that extension, which drops their alpha.
+use super::{
+	Mesh,
+	MeshVertex,
+};
+use cgmath::*;
+use std::{
+	collections::HashMap,
+	convert::TryFrom,
+	error::Error,
+	fmt,
+	fs,
+	io::{
+		self,
+		Write,
+	},
+	path::{
+		Path,
+		PathBuf,
+	},
+	str::SplitWhitespace,
+};
+
+#[derive(Debug)]
+pub enum ObjError
+{
+	Io
+	{
+		path :  PathBuf,
+		error : io::Error,
+	},
+	Parse
+	{
+		file :    String,
+		line :    usize,
+		details : String,
+	},
+}
+
+impl fmt::Display for ObjError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			ObjError::Io {
+				path,
+				error,
+			} => write!(f, "{}: {}", path.display(), error),
+			ObjError::Parse {
+				file,
+				line,
+				details,
+			} => write!(f, "{}:{}: {}", file, line, details),
+		}
+	}
+}
+
+impl Error for ObjError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial
+{
+	pub name : String,
+	pub ambient : Vector3<f32>,
+	pub diffuse : Vector3<f32>,
+	pub specular : Vector3<f32>,
+	pub shininess : f32,
+	pub dissolve : f32,
+	pub diffuse_texture : Option<String>,
+}
+
+impl ObjMaterial
+{
+	pub fn new(name : &str) -> Self
+	{
+		Self {
+			name : name.to_string(),
+			ambient : Vector3::zero(),
+			diffuse : Vector3::new(1.0, 1.0, 1.0),
+			specular : Vector3::zero(),
+			shininess : 0.0,
+			dissolve : 1.0,
+			diffuse_texture : None,
+		}
+	}
+}
+
+// A run of triangles in `ObjModel::mesh` sharing a group name and material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjGroup
+{
+	pub name :        String,
+	pub material :    Option<String>,
+	pub first_index : usize,
+	pub index_count : usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjModel
+{
+	pub mesh :      Mesh<MeshVertex>,
+	pub groups :    Vec<ObjGroup>,
+	pub materials : Vec<ObjMaterial>,
+}
+
+struct LineParser<'a>
+{
+	file :   &'a str,
+	line :   usize,
+	tokens : SplitWhitespace<'a>,
+}
+
+impl<'a> LineParser<'a>
+{
+	fn error(&self, details : String) -> ObjError
+	{
+		ObjError::Parse {
+			file :    self.file.to_string(),
+			line :    self.line,
+			details : details,
+		}
+	}
+
+	fn rest(&mut self) -> Vec<&'a str>
+	{
+		self.tokens.by_ref().collect()
+	}
+
+	fn name(&mut self, directive : &str) -> Result<String, ObjError>
+	{
+		let rest = self.rest();
+		if rest.is_empty()
+		{
+			return Err(self.error(format!("'{}' expects a name", directive)));
+		}
+		Ok(rest.join(" "))
+	}
+
+	// Parses between `min` and `max` floats from the rest of the line.
+	fn floats(&mut self, directive : &str, min : usize, max : usize) -> Result<Vec<f32>, ObjError>
+	{
+		let tokens = self.rest();
+		if tokens.len() < min || tokens.len() > max
+		{
+			return Err(self.error(format!(
+				"'{}' expects {} to {} numbers, found {}",
+				directive,
+				min,
+				max,
+				tokens.len()
+			)));
+		}
+
+		let mut values = Vec::with_capacity(tokens.len());
+		for token in tokens
+		{
+			match token.parse::<f32>()
+			{
+				Ok(value) if value.is_finite() => values.push(value),
+				_ => return Err(self.error(format!("'{}' has an invalid number '{}'", directive, token))),
+			}
+		}
+		Ok(values)
+	}
+
+	fn vector3(&mut self, directive : &str) -> Result<Vector3<f32>, ObjError>
+	{
+		let values = self.floats(directive, 3, 3)?;
+		Ok(Vector3::new(values[0], values[1], values[2]))
+	}
+}
+
+fn parse_lines<'a>(file : &'a str, source : &'a str) -> impl Iterator<Item = (&'a str, LineParser<'a>)>
+{
+	source.lines().enumerate().filter_map(move |(n, text)| {
+		let text = match text.find('#')
+		{
+			Some(comment) => &text[..comment],
+			None => text,
+		};
+		let mut tokens = text.split_whitespace();
+		tokens.next().map(|directive| {
+			(
+				directive,
+				LineParser {
+					file :   file,
+					line :   n + 1,
+					tokens : tokens,
+				},
+			)
+		})
+	})
+}
+
+// Parses the contents of an MTL file. `file` is only used in error messages.
+pub fn parse_mtl(file : &str, source : &str) -> Result<Vec<ObjMaterial>, ObjError>
+{
+	let mut materials : Vec<ObjMaterial> = Vec::new();
+
+	for (directive, mut line) in parse_lines(file, source)
+	{
+		if directive == "newmtl"
+		{
+			let name = line.name(directive)?;
+			materials.push(ObjMaterial::new(&name));
+			continue;
+		}
+
+		let material = match materials.last_mut()
+		{
+			Some(material) => material,
+			None => return Err(line.error(format!("'{}' appears before any 'newmtl'", directive))),
+		};
+
+		match directive
+		{
+			"Ka" => material.ambient = line.vector3(directive)?,
+			"Kd" => material.diffuse = line.vector3(directive)?,
+			"Ks" => material.specular = line.vector3(directive)?,
+			"Ns" => material.shininess = line.floats(directive, 1, 1)?[0],
+			"d" => material.dissolve = line.floats(directive, 1, 1)?[0],
+			"Tr" => material.dissolve = 1.0 - line.floats(directive, 1, 1)?[0],
+			"map_Kd" =>
+			{
+				// Options such as -s or -o come before the file name.
+				let tokens = line.rest();
+				match tokens.last()
+				{
+					Some(texture) => material.diffuse_texture = Some(texture.to_string()),
+					None => return Err(line.error("'map_Kd' expects a file name".to_string())),
+				}
+			}
+			_ => (), // illum, Ke, Ni, other maps, ... are not used by the renderer.
+		}
+	}
+
+	Ok(materials)
+}
+
+// One corner of a face: (position, texcoord, normal), already resolved to
+// zero-based indices.
+type FaceCorner = (usize, Option<usize>, Option<usize>);
+
+fn resolve_index(line : &LineParser, token : &str, count : usize, what : &str) -> Result<usize, ObjError>
+{
+	let index = token.parse::<i64>().map_err(|_| line.error(format!("invalid {} index '{}'", what, token)))?;
+
+	// Positive indices are 1-based, negative ones count back from the last
+	// element defined so far.
+	let resolved = if index > 0
+	{
+		index - 1
+	}
+	else
+	{
+		count as i64 + index
+	};
+	if index == 0 || resolved < 0 || resolved >= count as i64
+	{
+		return Err(line.error(format!("{} index {} is out of range of {} defined", what, index, count)));
+	}
+	Ok(resolved as usize)
+}
+
+fn parse_face_corner(
+	line : &LineParser, token : &str, positions : usize, texcoords : usize, normals : usize,
+) -> Result<FaceCorner, ObjError>
+{
+	if token.split('/').count() > 3
+	{
+		return Err(line.error(format!("face corner '{}' has too many components", token)));
+	}
+
+	let mut parts = token.split('/');
+	let position = resolve_index(line, parts.next().unwrap_or(""), positions, "position")?;
+	let texcoord = match parts.next()
+	{
+		None | Some("") => None,
+		Some(part) => Some(resolve_index(line, part, texcoords, "texcoord")?),
+	};
+	let normal = match parts.next()
+	{
+		None | Some("") => None,
+		Some(part) => Some(resolve_index(line, part, normals, "normal")?),
+	};
+	Ok((position, texcoord, normal))
+}
+
+// Splits a polygon into triangles by ear clipping, so concave faces are
+// handled. Returns indices into `polygon`, in the polygon's own winding.
+fn triangulate(polygon : &[Vector3<f32>]) -> Vec<[usize; 3]>
+{
+	if polygon.len() == 3
+	{
+		return vec![[0, 1, 2]];
+	}
+
+	// Newell's method gives a robust normal even for concave polygons.
+	let mut normal = Vector3::zero();
+	for n in 0..polygon.len()
+	{
+		let a = polygon[n];
+		let b = polygon[(n + 1) % polygon.len()];
+		normal += Vector3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y));
+	}
+
+	// Project onto the plane that drops the dominant axis, keeping the polygon
+	// counter-clockwise in 2D.
+	let abs = normal.map(f32::abs);
+	let project : Box<dyn Fn(Vector3<f32>) -> Vector2<f32>> = if abs.x >= abs.y && abs.x >= abs.z
+	{
+		let sign = normal.x.signum();
+		Box::new(move |p| Vector2::new(p.y, p.z * sign))
+	}
+	else if abs.y >= abs.z
+	{
+		let sign = normal.y.signum();
+		Box::new(move |p| Vector2::new(p.z, p.x * sign))
+	}
+	else
+	{
+		let sign = normal.z.signum();
+		Box::new(move |p| Vector2::new(p.x, p.y * sign))
+	};
+	let points : Vec<Vector2<f32>> = polygon.iter().map(|&p| project(p)).collect();
+
+	let cross = |o : Vector2<f32>, a : Vector2<f32>, b : Vector2<f32>| (a - o).perp_dot(b - o);
+	let contains = |a : Vector2<f32>, b : Vector2<f32>, c : Vector2<f32>, p : Vector2<f32>| {
+		cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+	};
+
+	let mut remaining : Vec<usize> = (0..polygon.len()).collect();
+	let mut triangles = Vec::with_capacity(polygon.len() - 2);
+	while remaining.len() > 3
+	{
+		let count = remaining.len();
+		let ear = (0..count).find(|&n| {
+			let (prev, current, next) = (remaining[(n + count - 1) % count], remaining[n], remaining[(n + 1) % count]);
+			let (a, b, c) = (points[prev], points[current], points[next]);
+			cross(a, b, c) > 0.0
+				&& remaining.iter().filter(|&&other| other != prev && other != current && other != next).all(|&other| {
+					points[other] == a || points[other] == b || points[other] == c || !contains(a, b, c, points[other])
+				})
+		});
+
+		// Self-intersecting or degenerate polygons have no ears left; fall back
+		// to a fan for whatever remains.
+		let n = ear.unwrap_or(1);
+		triangles.push([remaining[(n + count - 1) % count], remaining[n], remaining[(n + 1) % count]]);
+		remaining.remove(n);
+	}
+	triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+	triangles
+}
+
+// Parses the contents of an OBJ file. `file` is only used in error messages.
+// `load_material_library` is called with the name given to each `mtllib`.
+pub fn parse_obj<F>(file : &str, source : &str, mut load_material_library : F) -> Result<ObjModel, ObjError>
+where
+	F : FnMut(&str) -> Result<Vec<ObjMaterial>, ObjError>,
+{
+	let mut positions : Vec<Vector3<f32>> = Vec::new();
+	let mut colors : Vec<Option<Vector4<f32>>> = Vec::new();
+	let mut texcoords : Vec<Vector2<f32>> = Vec::new();
+	let mut normals : Vec<Vector3<f32>> = Vec::new();
+	let mut materials : Vec<ObjMaterial> = Vec::new();
+
+	let mut vertices : Vec<MeshVertex> = Vec::new();
+	let mut indices : Vec<u32> = Vec::new();
+	let mut vertex_lookup = HashMap::<(FaceCorner, Option<usize>), u32>::new();
+
+	let mut groups : Vec<ObjGroup> = Vec::new();
+	let mut group_name = String::from("default");
+	let mut material : Option<usize> = None;
+
+	let start_group =
+		|groups : &mut Vec<ObjGroup>, name : &str, material : Option<&ObjMaterial>, first_index : usize| {
+			if groups.last().map_or(false, |group| group.index_count == 0)
+			{
+				groups.pop();
+			}
+			groups.push(ObjGroup {
+				name :        name.to_string(),
+				material :    material.map(|m| m.name.clone()),
+				first_index : first_index,
+				index_count : 0,
+			});
+		};
+	start_group(&mut groups, &group_name, None, 0);
+
+	for (directive, mut line) in parse_lines(file, source)
+	{
+		match directive
+		{
+			"v" =>
+			{
+				// x y z [w] or x y z r g b
+				let values = line.floats(directive, 3, 6)?;
+				positions.push(Vector3::new(values[0], values[1], values[2]));
+				colors.push(match values.len()
+				{
+					6 => Some(Vector4::new(values[3], values[4], values[5], 1.0)),
+					3 | 4 => None,
+					count => return Err(line.error(format!("'v' expects 3, 4 or 6 numbers, found {}", count))),
+				});
+			}
+			"vt" =>
+			{
+				let values = line.floats(directive, 1, 3)?;
+				texcoords.push(Vector2::new(values[0], values.get(1).cloned().unwrap_or(0.0)));
+			}
+			"vn" => normals.push(line.vector3(directive)?),
+			"f" =>
+			{
+				let tokens = line.rest();
+				if tokens.len() < 3
+				{
+					return Err(line.error(format!("face needs at least 3 corners, found {}", tokens.len())));
+				}
+
+				let mut corners = Vec::with_capacity(tokens.len());
+				for token in tokens
+				{
+					corners.push(parse_face_corner(&line, token, positions.len(), texcoords.len(), normals.len())?);
+				}
+
+				let polygon : Vec<Vector3<f32>> = corners.iter().map(|corner| positions[corner.0]).collect();
+				for [a, b, c] in triangulate(&polygon)
+				{
+					// Reversed, since mirroring z flips the winding.
+					for &n in &[a, c, b]
+					{
+						let corner = corners[n];
+						let index = *vertex_lookup.entry((corner, material)).or_insert_with(|| {
+							let (position, texcoord, normal) = corner;
+							let color = colors[position].unwrap_or_else(|| match material
+							{
+								Some(m) => materials[m].diffuse.extend(materials[m].dissolve),
+								None => Vector4::new(1.0, 1.0, 1.0, 1.0),
+							});
+							let p = positions[position];
+							let t = texcoord.map_or(Vector2::zero(), |t| texcoords[t]);
+							let n = normal.map_or(Vector3::zero(), |n| normals[n]);
+							vertices.push(MeshVertex {
+								position : Vector3::new(p.x, p.y, -p.z),
+								normal :   Vector3::new(n.x, n.y, -n.z),
+								uv :       Vector2::new(t.x, 1.0 - t.y),
+								color :    color,
+							});
+							(vertices.len() - 1) as u32
+						});
+						indices.push(index);
+					}
+				}
+				groups.last_mut().unwrap().index_count = indices.len() - groups.last().unwrap().first_index;
+			}
+			"g" | "o" =>
+			{
+				group_name = line.name(directive)?;
+				start_group(&mut groups, &group_name, material.map(|m| &materials[m]), indices.len());
+			}
+			"usemtl" =>
+			{
+				let name = line.name(directive)?;
+				match materials.iter().rposition(|m| m.name == name)
+				{
+					Some(found) => material = Some(found),
+					None => return Err(line.error(format!("unknown material '{}'", name))),
+				}
+				start_group(&mut groups, &group_name, material.map(|m| &materials[m]), indices.len());
+			}
+			"mtllib" =>
+			{
+				for library in line.rest()
+				{
+					materials.extend(load_material_library(library)?);
+				}
+			}
+			_ => (), // s, l, p, curves and other directives are ignored.
+		}
+	}
+
+	if groups.last().map_or(false, |group| group.index_count == 0)
+	{
+		groups.pop();
+	}
+
+	if u32::try_from(vertices.len()).is_err()
+	{
+		return Err(ObjError::Parse {
+			file :    file.to_string(),
+			line :    0,
+			details : format!("{} vertices do not fit in 32-bit indices", vertices.len()),
+		});
+	}
+
+	Ok(ObjModel {
+		mesh :      Mesh::new(vertices, indices),
+		groups :    groups,
+		materials : materials,
+	})
+}
+
+fn read_file(path : &Path) -> Result<String, ObjError>
+{
+	fs::read_to_string(path).map_err(|error| ObjError::Io {
+		path :  path.to_path_buf(),
+		error : error,
+	})
+}
+
+// Loads an MTL file from disk.
+#[allow(dead_code)]
+pub fn load_mtl(path : &Path) -> Result<Vec<ObjMaterial>, ObjError>
+{
+	parse_mtl(&path.display().to_string(), &read_file(path)?)
+}
+
+// Loads an OBJ file from disk, along with any material libraries it
+// references relative to its own directory.
+#[allow(dead_code)]
+pub fn load_obj(path : &Path) -> Result<ObjModel, ObjError>
+{
+	let directory = path.parent().unwrap_or_else(|| Path::new(""));
+	parse_obj(&path.display().to_string(), &read_file(path)?, |library| load_mtl(&directory.join(library)))
+}
+
+// Writes positions with vertex colors, texture coordinates and normals, one
+// of each per vertex.
+pub fn write_obj<W : Write>(writer : &mut W, mesh : &Mesh<MeshVertex>) -> io::Result<()>
+{
+	for vertex in &mesh.vertices
+	{
+		let (p, c) = (vertex.position, vertex.color);
+		writeln!(writer, "v {} {} {} {} {} {}", p.x, p.y, -p.z, c.x, c.y, c.z)?;
+	}
+	for vertex in &mesh.vertices
+	{
+		writeln!(writer, "vt {} {}", vertex.uv.x, 1.0 - vertex.uv.y)?;
+	}
+	for vertex in &mesh.vertices
+	{
+		writeln!(writer, "vn {} {} {}", vertex.normal.x, vertex.normal.y, -vertex.normal.z)?;
+	}
+
+	for [a, b, c] in mesh.triangles()
+	{
+		// Reversed winding for the mirrored z, and indices start at 1.
+		let (a, b, c) = (a + 1, b + 1, c + 1);
+		writeln!(writer, "f {}/{}/{} {}/{}/{} {}/{}/{}", a, a, a, c, c, c, b, b, b)?;
+	}
+
+	Ok(())
+}
+
+// Saves a mesh to an OBJ file on disk.
+#[allow(dead_code)]
+pub fn save_obj(path : &Path, mesh : &Mesh<MeshVertex>) -> Result<(), ObjError>
+{
+	let io_error = |error| ObjError::Io {
+		path :  path.to_path_buf(),
+		error : error,
+	};
+
+	let mut writer = io::BufWriter::new(fs::File::create(path).map_err(io_error)?);
+	write_obj(&mut writer, mesh).map_err(io_error)?;
+	writer.flush().map_err(io_error)
+}
+
+#[cfg(test)]
+mod obj_tests
+{
+	use crate::geometry::{
+		obj::{
+			load_obj,
+			parse_mtl,
+			parse_obj,
+			write_obj,
+			ObjError,
+			ObjMaterial,
+		},
+		primitives::icosphere,
+	};
+	use cgmath::*;
+	use std::path::Path;
+
+	const EPSILON : f32 = 1.0e-5;
+
+	fn fixture(name : &str) -> std::path::PathBuf
+	{
+		Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("obj").join(name)
+	}
+
+	fn no_materials(library : &str) -> Result<Vec<ObjMaterial>, ObjError>
+	{
+		panic!("unexpected material library {}", library)
+	}
+
+	fn parse_error(source : &str) -> (usize, String)
+	{
+		match parse_obj("test.obj", source, |_| Ok(vec![ObjMaterial::new("red")]))
+		{
+			Err(ObjError::Parse {
+				line,
+				details,
+				..
+			}) => (line, details),
+			Err(error) => panic!("expected a parse error, got {}", error),
+			Ok(_) => panic!("expected a parse error for {:?}", source),
+		}
+	}
+
+	#[test]
+	fn test_load_cube_fixture()
+	{
+		let model = load_obj(&fixture("cube.obj")).unwrap();
+
+		// 6 faces, each with its own normal and 4 corners.
+		assert_eq!(24, model.mesh.vertices.len());
+		assert_eq!(12, model.mesh.triangle_count());
+
+		assert_eq!(2, model.materials.len());
+		assert_eq!(Some("checker.png".to_string()), model.materials[0].diffuse_texture);
+
+		let names : Vec<(&str, Option<&str>)> =
+			model.groups.iter().map(|g| (g.name.as_str(), g.material.as_ref().map(|m| m.as_str()))).collect();
+		assert_eq!(vec![("sides", Some("white")), ("caps", Some("red"))], names);
+		assert_eq!(24, model.groups[0].index_count);
+		assert_eq!(24, model.groups[1].first_index);
+		assert_eq!(12, model.groups[1].index_count);
+
+		// Winding is converted so every face points along its normal.
+		for [a, b, c] in model.mesh.triangles()
+		{
+			let v = |n : u32| model.mesh.vertices[n as usize];
+			let face_normal = (v(b).position - v(a).position).cross(v(c).position - v(a).position);
+			assert!(face_normal.dot(v(a).normal) > 0.0);
+			assert!(v(a).position.dot(v(a).normal) > 0.0);
+		}
+
+		// Material colors become vertex colors.
+		let top = model.mesh.vertices.iter().find(|v| v.normal.y > 0.5).unwrap();
+		assert_eq!(Vector4::new(1.0, 0.0, 0.0, 1.0), top.color);
+		let side = model.mesh.vertices.iter().find(|v| v.normal.x > 0.5).unwrap();
+		assert_eq!(Vector4::new(1.0, 1.0, 1.0, 1.0), side.color);
+	}
+
+	#[test]
+	fn test_load_polygons_fixture()
+	{
+		let model = load_obj(&fixture("polygons.obj")).unwrap();
+
+		// A concave "L" hexagon, then a quad that uses negative indices.
+		assert_eq!(4 + 2, model.mesh.triangle_count());
+
+		let area = |triangles : std::ops::Range<usize>| -> f32 {
+			triangles
+				.map(|t| {
+					let [a, b, c] = model.mesh.triangle(t);
+					let p = |n : u32| model.mesh.vertices[n as usize].position;
+					(p(b) - p(a)).cross(p(c) - p(a)).magnitude() * 0.5
+				})
+				.sum()
+		};
+		assert!((area(0..4) - 3.0).abs() < EPSILON, "ear clipping must not cover the notch of the L");
+		assert!((area(4..6) - 1.0).abs() < EPSILON);
+
+		// Per-vertex colors from the `v x y z r g b` extension.
+		assert!(model.mesh.vertices.iter().any(|v| v.color == Vector4::new(0.0, 1.0, 0.0, 1.0)));
+
+		// z is mirrored and texture v flipped.
+		let corner = model.mesh.vertices.iter().find(|v| v.position == Vector3::new(0.0, 0.0, -5.0)).unwrap();
+		assert!((corner.uv - Vector2::new(0.25, 0.25)).magnitude() < EPSILON);
+	}
+
+	#[test]
+	fn test_shared_vertices()
+	{
+		let source = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n";
+		let model = parse_obj("quad.obj", source, no_materials).unwrap();
+		assert_eq!(4, model.mesh.vertices.len());
+		assert_eq!(2, model.mesh.triangle_count());
+		assert_eq!(1, model.groups.len());
+		assert_eq!("default", model.groups[0].name);
+	}
+
+	#[test]
+	fn test_negative_indices()
+	{
+		let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf -3/-1/-1 -2/-1/-1 -1/-1/-1\n";
+		let model = parse_obj("negative.obj", source, no_materials).unwrap();
+		assert_eq!(3, model.mesh.vertices.len());
+		assert!(model.mesh.vertices.iter().all(|v| v.normal == Vector3::new(0.0, 0.0, -1.0)));
+	}
+
+	#[test]
+	fn test_parse_errors_report_lines()
+	{
+		assert_eq!((2, "'v' has an invalid number 'abc'".to_string()), parse_error("v 0 0 0\nv 0 abc 0\n"));
+		assert_eq!((1, "'v' expects 3 to 6 numbers, found 2".to_string()), parse_error("v 0 0\n"));
+		assert_eq!((2, "'v' expects 3, 4 or 6 numbers, found 5".to_string()), parse_error("\nv 0 0 0 1 1\n"));
+		assert_eq!(
+			(4, "position index 4 is out of range of 3 defined".to_string()),
+			parse_error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n")
+		);
+		assert_eq!((2, "position index 0 is out of range of 1 defined".to_string()), parse_error("v 0 0 0\nf 0 1 1\n"));
+		assert_eq!(
+			(2, "position index -2 is out of range of 1 defined".to_string()),
+			parse_error("v 0 0 0\nf -2 1 1\n")
+		);
+		assert_eq!(
+			(2, "texcoord index 1 is out of range of 0 defined".to_string()),
+			parse_error("v 0 0 0\nf 1/1 1/1 1/1\n")
+		);
+		assert_eq!((2, "face needs at least 3 corners, found 2".to_string()), parse_error("v 0 0 0\nf 1 1\n"));
+		assert_eq!((2, "invalid position index 'x'".to_string()), parse_error("v 0 0 0\nf x 1 1\n"));
+		assert_eq!(
+			(2, "face corner '1/1/1/1' has too many components".to_string()),
+			parse_error("v 0 0 0\nf 1/1/1/1 1 1\n")
+		);
+		assert_eq!((2, "unknown material 'blue'".to_string()), parse_error("mtllib a.mtl\nusemtl blue\n"));
+		assert_eq!((1, "'v' has an invalid number 'inf'".to_string()), parse_error("v inf 0 0\n"));
+	}
+
+	#[test]
+	fn test_malformed_input_does_not_panic()
+	{
+		let inputs = [
+			"",
+			"#only a comment",
+			"f",
+			"v",
+			"vt",
+			"vn 1 2",
+			"g",
+			"usemtl",
+			"f 1//",
+			"f //1 //1 //1",
+			"v 1 2 3\nf 1/ 1/ 1/",
+			"v 1 2 3\nf 99999999999999999999 1 1",
+			"v 1 2 3\nf -99999999999999999999 1 1",
+			"v nan 0 0",
+			"\u{0}\u{1}\u{2}",
+		];
+		for input in inputs.iter()
+		{
+			let _ = parse_obj("fuzz.obj", input, |_| Ok(Vec::new()));
+		}
+
+		// Degenerate polygons still triangulate without panicking.
+		let model = parse_obj("line.obj", "v 0 0 0\nv 1 0 0\nv 2 0 0\nv 3 0 0\nf 1 2 3 4\n", no_materials).unwrap();
+		assert_eq!(2, model.mesh.triangle_count());
+	}
+
+	#[test]
+	fn test_parse_mtl()
+	{
+		let materials = parse_mtl("test.mtl", "newmtl a\nKd 0.5 0.25 1\nTr 0.25\nnewmtl b\nNs 10\n").unwrap();
+		assert_eq!(2, materials.len());
+		assert_eq!(Vector3::new(0.5, 0.25, 1.0), materials[0].diffuse);
+		assert_eq!(0.75, materials[0].dissolve);
+		assert_eq!(10.0, materials[1].shininess);
+
+		match parse_mtl("test.mtl", "Kd 1 1 1\n")
+		{
+			Err(error) => assert_eq!("test.mtl:1: 'Kd' appears before any 'newmtl'", error.to_string()),
+			Ok(_) => panic!("expected an error"),
+		}
+	}
+
+	#[test]
+	fn test_write_round_trip()
+	{
+		let mut mesh = icosphere(1.5, 2);
+		for (n, vertex) in mesh.vertices.iter_mut().enumerate()
+		{
+			vertex.color = Vector4::new((n % 7) as f32 / 7.0, 0.5, 1.0, 1.0);
+		}
+
+		let mut data = Vec::new();
+		write_obj(&mut data, &mesh).unwrap();
+		let loaded = parse_obj("round_trip.obj", std::str::from_utf8(&data).unwrap(), no_materials).unwrap();
+
+		assert_eq!(mesh.vertices.len(), loaded.mesh.vertices.len());
+		assert_eq!(mesh.triangle_count(), loaded.mesh.triangle_count());
+		for (expected, actual) in mesh.to_triangle_soup().iter().zip(loaded.mesh.to_triangle_soup().iter())
+		{
+			assert_eq!(expected.position, actual.position);
+			assert_eq!(expected.normal, actual.normal);
+			assert!((expected.uv - actual.uv).magnitude() < EPSILON);
+			assert_eq!(expected.color, actual.color);
+		}
+	}
+
+	#[test]
+	fn test_missing_file()
+	{
+		match load_obj(&fixture("does_not_exist.obj"))
+		{
+			Err(ObjError::Io {
+				..
+			}) => (),
+			_ => panic!("expected an io error"),
+		}
+	}
+}
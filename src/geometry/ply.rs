@@ -0,0 +1,874 @@
+// Stanford PLY reader and writer, for ASCII and both binary byte orders.
+//
+// PLY has no fixed handedness; like OBJ, files are treated as right-handed
+// with counter-clockwise front faces and texture v pointing up, and are
+// converted to the conventions of the geometry module on load: z is negated,
+// winding is reversed and v is flipped. Saving converts back.
+//
+// The vertex element may carry x/y/z, nx/ny/nz, s/t (or u/v) and
+// red/green/blue/alpha, where integer colors are normalized to 0..1. Faces
+// with more than three corners are fan triangulated, and any other element
+// or property is skipped.
+use super::{
+	Mesh,
+	MeshVertex,
+};
+use cgmath::*;
+use std::{
+	error::Error,
+	fmt,
+	fs,
+	io,
+	io::Write,
+	path::{
+		Path,
+		PathBuf,
+	},
+	str::SplitWhitespace,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PlyFormat
+{
+	Ascii,
+	BinaryLittleEndian,
+	BinaryBigEndian,
+}
+
+#[derive(Debug)]
+pub enum PlyError
+{
+	Io
+	{
+		path :  PathBuf,
+		error : io::Error,
+	},
+	Parse
+	{
+		file :     String,
+		location : String,
+		details :  String,
+	},
+}
+
+impl fmt::Display for PlyError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			PlyError::Io {
+				path,
+				error,
+			} => write!(f, "{}: {}", path.display(), error),
+			PlyError::Parse {
+				file,
+				location,
+				details,
+			} => write!(f, "{}: {}: {}", file, location, details),
+		}
+	}
+}
+
+impl Error for PlyError {}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ScalarType
+{
+	I8,
+	U8,
+	I16,
+	U16,
+	I32,
+	U32,
+	F32,
+	F64,
+}
+
+impl ScalarType
+{
+	fn parse(name : &str) -> Option<Self>
+	{
+		match name
+		{
+			"char" | "int8" => Some(ScalarType::I8),
+			"uchar" | "uint8" => Some(ScalarType::U8),
+			"short" | "int16" => Some(ScalarType::I16),
+			"ushort" | "uint16" => Some(ScalarType::U16),
+			"int" | "int32" => Some(ScalarType::I32),
+			"uint" | "uint32" => Some(ScalarType::U32),
+			"float" | "float32" => Some(ScalarType::F32),
+			"double" | "float64" => Some(ScalarType::F64),
+			_ => None,
+		}
+	}
+
+	fn size(self) -> usize
+	{
+		match self
+		{
+			ScalarType::I8 | ScalarType::U8 => 1,
+			ScalarType::I16 | ScalarType::U16 => 2,
+			ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+			ScalarType::F64 => 8,
+		}
+	}
+
+	fn is_integer(self) -> bool
+	{
+		self != ScalarType::F32 && self != ScalarType::F64
+	}
+
+	// Largest value of an unsigned type, used to normalize integer colors.
+	fn unorm_max(self) -> f64
+	{
+		match self
+		{
+			ScalarType::U8 => 255.0,
+			ScalarType::U16 => 65535.0,
+			ScalarType::U32 => 4294967295.0,
+			_ => 1.0,
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+enum PropertyType
+{
+	Scalar(ScalarType),
+	List
+	{
+		count : ScalarType,
+		item :  ScalarType,
+	},
+}
+
+#[derive(Debug, Clone)]
+struct Property
+{
+	name :          String,
+	property_type : PropertyType,
+}
+
+#[derive(Debug, Clone)]
+struct Element
+{
+	name :       String,
+	count :      usize,
+	properties : Vec<Property>,
+}
+
+struct Header
+{
+	format :      PlyFormat,
+	elements :    Vec<Element>,
+	line_count :  usize,
+	body_offset : usize,
+}
+
+fn parse_error(file : &str, location : String, details : String) -> PlyError
+{
+	PlyError::Parse {
+		file :     file.to_string(),
+		location : location,
+		details :  details,
+	}
+}
+
+fn parse_header(file : &str, data : &[u8]) -> Result<Header, PlyError>
+{
+	let mut format = None;
+	let mut elements : Vec<Element> = Vec::new();
+	let mut offset = 0;
+	let mut line_number = 0;
+
+	loop
+	{
+		line_number += 1;
+		let error = |details : String| parse_error(file, format!("line {}", line_number), details);
+
+		let end = match data[offset..].iter().position(|&b| b == b'\n')
+		{
+			Some(end) => offset + end,
+			None => return Err(error("header is not terminated by 'end_header'".to_string())),
+		};
+		let line = match std::str::from_utf8(&data[offset..end])
+		{
+			Ok(line) => line.trim_end_matches('\r'),
+			Err(_) => return Err(error("header is not valid text".to_string())),
+		};
+		offset = end + 1;
+
+		let mut tokens = line.split_whitespace();
+		let keyword = tokens.next();
+		if line_number == 1
+		{
+			if keyword != Some("ply")
+			{
+				return Err(error("not a PLY file, expected 'ply'".to_string()));
+			}
+			continue;
+		}
+
+		let rest : Vec<&str> = tokens.collect();
+		match keyword
+		{
+			Some("format") => match rest.as_slice()
+			{
+				[name, "1.0"] => match *name
+				{
+					"ascii" => format = Some(PlyFormat::Ascii),
+					"binary_little_endian" => format = Some(PlyFormat::BinaryLittleEndian),
+					"binary_big_endian" => format = Some(PlyFormat::BinaryBigEndian),
+					_ => return Err(error(format!("unknown format '{}'", name))),
+				},
+				_ => return Err(error(format!("unsupported format line '{}'", line))),
+			},
+			Some("element") => match rest.as_slice()
+			{
+				[name, count] => match count.parse::<usize>()
+				{
+					Ok(count) => elements.push(Element {
+						name :       name.to_string(),
+						count :      count,
+						properties : Vec::new(),
+					}),
+					Err(_) => return Err(error(format!("element '{}' has an invalid count '{}'", name, count))),
+				},
+				_ => return Err(error("'element' expects a name and a count".to_string())),
+			},
+			Some("property") =>
+			{
+				let scalar = |name : &str| {
+					ScalarType::parse(name).ok_or_else(|| error(format!("unknown property type '{}'", name)))
+				};
+				let property = match rest.as_slice()
+				{
+					["list", count, item, name] => Property {
+						name :          name.to_string(),
+						property_type : PropertyType::List {
+							count : scalar(count)?,
+							item :  scalar(item)?,
+						},
+					},
+					[scalar_type, name] => Property {
+						name :          name.to_string(),
+						property_type : PropertyType::Scalar(scalar(scalar_type)?),
+					},
+					_ => return Err(error(format!("invalid property line '{}'", line))),
+				};
+				match elements.last_mut()
+				{
+					Some(element) => element.properties.push(property),
+					None => return Err(error("'property' appears before any 'element'".to_string())),
+				}
+			}
+			Some("end_header") => break,
+			Some("comment") | Some("obj_info") | None => (),
+			Some(other) => return Err(error(format!("unknown header keyword '{}'", other))),
+		}
+	}
+
+	match format
+	{
+		Some(format) => Ok(Header {
+			format :      format,
+			elements :    elements,
+			line_count :  line_number,
+			body_offset : offset,
+		}),
+		None => Err(parse_error(file, format!("line {}", line_number), "header has no 'format' line".to_string())),
+	}
+}
+
+// Reads the scalars of the body one at a time, whatever its encoding.
+trait BodyReader
+{
+	fn read(&mut self, scalar_type : ScalarType) -> Result<f64, String>;
+	fn location(&self) -> String;
+}
+
+struct AsciiReader<'a>
+{
+	lines :  std::str::Lines<'a>,
+	tokens : SplitWhitespace<'a>,
+	line :   usize,
+}
+
+impl<'a> BodyReader for AsciiReader<'a>
+{
+	fn read(&mut self, scalar_type : ScalarType) -> Result<f64, String>
+	{
+		let token = loop
+		{
+			if let Some(token) = self.tokens.next()
+			{
+				break token;
+			}
+			match self.lines.next()
+			{
+				Some(line) =>
+				{
+					self.tokens = line.split_whitespace();
+					self.line += 1;
+				}
+				None => return Err("unexpected end of file".to_string()),
+			}
+		};
+
+		match token.parse::<f64>()
+		{
+			Ok(value) if value.is_finite() && (!scalar_type.is_integer() || value.fract() == 0.0) => Ok(value),
+			_ => Err(format!("invalid {:?} value '{}'", scalar_type, token)),
+		}
+	}
+
+	fn location(&self) -> String
+	{
+		format!("line {}", self.line)
+	}
+}
+
+struct BinaryReader<'a>
+{
+	data :       &'a [u8],
+	offset :     usize,
+	big_endian : bool,
+}
+
+impl<'a> BodyReader for BinaryReader<'a>
+{
+	fn read(&mut self, scalar_type : ScalarType) -> Result<f64, String>
+	{
+		let size = scalar_type.size();
+		if self.data.len() - self.offset < size
+		{
+			return Err("unexpected end of file".to_string());
+		}
+
+		let mut bytes = [0u8; 8];
+		bytes[..size].copy_from_slice(&self.data[self.offset..self.offset + size]);
+		if self.big_endian
+		{
+			bytes[..size].reverse();
+		}
+		self.offset += size;
+
+		let value = match scalar_type
+		{
+			ScalarType::I8 => bytes[0] as i8 as f64,
+			ScalarType::U8 => bytes[0] as f64,
+			ScalarType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+			ScalarType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+			ScalarType::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+			ScalarType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+			ScalarType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+			ScalarType::F64 => f64::from_le_bytes(bytes),
+		};
+		if !value.is_finite()
+		{
+			return Err(format!("invalid {:?} value {}", scalar_type, value));
+		}
+
+		Ok(value)
+	}
+
+	fn location(&self) -> String
+	{
+		format!("byte {}", self.offset)
+	}
+}
+
+// Where a vertex property ends up in MeshVertex.
+#[derive(Copy, Clone)]
+enum VertexSlot
+{
+	Position(usize),
+	Normal(usize),
+	Uv(usize),
+	Color(usize),
+	Ignored,
+}
+
+fn vertex_slot(name : &str) -> VertexSlot
+{
+	match name
+	{
+		"x" => VertexSlot::Position(0),
+		"y" => VertexSlot::Position(1),
+		"z" => VertexSlot::Position(2),
+		"nx" => VertexSlot::Normal(0),
+		"ny" => VertexSlot::Normal(1),
+		"nz" => VertexSlot::Normal(2),
+		"s" | "u" | "texture_s" | "texture_u" => VertexSlot::Uv(0),
+		"t" | "v" | "texture_t" | "texture_v" => VertexSlot::Uv(1),
+		"red" | "diffuse_red" => VertexSlot::Color(0),
+		"green" | "diffuse_green" => VertexSlot::Color(1),
+		"blue" | "diffuse_blue" => VertexSlot::Color(2),
+		"alpha" | "diffuse_alpha" => VertexSlot::Color(3),
+		_ => VertexSlot::Ignored,
+	}
+}
+
+fn parse_body(file : &str, elements : &[Element], reader : &mut dyn BodyReader) -> Result<Mesh<MeshVertex>, PlyError>
+{
+	let mut vertices : Vec<MeshVertex> = Vec::new();
+	let mut indices : Vec<u32> = Vec::new();
+	let mut polygon : Vec<u32> = Vec::new();
+	let mut has_positions = false;
+
+	for element in elements
+	{
+		let is_vertex = element.name == "vertex";
+		let is_face = element.name == "face";
+		if is_vertex
+		{
+			let names : Vec<&str> = element.properties.iter().map(|p| p.name.as_str()).collect();
+			has_positions = ["x", "y", "z"].iter().all(|axis| names.contains(axis));
+		}
+
+		for _ in 0..element.count
+		{
+			let mut vertex = MeshVertex {
+				position : Vector3::zero(),
+				normal :   Vector3::zero(),
+				uv :       Vector2::zero(),
+				color :    Vector4::new(1.0, 1.0, 1.0, 1.0),
+			};
+
+			for property in &element.properties
+			{
+				let error = |reader : &dyn BodyReader, details : String| {
+					parse_error(file, reader.location(), format!("{} '{}': {}", element.name, property.name, details))
+				};
+
+				match property.property_type
+				{
+					PropertyType::Scalar(scalar_type) =>
+					{
+						let value = reader.read(scalar_type).map_err(|details| error(reader, details))?;
+						if !is_vertex
+						{
+							continue;
+						}
+						match vertex_slot(&property.name)
+						{
+							VertexSlot::Position(axis) => vertex.position[axis] = value as f32,
+							VertexSlot::Normal(axis) => vertex.normal[axis] = value as f32,
+							VertexSlot::Uv(axis) => vertex.uv[axis] = value as f32,
+							VertexSlot::Color(channel) =>
+							{
+								vertex.color[channel] = (value / scalar_type.unorm_max()) as f32
+							}
+							VertexSlot::Ignored => (),
+						}
+					}
+					PropertyType::List {
+						count,
+						item,
+					} =>
+					{
+						let length = reader.read(count).map_err(|details| error(reader, details))?;
+						if !count.is_integer() || length < 0.0
+						{
+							return Err(error(reader, format!("invalid list length {}", length)));
+						}
+
+						let is_face_indices =
+							is_face && (property.name == "vertex_indices" || property.name == "vertex_index");
+						polygon.clear();
+						for _ in 0..length as usize
+						{
+							let value = reader.read(item).map_err(|details| error(reader, details))?;
+							if is_face_indices
+							{
+								if !item.is_integer() || value < 0.0 || value >= vertices.len() as f64
+								{
+									return Err(error(
+										reader,
+										format!(
+											"vertex index {} is out of range of {} vertices",
+											value,
+											vertices.len()
+										),
+									));
+								}
+								polygon.push(value as u32);
+							}
+						}
+
+						if is_face_indices
+						{
+							if polygon.len() < 3
+							{
+								return Err(error(
+									reader,
+									format!("face needs at least 3 corners, found {}", polygon.len()),
+								));
+							}
+							// Fan triangulate, with winding reversed for the mirrored z.
+							for n in 1..polygon.len() - 1
+							{
+								indices.extend_from_slice(&[polygon[0], polygon[n + 1], polygon[n]]);
+							}
+						}
+					}
+				}
+			}
+
+			if is_vertex
+			{
+				vertex.position.z = -vertex.position.z;
+				vertex.normal.z = -vertex.normal.z;
+				vertex.uv.y = 1.0 - vertex.uv.y;
+				vertices.push(vertex);
+			}
+		}
+	}
+
+	if !vertices.is_empty() && !has_positions
+	{
+		return Err(parse_error(file, "header".to_string(), "vertex element has no x, y and z properties".to_string()));
+	}
+
+	Ok(Mesh::new(vertices, indices))
+}
+
+// Parses the contents of a PLY file. `file` is only used in error messages.
+pub fn parse_ply(file : &str, data : &[u8]) -> Result<Mesh<MeshVertex>, PlyError>
+{
+	let header = parse_header(file, data)?;
+	let body = &data[header.body_offset..];
+
+	match header.format
+	{
+		PlyFormat::Ascii =>
+		{
+			let text = std::str::from_utf8(body).map_err(|error| {
+				parse_error(
+					file,
+					format!("byte {}", header.body_offset + error.valid_up_to()),
+					"invalid text".to_string(),
+				)
+			})?;
+			let mut reader = AsciiReader {
+				lines :  text.lines(),
+				tokens : "".split_whitespace(),
+				line :   header.line_count,
+			};
+			parse_body(file, &header.elements, &mut reader)
+		}
+		PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian =>
+		{
+			let mut reader = BinaryReader {
+				data :       data,
+				offset :     header.body_offset,
+				big_endian : header.format == PlyFormat::BinaryBigEndian,
+			};
+			parse_body(file, &header.elements, &mut reader)
+		}
+	}
+}
+
+// Writes positions, normals, texture coordinates and 8-bit colors.
+pub fn write_ply<W : Write>(writer : &mut W, mesh : &Mesh<MeshVertex>, format : PlyFormat) -> io::Result<()>
+{
+	let format_name = match format
+	{
+		PlyFormat::Ascii => "ascii",
+		PlyFormat::BinaryLittleEndian => "binary_little_endian",
+		PlyFormat::BinaryBigEndian => "binary_big_endian",
+	};
+
+	writeln!(writer, "ply")?;
+	writeln!(writer, "format {} 1.0", format_name)?;
+	writeln!(writer, "element vertex {}", mesh.vertices.len())?;
+	for name in ["x", "y", "z", "nx", "ny", "nz", "s", "t"].iter()
+	{
+		writeln!(writer, "property float {}", name)?;
+	}
+	for name in ["red", "green", "blue", "alpha"].iter()
+	{
+		writeln!(writer, "property uchar {}", name)?;
+	}
+	writeln!(writer, "element face {}", mesh.triangle_count())?;
+	writeln!(writer, "property list uchar uint vertex_indices")?;
+	writeln!(writer, "end_header")?;
+
+	for vertex in &mesh.vertices
+	{
+		let floats = [
+			vertex.position.x,
+			vertex.position.y,
+			-vertex.position.z,
+			vertex.normal.x,
+			vertex.normal.y,
+			-vertex.normal.z,
+			vertex.uv.x,
+			1.0 - vertex.uv.y,
+		];
+		let unorm = |channel : f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+		let color = [unorm(vertex.color.x), unorm(vertex.color.y), unorm(vertex.color.z), unorm(vertex.color.w)];
+
+		match format
+		{
+			PlyFormat::Ascii => writeln!(
+				writer,
+				"{} {} {} {} {} {} {} {} {} {} {} {}",
+				floats[0],
+				floats[1],
+				floats[2],
+				floats[3],
+				floats[4],
+				floats[5],
+				floats[6],
+				floats[7],
+				color[0],
+				color[1],
+				color[2],
+				color[3]
+			)?,
+			PlyFormat::BinaryLittleEndian =>
+			{
+				for value in floats.iter()
+				{
+					writer.write_all(&value.to_le_bytes())?;
+				}
+				writer.write_all(&color)?;
+			}
+			PlyFormat::BinaryBigEndian =>
+			{
+				for value in floats.iter()
+				{
+					writer.write_all(&value.to_be_bytes())?;
+				}
+				writer.write_all(&color)?;
+			}
+		}
+	}
+
+	for [a, b, c] in mesh.triangles()
+	{
+		// Reversed winding for the mirrored z.
+		let triangle = [a, c, b];
+		match format
+		{
+			PlyFormat::Ascii => writeln!(writer, "3 {} {} {}", triangle[0], triangle[1], triangle[2])?,
+			PlyFormat::BinaryLittleEndian =>
+			{
+				writer.write_all(&[3])?;
+				for index in triangle.iter()
+				{
+					writer.write_all(&index.to_le_bytes())?;
+				}
+			}
+			PlyFormat::BinaryBigEndian =>
+			{
+				writer.write_all(&[3])?;
+				for index in triangle.iter()
+				{
+					writer.write_all(&index.to_be_bytes())?;
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+// Loads a PLY file from disk.
+#[allow(dead_code)]
+pub fn load_ply(path : &Path) -> Result<Mesh<MeshVertex>, PlyError>
+{
+	let data = fs::read(path).map_err(|error| PlyError::Io {
+		path :  path.to_path_buf(),
+		error : error,
+	})?;
+	parse_ply(&path.display().to_string(), &data)
+}
+
+// Saves a mesh to a PLY file on disk.
+#[allow(dead_code)]
+pub fn save_ply(path : &Path, mesh : &Mesh<MeshVertex>, format : PlyFormat) -> Result<(), PlyError>
+{
+	let io_error = |error| PlyError::Io {
+		path :  path.to_path_buf(),
+		error : error,
+	};
+
+	let mut writer = io::BufWriter::new(fs::File::create(path).map_err(io_error)?);
+	write_ply(&mut writer, mesh, format).map_err(io_error)?;
+	writer.flush().map_err(io_error)
+}
+
+#[cfg(test)]
+mod ply_tests
+{
+	use crate::geometry::{
+		ply::{
+			load_ply,
+			parse_ply,
+			write_ply,
+			PlyError,
+			PlyFormat,
+		},
+		primitives::{
+			icosphere,
+			torus,
+		},
+		ColoredVertex,
+		Mesh,
+		MeshVertex,
+	};
+	use cgmath::*;
+	use std::path::{
+		Path,
+		PathBuf,
+	};
+
+	const EPSILON : f32 = 1.0e-6;
+
+	fn fixture(name : &str) -> PathBuf
+	{
+		Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("ply").join(name)
+	}
+
+	fn round_trip(mesh : &Mesh<MeshVertex>, format : PlyFormat) -> Mesh<MeshVertex>
+	{
+		let mut data = Vec::new();
+		write_ply(&mut data, mesh, format).unwrap();
+		parse_ply("round_trip.ply", &data).unwrap()
+	}
+
+	fn colorful(mut mesh : Mesh<MeshVertex>) -> Mesh<MeshVertex>
+	{
+		for (n, vertex) in mesh.vertices.iter_mut().enumerate()
+		{
+			vertex.color = Vector4::new((n % 256) as f32, 128.0, 255.0, 64.0) / 255.0;
+		}
+		mesh
+	}
+
+	fn parse_error(source : &str) -> String
+	{
+		match parse_ply("test.ply", source.as_bytes())
+		{
+			Err(error) => error.to_string(),
+			Ok(_) => panic!("expected a parse error for {:?}", source),
+		}
+	}
+
+	#[test]
+	fn test_round_trip()
+	{
+		for mesh in [colorful(icosphere(1.5, 2)), colorful(torus(2.0, 0.5, 12, 8))].iter()
+		{
+			for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian].iter()
+			{
+				let loaded = round_trip(mesh, *format);
+				assert_eq!(mesh.indices, loaded.indices, "{:?}", format);
+				assert_eq!(mesh.vertices.len(), loaded.vertices.len());
+				for (expected, actual) in mesh.vertices.iter().zip(loaded.vertices.iter())
+				{
+					assert_eq!(expected.position, actual.position, "{:?}", format);
+					assert_eq!(expected.normal, actual.normal, "{:?}", format);
+					assert!((expected.uv - actual.uv).magnitude() < EPSILON, "{:?}", format);
+					assert!((expected.color - actual.color).magnitude() < EPSILON, "{:?}", format);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_load_fixture()
+	{
+		// An ASCII quad and triangle with 8-bit colors, a comment, an extra
+		// vertex property and an unknown element.
+		let mesh = load_ply(&fixture("colored_quad.ply")).unwrap();
+		assert_eq!(5, mesh.vertices.len());
+		assert_eq!(3, mesh.triangle_count());
+
+		let colored = mesh.map_vertices(|v| ColoredVertex::from(*v));
+		assert_eq!(Vector4::new(1.0, 0.0, 0.0, 1.0), colored.vertices[0].color);
+		assert_eq!(Vector4::new(0.0, 1.0, 0.0, 1.0), colored.vertices[1].color);
+		assert_eq!(Vector3::new(1.0, 1.0, -2.0), mesh.vertices[2].position);
+
+		// Counter-clockwise faces in the file face +z, which becomes -z.
+		for [a, b, c] in mesh.triangles()
+		{
+			let p = |n : u32| mesh.vertices[n as usize].position;
+			assert!((p(b) - p(a)).cross(p(c) - p(a)).z < 0.0);
+		}
+	}
+
+	#[test]
+	fn test_parse_errors_report_locations()
+	{
+		assert_eq!("test.ply: line 1: not a PLY file, expected 'ply'", parse_error("obj\n"));
+		assert_eq!(
+			"test.ply: line 3: header is not terminated by 'end_header'",
+			parse_error("ply\nformat ascii 1.0\nelement vertex 1")
+		);
+		assert_eq!(
+			"test.ply: line 3: unknown property type 'half'",
+			parse_error("ply\nformat ascii 1.0\nproperty half x\nend_header\n")
+		);
+		assert_eq!(
+			"test.ply: line 7: vertex 'y': invalid F32 value 'abc'",
+			parse_error(
+				"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nend_header\n0 abc\n"
+			)
+		);
+		assert_eq!(
+			"test.ply: line 13: face 'vertex_indices': vertex index 3 is out of range of 3 vertices",
+			parse_error(
+				"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float \
+				 z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n0 1 0\n1 0 0\n3 0 1 3\n"
+			)
+		);
+
+		let mut binary = b"ply\nformat binary_little_endian 1.0\nelement vertex 1\nproperty float x\nproperty float \
+		                   y\nproperty float z\nend_header\n"
+			.to_vec();
+		let body_offset = binary.len();
+		binary.extend_from_slice(&[0; 10]);
+		match parse_ply("test.ply", &binary)
+		{
+			Err(error) => assert_eq!(
+				format!("test.ply: byte {}: vertex 'z': unexpected end of file", body_offset + 8),
+				error.to_string()
+			),
+			Ok(_) => panic!("expected a truncated file error"),
+		}
+	}
+
+	#[test]
+	fn test_malformed_input_does_not_panic()
+	{
+		let inputs : [&[u8]; 7] = [
+			b"",
+			b"ply",
+			b"ply\nend_header\n",
+			b"ply\nformat binary_big_endian 1.0\nelement vertex 99999999999\nproperty float x\nend_header\n",
+			b"ply\nformat ascii 1.0\nelement face 1\nproperty list float int vertex_indices\nend_header\n1.5 0\n",
+			b"ply\nformat ascii 1.0\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n-1\n",
+			b"\xff\xfe\n",
+		];
+		for input in inputs.iter()
+		{
+			let _ = parse_ply("fuzz.ply", input);
+		}
+	}
+
+	#[test]
+	fn test_missing_file()
+	{
+		match load_ply(&fixture("does_not_exist.ply"))
+		{
+			Err(PlyError::Io {
+				..
+			}) => (),
+			_ => panic!("expected an io error"),
+		}
+	}
+}
@@ -0,0 +1,708 @@
+// Procedural primitive generators.
+//
+// All generators produce meshes centered on the origin, in the same winding as
+// the sample tetrahedron: for a triangle (a, b, c), (b - a) x (c - a) points
+// out of the surface, which is clockwise when seen from the outside. Hard edges
+// (cube, cylinder caps, platonic solids) get their own vertices so each face
+// can carry its own normal.
+use super::{
+	Mesh,
+	MeshVertex,
+};
+use cgmath::*;
+use std::{
+	collections::HashMap,
+	f32::consts::PI,
+};
+
+const TAU : f32 = 2.0 * PI;
+
+// Colors each vertex by its normal, which makes the shape readable without any
+// lighting.
+fn normal_color(normal : Vector3<f32>) -> Vector4<f32>
+{
+	(normal * 0.5 + Vector3::new(0.5, 0.5, 0.5)).extend(1.0)
+}
+
+fn vertex(position : Vector3<f32>, normal : Vector3<f32>, uv : Vector2<f32>) -> MeshVertex
+{
+	MeshVertex {
+		position : position,
+		normal :   normal,
+		uv :       uv,
+		color :    normal_color(normal),
+	}
+}
+
+fn is_degenerate(a : Vector3<f32>, b : Vector3<f32>, c : Vector3<f32>) -> bool
+{
+	(b - a).cross(c - a).magnitude2() < 1.0e-12
+}
+
+// Appends a (columns + 1) x (rows + 1) grid of vertices produced by
+// `surface(u, v)`, with u and v ranging over [0, 1]. The surface must be
+// parameterized so that dP/du x dP/dv points outwards. Triangles that collapse
+// (e.g. at the poles of a sphere) are skipped.
+fn push_grid<F>(vertices : &mut Vec<MeshVertex>, indices : &mut Vec<u32>, columns : u32, rows : u32, surface : F)
+where
+	F : Fn(f32, f32) -> MeshVertex,
+{
+	let base = vertices.len() as u32;
+	for j in 0..=rows
+	{
+		for i in 0..=columns
+		{
+			vertices.push(surface(i as f32 / columns as f32, j as f32 / rows as f32));
+		}
+	}
+
+	let index = |i : u32, j : u32| base + j * (columns + 1) + i;
+	for j in 0..rows
+	{
+		for i in 0..columns
+		{
+			let a = index(i, j);
+			let b = index(i, j + 1);
+			let c = index(i + 1, j);
+			let d = index(i + 1, j + 1);
+			for &[x, y, z] in &[[a, c, b], [c, d, b]]
+			{
+				let p = |n : u32| vertices[n as usize].position;
+				if !is_degenerate(p(x), p(y), p(z))
+				{
+					indices.extend_from_slice(&[x, y, z]);
+				}
+			}
+		}
+	}
+}
+
+// Appends a flat disk in the XZ plane at height `y`, facing up or down.
+fn push_disk(vertices : &mut Vec<MeshVertex>, indices : &mut Vec<u32>, radius : f32, y : f32, segments : u32, up : bool)
+{
+	let normal = if up
+	{
+		Vector3::unit_y()
+	}
+	else
+	{
+		-Vector3::unit_y()
+	};
+	let center = vertices.len() as u32;
+	vertices.push(vertex(Vector3::new(0.0, y, 0.0), normal, Vector2::new(0.5, 0.5)));
+
+	for i in 0..=segments
+	{
+		let phi = TAU * i as f32 / segments as f32;
+		let (sin, cos) = phi.sin_cos();
+		let position = Vector3::new(radius * cos, y, radius * sin);
+		vertices.push(vertex(position, normal, Vector2::new(0.5 + 0.5 * cos, 0.5 - 0.5 * sin)));
+	}
+
+	for i in 0..segments
+	{
+		let current = center + 1 + i;
+		let next = current + 1;
+		if up
+		{
+			indices.extend_from_slice(&[center, next, current]);
+		}
+		else
+		{
+			indices.extend_from_slice(&[center, current, next]);
+		}
+	}
+}
+
+// Axis aligned cube with edge length `size`. Each face is split into
+// `subdivisions` x `subdivisions` quads.
+#[allow(dead_code)]
+pub fn cube(size : f32, subdivisions : u32) -> Mesh<MeshVertex>
+{
+	assert!(subdivisions >= 1, "cube needs at least 1 subdivision");
+
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+
+	let normals = [
+		Vector3::unit_x(),
+		-Vector3::unit_x(),
+		Vector3::unit_y(),
+		-Vector3::unit_y(),
+		Vector3::unit_z(),
+		-Vector3::unit_z(),
+	];
+
+	for &normal in &normals
+	{
+		// t_u x t_v == normal, so the grid faces outwards. Side faces run v
+		// downwards so textures come out upright.
+		let t_u = if normal.y == 0.0
+		{
+			normal.cross(Vector3::unit_y())
+		}
+		else
+		{
+			Vector3::unit_x()
+		};
+		let t_v = normal.cross(t_u);
+		let center = normal * (size * 0.5);
+
+		push_grid(&mut vertices, &mut indices, subdivisions, subdivisions, |u, v| {
+			let position = center + t_u * ((u - 0.5) * size) + t_v * ((v - 0.5) * size);
+			vertex(position, normal, Vector2::new(u, v))
+		});
+	}
+
+	Mesh::new(vertices, indices)
+}
+
+// Latitude/longitude sphere. `segments` runs around the Y axis, `rings` from
+// the north to the south pole.
+#[allow(dead_code)]
+pub fn uv_sphere(radius : f32, segments : u32, rings : u32) -> Mesh<MeshVertex>
+{
+	assert!(segments >= 3 && rings >= 2, "uv_sphere needs at least 3 segments and 2 rings");
+
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+
+	push_grid(&mut vertices, &mut indices, segments, rings, |u, v| {
+		let (sin_phi, cos_phi) = (TAU * u).sin_cos();
+		let (sin_theta, cos_theta) = (PI * v).sin_cos();
+		let normal = Vector3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+		vertex(normal * radius, normal, Vector2::new(u, v))
+	});
+
+	Mesh::new(vertices, indices)
+}
+
+// Positions and faces of a unit icosahedron with shared vertices.
+fn icosahedron_shared() -> (Vec<Vector3<f32>>, Vec<[u32; 3]>)
+{
+	let phi = (1.0 + 5.0_f32.sqrt()) * 0.5;
+	let mut positions = Vec::new();
+	for &a in &[-1.0, 1.0]
+	{
+		for &b in &[-phi, phi]
+		{
+			positions.push(Vector3::new(0.0, a, b));
+			positions.push(Vector3::new(a, b, 0.0));
+			positions.push(Vector3::new(b, 0.0, a));
+		}
+	}
+
+	// Every pair of adjacent vertices is exactly 2 units apart, so the faces are
+	// the triples that are mutually adjacent.
+	let adjacent = |a : usize, b : usize| ((positions[a] - positions[b]).magnitude() - 2.0).abs() < 1.0e-4;
+	let mut faces = Vec::new();
+	for a in 0..positions.len()
+	{
+		for b in (a + 1)..positions.len()
+		{
+			for c in (b + 1)..positions.len()
+			{
+				if adjacent(a, b) && adjacent(b, c) && adjacent(a, c)
+				{
+					let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+					if normal.dot(positions[a]) > 0.0
+					{
+						faces.push([a as u32, b as u32, c as u32]);
+					}
+					else
+					{
+						faces.push([a as u32, c as u32, b as u32]);
+					}
+				}
+			}
+		}
+	}
+
+	let positions = positions.iter().map(|p| p.normalize()).collect();
+	(positions, faces)
+}
+
+fn spherical_uv(normal : Vector3<f32>) -> Vector2<f32>
+{
+	let u = normal.z.atan2(normal.x) / TAU;
+	let u = if u < 0.0
+	{
+		u + 1.0
+	}
+	else
+	{
+		u
+	};
+	let v = normal.y.max(-1.0).min(1.0).acos() / PI;
+	Vector2::new(u, v)
+}
+
+// Geodesic sphere made by repeatedly splitting each icosahedron face into 4.
+// Vertices are shared, so the UVs wrap across the seam.
+#[allow(dead_code)]
+pub fn icosphere(radius : f32, subdivisions : u32) -> Mesh<MeshVertex>
+{
+	let (mut positions, mut faces) = icosahedron_shared();
+
+	for _ in 0..subdivisions
+	{
+		let mut midpoints = HashMap::<(u32, u32), u32>::new();
+		let mut midpoint = |a : u32, b : u32, positions : &mut Vec<Vector3<f32>>| {
+			let key = (a.min(b), a.max(b));
+			*midpoints.entry(key).or_insert_with(|| {
+				let p = (positions[a as usize] + positions[b as usize]).normalize();
+				positions.push(p);
+				(positions.len() - 1) as u32
+			})
+		};
+
+		let mut next_faces = Vec::with_capacity(faces.len() * 4);
+		for &[a, b, c] in &faces
+		{
+			let ab = midpoint(a, b, &mut positions);
+			let bc = midpoint(b, c, &mut positions);
+			let ca = midpoint(c, a, &mut positions);
+			next_faces.push([a, ab, ca]);
+			next_faces.push([ab, b, bc]);
+			next_faces.push([ca, bc, c]);
+			next_faces.push([ab, bc, ca]);
+		}
+		faces = next_faces;
+	}
+
+	let vertices = positions.iter().map(|&normal| vertex(normal * radius, normal, spherical_uv(normal))).collect();
+	let indices = faces.iter().flat_map(|face| face.iter().cloned()).collect();
+	Mesh::new(vertices, indices)
+}
+
+// Capped cylinder along the Y axis. `rings` splits the side vertically.
+#[allow(dead_code)]
+pub fn cylinder(radius : f32, height : f32, segments : u32, rings : u32) -> Mesh<MeshVertex>
+{
+	assert!(segments >= 3 && rings >= 1, "cylinder needs at least 3 segments and 1 ring");
+
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+	let half_height = height * 0.5;
+
+	push_grid(&mut vertices, &mut indices, segments, rings, |u, v| {
+		let (sin, cos) = (TAU * u).sin_cos();
+		let normal = Vector3::new(cos, 0.0, sin);
+		let position = Vector3::new(radius * cos, half_height - v * height, radius * sin);
+		vertex(position, normal, Vector2::new(u, v))
+	});
+	push_disk(&mut vertices, &mut indices, radius, half_height, segments, true);
+	push_disk(&mut vertices, &mut indices, radius, -half_height, segments, false);
+
+	Mesh::new(vertices, indices)
+}
+
+// Capped cone along the Y axis with the apex at the top.
+#[allow(dead_code)]
+pub fn cone(radius : f32, height : f32, segments : u32, rings : u32) -> Mesh<MeshVertex>
+{
+	assert!(segments >= 3 && rings >= 1, "cone needs at least 3 segments and 1 ring");
+
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+	let half_height = height * 0.5;
+
+	push_grid(&mut vertices, &mut indices, segments, rings, |u, v| {
+		let (sin, cos) = (TAU * u).sin_cos();
+		let normal = Vector3::new(height * cos, radius, height * sin).normalize();
+		let position = Vector3::new(v * radius * cos, half_height - v * height, v * radius * sin);
+		vertex(position, normal, Vector2::new(u, v))
+	});
+	push_disk(&mut vertices, &mut indices, radius, -half_height, segments, false);
+
+	Mesh::new(vertices, indices)
+}
+
+// Torus around the Y axis. `major_segments` runs around the ring,
+// `minor_segments` around the tube.
+#[allow(dead_code)]
+pub fn torus(major_radius : f32, minor_radius : f32, major_segments : u32, minor_segments : u32) -> Mesh<MeshVertex>
+{
+	assert!(major_segments >= 3 && minor_segments >= 3, "torus needs at least 3 segments in each direction");
+
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+
+	push_grid(&mut vertices, &mut indices, major_segments, minor_segments, |u, v| {
+		let (sin_phi, cos_phi) = (TAU * u).sin_cos();
+		let (sin_theta, cos_theta) = (TAU * v).sin_cos();
+		let center = Vector3::new(major_radius * cos_phi, 0.0, major_radius * sin_phi);
+		let normal = Vector3::new(cos_theta * cos_phi, -sin_theta, cos_theta * sin_phi);
+		vertex(center + normal * minor_radius, normal, Vector2::new(u, v))
+	});
+
+	Mesh::new(vertices, indices)
+}
+
+// Flat grid in the XZ plane facing +Y. This is the only open primitive.
+#[allow(dead_code)]
+pub fn plane(width : f32, depth : f32, width_segments : u32, depth_segments : u32) -> Mesh<MeshVertex>
+{
+	assert!(width_segments >= 1 && depth_segments >= 1, "plane needs at least 1 segment in each direction");
+
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+
+	push_grid(&mut vertices, &mut indices, width_segments, depth_segments, |u, v| {
+		let position = Vector3::new((u - 0.5) * width, 0.0, (0.5 - v) * depth);
+		vertex(position, Vector3::unit_y(), Vector2::new(u, v))
+	});
+
+	Mesh::new(vertices, indices)
+}
+
+// Builds a convex polyhedron with flat faces. Each face is the set of
+// `corners` furthest along one of the `face_directions`; it is sorted around
+// that direction and fan triangulated.
+fn convex_polyhedron(radius : f32, corners : &[Vector3<f32>], face_directions : &[Vector3<f32>]) -> Mesh<MeshVertex>
+{
+	let corners : Vec<Vector3<f32>> = corners.iter().map(|c| c.normalize() * radius).collect();
+
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+
+	for direction in face_directions
+	{
+		let normal = direction.normalize();
+		let furthest = corners.iter().map(|c| c.dot(normal)).fold(std::f32::MIN, f32::max);
+		let mut face : Vec<Vector3<f32>> =
+			corners.iter().cloned().filter(|c| (c.dot(normal) - furthest).abs() < radius * 1.0e-4).collect();
+
+		let center = face.iter().fold(Vector3::zero(), |sum, &c| sum + c) / face.len() as f32;
+		let tangent = (face[0] - center).normalize();
+		let bitangent = normal.cross(tangent);
+		let angle = |c : &Vector3<f32>| (c - center).dot(bitangent).atan2((c - center).dot(tangent));
+		face.sort_by(|a, b| angle(a).partial_cmp(&angle(b)).unwrap());
+
+		let extent = face.iter().map(|c| (c - center).magnitude()).fold(0.0, f32::max);
+		let base = vertices.len() as u32;
+		for c in &face
+		{
+			let offset = c - center;
+			let uv = Vector2::new(0.5 + 0.5 * offset.dot(tangent) / extent, 0.5 - 0.5 * offset.dot(bitangent) / extent);
+			vertices.push(vertex(*c, normal, uv));
+		}
+
+		// Sorted counter-clockwise around the normal, so the fan faces outwards.
+		for n in 1..(face.len() as u32 - 1)
+		{
+			indices.extend_from_slice(&[base, base + n, base + n + 1]);
+		}
+	}
+
+	Mesh::new(vertices, indices)
+}
+
+fn cube_corners() -> Vec<Vector3<f32>>
+{
+	let mut corners = Vec::new();
+	for &x in &[-1.0, 1.0]
+	{
+		for &y in &[-1.0, 1.0]
+		{
+			for &z in &[-1.0, 1.0]
+			{
+				corners.push(Vector3::new(x, y, z));
+			}
+		}
+	}
+	corners
+}
+
+fn octahedron_corners() -> Vec<Vector3<f32>>
+{
+	vec![
+		Vector3::unit_x(),
+		-Vector3::unit_x(),
+		Vector3::unit_y(),
+		-Vector3::unit_y(),
+		Vector3::unit_z(),
+		-Vector3::unit_z(),
+	]
+}
+
+fn dodecahedron_corners() -> Vec<Vector3<f32>>
+{
+	let phi = (1.0 + 5.0_f32.sqrt()) * 0.5;
+	let mut corners = cube_corners();
+	// Oriented so its face centers line up with the corners of
+	// icosahedron_shared, making the two exact duals.
+	for &a in &[-1.0 / phi, 1.0 / phi]
+	{
+		for &b in &[-phi, phi]
+		{
+			corners.push(Vector3::new(0.0, b, a));
+			corners.push(Vector3::new(b, a, 0.0));
+			corners.push(Vector3::new(a, 0.0, b));
+		}
+	}
+	corners
+}
+
+// Platonic solids. `radius` is the circumradius. Each solid's faces point
+// towards the corners of its dual.
+
+#[allow(dead_code)]
+pub fn tetrahedron(radius : f32) -> Mesh<MeshVertex>
+{
+	let corners = [
+		Vector3::new(1.0, 1.0, 1.0),
+		Vector3::new(1.0, -1.0, -1.0),
+		Vector3::new(-1.0, 1.0, -1.0),
+		Vector3::new(-1.0, -1.0, 1.0),
+	];
+	let faces : Vec<Vector3<f32>> = corners.iter().map(|&c| -c).collect();
+	convex_polyhedron(radius, &corners, &faces)
+}
+
+#[allow(dead_code)]
+pub fn hexahedron(radius : f32) -> Mesh<MeshVertex>
+{
+	convex_polyhedron(radius, &cube_corners(), &octahedron_corners())
+}
+
+#[allow(dead_code)]
+pub fn octahedron(radius : f32) -> Mesh<MeshVertex>
+{
+	convex_polyhedron(radius, &octahedron_corners(), &cube_corners())
+}
+
+#[allow(dead_code)]
+pub fn dodecahedron(radius : f32) -> Mesh<MeshVertex>
+{
+	let (icosahedron_corners, _) = icosahedron_shared();
+	convex_polyhedron(radius, &dodecahedron_corners(), &icosahedron_corners)
+}
+
+#[allow(dead_code)]
+pub fn icosahedron(radius : f32) -> Mesh<MeshVertex>
+{
+	let (icosahedron_corners, _) = icosahedron_shared();
+	convex_polyhedron(radius, &icosahedron_corners, &dodecahedron_corners())
+}
+
+#[cfg(test)]
+mod primitives_tests
+{
+	use crate::geometry::{
+		primitives::{
+			cone,
+			cube,
+			cylinder,
+			dodecahedron,
+			hexahedron,
+			icosahedron,
+			icosphere,
+			octahedron,
+			plane,
+			tetrahedron,
+			torus,
+			uv_sphere,
+		},
+		Mesh,
+		MeshVertex,
+	};
+	use cgmath::*;
+	use std::{
+		collections::HashMap,
+		f32::consts::PI,
+	};
+
+	const EPSILON : f32 = 1.0e-4;
+
+	// Maps every vertex to the first vertex at the same position, so hard edges
+	// and UV seams do not count as holes.
+	fn welded_triangles(mesh : &Mesh<MeshVertex>) -> Vec<[usize; 3]>
+	{
+		let mut remap = Vec::with_capacity(mesh.vertices.len());
+		for (n, v) in mesh.vertices.iter().enumerate()
+		{
+			let first = (0..n).find(|&m| (mesh.vertices[m].position - v.position).magnitude() < EPSILON).unwrap_or(n);
+			remap.push(remap.get(first).cloned().unwrap_or(n));
+		}
+
+		mesh.triangles().map(|[a, b, c]| [remap[a as usize], remap[b as usize], remap[c as usize]]).collect()
+	}
+
+	// Every directed edge appears once, and its reverse once. This proves the
+	// surface is closed and the winding is consistent.
+	fn assert_watertight(mesh : &Mesh<MeshVertex>)
+	{
+		let mut edges = HashMap::<(usize, usize), u32>::new();
+		for [a, b, c] in welded_triangles(mesh)
+		{
+			for &edge in &[(a, b), (b, c), (c, a)]
+			{
+				*edges.entry(edge).or_insert(0) += 1;
+			}
+		}
+
+		for (&(a, b), &count) in &edges
+		{
+			assert_eq!(1, count, "edge {:?} is shared by {} triangles with the same direction", (a, b), count);
+			assert_eq!(Some(&1), edges.get(&(b, a)), "edge {:?} has no opposite edge", (a, b));
+		}
+	}
+
+	fn face_normal(mesh : &Mesh<MeshVertex>, triangle : [u32; 3]) -> Vector3<f32>
+	{
+		let p = |n : usize| mesh.vertices[triangle[n] as usize].position;
+		(p(1) - p(0)).cross(p(2) - p(0))
+	}
+
+	// Every face points the same way as its vertex normals.
+	fn assert_outward_winding(mesh : &Mesh<MeshVertex>)
+	{
+		for triangle in mesh.triangles()
+		{
+			let normal = face_normal(mesh, triangle);
+			let vertex_normals =
+				triangle.iter().fold(Vector3::zero(), |sum, &n| sum + mesh.vertices[n as usize].normal);
+			assert!(normal.dot(vertex_normals) > 0.0, "triangle {:?} faces inwards", triangle);
+		}
+	}
+
+	fn assert_attributes(mesh : &Mesh<MeshVertex>)
+	{
+		for v in &mesh.vertices
+		{
+			assert!((v.normal.magnitude() - 1.0).abs() < EPSILON, "normal {:?} is not unit length", v.normal);
+			assert!(v.uv.x >= 0.0 && v.uv.x <= 1.0 && v.uv.y >= 0.0 && v.uv.y <= 1.0, "uv {:?} out of range", v.uv);
+			for n in 0..4
+			{
+				assert!(v.color[n] >= 0.0 && v.color[n] <= 1.0, "color {:?} out of range", v.color);
+			}
+		}
+	}
+
+	fn signed_volume(mesh : &Mesh<MeshVertex>) -> f32
+	{
+		mesh.triangles()
+			.map(|[a, b, c]| {
+				let p = |n : u32| mesh.vertices[n as usize].position;
+				p(a).dot(p(b).cross(p(c))) / 6.0
+			})
+			.sum()
+	}
+
+	fn assert_closed_primitive(mesh : &Mesh<MeshVertex>, expected_volume : f32, tolerance : f32)
+	{
+		assert_watertight(mesh);
+		assert_outward_winding(mesh);
+		assert_attributes(mesh);
+
+		let volume = signed_volume(mesh);
+		assert!(
+			(volume - expected_volume).abs() <= expected_volume * tolerance,
+			"volume {} is not within {} of {}",
+			volume,
+			tolerance,
+			expected_volume
+		);
+	}
+
+	#[test]
+	fn test_cube()
+	{
+		let mesh = cube(2.0, 3);
+		assert_eq!(6 * 16, mesh.vertices.len());
+		assert_eq!(6 * 9 * 2, mesh.triangle_count());
+		assert_closed_primitive(&mesh, 8.0, 1.0e-5);
+	}
+
+	#[test]
+	fn test_uv_sphere()
+	{
+		let mesh = uv_sphere(1.0, 32, 16);
+		assert_eq!(33 * 17, mesh.vertices.len());
+		assert_eq!(32 * 2 * (16 - 1), mesh.triangle_count());
+		assert_closed_primitive(&mesh, 4.0 / 3.0 * PI, 0.02);
+	}
+
+	#[test]
+	fn test_icosphere()
+	{
+		for subdivisions in 0..4
+		{
+			let mesh = icosphere(1.0, subdivisions);
+			let faces = 20 * 4usize.pow(subdivisions);
+			assert_eq!(faces, mesh.triangle_count());
+			assert_eq!(faces / 2 + 2, mesh.vertices.len()); // Euler: V - E + F = 2
+			assert_closed_primitive(&mesh, 4.0 / 3.0 * PI, 0.65);
+		}
+
+		assert_closed_primitive(&icosphere(1.0, 4), 4.0 / 3.0 * PI, 0.01);
+	}
+
+	#[test]
+	fn test_cylinder()
+	{
+		let mesh = cylinder(1.0, 2.0, 64, 3);
+		assert_eq!(64 * 3 * 2 + 64 * 2, mesh.triangle_count());
+		assert_closed_primitive(&mesh, PI * 2.0, 0.01);
+	}
+
+	#[test]
+	fn test_cone()
+	{
+		let mesh = cone(1.0, 3.0, 64, 2);
+		assert_eq!(64 * 3 + 64, mesh.triangle_count());
+		assert_closed_primitive(&mesh, PI, 0.01);
+	}
+
+	#[test]
+	fn test_torus()
+	{
+		let mesh = torus(2.0, 0.5, 64, 32);
+		assert_eq!(64 * 32 * 2, mesh.triangle_count());
+		assert_closed_primitive(&mesh, 2.0 * PI * PI * 2.0 * 0.25, 0.01);
+	}
+
+	#[test]
+	fn test_plane()
+	{
+		let mesh = plane(4.0, 2.0, 4, 2);
+		assert_eq!(5 * 3, mesh.vertices.len());
+		assert_eq!(4 * 2 * 2, mesh.triangle_count());
+		assert_outward_winding(&mesh);
+		assert_attributes(&mesh);
+
+		for triangle in mesh.triangles()
+		{
+			assert!(face_normal(&mesh, triangle).normalize().dot(Vector3::unit_y()) > 1.0 - EPSILON);
+		}
+
+		let area : f32 = mesh.triangles().map(|t| face_normal(&mesh, t).magnitude() * 0.5).sum();
+		assert!((area - 8.0).abs() < EPSILON);
+	}
+
+	#[test]
+	fn test_platonic_solids()
+	{
+		// (mesh, face count, vertices per face, volume for a circumradius of 1)
+		let solids = [
+			(tetrahedron(1.0), 4, 3, 8.0 / (9.0 * 3.0_f32.sqrt())),
+			(hexahedron(1.0), 6, 4, 8.0 / (3.0 * 3.0_f32.sqrt())),
+			(octahedron(1.0), 8, 3, 4.0 / 3.0),
+			(dodecahedron(1.0), 12, 5, 2.785_164),
+			(icosahedron(1.0), 20, 3, 2.536_151),
+		];
+
+		for (mesh, faces, corners, volume) in solids.iter()
+		{
+			assert_eq!(faces * corners, mesh.vertices.len());
+			assert_eq!(faces * (corners - 2), mesh.triangle_count());
+			assert_closed_primitive(mesh, *volume, 1.0e-4);
+
+			for v in &mesh.vertices
+			{
+				assert!((v.position.magnitude() - 1.0).abs() < EPSILON);
+			}
+		}
+	}
+}
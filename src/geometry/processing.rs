@@ -0,0 +1,621 @@
+// Mesh processing: normals, welding, cleanup, validation and bounds.
+//
+// Imported meshes usually arrive as triangle soup, without normals, or with
+// slivers left over from the exporter. These operations bring them into a
+// shape that can be lit and culled. Everything works on the indexed Mesh type
+// and keeps the winding convention of the geometry module.
+use super::{
+	Mesh,
+	MeshVertex,
+	Vertex,
+};
+use cgmath::*;
+use std::collections::HashMap;
+
+// Axis aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb
+{
+	pub min : Vector3<f32>,
+	pub max : Vector3<f32>,
+}
+
+impl Aabb
+{
+	// None if there are no points.
+	pub fn from_points<I : IntoIterator<Item = Vector3<f32>>>(points : I) -> Option<Self>
+	{
+		let mut points = points.into_iter();
+		let first = points.next()?;
+		Some(points.fold(
+			Aabb {
+				min : first,
+				max : first,
+			},
+			|aabb, p| Aabb {
+				min : Vector3::new(aabb.min.x.min(p.x), aabb.min.y.min(p.y), aabb.min.z.min(p.z)),
+				max : Vector3::new(aabb.max.x.max(p.x), aabb.max.y.max(p.y), aabb.max.z.max(p.z)),
+			},
+		))
+	}
+
+	pub fn center(&self) -> Vector3<f32>
+	{
+		(self.min + self.max) * 0.5
+	}
+
+	// Half the size along each axis.
+	pub fn extents(&self) -> Vector3<f32>
+	{
+		(self.max - self.min) * 0.5
+	}
+
+	pub fn contains(&self, point : Vector3<f32>) -> bool
+	{
+		(0..3).all(|axis| point[axis] >= self.min[axis] && point[axis] <= self.max[axis])
+	}
+
+	pub fn union(&self, other : &Aabb) -> Aabb
+	{
+		Aabb::from_points(vec![self.min, self.max, other.min, other.max]).unwrap()
+	}
+
+	// The box around this box after an affine `transform`, for instance into
+	// world space with a model matrix.
+	pub fn transform(&self, transform : Matrix4<f32>) -> Aabb
+	{
+		let center = transform.transform_point(Point3::from_vec(self.center())).to_vec();
+		let extents = self.extents();
+		let linear = Matrix3::from_cols(transform.x.truncate(), transform.y.truncate(), transform.z.truncate());
+		let extents = Vector3::new(
+			linear.row(0).map(f32::abs).dot(extents),
+			linear.row(1).map(f32::abs).dot(extents),
+			linear.row(2).map(f32::abs).dot(extents),
+		);
+		Aabb {
+			min : center - extents,
+			max : center + extents,
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundingSphere
+{
+	pub center : Vector3<f32>,
+	pub radius : f32,
+}
+
+impl BoundingSphere
+{
+	// The sphere around this sphere after an affine `transform`. Non-uniform
+	// scale grows the radius by the largest axis scale.
+	pub fn transform(&self, transform : Matrix4<f32>) -> BoundingSphere
+	{
+		let scale = transform
+			.x
+			.truncate()
+			.magnitude()
+			.max(transform.y.truncate().magnitude())
+			.max(transform.z.truncate().magnitude());
+		BoundingSphere {
+			center : transform.transform_point(Point3::from_vec(self.center)).to_vec(),
+			radius : self.radius * scale,
+		}
+	}
+
+	// Ritter's approximation, or the sphere around the bounding box if that
+	// turns out smaller. Not minimal, but within a few percent for typical
+	// meshes. None if there are no points.
+	pub fn from_points(points : &[Vector3<f32>]) -> Option<Self>
+	{
+		let first = *points.first()?;
+		let farthest_from = |from : Vector3<f32>| {
+			points.iter().cloned().fold(from, |best, p| {
+				if (p - from).magnitude2() > (best - from).magnitude2()
+				{
+					p
+				}
+				else
+				{
+					best
+				}
+			})
+		};
+
+		let a = farthest_from(first);
+		let b = farthest_from(a);
+		let mut center = (a + b) * 0.5;
+		let mut radius = (b - a).magnitude() * 0.5;
+		for &p in points
+		{
+			let distance = (p - center).magnitude();
+			if distance > radius
+			{
+				// Grow just enough to touch p, keeping the far side in place.
+				center += (p - center) * ((distance - radius) / (2.0 * distance));
+				radius = (radius + distance) * 0.5;
+			}
+		}
+
+		let aabb_center = Aabb::from_points(points.iter().cloned())?.center();
+		let aabb_radius = points.iter().map(|&p| (p - aabb_center).magnitude()).fold(0.0, f32::max);
+		if aabb_radius < radius
+		{
+			center = aabb_center;
+			radius = aabb_radius;
+		}
+
+		Some(BoundingSphere {
+			center : center,
+			radius : radius,
+		})
+	}
+
+	pub fn contains(&self, point : Vector3<f32>) -> bool
+	{
+		(point - self.center).magnitude() <= self.radius
+	}
+}
+
+// Edge statistics from `check_winding`. Edges are matched by vertex position,
+// so hard edges and UV seams are not reported as boundaries.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct WindingReport
+{
+	// Edges used by a single triangle, i.e. holes in the surface.
+	pub boundary_edges :     usize,
+	// Edges whose two triangles traverse it in the same direction.
+	pub inconsistent_edges : usize,
+	// Edges shared by more than two triangles.
+	pub non_manifold_edges : usize,
+}
+
+impl WindingReport
+{
+	pub fn is_consistent(&self) -> bool
+	{
+		self.inconsistent_edges == 0 && self.non_manifold_edges == 0
+	}
+
+	pub fn is_closed(&self) -> bool
+	{
+		self.boundary_edges == 0
+	}
+}
+
+fn face_normal<V : Vertex>(mesh : &Mesh<V>, [a, b, c] : [u32; 3]) -> Vector3<f32>
+{
+	let p = |n : u32| mesh.vertices[n as usize].position();
+	(p(b) - p(a)).cross(p(c) - p(a))
+}
+
+fn normalize_or_zero(v : Vector3<f32>) -> Vector3<f32>
+{
+	if v.magnitude2() > 0.0
+	{
+		v.normalize()
+	}
+	else
+	{
+		v
+	}
+}
+
+// Positions closer than this are treated as the same point when matching
+// edges and smoothing normals, to absorb rounding in generated and exported
+// meshes.
+const POSITION_EPSILON : f32 = 1.0e-5;
+
+// Groups vertices for which `same` holds, given that matching vertices are
+// never more than `epsilon` apart. Returns the group of every vertex, with
+// groups numbered by their first vertex, and that first vertex of each group.
+fn cluster<V, F>(vertices : &[V], epsilon : f32, same : F) -> (Vec<u32>, Vec<usize>)
+where
+	V : Vertex,
+	F : Fn(&V, &V) -> bool,
+{
+	// Bucket by position, with cells at least as large as epsilon so matches
+	// can only be in the neighbouring cells.
+	let cell_size = epsilon.max(1.0e-6);
+	let cell = |p : Vector3<f32>| {
+		[(p.x / cell_size).floor() as i64, (p.y / cell_size).floor() as i64, (p.z / cell_size).floor() as i64]
+	};
+
+	let mut cells = HashMap::<[i64; 3], Vec<u32>>::new();
+	let mut firsts : Vec<usize> = Vec::new();
+	let mut groups = Vec::with_capacity(vertices.len());
+	for (n, vertex) in vertices.iter().enumerate()
+	{
+		let [x, y, z] = cell(vertex.position());
+		let mut found = None;
+		'search: for dx in -1..=1
+		{
+			for dy in -1..=1
+			{
+				for dz in -1..=1
+				{
+					if let Some(candidates) = cells.get(&[x + dx, y + dy, z + dz])
+					{
+						found =
+							candidates.iter().cloned().find(|&group| same(&vertices[firsts[group as usize]], vertex));
+						if found.is_some()
+						{
+							break 'search;
+						}
+					}
+				}
+			}
+		}
+
+		let group = match found
+		{
+			Some(group) => group,
+			None =>
+			{
+				firsts.push(n);
+				let group = firsts.len() as u32 - 1;
+				cells.entry([x, y, z]).or_default().push(group);
+				group
+			}
+		};
+		groups.push(group);
+	}
+
+	(groups, firsts)
+}
+
+// Numbers the distinct positions, so vertices that only differ in their other
+// attributes map to the same id.
+pub(super) fn position_ids<V : Vertex>(mesh : &Mesh<V>) -> Vec<u32>
+{
+	let (ids, _) =
+		cluster(&mesh.vertices, POSITION_EPSILON, |a, b| (a.position() - b.position()).magnitude() <= POSITION_EPSILON);
+	ids
+}
+
+// Gives every triangle its own vertices, with the face normal as vertex
+// normal.
+#[allow(dead_code)]
+pub fn flat_normals(mesh : &Mesh<MeshVertex>) -> Mesh<MeshVertex>
+{
+	let mut vertices = Vec::with_capacity(mesh.indices.len());
+	for triangle in mesh.triangles()
+	{
+		let normal = normalize_or_zero(face_normal(mesh, triangle));
+		vertices.extend(triangle.iter().map(|&n| MeshVertex {
+			normal : normal,
+			..mesh.vertices[n as usize]
+		}));
+	}
+
+	let indices = (0..vertices.len() as u32).collect();
+	Mesh::new(vertices, indices)
+}
+
+// Sets every vertex normal to the average of the faces around its position,
+// weighted by the angle of each face at that corner so the result does not
+// depend on how the surface is triangulated. Vertices at the same position
+// share the result, so UV seams do not show up in the shading.
+#[allow(dead_code)]
+pub fn smooth_normals(mesh : &Mesh<MeshVertex>) -> Mesh<MeshVertex>
+{
+	let ids = position_ids(mesh);
+	let mut sums = vec![Vector3::zero(); mesh.vertices.len()];
+	for triangle in mesh.triangles()
+	{
+		let normal = normalize_or_zero(face_normal(mesh, triangle));
+		for corner in 0..3
+		{
+			let p = |k : usize| mesh.vertices[triangle[(corner + k) % 3] as usize].position;
+			let angle = (p(1) - p(0)).angle(p(2) - p(0)).0;
+			if angle.is_finite()
+			{
+				sums[ids[triangle[corner] as usize] as usize] += normal * angle;
+			}
+		}
+	}
+
+	let mut result = mesh.clone();
+	for (vertex, &id) in result.vertices.iter_mut().zip(ids.iter())
+	{
+		vertex.normal = normalize_or_zero(sums[id as usize]);
+	}
+	result
+}
+
+// Merges vertices whose attributes are all within `epsilon` of each other.
+// The first vertex of every group is kept, and unused vertices are dropped.
+#[allow(dead_code)]
+pub fn weld<V : Vertex>(mesh : &Mesh<V>, epsilon : f32) -> Mesh<V>
+{
+	let (remap, firsts) = cluster(&mesh.vertices, epsilon, |a, b| a.approx_eq(b, epsilon));
+	let vertices = firsts.iter().map(|&n| mesh.vertices[n]).collect();
+	let indices = mesh.indices.to_u32().iter().map(|&i| remap[i as usize]).collect();
+	remove_unused_vertices(&Mesh::new(vertices, indices))
+}
+
+// Drops triangles that reuse a vertex or whose area is at most `epsilon`.
+#[allow(dead_code)]
+pub fn remove_degenerate_triangles<V : Vertex>(mesh : &Mesh<V>, epsilon : f32) -> Mesh<V>
+{
+	let mut indices = Vec::with_capacity(mesh.indices.len());
+	for triangle in mesh.triangles()
+	{
+		let [a, b, c] = triangle;
+		if a != b && b != c && c != a && face_normal(mesh, triangle).magnitude() * 0.5 > epsilon
+		{
+			indices.extend_from_slice(&triangle);
+		}
+	}
+
+	Mesh::new(mesh.vertices.clone(), indices)
+}
+
+// Drops vertices no triangle refers to, keeping the order of the rest.
+#[allow(dead_code)]
+pub fn remove_unused_vertices<V : Vertex>(mesh : &Mesh<V>) -> Mesh<V>
+{
+	let indices = mesh.indices.to_u32();
+	let mut remap = vec![u32::max_value(); mesh.vertices.len()];
+	for &i in &indices
+	{
+		remap[i as usize] = 0;
+	}
+
+	let mut vertices = Vec::new();
+	for (n, vertex) in mesh.vertices.iter().enumerate()
+	{
+		if remap[n] == 0
+		{
+			remap[n] = vertices.len() as u32;
+			vertices.push(*vertex);
+		}
+	}
+
+	let indices = indices.iter().map(|&i| remap[i as usize]).collect();
+	Mesh::new(vertices, indices)
+}
+
+// Checks that neighbouring triangles agree on their winding: every edge
+// shared by two triangles must be traversed in opposite directions.
+#[allow(dead_code)]
+pub fn check_winding<V : Vertex>(mesh : &Mesh<V>) -> WindingReport
+{
+	let ids = position_ids(mesh);
+
+	// Per undirected edge, how often it is traversed in each direction.
+	let mut edges = HashMap::<(u32, u32), (usize, usize)>::new();
+	for [a, b, c] in mesh.triangles()
+	{
+		let (a, b, c) = (ids[a as usize], ids[b as usize], ids[c as usize]);
+		for &(from, to) in &[(a, b), (b, c), (c, a)]
+		{
+			if from < to
+			{
+				edges.entry((from, to)).or_insert((0, 0)).0 += 1;
+			}
+			else if to < from
+			{
+				edges.entry((to, from)).or_insert((0, 0)).1 += 1;
+			}
+		}
+	}
+
+	let mut report = WindingReport::default();
+	for &(forward, backward) in edges.values()
+	{
+		match (forward, backward)
+		{
+			(1, 0) | (0, 1) => report.boundary_edges += 1,
+			(1, 1) => (),
+			(2, 0) | (0, 2) => report.inconsistent_edges += 1,
+			_ => report.non_manifold_edges += 1,
+		}
+	}
+	report
+}
+
+#[allow(dead_code)]
+pub fn aabb<V : Vertex>(mesh : &Mesh<V>) -> Option<Aabb>
+{
+	Aabb::from_points(mesh.vertices.iter().map(|v| v.position()))
+}
+
+#[allow(dead_code)]
+pub fn bounding_sphere<V : Vertex>(mesh : &Mesh<V>) -> Option<BoundingSphere>
+{
+	let points : Vec<Vector3<f32>> = mesh.vertices.iter().map(|v| v.position()).collect();
+	BoundingSphere::from_points(&points)
+}
+
+#[cfg(test)]
+mod processing_tests
+{
+	use crate::geometry::{
+		primitives::{
+			cube,
+			icosphere,
+			plane,
+			torus,
+			uv_sphere,
+		},
+		processing::{
+			aabb,
+			bounding_sphere,
+			check_winding,
+			flat_normals,
+			remove_degenerate_triangles,
+			remove_unused_vertices,
+			smooth_normals,
+			weld,
+			Aabb,
+		},
+		sample_colored_tetrahedron_vertices,
+		Mesh,
+		Vertex,
+	};
+	use cgmath::*;
+
+	const EPSILON : f32 = 1.0e-5;
+
+	fn soup<V : Vertex>(vertices : &[V]) -> Mesh<V>
+	{
+		Mesh::new(vertices.to_vec(), (0..vertices.len() as u32).collect())
+	}
+
+	#[test]
+	fn test_weld_tetrahedron()
+	{
+		let vertices = sample_colored_tetrahedron_vertices();
+		let welded = weld(&soup(&vertices), EPSILON);
+		assert_eq!(4, welded.vertices.len());
+		assert_eq!(12, welded.indices.len());
+		assert_eq!(vertices.to_vec(), welded.to_triangle_soup());
+	}
+
+	#[test]
+	fn test_weld_within_epsilon()
+	{
+		let mesh = soup(&cube(2.0, 1).to_triangle_soup());
+		let mut nudged = mesh.clone();
+		for (n, vertex) in nudged.vertices.iter_mut().enumerate()
+		{
+			vertex.position.x += n as f32 * 1.0e-4;
+		}
+
+		// Corners of different faces have different normals, so only the
+		// corners within each face merge.
+		assert_eq!(24, weld(&nudged, 1.0e-2).vertices.len());
+		assert_eq!(12, weld(&nudged, 1.0e-2).triangle_count());
+		assert_eq!(36, weld(&nudged, 0.5e-4).vertices.len());
+	}
+
+	#[test]
+	fn test_flat_normals()
+	{
+		let mesh = flat_normals(&icosphere(1.0, 1));
+		assert_eq!(80, mesh.triangle_count());
+		assert_eq!(240, mesh.vertices.len());
+		for [a, b, c] in mesh.triangles()
+		{
+			let v = |n : u32| mesh.vertices[n as usize];
+			let face = (v(b).position - v(a).position).cross(v(c).position - v(a).position).normalize();
+			for &n in &[a, b, c]
+			{
+				assert!((v(n).normal - face).magnitude() < EPSILON);
+			}
+		}
+	}
+
+	#[test]
+	fn test_smooth_normals()
+	{
+		// A sphere's smooth normals point away from the center, seams and
+		// poles included.
+		let mut mesh = uv_sphere(1.0, 32, 16);
+		for vertex in mesh.vertices.iter_mut()
+		{
+			vertex.normal = Vector3::zero();
+		}
+		let mesh = smooth_normals(&mesh);
+		for vertex in &mesh.vertices
+		{
+			assert!((vertex.normal - vertex.position.normalize()).magnitude() < 1.0e-2, "{:?}", vertex);
+		}
+	}
+
+	#[test]
+	fn test_remove_degenerate_triangles()
+	{
+		let mut mesh = icosphere(1.0, 0);
+		let count = mesh.triangle_count();
+		let mut indices = mesh.indices.to_u32();
+		indices.extend_from_slice(&[0, 0, 1, 0, 1, 1]);
+		mesh.vertices.push(mesh.vertices[0].lerp(&mesh.vertices[1], 0.5));
+		let middle = mesh.vertices.len() as u32 - 1;
+		indices.extend_from_slice(&[0, middle, 1]);
+		let mesh = Mesh::new(mesh.vertices, indices);
+
+		let cleaned = remove_degenerate_triangles(&mesh, EPSILON);
+		assert_eq!(count, cleaned.triangle_count());
+		assert_eq!(mesh.vertices.len(), cleaned.vertices.len());
+		assert_eq!(mesh.vertices.len() - 1, remove_unused_vertices(&cleaned).vertices.len());
+	}
+
+	#[test]
+	fn test_check_winding()
+	{
+		let tetrahedron = weld(&soup(&sample_colored_tetrahedron_vertices()), EPSILON);
+		let report = check_winding(&tetrahedron);
+		assert!(report.is_consistent() && report.is_closed(), "{:?}", report);
+
+		// Hard edges do not count as holes.
+		let report = check_winding(&cube(1.0, 1));
+		assert!(report.is_consistent() && report.is_closed(), "{:?}", report);
+		assert!(check_winding(&torus(2.0, 0.5, 8, 6)).is_closed());
+
+		let report = check_winding(&plane(1.0, 1.0, 2, 2));
+		assert!(report.is_consistent());
+		assert_eq!(8, report.boundary_edges);
+
+		// Flipping one face makes all three of its edges disagree.
+		let mut indices = tetrahedron.indices.to_u32();
+		indices.swap(1, 2);
+		let report = check_winding(&Mesh::new(tetrahedron.vertices, indices));
+		assert_eq!(3, report.inconsistent_edges);
+		assert!(!report.is_consistent());
+	}
+
+	#[test]
+	fn test_bounds()
+	{
+		let mesh = cube(2.0, 1);
+		let bounds = aabb(&mesh).unwrap();
+		assert_eq!(Vector3::new(-1.0, -1.0, -1.0), bounds.min);
+		assert_eq!(Vector3::new(1.0, 1.0, 1.0), bounds.max);
+		assert_eq!(Vector3::new(1.0, 1.0, 1.0), bounds.extents());
+
+		let sphere = bounding_sphere(&mesh).unwrap();
+		assert!((sphere.radius - 3.0_f32.sqrt()).abs() < EPSILON);
+
+		let mut offset = icosphere(2.0, 2);
+		for vertex in offset.vertices.iter_mut()
+		{
+			vertex.position += Vector3::new(10.0, -5.0, 3.0);
+		}
+		let sphere = bounding_sphere(&offset).unwrap();
+		assert!((sphere.center - Vector3::new(10.0, -5.0, 3.0)).magnitude() < 1.0e-2);
+		assert!(sphere.radius < 2.0 * 1.02);
+		for vertex in &offset.vertices
+		{
+			assert!((vertex.position - sphere.center).magnitude() <= sphere.radius + EPSILON);
+		}
+
+		let empty : Mesh<crate::geometry::MeshVertex> = Mesh::new(Vec::new(), Vec::new());
+		assert_eq!(None, aabb(&empty));
+		assert_eq!(None, bounding_sphere(&empty));
+	}
+
+	#[test]
+	fn test_transform_bounds()
+	{
+		let mesh = cube(2.0, 1);
+		let transform = Matrix4::from_translation(Vector3::new(5.0, 0.0, 0.0))
+			* Matrix4::from_angle_y(Deg(45.0))
+			* Matrix4::from_nonuniform_scale(1.0, 3.0, 1.0);
+
+		// Tight for the transformed corners of the box.
+		let bounds = aabb(&mesh).unwrap().transform(transform);
+		let expected = Aabb::from_points(
+			mesh.vertices.iter().map(|v| transform.transform_point(Point3::from_vec(v.position)).to_vec()),
+		)
+		.unwrap();
+		assert!((bounds.min - expected.min).magnitude() < EPSILON);
+		assert!((bounds.max - expected.max).magnitude() < EPSILON);
+
+		let sphere = bounding_sphere(&mesh).unwrap().transform(transform);
+		assert!((sphere.center - Vector3::new(5.0, 0.0, 0.0)).magnitude() < EPSILON);
+		assert!((sphere.radius - 3.0 * 3.0_f32.sqrt()).abs() < EPSILON);
+	}
+}
@@ -0,0 +1,287 @@
+// Rays, for picking and other intersection queries.
+//
+// A ray is `origin + t * direction` for t >= 0. The direction is not
+// normalized, so a ray moved into another space with `transform` keeps the
+// same t for the same points, and hits found in model space can be compared
+// with hits found in world space.
+use super::processing::Aabb;
+use crate::transforms::{
+	DepthRange,
+	Viewport,
+};
+use cgmath::*;
+
+// Intersections closer to parallel than this are treated as misses.
+const PARALLEL_EPSILON : f32 = 1.0e-8;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray
+{
+	pub origin :    Point3<f32>,
+	pub direction : Vector3<f32>,
+}
+
+// Where a ray hit a triangle (a, b, c): at `origin + t * direction`, which is
+// `a + u * (b - a) + v * (c - a)`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TriangleHit
+{
+	pub t : f32,
+	pub u : f32,
+	pub v : f32,
+}
+
+impl Ray
+{
+	pub fn new(origin : Point3<f32>, direction : Vector3<f32>) -> Self
+	{
+		Ray {
+			origin :    origin,
+			direction : direction,
+		}
+	}
+
+	// Ray under the cursor, in pixels like `transforms::project` returns them,
+	// from the near plane away from the camera. It reaches the far plane at
+	// t = 1, or for an infinite far plane points the same way. None if the
+	// matrices can not be inverted.
+	pub fn from_cursor(
+		cursor : Point2<f32>, view : Matrix4<f32>, projection : Matrix4<f32>, viewport : &Viewport,
+		depth_range : DepthRange,
+	) -> Option<Self>
+	{
+		let inverse = (projection * view).invert()?;
+		let ndc = viewport.to_ndc(Point3::new(cursor.x, cursor.y, viewport.min_depth), depth_range);
+		let (near_depth, far_depth) = depth_range.near_far::<f32>();
+		let near = inverse * Vector4::new(ndc.x, ndc.y, near_depth, 1.0);
+		let far = inverse * Vector4::new(ndc.x, ndc.y, far_depth, 1.0);
+
+		let origin = Point3::from_homogeneous(near);
+		// Points at infinity have w = 0, and xyz is their direction.
+		let direction = if far.w.abs() > f32::EPSILON * far.truncate().magnitude()
+		{
+			Point3::from_homogeneous(far) - origin
+		}
+		else
+		{
+			far.truncate() * far.w.signum()
+		};
+		Some(Ray::new(origin, direction))
+	}
+
+	pub fn at(&self, t : f32) -> Point3<f32>
+	{
+		self.origin + self.direction * t
+	}
+
+	// The same ray in the space `transform` maps to, for instance model space
+	// with the inverse of the model matrix.
+	pub fn transform(&self, transform : Matrix4<f32>) -> Self
+	{
+		Ray::new(transform.transform_point(self.origin), transform.transform_vector(self.direction))
+	}
+
+	// Möller-Trumbore intersection with triangle (a, b, c), from either side.
+	pub fn intersect_triangle(&self, a : Point3<f32>, b : Point3<f32>, c : Point3<f32>) -> Option<TriangleHit>
+	{
+		let ab = b - a;
+		let ac = c - a;
+		let p = self.direction.cross(ac);
+		let determinant = ab.dot(p);
+		if determinant.abs() < PARALLEL_EPSILON
+		{
+			return None;
+		}
+
+		let inverse_determinant = 1.0 / determinant;
+		let to_origin = self.origin - a;
+		let u = to_origin.dot(p) * inverse_determinant;
+		if !(0.0..=1.0).contains(&u)
+		{
+			return None;
+		}
+
+		let q = to_origin.cross(ab);
+		let v = self.direction.dot(q) * inverse_determinant;
+		if v < 0.0 || u + v > 1.0
+		{
+			return None;
+		}
+
+		let t = ac.dot(q) * inverse_determinant;
+		if t < 0.0
+		{
+			return None;
+		}
+
+		Some(TriangleHit {
+			t : t,
+			u : u,
+			v : v,
+		})
+	}
+
+	// Slab test. The t range over which the ray is inside `aabb`, if any.
+	pub fn intersect_aabb(&self, aabb : &Aabb) -> Option<(f32, f32)>
+	{
+		let mut t_min = 0.0_f32;
+		let mut t_max = f32::INFINITY;
+		for axis in 0..3
+		{
+			let origin = self.origin[axis];
+			let direction = self.direction[axis];
+			if direction == 0.0
+			{
+				if origin < aabb.min[axis] || origin > aabb.max[axis]
+				{
+					return None;
+				}
+				continue;
+			}
+
+			let t0 = (aabb.min[axis] - origin) / direction;
+			let t1 = (aabb.max[axis] - origin) / direction;
+			t_min = t_min.max(t0.min(t1));
+			t_max = t_max.min(t0.max(t1));
+			if t_min > t_max
+			{
+				return None;
+			}
+		}
+		Some((t_min, t_max))
+	}
+}
+
+#[cfg(test)]
+mod ray_tests
+{
+	use crate::{
+		geometry::{
+			processing::Aabb,
+			ray::Ray,
+		},
+		transforms::{
+			self,
+			ClipSpaceConvention,
+			DepthRange,
+			Handedness,
+			Viewport,
+		},
+	};
+	use cgmath::*;
+
+	const EPSILON : f32 = 1.0e-4;
+
+	fn triangle() -> [Point3<f32>; 3]
+	{
+		[Point3::new(-1.0, -1.0, 2.0), Point3::new(0.0, 1.0, 2.0), Point3::new(1.0, -1.0, 2.0)]
+	}
+
+	#[test]
+	fn test_triangle_hit()
+	{
+		let [a, b, c] = triangle();
+		let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+		let hit = ray.intersect_triangle(a, b, c).unwrap();
+		assert!((hit.t - 2.0).abs() < EPSILON);
+		let point = a + (b - a) * hit.u + (c - a) * hit.v;
+		assert!((point - ray.at(hit.t)).magnitude() < EPSILON);
+
+		// Hits from behind too, since picking should not depend on winding.
+		let ray = Ray::new(Point3::new(0.0, 0.0, 4.0), Vector3::new(0.0, 0.0, -2.0));
+		assert!((ray.intersect_triangle(a, b, c).unwrap().t - 1.0).abs() < EPSILON);
+	}
+
+	#[test]
+	fn test_triangle_misses()
+	{
+		let [a, b, c] = triangle();
+		// Beside the triangle.
+		assert_eq!(None, Ray::new(Point3::new(0.9, 0.9, 0.0), Vector3::unit_z()).intersect_triangle(a, b, c));
+		// Pointing away.
+		assert_eq!(None, Ray::new(Point3::origin(), -Vector3::unit_z()).intersect_triangle(a, b, c));
+		// Parallel to the plane of the triangle.
+		assert_eq!(None, Ray::new(Point3::new(-2.0, 0.0, 2.0), Vector3::unit_x()).intersect_triangle(a, b, c));
+	}
+
+	#[test]
+	fn test_aabb()
+	{
+		let aabb = Aabb {
+			min : Vector3::new(-1.0, -1.0, -1.0),
+			max : Vector3::new(1.0, 1.0, 1.0),
+		};
+		let (near, far) = Ray::new(Point3::new(0.0, 0.0, -3.0), Vector3::unit_z()).intersect_aabb(&aabb).unwrap();
+		assert_eq!((2.0, 4.0), (near, far));
+
+		// Starting inside.
+		assert_eq!(Some((0.0, 1.0)), Ray::new(Point3::origin(), Vector3::unit_x()).intersect_aabb(&aabb));
+		// Parallel to a slab and outside it.
+		assert_eq!(None, Ray::new(Point3::new(0.0, 2.0, -3.0), Vector3::unit_z()).intersect_aabb(&aabb));
+		// Behind the ray.
+		assert_eq!(None, Ray::new(Point3::new(0.0, 0.0, 3.0), Vector3::unit_z()).intersect_aabb(&aabb));
+	}
+
+	#[test]
+	fn test_transform_keeps_t()
+	{
+		let ray = Ray::new(Point3::new(1.0, 2.0, 3.0), Vector3::new(0.0, 0.0, 2.0));
+		let model = Matrix4::from_translation(Vector3::new(5.0, 0.0, 0.0)) * Matrix4::from_scale(2.0);
+		let local = ray.transform(model.invert().unwrap());
+		for &t in &[0.0, 0.5, 3.0]
+		{
+			assert!((model.transform_point(local.at(t)) - ray.at(t)).magnitude() < EPSILON);
+		}
+	}
+
+	#[test]
+	fn test_from_cursor()
+	{
+		let eye = Point3::new(0.0, 0.0, -2.0);
+		let view = transforms::look_at_lh(eye, Point3::origin(), Vector3::unit_y());
+		let perspective = PerspectiveFov {
+			fovy :   Rad(std::f32::consts::FRAC_PI_2),
+			aspect : 2.0,
+			near :   0.5,
+			far :    10.0,
+		};
+		let viewport = Viewport::new(200.0, 100.0);
+
+		for &depth_range in &[DepthRange::ZeroToOne, DepthRange::MinusOneToOne, DepthRange::OneToZero]
+		{
+			let convention = ClipSpaceConvention::new(Handedness::Left, depth_range);
+			let finite = transforms::perspective(perspective, convention);
+			let infinite =
+				transforms::perspective_infinite(perspective.fovy, perspective.aspect, perspective.near, convention);
+
+			// Through the center of the screen, straight ahead from the near plane
+			// to the far plane.
+			let center = Point2::new(100.0, 50.0);
+			let ray = Ray::from_cursor(center, view, finite, &viewport, depth_range).unwrap();
+			assert!((ray.origin - Point3::new(0.0, 0.0, -1.5)).magnitude() < EPSILON, "{:?}", depth_range);
+			assert!((ray.at(1.0) - Point3::new(0.0, 0.0, 8.0)).magnitude() < 1.0e-2, "{:?}", depth_range);
+
+			for &projection in &[finite, infinite]
+			{
+				// The top right corner is at 45 degrees up, and 2 to the right for
+				// every 1 up with this aspect ratio. Screen y points down.
+				let ray = Ray::from_cursor(Point2::new(200.0, 0.0), view, projection, &viewport, depth_range).unwrap();
+				let direction = ray.direction.normalize();
+				assert!((direction - Vector3::new(2.0, 1.0, 1.0).normalize()).magnitude() < EPSILON);
+
+				// Every cursor ray passes through the eye, and projects back onto the
+				// same pixel.
+				for &cursor in &[Point2::new(13.0, 77.0), Point2::new(150.0, 20.0)]
+				{
+					let ray = Ray::from_cursor(cursor, view, projection, &viewport, depth_range).unwrap();
+					let to_eye = eye - ray.origin;
+					assert!(to_eye.cross(ray.direction).magnitude() < EPSILON * ray.direction.magnitude());
+					assert!(ray.direction.dot(ray.origin - eye) > 0.0);
+
+					let pixel = transforms::project(ray.at(0.5), view, projection, &viewport, depth_range).unwrap();
+					assert!((Point2::new(pixel.x, pixel.y) - cursor).magnitude() < 1.0e-2);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,586 @@
+// Mesh simplification by quadric error metric edge collapse.
+//
+// Every vertex carries a quadric, the sum of the squared distances to the
+// planes of the triangles around it (Garland and Heckbert). Collapsing an edge
+// merges its two vertices into one placed where the summed quadric is
+// smallest, and the cheapest edge is always collapsed first. The merged vertex
+// takes its other attributes, like color and texture coordinates, from
+// interpolating the two endpoints with `Vertex::lerp`.
+//
+// Edges used by a single triangle are boundaries, including attribute seams
+// where vertices are split, and get extra planes perpendicular to the surface
+// so they keep their shape and seams do not crack open. Collapses that would
+// flip a triangle or make the mesh non-manifold are skipped.
+use super::{
+	processing::remove_unused_vertices,
+	Mesh,
+	Vertex,
+};
+use cgmath::*;
+use std::{
+	cmp::Ordering,
+	collections::BinaryHeap,
+};
+
+// Weight of the planes that hold boundary edges in place, relative to the
+// planes of the triangles.
+const BOUNDARY_WEIGHT : f64 = 100.0;
+
+// One level of detail.
+#[derive(Debug, Clone)]
+pub struct LodLevel<V>
+{
+	pub mesh :         Mesh<V>,
+	// Fraction of the source triangles that was asked for.
+	pub target_ratio : f32,
+	// Square root of the largest quadric error of any collapse so far. This is
+	// roughly how far, in mesh units, the surface has moved.
+	pub error :        f32,
+}
+
+// Sum of squared distances to a set of planes, as x'Ax + 2b'x + c.
+#[derive(Debug, Copy, Clone)]
+struct Quadric
+{
+	a : Matrix3<f64>,
+	b : Vector3<f64>,
+	c : f64,
+}
+
+impl Quadric
+{
+	fn zero() -> Self
+	{
+		Quadric {
+			a : Matrix3::zero(),
+			b : Vector3::zero(),
+			c : 0.0,
+		}
+	}
+
+	// Plane through `point` with unit `normal`.
+	fn plane(normal : Vector3<f64>, point : Vector3<f64>, weight : f64) -> Self
+	{
+		let d = -normal.dot(point);
+		Quadric {
+			a : Matrix3::from_cols(normal * normal.x, normal * normal.y, normal * normal.z) * weight,
+			b : normal * (d * weight),
+			c : d * d * weight,
+		}
+	}
+
+	fn add(&self, other : &Quadric) -> Self
+	{
+		Quadric {
+			a : self.a + other.a,
+			b : self.b + other.b,
+			c : self.c + other.c,
+		}
+	}
+
+	fn error(&self, p : Vector3<f64>) -> f64
+	{
+		p.dot(self.a * p) + 2.0 * self.b.dot(p) + self.c
+	}
+
+	// Point of least error, None when it is not unique, like on a flat patch.
+	fn minimum(&self) -> Option<Vector3<f64>>
+	{
+		if self.a.determinant().abs() < 1.0e-12
+		{
+			return None;
+		}
+		self.a.invert().map(|inverse| -(inverse * self.b))
+	}
+}
+
+fn to_f64(v : Vector3<f32>) -> Vector3<f64>
+{
+	v.cast().unwrap()
+}
+
+// Edge collapse in the queue. Stale once either vertex changed after it was
+// queued.
+struct Candidate<V>
+{
+	cost :     f64,
+	a :        u32,
+	b :        u32,
+	versions : (u32, u32),
+	vertex :   V,
+}
+
+impl<V> PartialEq for Candidate<V>
+{
+	fn eq(&self, other : &Self) -> bool
+	{
+		self.cost == other.cost
+	}
+}
+
+impl<V> Eq for Candidate<V> {}
+
+impl<V> PartialOrd for Candidate<V>
+{
+	fn partial_cmp(&self, other : &Self) -> Option<Ordering>
+	{
+		Some(self.cmp(other))
+	}
+}
+
+// Reversed, so BinaryHeap pops the cheapest collapse.
+impl<V> Ord for Candidate<V>
+{
+	fn cmp(&self, other : &Self) -> Ordering
+	{
+		other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+	}
+}
+
+struct Simplifier<V>
+{
+	vertices : Vec<V>,
+	quadrics : Vec<Quadric>,
+	versions : Vec<u32>,
+	triangles : Vec<[u32; 3]>,
+	removed : Vec<bool>,
+	// Triangles around each vertex. May still list removed triangles.
+	vertex_triangles : Vec<Vec<usize>>,
+	heap : BinaryHeap<Candidate<V>>,
+	triangle_count : usize,
+	max_error : f64,
+}
+
+impl<V : Vertex> Simplifier<V>
+{
+	fn new(mesh : &Mesh<V>) -> Self
+	{
+		let triangles : Vec<[u32; 3]> = mesh.triangles().collect();
+		let mut vertex_triangles = vec![Vec::new(); mesh.vertices.len()];
+		for (t, triangle) in triangles.iter().enumerate()
+		{
+			for &v in triangle
+			{
+				vertex_triangles[v as usize].push(t);
+			}
+		}
+
+		let mut simplifier = Simplifier {
+			vertices : mesh.vertices.clone(),
+			quadrics : vec![Quadric::zero(); mesh.vertices.len()],
+			versions : vec![0; mesh.vertices.len()],
+			removed : vec![false; triangles.len()],
+			triangle_count : triangles.len(),
+			triangles : triangles,
+			vertex_triangles : vertex_triangles,
+			heap : BinaryHeap::new(),
+			max_error : 0.0,
+		};
+		simplifier.init_quadrics();
+
+		for a in 0..simplifier.vertices.len() as u32
+		{
+			for b in simplifier.neighbours(a)
+			{
+				if a < b
+				{
+					simplifier.push_candidate(a, b);
+				}
+			}
+		}
+		simplifier
+	}
+
+	fn position(&self, v : u32) -> Vector3<f64>
+	{
+		to_f64(self.vertices[v as usize].position())
+	}
+
+	fn init_quadrics(&mut self)
+	{
+		// Directed edges, to find the ones without a twin.
+		let mut edges = std::collections::HashMap::<(u32, u32), usize>::new();
+		for &[a, b, c] in &self.triangles
+		{
+			for &edge in &[(a, b), (b, c), (c, a)]
+			{
+				*edges.entry(edge).or_insert(0) += 1;
+			}
+		}
+
+		for t in 0..self.triangles.len()
+		{
+			let [a, b, c] = self.triangles[t];
+			let (pa, pb, pc) = (self.position(a), self.position(b), self.position(c));
+			let normal = (pb - pa).cross(pc - pa);
+			if normal.magnitude2() == 0.0
+			{
+				continue;
+			}
+			let normal = normal.normalize();
+
+			let plane = Quadric::plane(normal, pa, 1.0);
+			for &v in &[a, b, c]
+			{
+				self.quadrics[v as usize] = self.quadrics[v as usize].add(&plane);
+			}
+
+			for &(from, to) in &[(a, b), (b, c), (c, a)]
+			{
+				if edges.contains_key(&(to, from))
+				{
+					continue;
+				}
+				let (p, q) = (self.position(from), self.position(to));
+				let edge = q - p;
+				let side = edge.cross(normal);
+				if side.magnitude2() == 0.0
+				{
+					continue;
+				}
+				let plane = Quadric::plane(side.normalize(), p, BOUNDARY_WEIGHT * edge.magnitude2());
+				for &v in &[from, to]
+				{
+					self.quadrics[v as usize] = self.quadrics[v as usize].add(&plane);
+				}
+			}
+		}
+	}
+
+	fn live_triangles<'a>(&'a self, v : u32) -> impl Iterator<Item = usize> + 'a
+	{
+		self.vertex_triangles[v as usize].iter().cloned().filter(move |&t| !self.removed[t])
+	}
+
+	// Vertices sharing a triangle with `v`, sorted.
+	fn neighbours(&self, v : u32) -> Vec<u32>
+	{
+		let mut result : Vec<u32> =
+			self.live_triangles(v).flat_map(|t| self.triangles[t].to_vec()).filter(|&n| n != v).collect();
+		result.sort();
+		result.dedup();
+		result
+	}
+
+	fn push_candidate(&mut self, a : u32, b : u32)
+	{
+		let quadric = self.quadrics[a as usize].add(&self.quadrics[b as usize]);
+		let (pa, pb) = (self.position(a), self.position(b));
+		let midpoint = (pa + pb) * 0.5;
+
+		// The minimum can land far away when the quadric is nearly singular.
+		let mut options = vec![pa, pb, midpoint];
+		if let Some(minimum) = quadric.minimum()
+		{
+			if (minimum - midpoint).magnitude() <= (pb - pa).magnitude()
+			{
+				options.push(minimum);
+			}
+		}
+		let (cost, position) = options.into_iter().map(|p| (quadric.error(p).max(0.0), p)).fold(
+			(f64::INFINITY, pa),
+			|best, option| {
+				if option.0 < best.0
+				{
+					option
+				}
+				else
+				{
+					best
+				}
+			},
+		);
+
+		// Attributes come from the closest point on the edge.
+		let edge = pb - pa;
+		let t = if edge.magnitude2() > 0.0
+		{
+			((position - pa).dot(edge) / edge.magnitude2()).clamp(0.0, 1.0)
+		}
+		else
+		{
+			0.0
+		};
+		let vertex = self.vertices[a as usize]
+			.lerp(&self.vertices[b as usize], t as f32)
+			.with_position(position.cast().unwrap());
+
+		self.heap.push(Candidate {
+			cost :     cost,
+			a :        a,
+			b :        b,
+			versions : (self.versions[a as usize], self.versions[b as usize]),
+			vertex :   vertex,
+		});
+	}
+
+	// Collapsing keeps the mesh manifold only if the vertices next to both a
+	// and b are exactly the third corners of the triangles on the edge. The
+	// last triangles are never collapsed away.
+	fn satisfies_link_condition(&self, a : u32, b : u32) -> bool
+	{
+		let shared : Vec<usize> = self.live_triangles(a).filter(|&t| self.triangles[t].contains(&b)).collect();
+		if shared.is_empty() || shared.len() == self.triangle_count
+		{
+			return false;
+		}
+
+		let neighbours_b = self.neighbours(b);
+		let common = self.neighbours(a).into_iter().filter(|n| neighbours_b.binary_search(n).is_ok()).count();
+		common == shared.len()
+	}
+
+	// True if moving a and b to `position` turns any remaining triangle
+	// around, or flattens it.
+	fn flips(&self, a : u32, b : u32, position : Vector3<f64>) -> bool
+	{
+		self.live_triangles(a).chain(self.live_triangles(b)).any(|t| {
+			let triangle = self.triangles[t];
+			if triangle.contains(&a) && triangle.contains(&b)
+			{
+				return false;
+			}
+
+			let old : Vec<Vector3<f64>> = triangle.iter().map(|&v| self.position(v)).collect();
+			let new : Vec<Vector3<f64>> = triangle
+				.iter()
+				.map(|&v| {
+					if v == a || v == b
+					{
+						position
+					}
+					else
+					{
+						self.position(v)
+					}
+				})
+				.collect();
+			let old_normal = (old[1] - old[0]).cross(old[2] - old[0]);
+			let new_normal = (new[1] - new[0]).cross(new[2] - new[0]);
+			old_normal.dot(new_normal) <= 0.0
+		})
+	}
+
+	// Collapses edges until at most `target` triangles are left, or no edge
+	// can be collapsed.
+	fn reduce(&mut self, target : usize)
+	{
+		while self.triangle_count > target
+		{
+			let candidate = match self.heap.pop()
+			{
+				Some(candidate) => candidate,
+				None => break,
+			};
+			let (a, b) = (candidate.a, candidate.b);
+			if candidate.versions != (self.versions[a as usize], self.versions[b as usize])
+			{
+				continue;
+			}
+			if !self.satisfies_link_condition(a, b) || self.flips(a, b, to_f64(candidate.vertex.position()))
+			{
+				continue;
+			}
+
+			self.collapse(a, b, candidate.vertex);
+			self.max_error = self.max_error.max(candidate.cost);
+		}
+	}
+
+	// Merges b into a, which becomes `vertex`.
+	fn collapse(&mut self, a : u32, b : u32, vertex : V)
+	{
+		self.vertices[a as usize] = vertex;
+		self.quadrics[a as usize] = self.quadrics[a as usize].add(&self.quadrics[b as usize]);
+
+		let triangles_b = std::mem::take(&mut self.vertex_triangles[b as usize]);
+		for t in triangles_b
+		{
+			if self.removed[t]
+			{
+				continue;
+			}
+			if self.triangles[t].contains(&a)
+			{
+				self.removed[t] = true;
+				self.triangle_count -= 1;
+				continue;
+			}
+			for v in self.triangles[t].iter_mut()
+			{
+				if *v == b
+				{
+					*v = a;
+				}
+			}
+			self.vertex_triangles[a as usize].push(t);
+		}
+
+		let removed = &self.removed;
+		self.vertex_triangles[a as usize].retain(|&t| !removed[t]);
+		self.versions[a as usize] += 1;
+		self.versions[b as usize] += 1;
+
+		for n in self.neighbours(a)
+		{
+			self.push_candidate(a, n);
+		}
+	}
+
+	fn mesh(&self) -> Mesh<V>
+	{
+		let indices = self
+			.triangles
+			.iter()
+			.zip(&self.removed)
+			.filter(|&(_, &removed)| !removed)
+			.flat_map(|(triangle, _)| triangle.to_vec())
+			.collect();
+		remove_unused_vertices(&Mesh::new(self.vertices.clone(), indices))
+	}
+
+	fn error(&self) -> f32
+	{
+		self.max_error.sqrt() as f32
+	}
+}
+
+fn target_triangles(triangle_count : usize, ratio : f32) -> usize
+{
+	(triangle_count as f32 * ratio.clamp(0.0, 1.0)).ceil() as usize
+}
+
+// Reduces `mesh` to about `target_ratio` of its triangles. Stops early if no
+// further edge can be collapsed without damaging the mesh.
+#[allow(dead_code)]
+pub fn simplify<V : Vertex>(mesh : &Mesh<V>, target_ratio : f32) -> LodLevel<V>
+{
+	let mut simplifier = Simplifier::new(mesh);
+	simplifier.reduce(target_triangles(mesh.triangle_count(), target_ratio));
+	LodLevel {
+		mesh :         simplifier.mesh(),
+		target_ratio : target_ratio,
+		error :        simplifier.error(),
+	}
+}
+
+// One level per ratio, each a fraction of the triangles of `mesh`. Levels are
+// produced by continuing to collapse the previous one, so their errors never
+// decrease. Ratios are expected in decreasing order.
+#[allow(dead_code)]
+pub fn lod_chain<V : Vertex>(mesh : &Mesh<V>, ratios : &[f32]) -> Vec<LodLevel<V>>
+{
+	let mut simplifier = Simplifier::new(mesh);
+	ratios
+		.iter()
+		.map(|&ratio| {
+			simplifier.reduce(target_triangles(mesh.triangle_count(), ratio));
+			LodLevel {
+				mesh :         simplifier.mesh(),
+				target_ratio : ratio,
+				error :        simplifier.error(),
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod simplify_tests
+{
+	use crate::geometry::{
+		primitives::{
+			icosphere,
+			plane,
+			uv_sphere,
+		},
+		processing::{
+			aabb,
+			check_winding,
+		},
+		simplify::{
+			lod_chain,
+			simplify,
+		},
+		Vertex,
+	};
+	use cgmath::*;
+
+	#[test]
+	fn test_simplify_sphere()
+	{
+		let mesh = icosphere(1.0, 4);
+		assert_eq!(5120, mesh.triangle_count());
+
+		let level = simplify(&mesh, 0.1);
+		assert!(level.mesh.triangle_count() <= 512);
+		assert!(level.mesh.triangle_count() > 400);
+		assert!(level.error > 0.0 && level.error < 0.1, "error {}", level.error);
+
+		let winding = check_winding(&level.mesh);
+		assert!(winding.is_consistent() && winding.is_closed(), "{:?}", winding);
+		for vertex in &level.mesh.vertices
+		{
+			let radius = vertex.position.magnitude();
+			assert!(radius > 0.95 && radius < 1.01, "radius {}", radius);
+		}
+	}
+
+	#[test]
+	fn test_flat_plane_keeps_its_outline()
+	{
+		let mesh = plane(2.0, 1.0, 16, 8);
+		let level = simplify(&mesh, 2.0 / mesh.triangle_count() as f32);
+
+		// A flat rectangle only needs two triangles, at no error.
+		assert_eq!(2, level.mesh.triangle_count());
+		assert!(level.error < 1.0e-3, "error {}", level.error);
+		assert_eq!(aabb(&mesh), aabb(&level.mesh));
+		for vertex in &level.mesh.vertices
+		{
+			assert_eq!(0.0, vertex.position.y);
+		}
+	}
+
+	#[test]
+	fn test_colors_follow_positions()
+	{
+		// Color as a linear function of height, which interpolation along
+		// edges should keep up to the distance the vertices moved.
+		let color = |p : Vector3<f32>| vec4(p.y * 0.5 + 0.5, 0.0, 0.5 - p.y * 0.5, 1.0);
+		let mut mesh = uv_sphere(1.0, 32, 16);
+		for vertex in mesh.vertices.iter_mut()
+		{
+			vertex.color = color(vertex.position);
+		}
+
+		let level = simplify(&mesh, 0.25);
+		assert!(level.mesh.triangle_count() < mesh.triangle_count() / 3);
+		for vertex in &level.mesh.vertices
+		{
+			let expected = color(vertex.position());
+			assert!((vertex.color - expected).magnitude() < 0.1, "{:?} vs {:?}", vertex.color, expected);
+		}
+	}
+
+	#[test]
+	fn test_lod_chain()
+	{
+		let mesh = icosphere(1.0, 3);
+		let ratios = [1.0, 0.5, 0.25, 0.1];
+		let levels = lod_chain(&mesh, &ratios);
+
+		assert_eq!(4, levels.len());
+		assert_eq!(mesh.triangle_count(), levels[0].mesh.triangle_count());
+		assert_eq!(0.0, levels[0].error);
+		for pair in levels.windows(2)
+		{
+			assert!(pair[1].mesh.triangle_count() < pair[0].mesh.triangle_count());
+			assert!(pair[1].error >= pair[0].error);
+		}
+		for (level, &ratio) in levels.iter().zip(&ratios)
+		{
+			assert_eq!(ratio, level.target_ratio);
+			assert!(level.mesh.triangle_count() as f32 <= (mesh.triangle_count() as f32 * ratio).ceil());
+		}
+	}
+}
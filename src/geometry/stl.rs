@@ -0,0 +1,488 @@
+// STL reader and writer, for both the ASCII and the binary variant.
+//
+// STL is right-handed with counter-clockwise facets, so like the other
+// loaders z is negated and winding reversed on load, and converted back on
+// save. STL stores unshared triangles, so every facet gets three vertices of
+// its own, with the facet normal as vertex normal and a white color.
+use super::{
+	Mesh,
+	MeshVertex,
+};
+use cgmath::*;
+use std::{
+	error::Error,
+	fmt,
+	fs,
+	io,
+	io::Write,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StlFormat
+{
+	Ascii,
+	Binary,
+}
+
+#[derive(Debug)]
+pub enum StlError
+{
+	Io
+	{
+		path :  PathBuf,
+		error : io::Error,
+	},
+	Parse
+	{
+		file :     String,
+		location : String,
+		details :  String,
+	},
+}
+
+impl fmt::Display for StlError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			StlError::Io {
+				path,
+				error,
+			} => write!(f, "{}: {}", path.display(), error),
+			StlError::Parse {
+				file,
+				location,
+				details,
+			} => write!(f, "{}: {}: {}", file, location, details),
+		}
+	}
+}
+
+impl Error for StlError {}
+
+const BINARY_HEADER_SIZE : usize = 80 + 4;
+const BINARY_FACET_SIZE : usize = 12 * 4 + 2;
+
+fn parse_error(file : &str, location : String, details : String) -> StlError
+{
+	StlError::Parse {
+		file :     file.to_string(),
+		location : location,
+		details :  details,
+	}
+}
+
+// Adds one facet given in file space.
+fn push_facet(vertices : &mut Vec<MeshVertex>, normal : Vector3<f32>, corners : [Vector3<f32>; 3])
+{
+	let mirror = |v : Vector3<f32>| Vector3::new(v.x, v.y, -v.z);
+	let [a, b, c] = corners;
+
+	// Exporters are allowed to leave the normal zeroed.
+	let normal = if normal.magnitude2() > 0.0
+	{
+		normal.normalize()
+	}
+	else
+	{
+		let cross = (b - a).cross(c - a);
+		if cross.magnitude2() > 0.0
+		{
+			cross.normalize()
+		}
+		else
+		{
+			cross
+		}
+	};
+
+	for &position in [a, c, b].iter()
+	{
+		vertices.push(MeshVertex {
+			position : mirror(position),
+			normal :   mirror(normal),
+			uv :       Vector2::zero(),
+			color :    Vector4::new(1.0, 1.0, 1.0, 1.0),
+		});
+	}
+}
+
+fn facets_mesh(vertices : Vec<MeshVertex>) -> Mesh<MeshVertex>
+{
+	let indices = (0..vertices.len() as u32).collect();
+	Mesh::new(vertices, indices)
+}
+
+fn parse_binary(file : &str, data : &[u8]) -> Result<Mesh<MeshVertex>, StlError>
+{
+	let facet_count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+
+	let read_vector = |offset : usize| -> Result<Vector3<f32>, StlError> {
+		let mut v = Vector3::zero();
+		for axis in 0..3
+		{
+			let at = offset + axis * 4;
+			v[axis] = f32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+		}
+		if v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
+		{
+			Ok(v)
+		}
+		else
+		{
+			Err(parse_error(file, format!("byte {}", offset), format!("invalid vector {:?}", v)))
+		}
+	};
+
+	let mut vertices = Vec::new();
+	for facet in 0..facet_count
+	{
+		let offset = BINARY_HEADER_SIZE + facet * BINARY_FACET_SIZE;
+		let normal = read_vector(offset)?;
+		let corners = [read_vector(offset + 12)?, read_vector(offset + 24)?, read_vector(offset + 36)?];
+		push_facet(&mut vertices, normal, corners);
+	}
+
+	Ok(facets_mesh(vertices))
+}
+
+fn parse_ascii(file : &str, source : &str) -> Result<Mesh<MeshVertex>, StlError>
+{
+	let mut vertices = Vec::new();
+	let mut normal = Vector3::zero();
+	let mut corners : Vec<Vector3<f32>> = Vec::new();
+	let mut in_solid = false;
+	let mut in_facet = false;
+	let mut in_loop = false;
+	let mut ended = false;
+
+	for (n, line) in source.lines().enumerate()
+	{
+		let error = |details : String| parse_error(file, format!("line {}", n + 1), details);
+		let vector = |tokens : &[&str], keyword : &str| -> Result<Vector3<f32>, StlError> {
+			let values : Vec<f32> =
+				tokens.iter().filter_map(|t| t.parse::<f32>().ok()).filter(|v| v.is_finite()).collect();
+			if tokens.len() != 3 || values.len() != 3
+			{
+				return Err(error(format!("'{}' expects 3 numbers, found '{}'", keyword, tokens.join(" "))));
+			}
+			Ok(Vector3::new(values[0], values[1], values[2]))
+		};
+
+		let tokens : Vec<&str> = line.split_whitespace().collect();
+		let keyword = match tokens.first()
+		{
+			Some(&keyword) => keyword,
+			None => continue,
+		};
+		if ended
+		{
+			return Err(error(format!("unexpected '{}' after 'endsolid'", keyword)));
+		}
+
+		match (keyword, in_solid, in_facet, in_loop)
+		{
+			("solid", false, _, _) => in_solid = true,
+			("facet", true, false, _) =>
+			{
+				if tokens.get(1) != Some(&"normal")
+				{
+					return Err(error("expected 'facet normal'".to_string()));
+				}
+				normal = vector(&tokens[2..], "facet normal")?;
+				in_facet = true;
+			}
+			("outer", true, true, false) if tokens[1..] == ["loop"] => in_loop = true,
+			("vertex", true, true, true) =>
+			{
+				if corners.len() == 3
+				{
+					return Err(error("facet has more than 3 vertices".to_string()));
+				}
+				corners.push(vector(&tokens[1..], "vertex")?);
+			}
+			("endloop", true, true, true) =>
+			{
+				if corners.len() != 3
+				{
+					return Err(error(format!("facet needs 3 vertices, found {}", corners.len())));
+				}
+				in_loop = false;
+			}
+			("endfacet", true, true, false) =>
+			{
+				push_facet(&mut vertices, normal, [corners[0], corners[1], corners[2]]);
+				corners.clear();
+				in_facet = false;
+			}
+			("endsolid", true, false, false) => ended = true,
+			_ => return Err(error(format!("unexpected '{}'", keyword))),
+		}
+	}
+
+	if !ended
+	{
+		return Err(parse_error(file, "end of file".to_string(), "missing 'endsolid'".to_string()));
+	}
+
+	Ok(facets_mesh(vertices))
+}
+
+// Parses the contents of an STL file. `file` is only used in error messages.
+//
+// Binary files are recognized by their size matching the facet count in the
+// header, since binary headers are allowed to start with "solid" too.
+pub fn parse_stl(file : &str, data : &[u8]) -> Result<Mesh<MeshVertex>, StlError>
+{
+	if data.len() >= BINARY_HEADER_SIZE
+	{
+		let facet_count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+		if facet_count.checked_mul(BINARY_FACET_SIZE).and_then(|size| size.checked_add(BINARY_HEADER_SIZE))
+			== Some(data.len())
+		{
+			return parse_binary(file, data);
+		}
+	}
+
+	if data.starts_with(b"solid")
+	{
+		let source = std::str::from_utf8(data)
+			.map_err(|error| parse_error(file, format!("byte {}", error.valid_up_to()), "invalid text".to_string()))?;
+		return parse_ascii(file, source);
+	}
+
+	Err(parse_error(
+		file,
+		"header".to_string(),
+		"not an ASCII STL file, and the size does not match a binary STL file".to_string(),
+	))
+}
+
+// Writes every triangle as a facet, with its normal computed from the
+// triangle's corners.
+pub fn write_stl<W : Write>(writer : &mut W, mesh : &Mesh<MeshVertex>, format : StlFormat) -> io::Result<()>
+{
+	let facets = mesh.triangles().map(|[a, b, c]| {
+		// Back to file space: mirror z and reverse the winding.
+		let p = |n : u32| {
+			let v = mesh.vertices[n as usize].position;
+			Vector3::new(v.x, v.y, -v.z)
+		};
+		let corners = [p(a), p(c), p(b)];
+		let cross = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+		let normal = if cross.magnitude2() > 0.0
+		{
+			cross.normalize()
+		}
+		else
+		{
+			cross
+		};
+		(normal, corners)
+	});
+
+	match format
+	{
+		StlFormat::Ascii =>
+		{
+			writeln!(writer, "solid mesh")?;
+			for (normal, corners) in facets
+			{
+				writeln!(writer, "  facet normal {} {} {}", normal.x, normal.y, normal.z)?;
+				writeln!(writer, "    outer loop")?;
+				for corner in corners.iter()
+				{
+					writeln!(writer, "      vertex {} {} {}", corner.x, corner.y, corner.z)?;
+				}
+				writeln!(writer, "    endloop")?;
+				writeln!(writer, "  endfacet")?;
+			}
+			writeln!(writer, "endsolid mesh")?;
+		}
+		StlFormat::Binary =>
+		{
+			let mut header = [b' '; 80];
+			header[..6].copy_from_slice(b"binary");
+			writer.write_all(&header)?;
+			writer.write_all(&(mesh.triangle_count() as u32).to_le_bytes())?;
+			for (normal, corners) in facets
+			{
+				for v in [normal, corners[0], corners[1], corners[2]].iter()
+				{
+					writer.write_all(&v.x.to_le_bytes())?;
+					writer.write_all(&v.y.to_le_bytes())?;
+					writer.write_all(&v.z.to_le_bytes())?;
+				}
+				writer.write_all(&[0, 0])?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+// Loads an STL file from disk.
+#[allow(dead_code)]
+pub fn load_stl(path : &Path) -> Result<Mesh<MeshVertex>, StlError>
+{
+	let data = fs::read(path).map_err(|error| StlError::Io {
+		path :  path.to_path_buf(),
+		error : error,
+	})?;
+	parse_stl(&path.display().to_string(), &data)
+}
+
+// Saves a mesh to an STL file on disk.
+#[allow(dead_code)]
+pub fn save_stl(path : &Path, mesh : &Mesh<MeshVertex>, format : StlFormat) -> Result<(), StlError>
+{
+	let io_error = |error| StlError::Io {
+		path :  path.to_path_buf(),
+		error : error,
+	};
+
+	let mut writer = io::BufWriter::new(fs::File::create(path).map_err(io_error)?);
+	write_stl(&mut writer, mesh, format).map_err(io_error)?;
+	writer.flush().map_err(io_error)
+}
+
+#[cfg(test)]
+mod stl_tests
+{
+	use crate::geometry::{
+		primitives::{
+			cube,
+			torus,
+		},
+		stl::{
+			load_stl,
+			parse_stl,
+			write_stl,
+			StlError,
+			StlFormat,
+		},
+		Mesh,
+		MeshVertex,
+	};
+	use cgmath::*;
+	use std::path::{
+		Path,
+		PathBuf,
+	};
+
+	const EPSILON : f32 = 1.0e-5;
+
+	fn fixture(name : &str) -> PathBuf
+	{
+		Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("stl").join(name)
+	}
+
+	fn parse_error(source : &str) -> String
+	{
+		match parse_stl("test.stl", source.as_bytes())
+		{
+			Err(error) => error.to_string(),
+			Ok(_) => panic!("expected a parse error for {:?}", source),
+		}
+	}
+
+	fn assert_faces_point_along_normals(mesh : &Mesh<MeshVertex>)
+	{
+		for [a, b, c] in mesh.triangles()
+		{
+			let v = |n : u32| mesh.vertices[n as usize];
+			let face_normal = (v(b).position - v(a).position).cross(v(c).position - v(a).position);
+			assert!(face_normal.dot(v(a).normal) > 0.0);
+		}
+	}
+
+	#[test]
+	fn test_round_trip()
+	{
+		for mesh in [cube(2.0, 2), torus(2.0, 0.5, 12, 8)].iter()
+		{
+			for format in [StlFormat::Ascii, StlFormat::Binary].iter()
+			{
+				let mut data = Vec::new();
+				write_stl(&mut data, mesh, *format).unwrap();
+				let loaded = parse_stl("round_trip.stl", &data).unwrap();
+
+				// STL does not share vertices, so compare the triangle soups.
+				let expected = mesh.to_triangle_soup();
+				let actual = loaded.to_triangle_soup();
+				assert_eq!(expected.len(), actual.len(), "{:?}", format);
+				for (expected, actual) in expected.iter().zip(actual.iter())
+				{
+					assert_eq!(expected.position, actual.position, "{:?}", format);
+				}
+				assert_faces_point_along_normals(&loaded);
+			}
+		}
+	}
+
+	#[test]
+	fn test_binary_header_starting_with_solid()
+	{
+		let mut data = Vec::new();
+		write_stl(&mut data, &cube(1.0, 1), StlFormat::Binary).unwrap();
+		data[..6].copy_from_slice(b"solid ");
+		assert_eq!(12, parse_stl("solid.stl", &data).unwrap().triangle_count());
+	}
+
+	#[test]
+	fn test_load_fixture()
+	{
+		// Two facets facing +z in the file, one of them with a zeroed normal.
+		let mesh = load_stl(&fixture("triangle.stl")).unwrap();
+		assert_eq!(2, mesh.triangle_count());
+		assert!(mesh.vertices.iter().all(|v| (v.normal - Vector3::new(0.0, 0.0, -1.0)).magnitude() < EPSILON));
+		assert!(mesh.vertices.iter().any(|v| v.position == Vector3::new(1.0, 0.0, -3.0)));
+		assert_faces_point_along_normals(&mesh);
+	}
+
+	#[test]
+	fn test_parse_errors_report_lines()
+	{
+		assert_eq!("test.stl: line 2: unexpected 'vertex'", parse_error("solid a\nvertex 0 0 0\n"));
+		assert_eq!(
+			"test.stl: line 2: 'facet normal' expects 3 numbers, found '0 x 1'",
+			parse_error("solid a\nfacet normal 0 x 1\n")
+		);
+		assert_eq!(
+			"test.stl: line 6: facet needs 3 vertices, found 2",
+			parse_error("solid a\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\n")
+		);
+		assert_eq!("test.stl: end of file: missing 'endsolid'", parse_error("solid a\n"));
+		assert_eq!(
+			"test.stl: header: not an ASCII STL file, and the size does not match a binary STL file",
+			parse_error("hello")
+		);
+	}
+
+	#[test]
+	fn test_malformed_input_does_not_panic()
+	{
+		let inputs : [&[u8]; 5] = [b"", b"solid", b"solid\nfacet\n", b"solid\nendsolid\nsolid\n", &[0xff; 84]];
+		for input in inputs.iter()
+		{
+			let _ = parse_stl("fuzz.stl", input);
+		}
+	}
+
+	#[test]
+	fn test_missing_file()
+	{
+		match load_stl(&fixture("does_not_exist.stl"))
+		{
+			Err(StlError::Io {
+				..
+			}) => (),
+			_ => panic!("expected an io error"),
+		}
+	}
+}
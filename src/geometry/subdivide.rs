@@ -0,0 +1,341 @@
+// Subdivision of indexed triangle meshes.
+//
+// Both schemes split every triangle into four, through a new vertex on each
+// edge, and keep the winding of the source triangles. New vertices take their
+// attributes, like color and texture coordinates, from halfway along their
+// edge. Vertices that are split along an attribute seam get matching positions
+// on both sides, so the seam does not open.
+//
+// Only positions are changed. Meshes with normals need them recomputed, for
+// instance with `processing::smooth_normals`.
+use super::{
+	processing::position_ids,
+	Mesh,
+	Vertex,
+};
+use cgmath::*;
+use std::collections::HashMap;
+
+// Splits every triangle of `mesh`, placing the vertex on the edge between
+// vertices a and b, whose positions have ids ia and ib, at
+// `edge_position(ia, ib)`. `vertices` replace the original vertices.
+fn split<V, F>(mesh : &Mesh<V>, mut vertices : Vec<V>, ids : &[u32], edge_position : F) -> Mesh<V>
+where
+	V : Vertex,
+	F : Fn(u32, u32) -> Vector3<f32>,
+{
+	let mut edge_vertices = HashMap::<(u32, u32), u32>::new();
+	let mut edge_vertex = |a : u32, b : u32, vertices : &mut Vec<V>| {
+		let key = (a.min(b), a.max(b));
+		*edge_vertices.entry(key).or_insert_with(|| {
+			let (a, b) = key;
+			let position = edge_position(ids[a as usize], ids[b as usize]);
+			vertices.push(mesh.vertices[a as usize].lerp(&mesh.vertices[b as usize], 0.5).with_position(position));
+			(vertices.len() - 1) as u32
+		})
+	};
+
+	let mut indices = Vec::with_capacity(mesh.indices.len() * 4);
+	for [a, b, c] in mesh.triangles()
+	{
+		let ab = edge_vertex(a, b, &mut vertices);
+		let bc = edge_vertex(b, c, &mut vertices);
+		let ca = edge_vertex(c, a, &mut vertices);
+		indices.extend_from_slice(&[a, ab, ca, ab, b, bc, ca, bc, c, ab, bc, ca]);
+	}
+	Mesh::new(vertices, indices)
+}
+
+// Position of every position id.
+fn id_positions<V : Vertex>(mesh : &Mesh<V>, ids : &[u32]) -> Vec<Vector3<f32>>
+{
+	let mut positions = vec![Vector3::zero(); ids.iter().map(|&id| id as usize + 1).max().unwrap_or(0)];
+	for (vertex, &id) in mesh.vertices.iter().zip(ids)
+	{
+		positions[id as usize] = vertex.position();
+	}
+	positions
+}
+
+// One level of midpoint subdivision. The shape stays the same, only the
+// triangles get smaller.
+#[allow(dead_code)]
+pub fn midpoint<V : Vertex>(mesh : &Mesh<V>) -> Mesh<V>
+{
+	let ids = position_ids(mesh);
+	let positions = id_positions(mesh, &ids);
+	split(mesh, mesh.vertices.clone(), &ids, |a, b| (positions[a as usize] + positions[b as usize]) * 0.5)
+}
+
+// One level of midpoint subdivision with every vertex, old and new, projected
+// onto the sphere around `center`. Repeated on any closed convex mesh, like a
+// tetrahedron, this converges to the sphere.
+#[allow(dead_code)]
+pub fn midpoint_on_sphere<V : Vertex>(mesh : &Mesh<V>, center : Vector3<f32>, radius : f32) -> Mesh<V>
+{
+	let project = |p : Vector3<f32>| center + (p - center).normalize_to(radius);
+	let mut result = midpoint(mesh);
+	for vertex in result.vertices.iter_mut()
+	{
+		*vertex = vertex.with_position(project(vertex.position()));
+	}
+	result
+}
+
+// One level of Loop subdivision, which approaches a smooth surface when
+// repeated. Boundary edges, and edges shared by more than two triangles, are
+// kept as creases that are subdivided as curves.
+#[allow(dead_code)]
+pub fn loop_subdivide<V : Vertex>(mesh : &Mesh<V>) -> Mesh<V>
+{
+	let ids = position_ids(mesh);
+	let positions = id_positions(mesh, &ids);
+
+	// Vertices opposite every edge, and the neighbours of every vertex, by
+	// position id.
+	let mut opposites = HashMap::<(u32, u32), Vec<u32>>::new();
+	let mut neighbours = vec![Vec::new(); positions.len()];
+	for [a, b, c] in mesh.triangles()
+	{
+		let (a, b, c) = (ids[a as usize], ids[b as usize], ids[c as usize]);
+		for &(from, to, opposite) in &[(a, b, c), (b, c, a), (c, a, b)]
+		{
+			opposites.entry((from.min(to), from.max(to))).or_default().push(opposite);
+			neighbours[from as usize].push(to);
+			neighbours[to as usize].push(from);
+		}
+	}
+
+	let mut creases = vec![Vec::new(); positions.len()];
+	for (&(a, b), opposite) in &opposites
+	{
+		if opposite.len() != 2
+		{
+			creases[a as usize].push(b);
+			creases[b as usize].push(a);
+		}
+	}
+
+	let even : Vec<Vector3<f32>> = (0..positions.len())
+		.map(|id| {
+			let p = positions[id];
+			let mut ring = neighbours[id].clone();
+			ring.sort();
+			ring.dedup();
+			match creases[id].len()
+			{
+				0 if !ring.is_empty() =>
+				{
+					let n = ring.len() as f32;
+					let cos = (2.0 * std::f32::consts::PI / n).cos();
+					let beta = (5.0 / 8.0 - (3.0 / 8.0 + cos / 4.0).powi(2)) / n;
+					let sum = ring.iter().fold(Vector3::zero(), |sum, &n| sum + positions[n as usize]);
+					p * (1.0 - n * beta) + sum * beta
+				}
+				2 =>
+				{
+					let (b0, b1) = (positions[creases[id][0] as usize], positions[creases[id][1] as usize]);
+					p * 0.75 + (b0 + b1) * 0.125
+				}
+				// Isolated points and corners where creases meet stay put.
+				_ => p,
+			}
+		})
+		.collect();
+
+	let vertices =
+		mesh.vertices.iter().zip(&ids).map(|(vertex, &id)| vertex.with_position(even[id as usize])).collect();
+	split(mesh, vertices, &ids, |a, b| {
+		let (pa, pb) = (positions[a as usize], positions[b as usize]);
+		match opposites.get(&(a.min(b), a.max(b))).map(|o| o.as_slice())
+		{
+			Some(&[c, d]) => (pa + pb) * 0.375 + (positions[c as usize] + positions[d as usize]) * 0.125,
+			_ => (pa + pb) * 0.5,
+		}
+	})
+}
+
+#[cfg(test)]
+mod subdivide_tests
+{
+	use crate::geometry::{
+		primitives::plane,
+		processing::{
+			check_winding,
+			weld,
+		},
+		sample_colored_tetrahedron_vertices,
+		subdivide::{
+			loop_subdivide,
+			midpoint,
+			midpoint_on_sphere,
+		},
+		ColoredVertex,
+		Mesh,
+		Vertex,
+	};
+	use cgmath::*;
+	use std::collections::{
+		HashMap,
+		HashSet,
+	};
+
+	type Subdivision = fn(&Mesh<ColoredVertex>) -> Mesh<ColoredVertex>;
+
+	fn tetrahedron() -> Mesh<ColoredVertex>
+	{
+		let vertices = sample_colored_tetrahedron_vertices();
+		weld(&Mesh::new(vertices.to_vec(), (0..vertices.len() as u32).collect()), 1.0e-5)
+	}
+
+	fn edge_count<V : Vertex>(mesh : &Mesh<V>) -> usize
+	{
+		let mut edges = HashSet::new();
+		for [a, b, c] in mesh.triangles()
+		{
+			for &(from, to) in &[(a, b), (b, c), (c, a)]
+			{
+				edges.insert((from.min(to), from.max(to)));
+			}
+		}
+		edges.len()
+	}
+
+	// Vertex, edge and face counts.
+	fn counts<V : Vertex>(mesh : &Mesh<V>) -> (usize, usize, usize)
+	{
+		(mesh.vertices.len(), edge_count(mesh), mesh.triangle_count())
+	}
+
+	#[test]
+	fn test_counts_per_level()
+	{
+		// Around the centroid, which the projection needs to be inside the
+		// mesh.
+		let subdivisions : [Subdivision; 3] =
+			[midpoint, loop_subdivide, |mesh| midpoint_on_sphere(mesh, vec3(0.0, 1.0 / 6.0_f32.sqrt(), 0.0), 1.0)];
+		for subdivide in &subdivisions
+		{
+			let mut mesh = tetrahedron();
+			assert_eq!((4, 6, 4), counts(&mesh));
+			for level in 1..=4
+			{
+				mesh = subdivide(&mesh);
+				// Every edge splits in two and gains three more in each face.
+				let faces = 4 * 4usize.pow(level);
+				let edges = 6 * 4usize.pow(level);
+				assert_eq!((edges - faces + 2, edges, faces), counts(&mesh), "level {}", level);
+
+				let winding = check_winding(&mesh);
+				assert!(winding.is_consistent() && winding.is_closed(), "{:?}", winding);
+			}
+		}
+	}
+
+	#[test]
+	fn test_counts_with_boundary()
+	{
+		// A 2 by 2 grid: 9 vertices, 16 edges, 8 triangles.
+		let mut mesh = plane(1.0, 1.0, 2, 2);
+		assert_eq!((9, 16, 8), counts(&mesh));
+		mesh = loop_subdivide(&mesh);
+		assert_eq!((25, 56, 32), counts(&mesh));
+		mesh = loop_subdivide(&mesh);
+		assert_eq!((81, 208, 128), counts(&mesh));
+
+		// A flat mesh stays flat, and its boundary stays inside the outline.
+		for vertex in &mesh.vertices
+		{
+			assert!(vertex.position.y.abs() < 1.0e-6);
+			assert!(vertex.position.x.abs() <= 0.5 && vertex.position.z.abs() <= 0.5);
+		}
+		assert_eq!(32, check_winding(&mesh).boundary_edges);
+	}
+
+	#[test]
+	fn test_tetrahedron_to_sphere()
+	{
+		let center = vec3(0.0, 1.0, 0.0);
+		let mut mesh = tetrahedron();
+		for _ in 0..5
+		{
+			mesh = midpoint_on_sphere(&mesh, center, 2.0);
+		}
+
+		for vertex in &mesh.vertices
+		{
+			assert!(((vertex.position - center).magnitude() - 2.0).abs() < 1.0e-5);
+		}
+		// Small triangles, so the faces hug the sphere too.
+		for [a, b, c] in mesh.triangles()
+		{
+			let p = |n : u32| mesh.vertices[n as usize].position;
+			let centroid = (p(a) + p(b) + p(c)) / 3.0;
+			assert!((centroid - center).magnitude() > 1.95);
+		}
+	}
+
+	#[test]
+	fn test_loop_smooths()
+	{
+		// Largest angle between the normals of neighbouring triangles. It
+		// shrinks with every level as the corners of the tetrahedron round off.
+		let max_crease = |mesh : &Mesh<ColoredVertex>| {
+			let mut normals = HashMap::<(u32, u32), Vec<Vector3<f32>>>::new();
+			for [a, b, c] in mesh.triangles()
+			{
+				let p = |n : u32| mesh.vertices[n as usize].position;
+				let normal = (p(b) - p(a)).cross(p(c) - p(a)).normalize();
+				for &(from, to) in &[(a, b), (b, c), (c, a)]
+				{
+					normals.entry((from.min(to), from.max(to))).or_default().push(normal);
+				}
+			}
+			normals.values().map(|n| n[0].angle(n[1]).0).fold(0.0, f32::max)
+		};
+
+		let source = tetrahedron();
+		let mut mesh = source.clone();
+		let mut previous = max_crease(&mesh);
+		for _ in 0..4
+		{
+			mesh = loop_subdivide(&mesh);
+			let current = max_crease(&mesh);
+			assert!(current < previous, "{} >= {}", current, previous);
+			previous = current;
+		}
+
+		// Loop subdivision stays within the convex hull of the source.
+		for [a, b, c] in source.triangles()
+		{
+			let p = |n : u32| source.vertices[n as usize].position;
+			let normal = (p(b) - p(a)).cross(p(c) - p(a));
+			for vertex in &mesh.vertices
+			{
+				assert!((vertex.position - p(a)).dot(normal) < 1.0e-5);
+			}
+		}
+	}
+
+	#[test]
+	fn test_attributes_interpolated()
+	{
+		let mesh = tetrahedron();
+		let subdivided = midpoint(&mesh);
+
+		// The original vertices keep their colors, new ones average the ends
+		// of their edge.
+		assert_eq!(mesh.vertices[..], subdivided.vertices[..4]);
+		for [a, b, c] in mesh.triangles()
+		{
+			for &(from, to) in &[(a, b), (b, c), (c, a)]
+			{
+				let (from, to) = (mesh.vertices[from as usize], mesh.vertices[to as usize]);
+				let position = (from.position + to.position) * 0.5;
+				let found =
+					subdivided.vertices.iter().find(|v| (v.position() - position).magnitude() < 1.0e-6).unwrap();
+				assert!((found.color - (from.color + to.color) * 0.5).magnitude() < 1.0e-6);
+			}
+		}
+	}
+}
@@ -0,0 +1,540 @@
+// Reloading the scene file and the files it uses while the app runs.
+//
+// FileWatcher polls file modification times and sizes, so it needs no platform
+// notifications and behaves the same on every system. SceneReloader watches a
+// scene file and its mesh and animation files. When any of them changes it
+// reads the scene file again and diffs the result against the scene in use,
+// so the renderer only uploads meshes whose description or file changed and
+// only rebuilds the objects when they, their animations or the mesh list
+// changed. A reload that fails leaves the scene in use as it was.
+use crate::scene_file::{
+	self,
+	AnimationSource,
+	MeshSource,
+	SceneDescription,
+	SceneFileError,
+};
+use std::{
+	fs,
+	path::{
+		Path,
+		PathBuf,
+	},
+	time::SystemTime,
+};
+
+// What a file looked like when last polled. Sizes catch rewrites landing in
+// the same modification time tick.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct FileStamp
+{
+	modified : Option<SystemTime>,
+	len :      u64,
+}
+
+// None for files that do not exist, so creating them counts as a change.
+fn stamp(path : &Path) -> Option<FileStamp>
+{
+	fs::metadata(path).ok().map(|metadata| FileStamp {
+		modified : metadata.modified().ok(),
+		len :      metadata.len(),
+	})
+}
+
+#[derive(Debug, Default)]
+pub struct FileWatcher
+{
+	files : Vec<(PathBuf, Option<FileStamp>)>,
+}
+
+#[allow(dead_code)]
+impl FileWatcher
+{
+	pub fn new() -> Self
+	{
+		FileWatcher {
+			files : Vec::new(),
+		}
+	}
+
+	// Reports changes to `path` from now on.
+	pub fn watch(&mut self, path : &Path)
+	{
+		if !self.files.iter().any(|(file, _)| file == path)
+		{
+			self.files.push((path.to_path_buf(), stamp(path)));
+		}
+	}
+
+	// Watches exactly `paths`. Files already watched keep their last stamp, so
+	// changes made since the last poll are still reported.
+	pub fn set_files(&mut self, paths : &[PathBuf])
+	{
+		self.files.retain(|(file, _)| paths.contains(file));
+		for path in paths
+		{
+			self.watch(path);
+		}
+	}
+
+	pub fn files(&self) -> Vec<&Path>
+	{
+		self.files.iter().map(|(file, _)| file.as_path()).collect()
+	}
+
+	// Files created, modified or removed since the last poll.
+	pub fn poll(&mut self) -> Vec<PathBuf>
+	{
+		let mut changed = Vec::new();
+		for (file, last) in &mut self.files
+		{
+			let current = stamp(file);
+			if current != *last
+			{
+				*last = current;
+				changed.push(file.clone());
+			}
+		}
+		changed
+	}
+}
+
+// The mesh and animation files a scene uses.
+pub fn scene_files(description : &SceneDescription, directory : &Path) -> Vec<PathBuf>
+{
+	let meshes = description.meshes.iter().filter_map(|mesh| match &mesh.source
+	{
+		MeshSource::File(file) => Some(directory.join(file)),
+		_ => None,
+	});
+	let animations = description.animations.iter().filter_map(|animation| match &animation.source
+	{
+		AnimationSource::File(file) => Some(directory.join(file)),
+		_ => None,
+	});
+	meshes.chain(animations).collect()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MeshUpdate
+{
+	// Reuse the mesh at this index of the old description.
+	Keep(usize),
+	Load,
+}
+
+// What changed between two descriptions of a scene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneDiff
+{
+	// One entry per mesh of the new description, in order.
+	pub meshes :          Vec<MeshUpdate>,
+	// The objects must be built again. Also set when meshes were added,
+	// removed or reordered, as that changes the ids objects refer to them by.
+	pub rebuild_objects : bool,
+	pub camera :          bool,
+	pub clear_color :     bool,
+}
+
+#[allow(dead_code)]
+impl SceneDiff
+{
+	pub fn is_empty(&self) -> bool
+	{
+		self.meshes.iter().enumerate().all(|(index, update)| *update == MeshUpdate::Keep(index))
+			&& !self.rebuild_objects
+			&& !self.camera
+			&& !self.clear_color
+	}
+
+	pub fn loads_meshes(&self) -> bool
+	{
+		self.meshes.contains(&MeshUpdate::Load)
+	}
+}
+
+// Compares the description in use with a new one. `changed` lists files that
+// changed on disk since `old` was loaded, and forces the meshes and
+// animations read from them to be loaded again.
+pub fn diff_scenes(
+	old : &SceneDescription, new : &SceneDescription, directory : &Path, changed : &[PathBuf],
+) -> SceneDiff
+{
+	let file_changed = |file : &str| changed.contains(&directory.join(file));
+
+	let meshes = new
+		.meshes
+		.iter()
+		.map(|mesh| {
+			let unchanged = |(_, old_mesh) : &(usize, &scene_file::MeshDescription)| {
+				old_mesh.name == mesh.name
+					&& old_mesh.source == mesh.source
+					&& match &mesh.source
+					{
+						MeshSource::File(file) => !file_changed(file),
+						_ => true,
+					}
+			};
+			match old.meshes.iter().enumerate().find(unchanged)
+			{
+				Some((index, _)) => MeshUpdate::Keep(index),
+				None => MeshUpdate::Load,
+			}
+		})
+		.collect();
+
+	let mesh_names = |description : &SceneDescription| -> Vec<String> {
+		description.meshes.iter().map(|mesh| mesh.name.clone()).collect()
+	};
+	let animation_file_changed = new.animations.iter().any(|animation| match &animation.source
+	{
+		AnimationSource::File(file) => file_changed(file),
+		_ => false,
+	});
+
+	SceneDiff {
+		meshes :          meshes,
+		rebuild_objects : old.objects != new.objects
+			|| old.animations != new.animations
+			|| animation_file_changed
+			|| mesh_names(old) != mesh_names(new),
+		camera :          old.camera != new.camera,
+		clear_color :     old.clear_color != new.clear_color,
+	}
+}
+
+// A new description of the watched scene and what it changes.
+#[derive(Debug, Clone)]
+pub struct Reload
+{
+	pub description : SceneDescription,
+	pub diff :        SceneDiff,
+}
+
+// Watches a scene file and the files it uses.
+pub struct SceneReloader
+{
+	path :        PathBuf,
+	directory :   PathBuf,
+	description : SceneDescription,
+	watcher :     FileWatcher,
+	// Files changed since the last accepted reload. A reload that fails or is
+	// not accepted leaves them here, so the next one still picks them up.
+	changed :     Vec<PathBuf>,
+}
+
+#[allow(dead_code)]
+impl SceneReloader
+{
+	// `description` is the scene as loaded from `path`.
+	pub fn new(path : &Path, description : SceneDescription) -> Self
+	{
+		let directory = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+		let mut reloader = SceneReloader {
+			path :        path.to_path_buf(),
+			directory :   directory,
+			description : description,
+			watcher :     FileWatcher::new(),
+			changed :     Vec::new(),
+		};
+		reloader.watch(&[]);
+		reloader
+	}
+
+	// The directory the scene's files are relative to.
+	pub fn directory(&self) -> &Path
+	{
+		&self.directory
+	}
+
+	// The description in use.
+	pub fn description(&self) -> &SceneDescription
+	{
+		&self.description
+	}
+
+	pub fn watched_files(&self) -> Vec<&Path>
+	{
+		self.watcher.files()
+	}
+
+	fn watch(&mut self, extra : &[PathBuf])
+	{
+		let mut files = vec![self.path.clone()];
+		files.extend(scene_files(&self.description, &self.directory));
+		files.extend(extra.iter().cloned());
+		self.watcher.set_files(&files);
+	}
+
+	// Checks the files for changes, and reads the scene file again if there
+	// are any. Returns what to reload, or why the scene file could not be
+	// read. Either way the description in use stays until the reload is
+	// accepted.
+	pub fn poll(&mut self) -> Option<Result<Reload, SceneFileError>>
+	{
+		let changed = self.watcher.poll();
+		if changed.is_empty()
+		{
+			return None;
+		}
+		for path in changed
+		{
+			if !self.changed.contains(&path)
+			{
+				self.changed.push(path);
+			}
+		}
+
+		let description = match scene_file::load_scene(&self.path)
+		{
+			Ok(description) => description,
+			Err(error) => return Some(Err(error)),
+		};
+		// Until the reload is accepted, watch the files of both descriptions,
+		// so fixing a broken file the new one added is noticed.
+		self.watch(&scene_files(&description, &self.directory));
+
+		let diff = diff_scenes(&self.description, &description, &self.directory, &self.changed);
+		if diff.is_empty()
+		{
+			return None;
+		}
+		Some(Ok(Reload {
+			description : description,
+			diff :        diff,
+		}))
+	}
+
+	// Makes a reload the renderer applied the description in use.
+	pub fn accept(&mut self, reload : Reload)
+	{
+		self.description = reload.description;
+		self.changed.clear();
+		self.watch(&[]);
+	}
+}
+
+#[cfg(test)]
+mod hot_reload_tests
+{
+	use super::{
+		diff_scenes,
+		FileWatcher,
+		MeshUpdate,
+		SceneReloader,
+	};
+	use crate::scene_file::{
+		parse_scene,
+		MeshSource,
+		SceneDescription,
+		SceneFileError,
+	};
+	use cgmath::*;
+	use std::{
+		fs,
+		path::{
+			Path,
+			PathBuf,
+		},
+	};
+
+	// Fresh directory per test, so tests can run in parallel.
+	fn temp_dir(test : &str) -> PathBuf
+	{
+		let directory = std::env::temp_dir().join(format!("hot_reload_tests_{}_{}", std::process::id(), test));
+		fs::create_dir_all(&directory).unwrap();
+		directory
+	}
+
+	fn scene(meshes : &str, objects : &str) -> SceneDescription
+	{
+		let source = format!("Scene(version: 1, meshes: [{}], objects: [{}])", meshes, objects);
+		parse_scene("test.ron", &source).unwrap()
+	}
+
+	const MESHES : &str = "Mesh(name: \"a\", source: File(\"a.obj\")), Mesh(name: \"b\", source: Cube(size: 1))";
+	const OBJECTS : &str = "Object(name: \"x\", mesh: \"a\"), Object(name: \"y\", mesh: \"b\")";
+
+	#[test]
+	fn test_watcher_reports_changes()
+	{
+		let directory = temp_dir("watcher");
+		let file = directory.join("watched.txt");
+		let missing = directory.join("missing.txt");
+		fs::write(&file, "one").unwrap();
+
+		let mut watcher = FileWatcher::new();
+		watcher.watch(&file);
+		watcher.watch(&missing);
+		watcher.watch(&file);
+		assert_eq!(vec![file.as_path(), missing.as_path()], watcher.files());
+		assert!(watcher.poll().is_empty());
+
+		fs::write(&file, "three").unwrap();
+		assert_eq!(vec![file.clone()], watcher.poll());
+		assert!(watcher.poll().is_empty());
+
+		fs::write(&missing, "created").unwrap();
+		fs::remove_file(&file).unwrap();
+		assert_eq!(vec![file.clone(), missing.clone()], watcher.poll());
+
+		// Changes made before the watched files are replaced are kept.
+		fs::write(&missing, "changed again").unwrap();
+		watcher.set_files(std::slice::from_ref(&missing));
+		assert_eq!(vec![missing.as_path()], watcher.files());
+		assert_eq!(vec![missing], watcher.poll());
+
+		fs::remove_dir_all(&directory).unwrap();
+	}
+
+	#[test]
+	fn test_diff_scenes()
+	{
+		let directory = Path::new("scenes");
+		let old = scene(MESHES, OBJECTS);
+		assert!(diff_scenes(&old, &old, directory, &[]).is_empty());
+
+		// A changed mesh file reloads that mesh alone.
+		let diff = diff_scenes(&old, &old, directory, &[directory.join("a.obj")]);
+		assert_eq!(vec![MeshUpdate::Load, MeshUpdate::Keep(1)], diff.meshes);
+		assert!(!diff.rebuild_objects && !diff.camera && !diff.clear_color);
+		assert!(diff.loads_meshes());
+
+		let mut new = old.clone();
+		new.meshes[1].source = MeshSource::Cube {
+			size :         2.0,
+			subdivisions : 0,
+		};
+		let diff = diff_scenes(&old, &new, directory, &[]);
+		assert_eq!(vec![MeshUpdate::Keep(0), MeshUpdate::Load], diff.meshes);
+		assert!(!diff.rebuild_objects);
+
+		let mut new = old.clone();
+		new.clear_color = Vector4::new(1.0, 0.0, 0.0, 1.0);
+		new.camera.fovy = Deg(60.0);
+		let diff = diff_scenes(&old, &new, directory, &[]);
+		assert!(diff.clear_color && diff.camera && !diff.rebuild_objects && !diff.loads_meshes());
+
+		let mut new = old.clone();
+		new.objects[1].transform.translation.y = 1.0;
+		let diff = diff_scenes(&old, &new, directory, &[]);
+		assert!(diff.rebuild_objects && !diff.loads_meshes());
+
+		// Reordered meshes are kept, but change the ids objects use.
+		let new =
+			scene("Mesh(name: \"b\", source: Cube(size: 1)), Mesh(name: \"a\", source: File(\"a.obj\"))", OBJECTS);
+		let diff = diff_scenes(&old, &new, directory, &[]);
+		assert_eq!(vec![MeshUpdate::Keep(1), MeshUpdate::Keep(0)], diff.meshes);
+		assert!(diff.rebuild_objects);
+	}
+
+	#[test]
+	fn test_reloader()
+	{
+		let directory = temp_dir("reloader");
+		let scene_path = directory.join("scene.ron");
+		let mesh_path = directory.join("a.obj");
+		let mesh =
+			fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/meshes/tetrahedron.obj")).unwrap();
+		fs::write(&mesh_path, &mesh).unwrap();
+		let source = format!("Scene(version: 1, meshes: [{}], objects: [{}])", MESHES, OBJECTS);
+		fs::write(&scene_path, &source).unwrap();
+
+		let description = parse_scene("scene.ron", &source).unwrap();
+		let mut reloader = SceneReloader::new(&scene_path, description.clone());
+		assert_eq!(vec![scene_path.as_path(), mesh_path.as_path()], reloader.watched_files());
+		assert!(reloader.poll().is_none());
+
+		// Editing the mesh file reloads just that mesh.
+		fs::write(&mesh_path, format!("# edited\n{}", mesh)).unwrap();
+		let reload = reloader.poll().unwrap().unwrap();
+		assert_eq!(vec![MeshUpdate::Load, MeshUpdate::Keep(1)], reload.diff.meshes);
+		assert!(!reload.diff.rebuild_objects);
+		reloader.accept(reload);
+		assert!(reloader.poll().is_none());
+
+		// A broken scene file keeps the description in use.
+		fs::write(&scene_path, "Scene(version: 1, meshes: [").unwrap();
+		match reloader.poll()
+		{
+			Some(Err(SceneFileError::Parse {
+				..
+			})) => (),
+			other => panic!("expected a parse error, got {:?}", other),
+		}
+		assert_eq!(&description, reloader.description());
+
+		// So does a primitive the generators can't build, before it gets to them.
+		for primitive in ["Cube(size: 0)", "Cube(size: 1, subdivisions: 0)", "Tetrahedron(radius: 0)"].iter()
+		{
+			fs::write(&scene_path, source.replace("Cube(size: 1)", primitive)).unwrap();
+			match reloader.poll()
+			{
+				Some(Err(SceneFileError::Parse {
+					..
+				})) => (),
+				other => panic!("expected a parse error for {}, got {:?}", primitive, other),
+			}
+			assert_eq!(&description, reloader.description());
+		}
+
+		// Fixing it diffs against the description in use, so the unchanged
+		// meshes are kept. Formatting changes alone are not reloads.
+		fs::write(&scene_path, source.replace("objects:", "clear_color: (1, 1, 1, 1), objects:")).unwrap();
+		let reload = reloader.poll().unwrap().unwrap();
+		assert_eq!(vec![MeshUpdate::Keep(0), MeshUpdate::Keep(1)], reload.diff.meshes);
+		assert!(reload.diff.clear_color && !reload.diff.rebuild_objects);
+		reloader.accept(reload);
+		assert_eq!(Vector4::new(1.0, 1.0, 1.0, 1.0), reloader.description().clear_color);
+		fs::write(&scene_path, source.replace("objects:", "clear_color: (1, 1, 1, 1),\n\n objects:")).unwrap();
+		assert!(reloader.poll().is_none());
+
+		// Meshes the scene starts using are watched once it is accepted.
+		let cube_path = directory.join("cube.obj");
+		fs::write(&cube_path, &mesh).unwrap();
+		fs::write(&scene_path, source.replace("Cube(size: 1)", "File(\"cube.obj\")")).unwrap();
+		let reload = reloader.poll().unwrap().unwrap();
+		assert_eq!(vec![MeshUpdate::Keep(0), MeshUpdate::Load], reload.diff.meshes);
+		reloader.accept(reload);
+		assert!(reloader.watched_files().contains(&cube_path.as_path()));
+
+		fs::remove_dir_all(&directory).unwrap();
+	}
+
+	#[test]
+	fn test_reloader_keeps_changes_until_accepted()
+	{
+		let directory = temp_dir("pending");
+		let scene_path = directory.join("scene.ron");
+		let mesh_path = directory.join("a.obj");
+		let mesh =
+			fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/meshes/tetrahedron.obj")).unwrap();
+		fs::write(&mesh_path, &mesh).unwrap();
+		let source = format!("Scene(version: 1, meshes: [{}], objects: [{}])", MESHES, OBJECTS);
+		fs::write(&scene_path, &source).unwrap();
+		let mut reloader = SceneReloader::new(&scene_path, parse_scene("scene.ron", &source).unwrap());
+
+		// The mesh changes in the same poll as a broken scene edit.
+		fs::write(&mesh_path, format!("# edited\n{}", mesh)).unwrap();
+		fs::write(&scene_path, "Scene(version: 1, meshes: [").unwrap();
+		assert!(reloader.poll().unwrap().is_err());
+
+		// Fixing the scene still reloads the mesh.
+		fs::write(&scene_path, source.replace("objects:", "clear_color: (1, 1, 1, 1), objects:")).unwrap();
+		let reload = reloader.poll().unwrap().unwrap();
+		assert_eq!(vec![MeshUpdate::Load, MeshUpdate::Keep(1)], reload.diff.meshes);
+
+		// So does the next change after a reload the renderer failed to apply.
+		fs::write(&scene_path, &source).unwrap();
+		let reload = reloader.poll().unwrap().unwrap();
+		assert_eq!(vec![MeshUpdate::Load, MeshUpdate::Keep(1)], reload.diff.meshes);
+
+		// Accepting it settles the mesh.
+		reloader.accept(reload);
+		fs::write(&scene_path, source.replace("objects:", "clear_color: (1, 0, 0, 1), objects:")).unwrap();
+		let reload = reloader.poll().unwrap().unwrap();
+		assert_eq!(vec![MeshUpdate::Keep(0), MeshUpdate::Keep(1)], reload.diff.meshes);
+
+		fs::remove_dir_all(&directory).unwrap();
+	}
+}
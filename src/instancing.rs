@@ -0,0 +1,220 @@
+// Per-instance data for drawing many copies of one mesh in a single call.
+//
+// InstanceData is the second vertex stream of an instanced draw, stepped once
+// per instance instead of once per vertex. Building it is kept apart from the
+// renderer, so layouts like the field of tetrahedra can be tested without a
+// device.
+use crate::transform::Transform;
+use cgmath::*;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InstanceData
+{
+	// Model to world transform of the instance.
+	pub transform : Matrix4<f32>,
+	// Multiplies the vertex colors.
+	pub tint :      Vector4<f32>,
+}
+
+crate::impl_vertex_layout!(InstanceData {
+	transform => "TRANSFORM",
+	tint => "COLOR" 1,
+});
+
+#[allow(dead_code)]
+impl InstanceData
+{
+	pub fn new(transform : &Transform, tint : Vector4<f32>) -> Self
+	{
+		InstanceData {
+			transform : transform.matrix(),
+			tint :      tint,
+		}
+	}
+}
+
+// A grid of instances on the xz plane, centered on `center`, each turned,
+// scaled and tinted at random. The same seed always gives the same field.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Field
+{
+	pub columns :   usize,
+	pub rows :      usize,
+	// Distance between neighbouring instances.
+	pub spacing :   f32,
+	pub center :    Vector3<f32>,
+	pub min_scale : f32,
+	pub max_scale : f32,
+	pub seed :      u32,
+}
+
+impl Default for Field
+{
+	fn default() -> Self
+	{
+		Field {
+			columns :   100,
+			rows :      100,
+			spacing :   1.5,
+			center :    Vector3::new(0.0, -1.5, 0.0),
+			min_scale : 0.2,
+			max_scale : 0.5,
+			seed :      1,
+		}
+	}
+}
+
+// Integer hash with good avalanche, so neighbouring instances look unrelated.
+fn hash(mut x : u32) -> u32
+{
+	x ^= x >> 16;
+	x = x.wrapping_mul(0x7feb_352d);
+	x ^= x >> 15;
+	x = x.wrapping_mul(0x846c_a68b);
+	x ^= x >> 16;
+	x
+}
+
+// Random number in [0, 1) for the `n`th random choice of an instance.
+fn random(seed : u32, instance : usize, n : u32) -> f32
+{
+	let h = hash(seed ^ hash(instance as u32 ^ hash(n)));
+	(h >> 8) as f32 / (1 << 24) as f32
+}
+
+// Instance data for every instance of the field, row by row.
+pub fn build_field(field : &Field) -> Vec<InstanceData>
+{
+	let width = (field.columns as f32 - 1.0) * field.spacing;
+	let depth = (field.rows as f32 - 1.0) * field.spacing;
+	let corner = field.center - Vector3::new(width * 0.5, 0.0, depth * 0.5);
+
+	let mut instances = Vec::with_capacity(field.columns * field.rows);
+	for row in 0..field.rows
+	{
+		for column in 0..field.columns
+		{
+			let instance = instances.len();
+			let r = |n| random(field.seed, instance, n);
+			let scale = field.min_scale + (field.max_scale - field.min_scale) * r(0);
+			let transform = Transform::new(
+				corner + Vector3::new(column as f32 * field.spacing, 0.0, row as f32 * field.spacing),
+				Quaternion::from_angle_y(Deg(360.0 * r(1))),
+				Vector3::new(scale, scale, scale),
+			);
+			let tint = Vector4::new(0.5 + 0.5 * r(2), 0.5 + 0.5 * r(3), 0.5 + 0.5 * r(4), 1.0);
+			instances.push(InstanceData::new(&transform, tint));
+		}
+	}
+	instances
+}
+
+#[cfg(test)]
+mod instancing_tests
+{
+	use super::{
+		build_field,
+		Field,
+		InstanceData,
+	};
+	use crate::{
+		transform::Transform,
+		vertex_layout::{
+			VertexFormat,
+			VertexLayout,
+		},
+	};
+	use cgmath::*;
+
+	fn test_field() -> Field
+	{
+		Field {
+			columns :   4,
+			rows :      3,
+			spacing :   2.0,
+			center :    Vector3::new(1.0, -1.0, 5.0),
+			min_scale : 0.25,
+			max_scale : 0.75,
+			seed :      7,
+		}
+	}
+
+	fn position(instance : &InstanceData) -> Vector3<f32>
+	{
+		instance.transform.w.truncate()
+	}
+
+	#[test]
+	fn test_instance_layout()
+	{
+		let elements = InstanceData::elements();
+		let semantics : Vec<(&str, u32, usize)> =
+			elements.iter().map(|e| (e.semantic_name, e.semantic_index, e.offset)).collect();
+		assert_eq!(
+			vec![
+				("TRANSFORM", 0, 0),
+				("TRANSFORM", 1, 16),
+				("TRANSFORM", 2, 32),
+				("TRANSFORM", 3, 48),
+				("COLOR", 1, 64)
+			],
+			semantics
+		);
+		assert!(elements.iter().all(|e| e.format == VertexFormat::Float4));
+		assert_eq!(80, InstanceData::stride());
+	}
+
+	#[test]
+	fn test_instance_from_transform()
+	{
+		let transform = Transform::new(
+			Vector3::new(1.0, 2.0, 3.0),
+			Quaternion::from_angle_x(Deg(30.0)),
+			Vector3::new(2.0, 2.0, 2.0),
+		);
+		let tint = Vector4::new(1.0, 0.5, 0.25, 1.0);
+		let instance = InstanceData::new(&transform, tint);
+		assert_eq!(transform.matrix(), instance.transform);
+		assert_eq!(tint, instance.tint);
+	}
+
+	#[test]
+	fn test_field_grid()
+	{
+		let field = test_field();
+		let instances = build_field(&field);
+		assert_eq!(12, instances.len());
+
+		let mean = instances.iter().map(position).fold(Vector3::zero(), |sum, p| sum + p) / instances.len() as f32;
+		assert!((mean - field.center).magnitude() < 1e-5);
+
+		// Row by row, `spacing` apart.
+		assert!((position(&instances[1]) - position(&instances[0]) - Vector3::new(2.0, 0.0, 0.0)).magnitude() < 1e-5);
+		assert!((position(&instances[4]) - position(&instances[0]) - Vector3::new(0.0, 0.0, 2.0)).magnitude() < 1e-5);
+		assert!(instances.iter().all(|instance| (position(instance).y - field.center.y).abs() < 1e-5));
+	}
+
+	#[test]
+	fn test_field_randomness()
+	{
+		let field = test_field();
+		let instances = build_field(&field);
+		for instance in &instances
+		{
+			let scale = instance.transform.y.truncate().magnitude();
+			assert!(scale >= field.min_scale - 1e-5 && scale <= field.max_scale + 1e-5, "scale {}", scale);
+			let tint = instance.tint;
+			assert!([tint.x, tint.y, tint.z].iter().all(|c| (0.5..=1.0).contains(c)));
+			assert_eq!(1.0, instance.tint.w);
+		}
+		assert!(instances.windows(2).all(|pair| pair[0].tint != pair[1].tint));
+
+		assert_eq!(instances, build_field(&field));
+		let reseeded = build_field(&Field {
+			seed : 8,
+			..field
+		});
+		assert_ne!(instances, reseeded);
+	}
+}
@@ -1,8 +1,19 @@
 // Declare Modules
+mod animation;
+mod camera;
+mod camera_path;
+mod constant_buffer;
 pub mod dx_descriptor_handles;
+mod dx_input_layout;
 mod dx_renderer;
 mod geometry;
+mod hot_reload;
+mod instancing;
+mod scene;
+mod scene_file;
+mod transform;
 mod transforms;
+mod vertex_layout;
 pub mod weak_ptr;
 mod win_platform;
 mod win_utilities;
@@ -14,12 +25,63 @@ use std::{
 	thread,
 };
 
+// Adds a small tetrahedron above the picked object, or at the top level.
+fn add_tetrahedron(renderer : &mut dx_renderer::Renderer)
+{
+	let mesh = match renderer.find_mesh("tetrahedron")
+	{
+		Some(mesh) => mesh,
+		None => return,
+	};
+	let parent = renderer.picked_object();
+	let name = format!("tetrahedron {}", renderer.scene().len());
+	let transform = transform::Transform {
+		translation : cgmath::Vector3::new(0.0, 1.0, 0.0),
+		scale : cgmath::Vector3::new(0.5, 0.5, 0.5),
+		..transform::Transform::identity()
+	};
+	let object = scene::SceneObject::new(&name, mesh, scene::Material::default());
+	renderer.scene_mut().add(object, transform, parent).unwrap();
+}
+
+// How often to check the scene, its files and the camera path for changes.
+const RELOAD_INTERVAL : std::time::Duration = std::time::Duration::from_millis(250);
+
+// Applies changes to the scene file, the mesh and animation files it uses and
+// the camera path file. Anything that fails to load is reported and the
+// previous version stays in use.
+fn reload_changed_files(
+	renderer : &mut dx_renderer::Renderer, scene_reloader : &mut hot_reload::SceneReloader,
+	camera_path_watcher : &mut hot_reload::FileWatcher,
+)
+{
+	match scene_reloader.poll()
+	{
+		Some(Ok(reload)) => match renderer.reload_scene(&reload.description, &reload.diff, scene_reloader.directory())
+		{
+			Ok(()) => scene_reloader.accept(reload),
+			Err(error) => println!("Failed to reload scene: {}", error),
+		},
+		Some(Err(error)) => println!("Failed to reload scene: {}", error),
+		None => (),
+	}
+
+	for file in camera_path_watcher.poll()
+	{
+		match camera_path::load_camera_path(&file)
+		{
+			Ok(path) => renderer.set_camera_path(path),
+			Err(error) => println!("Failed to reload camera path: {}", error),
+		}
+	}
+}
+
 // Main Function
 fn main()
 {
 	let (window_sender, window_reciever) = mpsc::channel::<win_window::Window>();
 	let (exit_sender, _exit_receiver) = mpsc::channel::<win_platform::ExitResult>();
-	let (input_sender, _input_receiver) = mpsc::channel::<u32>();
+	let (input_sender, input_receiver) = mpsc::channel::<win_platform::InputEvent>();
 
 	let windows_thread = thread::Builder::new()
 		.name("win_platform_thread".to_string())
@@ -30,14 +92,77 @@ fn main()
 
 	let mut renderer = dx_renderer::Renderer::new();
 	renderer.load_pipeline(window);
+
+	// Depth precision options, fixed for the run since they are part of the
+	// pipeline state.
+	let has_flag = |flag : &str| std::env::args().any(|arg| arg == flag);
+	let depth = if has_flag("--reverse-z")
+	{
+		camera::DepthMode::Reversed
+	}
+	else
+	{
+		camera::DepthMode::Standard
+	};
+	renderer.set_depth_mode(depth, has_flag("--infinite-far"));
 	renderer.load_assets();
 
+	// The scene to show, described in a scene file.
+	let mut args = std::env::args().skip_while(|arg| arg != "--scene").skip(1);
+	let scene_path = match args.next()
+	{
+		Some(file) => std::path::PathBuf::from(file),
+		None => std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes").join("tetrahedron.ron"),
+	};
+	let directory = scene_path.parent().unwrap_or_else(|| std::path::Path::new(""));
+	let description = match scene_file::load_scene(&scene_path)
+		.and_then(|scene| renderer.load_scene(&scene, directory).map(|()| scene))
+	{
+		Ok(scene) => scene,
+		Err(error) =>
+		{
+			println!("Failed to load scene: {}", error);
+			scene_file::SceneDescription::default()
+		}
+	};
+	// Edits to the scene and its files show up while running.
+	let mut scene_reloader = hot_reload::SceneReloader::new(&scene_path, description);
+
+	// The field of tetrahedra: thousands of copies drawn in one instanced draw.
+	if has_flag("--field")
+	{
+		match renderer.find_mesh("tetrahedron")
+		{
+			Some(tetrahedron) =>
+			{
+				renderer.set_instances(tetrahedron, &instancing::build_field(&instancing::Field::default()))
+			}
+			None => println!("The scene has no tetrahedron mesh to fill the field with."),
+		}
+	}
+
+	// An optional camera flythrough, previewed as debug lines and followed with
+	// 'C'.
+	let mut camera_path_watcher = hot_reload::FileWatcher::new();
+	let mut args = std::env::args().skip_while(|arg| arg != "--camera-path").skip(1);
+	if let Some(file) = args.next()
+	{
+		let file = std::path::Path::new(&file);
+		match camera_path::load_camera_path(file)
+		{
+			Ok(path) => renderer.set_camera_path(path),
+			Err(error) => println!("Failed to load camera path: {}", error),
+		}
+		camera_path_watcher.watch(file);
+	}
+
 	use std::time::Instant;
 
 	// timing measures for Frames-Per-Second
 	let now = Instant::now();
 	let mut second_fence = 0;
 	let mut count = 0;
+	let mut last_reload_check = Instant::now();
 
 	loop
 	{
@@ -48,11 +173,63 @@ fn main()
 			{
 				second_fence = current_seconds;
 				let fps = count / current_seconds;
-				println!("FPS: {:?}", fps);
+				let stats = renderer.frame_stats();
+				println!("FPS: {:?} visible: {} culled: {}", fps, stats.culling.visible, stats.culling.culled);
+				if stats.over_limit > 0
+				{
+					println!("{} visible objects were not drawn, past the limit of drawn objects", stats.over_limit);
+				}
 			}
 			count += 1;
 		}
 
+		for event in input_receiver.try_iter()
+		{
+			match event
+			{
+				win_platform::InputEvent::LeftButtonDown {
+					x,
+					y,
+				} => renderer.pick(x, y),
+				win_platform::InputEvent::KeyDown {
+					virtual_key,
+				} if virtual_key == 'P' as u32 => renderer.toggle_projection(),
+				win_platform::InputEvent::KeyDown {
+					virtual_key,
+				} if virtual_key == 'C' as u32 => renderer.toggle_camera_path(),
+				win_platform::InputEvent::KeyDown {
+					virtual_key,
+				} if virtual_key == 'A' as u32 => add_tetrahedron(&mut renderer),
+				win_platform::InputEvent::KeyDown {
+					virtual_key,
+				} if virtual_key == 'X' as u32 =>
+				{
+					if let Some(object) = renderer.picked_object()
+					{
+						renderer.remove_object(object).unwrap();
+					}
+				}
+				win_platform::InputEvent::KeyDown {
+					virtual_key,
+				} if virtual_key == 'R' as u32 =>
+				{
+					if let Some(object) = renderer.picked_object()
+					{
+						renderer.scene_mut().reparent(object, None).unwrap();
+					}
+				}
+				win_platform::InputEvent::KeyDown {
+					..
+				} => (),
+			}
+		}
+
+		if last_reload_check.elapsed() >= RELOAD_INTERVAL
+		{
+			last_reload_check = Instant::now();
+			reload_changed_files(&mut renderer, &mut scene_reloader, &mut camera_path_watcher);
+		}
+
 		renderer.update();
 		let result = renderer.render();
 
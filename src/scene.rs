@@ -0,0 +1,479 @@
+// Objects to draw, each a mesh, a material and a transform.
+//
+// Objects sit in a TransformHierarchy, so children follow their parents, and
+// can be added, removed and reparented while the scene is being drawn. An
+// ObjectId is the object's node in the hierarchy, and like those it is handed
+// out again once the object is removed. Meshes are referenced by MeshId, an
+// index into whatever list the renderer keeps them in.
+use crate::{
+	animation::AnimationClip,
+	transform::{
+		Transform,
+		TransformHierarchy,
+	},
+};
+use cgmath::*;
+use std::{
+	error::Error,
+	fmt,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MeshId(pub usize);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId(usize);
+
+impl ObjectId
+{
+	pub fn index(self) -> usize
+	{
+		self.0
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Material
+{
+	// Multiplies the vertex colors.
+	pub color : Vector4<f32>,
+}
+
+impl Default for Material
+{
+	fn default() -> Self
+	{
+		Material {
+			color : Vector4::new(1.0, 1.0, 1.0, 1.0),
+		}
+	}
+}
+
+#[allow(dead_code)]
+impl Material
+{
+	pub fn new(color : Vector4<f32>) -> Self
+	{
+		Material {
+			color : color,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneObject
+{
+	pub name :      String,
+	pub mesh :      MeshId,
+	pub material :  Material,
+	// Played on top of the object's transform. A color track replaces the
+	// material color.
+	pub animation : Option<AnimationClip>,
+}
+
+#[allow(dead_code)]
+impl SceneObject
+{
+	pub fn new(name : &str, mesh : MeshId, material : Material) -> Self
+	{
+		SceneObject {
+			name :      name.to_string(),
+			mesh :      mesh,
+			material :  material,
+			animation : None,
+		}
+	}
+}
+
+// One object as it should be drawn this frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Draw
+{
+	pub object : ObjectId,
+	pub mesh :   MeshId,
+	pub world :  Matrix4<f32>,
+	pub color :  Vector4<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneError
+{
+	UnknownObject
+	{
+		object : ObjectId,
+	},
+	// The new parent is the object itself or below it.
+	Cycle
+	{
+		object : ObjectId,
+		parent : ObjectId,
+	},
+}
+
+impl fmt::Display for SceneError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			SceneError::UnknownObject {
+				object,
+			} => write!(f, "there is no object {}", object.0),
+			SceneError::Cycle {
+				object,
+				parent,
+			} => write!(f, "object {} cannot be moved under object {}, which is below it", object.0, parent.0),
+		}
+	}
+}
+
+impl Error for SceneError {}
+
+#[derive(Debug, Clone)]
+struct Slot
+{
+	object :    SceneObject,
+	// The transform without animation. The hierarchy holds the animated one.
+	transform : Transform,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Scene
+{
+	hierarchy : TransformHierarchy,
+	slots :     Vec<Option<Slot>>,
+}
+
+#[allow(dead_code)]
+impl Scene
+{
+	pub fn new() -> Self
+	{
+		Scene {
+			hierarchy : TransformHierarchy::new(),
+			slots :     Vec::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize
+	{
+		self.hierarchy.len()
+	}
+
+	pub fn is_empty(&self) -> bool
+	{
+		self.hierarchy.is_empty()
+	}
+
+	pub fn contains(&self, object : ObjectId) -> bool
+	{
+		self.hierarchy.contains(object.0)
+	}
+
+	fn check(&self, object : ObjectId) -> Result<(), SceneError>
+	{
+		if self.contains(object)
+		{
+			Ok(())
+		}
+		else
+		{
+			Err(SceneError::UnknownObject {
+				object : object,
+			})
+		}
+	}
+
+	fn slot(&self, object : ObjectId) -> Result<&Slot, SceneError>
+	{
+		self.check(object)?;
+		Ok(self.slots[object.0].as_ref().unwrap())
+	}
+
+	fn slot_mut(&mut self, object : ObjectId) -> Result<&mut Slot, SceneError>
+	{
+		self.check(object)?;
+		Ok(self.slots[object.0].as_mut().unwrap())
+	}
+
+	// Adds an object under `parent`, or at the top level.
+	pub fn add(
+		&mut self, object : SceneObject, transform : Transform, parent : Option<ObjectId>,
+	) -> Result<ObjectId, SceneError>
+	{
+		if let Some(parent) = parent
+		{
+			self.check(parent)?;
+		}
+		let node = self.hierarchy.add(transform, parent.map(|p| p.0));
+		if node >= self.slots.len()
+		{
+			self.slots.resize_with(node + 1, || None);
+		}
+		self.slots[node] = Some(Slot {
+			object :    object,
+			transform : transform,
+		});
+		Ok(ObjectId(node))
+	}
+
+	// Removes an object along with its children, and returns all the removed
+	// objects.
+	pub fn remove(&mut self, object : ObjectId) -> Result<Vec<ObjectId>, SceneError>
+	{
+		self.check(object)?;
+		let removed = self.hierarchy.remove(object.0);
+		for &node in &removed
+		{
+			self.slots[node] = None;
+		}
+		Ok(removed.into_iter().map(ObjectId).collect())
+	}
+
+	// Moves an object under `parent`, or to the top level, keeping its local
+	// transform.
+	pub fn reparent(&mut self, object : ObjectId, parent : Option<ObjectId>) -> Result<(), SceneError>
+	{
+		self.check(object)?;
+		if let Some(parent) = parent
+		{
+			self.check(parent)?;
+			if self.hierarchy.is_ancestor(object.0, parent.0)
+			{
+				return Err(SceneError::Cycle {
+					object : object,
+					parent : parent,
+				});
+			}
+		}
+		self.hierarchy.reparent(object.0, parent.map(|p| p.0));
+		Ok(())
+	}
+
+	pub fn parent(&self, object : ObjectId) -> Result<Option<ObjectId>, SceneError>
+	{
+		self.check(object)?;
+		Ok(self.hierarchy.parent(object.0).map(ObjectId))
+	}
+
+	pub fn children(&self, object : ObjectId) -> Result<Vec<ObjectId>, SceneError>
+	{
+		self.check(object)?;
+		Ok(self.hierarchy.children(object.0).iter().cloned().map(ObjectId).collect())
+	}
+
+	// Every object, parents before their children.
+	pub fn objects(&self) -> Vec<ObjectId>
+	{
+		self.hierarchy.nodes().into_iter().map(ObjectId).collect()
+	}
+
+	pub fn object(&self, object : ObjectId) -> Result<&SceneObject, SceneError>
+	{
+		self.slot(object).map(|slot| &slot.object)
+	}
+
+	pub fn object_mut(&mut self, object : ObjectId) -> Result<&mut SceneObject, SceneError>
+	{
+		self.slot_mut(object).map(|slot| &mut slot.object)
+	}
+
+	pub fn find(&self, name : &str) -> Option<ObjectId>
+	{
+		self.objects().into_iter().find(|&object| self.slots[object.0].as_ref().unwrap().object.name == name)
+	}
+
+	pub fn transform(&self, object : ObjectId) -> Result<&Transform, SceneError>
+	{
+		self.slot(object).map(|slot| &slot.transform)
+	}
+
+	pub fn set_transform(&mut self, object : ObjectId, transform : Transform) -> Result<(), SceneError>
+	{
+		self.slot_mut(object)?.transform = transform;
+		self.hierarchy.set_local(object.0, transform);
+		Ok(())
+	}
+
+	// World matrix as of the last `animate`.
+	pub fn world_matrix(&mut self, object : ObjectId) -> Result<Matrix4<f32>, SceneError>
+	{
+		self.check(object)?;
+		Ok(self.hierarchy.world_matrix(object.0))
+	}
+
+	// Poses the animated objects at `time`. Objects without an animation keep
+	// their cached world matrices.
+	pub fn animate(&mut self, time : f32)
+	{
+		for node in 0..self.slots.len()
+		{
+			if let Some(Slot {
+				object: SceneObject {
+					animation: Some(clip),
+					..
+				},
+				transform,
+			}) = &self.slots[node]
+			{
+				let local = clip.sample_transform(time, transform);
+				self.hierarchy.set_local(node, local);
+			}
+		}
+	}
+
+	// Animates the scene to `time` and lists every object to draw, parents
+	// before their children.
+	pub fn draws(&mut self, time : f32) -> Vec<Draw>
+	{
+		self.animate(time);
+		let objects = self.objects();
+		objects
+			.into_iter()
+			.map(|object| {
+				let world = self.hierarchy.world_matrix(object.0);
+				let SceneObject {
+					mesh,
+					material,
+					animation,
+					..
+				} = &self.slots[object.0].as_ref().unwrap().object;
+				Draw {
+					object : object,
+					mesh :   *mesh,
+					world :  world,
+					color :  animation.as_ref().and_then(|clip| clip.sample_color(time)).unwrap_or(material.color),
+				}
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod scene_tests
+{
+	use super::{
+		Material,
+		MeshId,
+		Scene,
+		SceneError,
+		SceneObject,
+	};
+	use crate::{
+		animation::AnimationClip,
+		transform::Transform,
+	};
+	use cgmath::*;
+
+	fn test_object(name : &str) -> SceneObject
+	{
+		SceneObject::new(name, MeshId(0), Material::default())
+	}
+
+	fn assert_matrix_near(expected : Matrix4<f32>, actual : Matrix4<f32>)
+	{
+		let expected : &[f32; 16] = expected.as_ref();
+		let actual : &[f32; 16] = actual.as_ref();
+		for (e, a) in expected.iter().zip(actual.iter())
+		{
+			assert!((e - a).abs() < 1e-5, "expected {:?}, got {:?}", expected, actual);
+		}
+	}
+
+	#[test]
+	fn test_add_and_draw()
+	{
+		let mut scene = Scene::new();
+		let red = Material::new(Vector4::new(1.0, 0.0, 0.0, 1.0));
+		let root = scene.add(test_object("root"), Transform::from_translation(Vector3::unit_x()), None).unwrap();
+		let child = scene
+			.add(SceneObject::new("child", MeshId(1), red), Transform::from_translation(Vector3::unit_y()), Some(root))
+			.unwrap();
+
+		assert_eq!(2, scene.len());
+		assert_eq!(Some(child), scene.find("child"));
+		assert_eq!(Ok(Some(root)), scene.parent(child));
+
+		let draws = scene.draws(0.0);
+		assert_eq!(vec![root, child], draws.iter().map(|draw| draw.object).collect::<Vec<_>>());
+		assert_eq!(MeshId(1), draws[1].mesh);
+		assert_eq!(red.color, draws[1].color);
+		assert_matrix_near(Matrix4::from_translation(Vector3::new(1.0, 1.0, 0.0)), draws[1].world);
+	}
+
+	#[test]
+	fn test_remove()
+	{
+		let mut scene = Scene::new();
+		let root = scene.add(test_object("root"), Transform::identity(), None).unwrap();
+		let child = scene.add(test_object("child"), Transform::identity(), Some(root)).unwrap();
+		let other = scene.add(test_object("other"), Transform::identity(), None).unwrap();
+
+		assert_eq!(Ok(vec![root, child]), scene.remove(root));
+		assert_eq!(vec![other], scene.objects());
+		assert_eq!(
+			Err(SceneError::UnknownObject {
+				object : child,
+			}),
+			scene.object(child).map(|_| ())
+		);
+		assert!(scene.remove(root).is_err());
+		assert_eq!(1, scene.draws(0.0).len());
+
+		let added = scene.add(test_object("added"), Transform::identity(), None).unwrap();
+		assert_eq!("added", scene.object(added).unwrap().name);
+	}
+
+	#[test]
+	fn test_reparent()
+	{
+		let mut scene = Scene::new();
+		let a = scene.add(test_object("a"), Transform::from_translation(Vector3::unit_x()), None).unwrap();
+		let b = scene.add(test_object("b"), Transform::from_translation(Vector3::unit_z()), Some(a)).unwrap();
+		let c = scene.add(test_object("c"), Transform::identity(), Some(b)).unwrap();
+
+		assert_eq!(
+			Err(SceneError::Cycle {
+				object : a,
+				parent : c,
+			}),
+			scene.reparent(a, Some(c))
+		);
+
+		scene.reparent(c, None).unwrap();
+		assert_eq!(Ok(None), scene.parent(c));
+		assert_eq!(Ok(vec![]), scene.children(b));
+		assert_matrix_near(Matrix4::identity(), scene.world_matrix(c).unwrap());
+
+		scene.reparent(a, Some(c)).unwrap();
+		assert_eq!(vec![c, a, b], scene.objects());
+		assert_matrix_near(Matrix4::from_translation(Vector3::new(1.0, 0.0, 1.0)), scene.world_matrix(b).unwrap());
+	}
+
+	#[test]
+	fn test_animation()
+	{
+		let mut scene = Scene::new();
+		let base = Transform::from_translation(Vector3::new(0.0, 2.0, 0.0));
+		let mut object = test_object("spinner");
+		object.animation = Some(AnimationClip::spin(Vector3::unit_y(), 90.0));
+		let spinner = scene.add(object, base, None).unwrap();
+		let child =
+			scene.add(test_object("child"), Transform::from_translation(Vector3::unit_x()), Some(spinner)).unwrap();
+
+		let draws = scene.draws(1.0);
+		let expected = Matrix4::from_translation(Vector3::new(0.0, 2.0, 0.0))
+			* Matrix4::from_angle_y(Deg(90.0))
+			* Matrix4::from_translation(Vector3::unit_x());
+		assert_matrix_near(expected, draws[1].world);
+		assert_eq!(Ok(&base), scene.transform(spinner));
+
+		// Moving the base moves the animation with it.
+		scene.set_transform(spinner, Transform::identity()).unwrap();
+		scene.animate(1.0);
+		assert_matrix_near(
+			Matrix4::from_angle_y(Deg(90.0)) * Matrix4::from_translation(Vector3::unit_x()),
+			scene.world_matrix(child).unwrap(),
+		);
+	}
+}
@@ -0,0 +1,1110 @@
+// Scene description files.
+//
+// A scene file is RON naming the meshes and animations a scene uses, the
+// objects placed in it, its camera and its clear color:
+//
+//   Scene(
+//       version: 1,
+//       clear_color: (0.0, 0.2, 0.4, 1.0),
+//       camera: Camera(
+//           eye: (0.0, 0.66, -2.5),
+//           target: (0.0, 0.66, 0.0),
+//           up: (0.0, 1.0, 0.0),
+//           fovy: 90.0,
+//           near: 0.1,
+//           far: 100.0,
+//           projection: Perspective,
+//       ),
+//       meshes: [
+//           Mesh(name: "tetrahedron", source: File("tetrahedron.obj")),
+//           Mesh(name: "ball", source: Icosphere(radius: 0.5)),
+//       ],
+//       animations: [
+//           Animation(name: "spin", source: Spin(
+//               axis: (0.0, 1.0, 0.0),
+//               degrees_per_second: 90.0,
+//           )),
+//       ],
+//       objects: [
+//           Object(
+//               name: "tetrahedron",
+//               mesh: "tetrahedron",
+//               transform: Transform(
+//                   translation: (0.0, 0.0, 0.0),
+//                   rotation: (0.0, 0.0, 0.0, 1.0),
+//                   scale: (1.0, 1.0, 1.0),
+//               ),
+//               material: Material(color: (1.0, 1.0, 1.0, 1.0)),
+//               animation: Some("spin"),
+//               children: [],
+//           ),
+//       ],
+//   )
+//
+// Only `version`, `meshes`, `objects` and an object's `name` and `mesh` are
+// required, everything else has a default. Struct names like `Camera` may be
+// left out. Mesh sources are File, Tetrahedron(radius), Cube(size,
+// subdivisions), Icosphere(radius, subdivisions) and Plane(width, depth), and
+// animation sources File and Spin(axis, degrees_per_second). Files are OBJ,
+// PLY, STL or glTF meshes and .anim clips, relative to the scene file.
+// Rotations are quaternions written x, y, z, w.
+//
+// Files of any other version are rejected. Errors the RON parser finds point
+// at the line and column where it stopped, just past the offending value.
+// Names are checked once the whole file is read. The saver writes every field,
+// so a saved description reads back unchanged.
+use crate::{
+	animation::{
+		self,
+		AnimationClip,
+	},
+	camera::{
+		Camera,
+		Projection,
+	},
+	geometry::{
+		gltf,
+		obj,
+		ply,
+		primitives,
+		stl,
+		ColoredVertex,
+		Mesh,
+	},
+	scene::{
+		Material,
+		MeshId,
+		ObjectId,
+		Scene,
+		SceneObject,
+	},
+	transform::Transform,
+};
+use cgmath::*;
+use serde::{
+	de::{
+		self,
+		Deserializer,
+	},
+	ser::Serializer,
+	Deserialize,
+	Serialize,
+};
+use std::{
+	collections::HashMap,
+	error::Error,
+	fmt,
+	fs,
+	io,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+pub const SCENE_VERSION : i64 = 1;
+
+pub const MAX_CUBE_SUBDIVISIONS : u32 = 64;
+pub const MAX_ICOSPHERE_SUBDIVISIONS : u32 = 6;
+
+// Sizes and radii must be positive. Parsing checks every parameter, so the
+// primitives never see values they would panic or run out of memory on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum MeshSource
+{
+	File(String),
+	Tetrahedron
+	{
+		#[serde(deserialize_with = "positive")]
+		radius : f32,
+	},
+	// 1 to MAX_CUBE_SUBDIVISIONS subdivisions along each edge.
+	Cube
+	{
+		#[serde(deserialize_with = "positive")]
+		size :         f32,
+		#[serde(default = "default_cube_subdivisions", deserialize_with = "cube_subdivisions")]
+		subdivisions : u32,
+	},
+	// 0 to MAX_ICOSPHERE_SUBDIVISIONS subdivisions, each one quadrupling the
+	// triangles.
+	Icosphere
+	{
+		#[serde(deserialize_with = "positive")]
+		radius :       f32,
+		#[serde(default, deserialize_with = "icosphere_subdivisions")]
+		subdivisions : u32,
+	},
+	Plane
+	{
+		#[serde(deserialize_with = "positive")]
+		width : f32,
+		#[serde(deserialize_with = "positive")]
+		depth : f32,
+	},
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "Mesh", deny_unknown_fields)]
+pub struct MeshDescription
+{
+	pub name :   String,
+	pub source : MeshSource,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum AnimationSource
+{
+	File(String),
+	Spin
+	{
+		#[serde(serialize_with = "vector3::serialize", deserialize_with = "spin_axis")]
+		axis : Vector3<f32>,
+		#[serde(deserialize_with = "spin_speed")]
+		degrees_per_second : f32,
+	},
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "Animation", deny_unknown_fields)]
+pub struct AnimationDescription
+{
+	pub name :   String,
+	pub source : AnimationSource,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "Camera", default, deny_unknown_fields)]
+pub struct CameraDescription
+{
+	#[serde(with = "point3")]
+	pub eye :        Point3<f32>,
+	#[serde(with = "point3")]
+	pub target :     Point3<f32>,
+	#[serde(with = "vector3")]
+	pub up :         Vector3<f32>,
+	#[serde(with = "degrees")]
+	pub fovy :       Deg<f32>,
+	pub near :       f32,
+	pub far :        f32,
+	pub projection : Projection,
+}
+
+impl Default for CameraDescription
+{
+	fn default() -> Self
+	{
+		CameraDescription {
+			eye :        Point3::new(0.0, 0.66, -2.5),
+			target :     Point3::new(0.0, 0.66, 0.0),
+			up :         Vector3::unit_y(),
+			fovy :       Deg(90.0),
+			near :       0.1,
+			far :        100.0,
+			projection : Projection::Perspective,
+		}
+	}
+}
+
+#[allow(dead_code)]
+impl CameraDescription
+{
+	// Sets up `camera` as described. The aspect ratio and depth mode belong to
+	// the renderer and are left alone.
+	pub fn apply(&self, camera : &mut Camera)
+	{
+		camera.eye = self.eye;
+		camera.target = self.target;
+		camera.up = self.up;
+		camera.fovy = self.fovy.into();
+		camera.near = self.near;
+		camera.far = self.far;
+		camera.projection = self.projection;
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "Object", deny_unknown_fields)]
+pub struct ObjectDescription
+{
+	pub name :      String,
+	// Name of one of the scene's meshes.
+	pub mesh :      String,
+	#[serde(default = "Transform::identity", with = "transform")]
+	pub transform : Transform,
+	#[serde(default, with = "material")]
+	pub material :  Material,
+	// Name of one of the scene's animations.
+	#[serde(default)]
+	pub animation : Option<String>,
+	#[serde(default)]
+	pub children :  Vec<ObjectDescription>,
+}
+
+#[allow(dead_code)]
+impl ObjectDescription
+{
+	pub fn new(name : &str, mesh : &str) -> Self
+	{
+		ObjectDescription {
+			name :      name.to_string(),
+			mesh :      mesh.to_string(),
+			transform : Transform::identity(),
+			material :  Material::default(),
+			animation : None,
+			children :  Vec::new(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneDescription
+{
+	pub clear_color : Vector4<f32>,
+	pub camera :      CameraDescription,
+	pub meshes :      Vec<MeshDescription>,
+	pub animations :  Vec<AnimationDescription>,
+	pub objects :     Vec<ObjectDescription>,
+}
+
+impl Default for SceneDescription
+{
+	fn default() -> Self
+	{
+		SceneDescription {
+			clear_color : Vector4::new(0.0, 0.2, 0.4, 1.0),
+			camera :      CameraDescription::default(),
+			meshes :      Vec::new(),
+			animations :  Vec::new(),
+			objects :     Vec::new(),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum SceneFileError
+{
+	Io
+	{
+		path :  PathBuf,
+		error : io::Error,
+	},
+	Parse
+	{
+		file :    String,
+		line :    usize,
+		column :  usize,
+		details : String,
+	},
+	// The file parsed, but names two meshes or two animations alike, or has
+	// an object using a mesh or animation it does not have.
+	Names
+	{
+		file :    String,
+		details : String,
+	},
+	// A mesh or animation file the scene uses failed to load.
+	Asset
+	{
+		path :    PathBuf,
+		details : String,
+	},
+	// An object names a mesh or animation the scene does not have. Loaded
+	// files are checked as they are parsed, so this only comes from
+	// descriptions built in code.
+	UnknownReference
+	{
+		object :    String,
+		reference : String,
+	},
+}
+
+impl fmt::Display for SceneFileError
+{
+	fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			SceneFileError::Io {
+				path,
+				error,
+			} => write!(f, "{}: {}", path.display(), error),
+			SceneFileError::Parse {
+				file,
+				line,
+				column,
+				details,
+			} => write!(f, "{}:{}:{}: {}", file, line, column, details),
+			SceneFileError::Names {
+				file,
+				details,
+			} => write!(f, "{}: {}", file, details),
+			SceneFileError::Asset {
+				path,
+				details,
+			} => write!(f, "{}: {}", path.display(), details),
+			SceneFileError::UnknownReference {
+				object,
+				reference,
+			} => write!(f, "object `{}` uses `{}`, which the scene does not have", object, reference),
+		}
+	}
+}
+
+impl Error for SceneFileError {}
+
+// The file's side of a SceneDescription, with the version in front.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Scene", deny_unknown_fields)]
+struct SceneFile
+{
+	// Read first, so files from other versions fail on it rather than on
+	// whatever changed.
+	#[serde(deserialize_with = "version")]
+	version :     i64,
+	#[serde(default = "default_clear_color", with = "vector4")]
+	clear_color : Vector4<f32>,
+	#[serde(default)]
+	camera :      CameraDescription,
+	meshes :      Vec<MeshDescription>,
+	#[serde(default)]
+	animations :  Vec<AnimationDescription>,
+	objects :     Vec<ObjectDescription>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Transform", default, deny_unknown_fields)]
+struct TransformFile
+{
+	#[serde(with = "vector3")]
+	translation : Vector3<f32>,
+	#[serde(with = "quaternion")]
+	rotation :    Quaternion<f32>,
+	#[serde(with = "vector3")]
+	scale :       Vector3<f32>,
+}
+
+impl Default for TransformFile
+{
+	fn default() -> Self
+	{
+		TransformFile::from(&Transform::identity())
+	}
+}
+
+impl From<&Transform> for TransformFile
+{
+	fn from(transform : &Transform) -> Self
+	{
+		TransformFile {
+			translation : transform.translation,
+			rotation :    transform.rotation,
+			scale :       transform.scale,
+		}
+	}
+}
+
+impl From<TransformFile> for Transform
+{
+	fn from(file : TransformFile) -> Self
+	{
+		Transform::new(file.translation, file.rotation, file.scale)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Material", default, deny_unknown_fields)]
+struct MaterialFile
+{
+	#[serde(with = "vector4")]
+	color : Vector4<f32>,
+}
+
+impl Default for MaterialFile
+{
+	fn default() -> Self
+	{
+		MaterialFile::from(&Material::default())
+	}
+}
+
+impl From<&Material> for MaterialFile
+{
+	fn from(material : &Material) -> Self
+	{
+		MaterialFile {
+			color : material.color,
+		}
+	}
+}
+
+impl From<MaterialFile> for Material
+{
+	fn from(file : MaterialFile) -> Self
+	{
+		Material::new(file.color)
+	}
+}
+
+fn default_clear_color() -> Vector4<f32>
+{
+	SceneDescription::default().clear_color
+}
+
+fn version<'de, D : Deserializer<'de>>(deserializer : D) -> Result<i64, D::Error>
+{
+	let version = i64::deserialize(deserializer)?;
+	if version != SCENE_VERSION
+	{
+		return Err(de::Error::custom(format!("unsupported version {}, expected version {}", version, SCENE_VERSION)));
+	}
+	Ok(version)
+}
+
+fn positive<'de, D : Deserializer<'de>>(deserializer : D) -> Result<f32, D::Error>
+{
+	let x = f32::deserialize(deserializer)?;
+	if !(x.is_finite() && x > 0.0)
+	{
+		return Err(de::Error::custom(format!("expected a positive number, found {}", x)));
+	}
+	Ok(x)
+}
+
+fn subdivisions<'de, D : Deserializer<'de>>(deserializer : D, min : u32, max : u32) -> Result<u32, D::Error>
+{
+	let subdivisions = u32::deserialize(deserializer)?;
+	if subdivisions < min || subdivisions > max
+	{
+		return Err(de::Error::custom(format!("expected {} to {} subdivisions, found {}", min, max, subdivisions)));
+	}
+	Ok(subdivisions)
+}
+
+fn default_cube_subdivisions() -> u32
+{
+	1
+}
+
+fn cube_subdivisions<'de, D : Deserializer<'de>>(deserializer : D) -> Result<u32, D::Error>
+{
+	subdivisions(deserializer, 1, MAX_CUBE_SUBDIVISIONS)
+}
+
+fn icosphere_subdivisions<'de, D : Deserializer<'de>>(deserializer : D) -> Result<u32, D::Error>
+{
+	subdivisions(deserializer, 0, MAX_ICOSPHERE_SUBDIVISIONS)
+}
+
+fn spin_axis<'de, D : Deserializer<'de>>(deserializer : D) -> Result<Vector3<f32>, D::Error>
+{
+	let axis = vector3::deserialize(deserializer)?;
+	if axis.magnitude2() == 0.0
+	{
+		return Err(de::Error::custom("a spin needs an axis"));
+	}
+	Ok(axis)
+}
+
+fn spin_speed<'de, D : Deserializer<'de>>(deserializer : D) -> Result<f32, D::Error>
+{
+	let degrees_per_second = f32::deserialize(deserializer)?;
+	if degrees_per_second == 0.0
+	{
+		return Err(de::Error::custom("a spin needs a speed"));
+	}
+	Ok(degrees_per_second)
+}
+
+// A module for `#[serde(with = "...")]` that reads and writes `$type` as
+// `$file_type`, for the types serde has no derive for.
+macro_rules! file_format {
+	($module:ident, $type:ty, $file_type:ty, $to_file:expr, $from_file:expr) => {
+		mod $module
+		{
+			use super::*;
+
+			pub fn serialize<S : Serializer>(value : &$type, serializer : S) -> Result<S::Ok, S::Error>
+			{
+				let to_file : fn(&$type) -> $file_type = $to_file;
+				to_file(value).serialize(serializer)
+			}
+
+			pub fn deserialize<'de, D : Deserializer<'de>>(deserializer : D) -> Result<$type, D::Error>
+			{
+				let from_file : fn($file_type) -> $type = $from_file;
+				<$file_type>::deserialize(deserializer).map(from_file)
+			}
+		}
+	};
+}
+
+file_format!(vector3, Vector3<f32>, (f32, f32, f32), |&v| v.into(), Vector3::from);
+file_format!(vector4, Vector4<f32>, (f32, f32, f32, f32), |&v| v.into(), Vector4::from);
+file_format!(point3, Point3<f32>, (f32, f32, f32), |&p| p.into(), Point3::from);
+file_format!(quaternion, Quaternion<f32>, (f32, f32, f32, f32), |q| (q.v.x, q.v.y, q.v.z, q.s), |(x, y, z, w)| {
+	Quaternion::new(w, x, y, z)
+});
+file_format!(degrees, Deg<f32>, f32, |angle| angle.0, Deg);
+file_format!(transform, Transform, TransformFile, |transform| transform.into(), Transform::from);
+file_format!(material, Material, MaterialFile, |material| material.into(), Material::from);
+
+// Checks that mesh and animation names are unique and that objects only use
+// names the scene has.
+fn check_names(description : &SceneDescription) -> Result<(), String>
+{
+	let mut meshes : Vec<&str> = Vec::new();
+	for mesh in &description.meshes
+	{
+		if meshes.contains(&mesh.name.as_str())
+		{
+			return Err(format!("there already is a mesh named `{}`", mesh.name));
+		}
+		meshes.push(&mesh.name);
+	}
+	let mut animations : Vec<&str> = Vec::new();
+	for animation in &description.animations
+	{
+		if animations.contains(&animation.name.as_str())
+		{
+			return Err(format!("there already is an animation named `{}`", animation.name));
+		}
+		animations.push(&animation.name);
+	}
+
+	let mut pending : Vec<&ObjectDescription> = description.objects.iter().collect();
+	while let Some(object) = pending.pop()
+	{
+		if !meshes.contains(&object.mesh.as_str())
+		{
+			return Err(format!("object `{}` uses mesh `{}`, which the scene does not have", object.name, object.mesh));
+		}
+		if let Some(animation) = object.animation.as_ref().filter(|name| !animations.contains(&name.as_str()))
+		{
+			return Err(format!(
+				"object `{}` uses animation `{}`, which the scene does not have",
+				object.name, animation
+			));
+		}
+		pending.extend(&object.children);
+	}
+	Ok(())
+}
+
+// Parses the text of a scene file. `file` is only used in error messages.
+pub fn parse_scene(file : &str, source : &str) -> Result<SceneDescription, SceneFileError>
+{
+	let scene : SceneFile = ron::de::from_str(source).map_err(|error| SceneFileError::Parse {
+		file :    file.to_string(),
+		line :    error.position.line,
+		column :  error.position.col,
+		details : error.code.to_string(),
+	})?;
+	let description = SceneDescription {
+		clear_color : scene.clear_color,
+		camera :      scene.camera,
+		meshes :      scene.meshes,
+		animations :  scene.animations,
+		objects :     scene.objects,
+	};
+	check_names(&description).map_err(|details| SceneFileError::Names {
+		file :    file.to_string(),
+		details : details,
+	})?;
+	Ok(description)
+}
+
+pub fn load_scene(path : &Path) -> Result<SceneDescription, SceneFileError>
+{
+	let source = fs::read_to_string(path).map_err(|error| SceneFileError::Io {
+		path :  path.to_path_buf(),
+		error : error,
+	})?;
+	parse_scene(&path.display().to_string(), &source)
+}
+
+// Writes a description in the scene file format.
+#[allow(dead_code)]
+pub fn write_scene(description : &SceneDescription) -> String
+{
+	let scene = SceneFile {
+		version :     SCENE_VERSION,
+		clear_color : description.clear_color,
+		camera :      description.camera,
+		meshes :      description.meshes.clone(),
+		animations :  description.animations.clone(),
+		objects :     description.objects.clone(),
+	};
+	let config = ron::ser::PrettyConfig::new().struct_names(true);
+	ron::ser::to_string_pretty(&scene, config).expect("scene descriptions always serialize")
+}
+
+#[allow(dead_code)]
+pub fn save_scene(path : &Path, description : &SceneDescription) -> Result<(), SceneFileError>
+{
+	fs::write(path, write_scene(description)).map_err(|error| SceneFileError::Io {
+		path :  path.to_path_buf(),
+		error : error,
+	})
+}
+
+fn asset_error<E : fmt::Display>(path : &Path) -> impl Fn(E) -> SceneFileError + '_
+{
+	move |error| SceneFileError::Asset {
+		path :    path.to_path_buf(),
+		details : error.to_string(),
+	}
+}
+
+// Loads or generates one mesh. Files are relative to `directory`, the scene
+// file's directory.
+pub fn load_mesh(source : &MeshSource, directory : &Path) -> Result<Mesh<ColoredVertex>, SceneFileError>
+{
+	let mesh = match source
+	{
+		MeshSource::File(file) =>
+		{
+			let path = directory.join(file);
+			let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+			let mesh = match extension.as_str()
+			{
+				"obj" => obj::load_obj(&path).map(|model| model.mesh).map_err(asset_error(&path))?,
+				"ply" => ply::load_ply(&path).map_err(asset_error(&path))?,
+				"stl" => stl::load_stl(&path).map_err(asset_error(&path))?,
+				"gltf" | "glb" => gltf::load_gltf(&path).map(|scene| scene.flatten()).map_err(asset_error(&path))?,
+				_ => return Err(asset_error(&path)("not an OBJ, PLY, STL or glTF file")),
+			};
+			// Also catches files caught half written while reloading.
+			if mesh.triangle_count() == 0
+			{
+				return Err(asset_error(&path)("the mesh has no triangles"));
+			}
+			mesh
+		}
+		MeshSource::Tetrahedron {
+			radius,
+		} => primitives::tetrahedron(*radius),
+		MeshSource::Cube {
+			size,
+			subdivisions,
+		} => primitives::cube(*size, *subdivisions),
+		MeshSource::Icosphere {
+			radius,
+			subdivisions,
+		} => primitives::icosphere(*radius, *subdivisions),
+		MeshSource::Plane {
+			width,
+			depth,
+		} => primitives::plane(*width, *depth, 1, 1),
+	};
+	Ok(mesh.map_vertices(|&vertex| ColoredVertex::from(vertex)))
+}
+
+// Loads every mesh of a scene, in order.
+pub fn load_meshes(
+	description : &SceneDescription, directory : &Path,
+) -> Result<Vec<Mesh<ColoredVertex>>, SceneFileError>
+{
+	description.meshes.iter().map(|mesh| load_mesh(&mesh.source, directory)).collect()
+}
+
+pub fn load_animation(source : &AnimationSource, directory : &Path) -> Result<AnimationClip, SceneFileError>
+{
+	match source
+	{
+		AnimationSource::File(file) =>
+		{
+			let path = directory.join(file);
+			animation::load_animation(&path).map_err(asset_error(&path))
+		}
+		AnimationSource::Spin {
+			axis,
+			degrees_per_second,
+		} => Ok(AnimationClip::spin(*axis, *degrees_per_second)),
+	}
+}
+
+// Builds the objects of a description into a Scene. `meshes` holds the
+// MeshId of each of the description's meshes, in order.
+pub fn build_scene(
+	description : &SceneDescription, meshes : &[MeshId], directory : &Path,
+) -> Result<Scene, SceneFileError>
+{
+	let mesh_ids : HashMap<&str, MeshId> =
+		description.meshes.iter().map(|mesh| mesh.name.as_str()).zip(meshes.iter().cloned()).collect();
+	let mut clips = HashMap::new();
+	for animation in &description.animations
+	{
+		clips.insert(animation.name.as_str(), load_animation(&animation.source, directory)?);
+	}
+
+	let mut scene = Scene::new();
+	let mut pending : Vec<(&ObjectDescription, Option<ObjectId>)> =
+		description.objects.iter().rev().map(|object| (object, None)).collect();
+	while let Some((object, parent)) = pending.pop()
+	{
+		let unknown = |reference : &str| SceneFileError::UnknownReference {
+			object :    object.name.clone(),
+			reference : reference.to_string(),
+		};
+		let mesh = *mesh_ids.get(object.mesh.as_str()).ok_or_else(|| unknown(&object.mesh))?;
+		let mut scene_object = SceneObject::new(&object.name, mesh, object.material);
+		if let Some(name) = &object.animation
+		{
+			scene_object.animation = Some(clips.get(name.as_str()).ok_or_else(|| unknown(name))?.clone());
+		}
+		let id = scene.add(scene_object, object.transform, parent).unwrap();
+		pending.extend(object.children.iter().rev().map(|child| (child, Some(id))));
+	}
+	Ok(scene)
+}
+
+#[cfg(test)]
+mod scene_file_tests
+{
+	use super::{
+		build_scene,
+		load_mesh,
+		load_scene,
+		parse_scene,
+		write_scene,
+		AnimationDescription,
+		AnimationSource,
+		CameraDescription,
+		MeshDescription,
+		MeshSource,
+		ObjectDescription,
+		SceneDescription,
+		SceneFileError,
+	};
+	use crate::{
+		camera::Projection,
+		geometry::{
+			sample_colored_tetrahedron_mesh,
+			MeshVertex,
+		},
+		scene::{
+			Material,
+			MeshId,
+		},
+		transform::Transform,
+	};
+	use cgmath::*;
+	use std::{
+		fs,
+		path::{
+			Path,
+			PathBuf,
+		},
+	};
+
+	fn scenes_directory() -> PathBuf
+	{
+		Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes")
+	}
+
+	fn error_at(source : &str) -> (usize, usize, String)
+	{
+		match parse_scene("test.ron", source)
+		{
+			Err(SceneFileError::Parse {
+				line,
+				column,
+				details,
+				..
+			}) => (line, column, details),
+			other => panic!("expected a parse error, got {:?}", other),
+		}
+	}
+
+	fn test_description() -> SceneDescription
+	{
+		let mut moon = ObjectDescription::new("moon \"small\"", "ball");
+		moon.transform = Transform::new(
+			Vector3::new(1.5, -0.25, 1e-7),
+			Quaternion::from_angle_z(Deg(33.0)),
+			Vector3::new(0.3, 0.3, 0.3),
+		);
+		moon.material = Material::new(Vector4::new(0.1, 0.2, 0.3, 0.4));
+		moon.animation = Some("bob".to_string());
+
+		let mut root = ObjectDescription::new("root", "tetrahedron");
+		root.animation = Some("spin".to_string());
+		root.children = vec![moon, ObjectDescription::new("floor", "floor")];
+
+		SceneDescription {
+			clear_color : Vector4::new(0.25, 0.5, 0.75, 1.0),
+			camera :      CameraDescription {
+				eye :        Point3::new(1.0, 2.0, -3.0),
+				target :     Point3::new(0.0, 0.5, 0.0),
+				up :         Vector3::unit_y(),
+				fovy :       Deg(60.0),
+				near :       0.01,
+				far :        1000.0,
+				projection : Projection::Orthographic,
+			},
+			meshes :      vec![
+				MeshDescription {
+					name :   "tetrahedron".to_string(),
+					source : MeshSource::File("meshes/tetrahedron.obj".to_string()),
+				},
+				MeshDescription {
+					name :   "ball".to_string(),
+					source : MeshSource::Icosphere {
+						radius :       0.5,
+						subdivisions : 2,
+					},
+				},
+				MeshDescription {
+					name :   "floor".to_string(),
+					source : MeshSource::Plane {
+						width : 10.0,
+						depth : 10.0,
+					},
+				},
+			],
+			animations :  vec![
+				AnimationDescription {
+					name :   "spin".to_string(),
+					source : AnimationSource::Spin {
+						axis : Vector3::unit_y(),
+						degrees_per_second : 90.0,
+					},
+				},
+				AnimationDescription {
+					name :   "bob".to_string(),
+					source : AnimationSource::File("../fixtures/animation/bob.anim".to_string()),
+				},
+			],
+			objects :     vec![root, ObjectDescription::new("lonely", "tetrahedron")],
+		}
+	}
+
+	#[test]
+	fn test_round_trip()
+	{
+		let description = test_description();
+		let written = write_scene(&description);
+		assert_eq!(description, parse_scene("written.ron", &written).unwrap());
+		assert_eq!(written, write_scene(&parse_scene("written.ron", &written).unwrap()));
+	}
+
+	#[test]
+	fn test_sample_scene()
+	{
+		let description = load_scene(&scenes_directory().join("tetrahedron.ron")).unwrap();
+		assert_eq!(CameraDescription::default(), description.camera);
+		assert_eq!(Vector4::new(0.0, 0.2, 0.4, 1.0), description.clear_color);
+		assert_eq!(vec!["tetrahedron"], description.meshes.iter().map(|m| m.name.as_str()).collect::<Vec<_>>());
+		assert_eq!(1, description.objects.len());
+		assert_eq!(2, description.objects[0].children.len());
+
+		let written = write_scene(&description);
+		assert_eq!(description, parse_scene("written.ron", &written).unwrap());
+
+		// The sample mesh file is the tetrahedron the sample used to build in code.
+		let mesh = load_mesh(&description.meshes[0].source, &scenes_directory()).unwrap();
+		let expected = sample_colored_tetrahedron_mesh();
+		assert_eq!(expected.triangle_count(), mesh.triangle_count());
+		for (e, v) in expected.to_triangle_soup().into_iter().zip(mesh.to_triangle_soup())
+		{
+			let (e, v) = (MeshVertex::from(e), MeshVertex::from(v));
+			assert!((e.position - v.position).magnitude() < 1e-6, "{:?} != {:?}", e, v);
+			assert_eq!(e.color, v.color);
+		}
+	}
+
+	#[test]
+	fn test_defaults()
+	{
+		let description = parse_scene(
+			"test.ron",
+			"(version: 1, meshes: [(name: \"m\", source: Cube(size: 1))], objects: [(name: \"o\", mesh: \"m\")])",
+		)
+		.unwrap();
+		assert_eq!(SceneDescription::default().camera, description.camera);
+		assert_eq!(
+			MeshSource::Cube {
+				size :         1.0,
+				subdivisions : 1,
+			},
+			description.meshes[0].source
+		);
+		assert_eq!(ObjectDescription::new("o", "m"), description.objects[0]);
+
+		let mesh = load_mesh(&description.meshes[0].source, &scenes_directory()).unwrap();
+		assert_eq!(12, mesh.triangle_count());
+	}
+
+	#[test]
+	fn test_mesh_parameters_are_checked()
+	{
+		let scene =
+			|source : &str| format!("Scene(version: 1, meshes: [Mesh(name: \"m\", source: {})], objects: [])", source);
+
+		for source in [
+			"Tetrahedron(radius: 0)",
+			"Tetrahedron(radius: -1)",
+			"Tetrahedron(radius: inf)",
+			"Cube(size: 0)",
+			"Cube(size: 1, subdivisions: 0)",
+			"Cube(size: 1, subdivisions: 65)",
+			"Icosphere(radius: 0, subdivisions: 1)",
+			"Icosphere(radius: 1, subdivisions: 20)",
+			"Plane(width: 1, depth: 0)",
+		]
+		.iter()
+		{
+			let (line, _, details) = error_at(&scene(source));
+			assert_eq!(1, line, "{}", source);
+			assert!(details.starts_with("expected"), "{}: {}", source, details);
+		}
+
+		// The error points just past the offending value.
+		let (_, column, details) = error_at(&scene("Icosphere(radius: 1, subdivisions: 20)"));
+		assert_eq!(89, column);
+		assert!(details.contains("0 to 6 subdivisions, found 20"), "{}", details);
+
+		for source in [
+			"Tetrahedron(radius: 0.5)",
+			"Cube(size: 2, subdivisions: 64)",
+			"Icosphere(radius: 1)",
+			"Icosphere(radius: 1, subdivisions: 6)",
+			"Plane(width: 1, depth: 2)",
+		]
+		.iter()
+		{
+			let description = parse_scene("test.ron", &scene(source)).unwrap();
+			let mesh = load_mesh(&description.meshes[0].source, &scenes_directory()).unwrap();
+			assert!(mesh.triangle_count() > 0, "{}", source);
+		}
+	}
+
+	#[test]
+	fn test_parse_errors_report_locations()
+	{
+		let scene = |objects : &str| {
+			format!(
+				"Scene(\n    version: 1,\n    meshes: [Mesh(name: \"m\", source: Tetrahedron(radius: 1.0))],\n{}\n)",
+				objects
+			)
+		};
+
+		// Errors are reported where the parser stopped: just past the offending
+		// value, or at the end of a struct missing a field.
+		let (line, column, details) = error_at("Scene(\n    version: 2,\n)");
+		assert_eq!((2, 15), (line, column));
+		assert!(details.contains("unsupported version 2"), "{}", details);
+
+		let (line, column, details) = error_at("Scene(version: 1, objects: [])");
+		assert_eq!((1, 30), (line, column));
+		assert!(details.contains("missing field `meshes`"), "{}", details);
+
+		let (line, column, details) = error_at(&scene("    objects: [], colour: (1, 1, 1, 1),"));
+		assert_eq!((4, 24), (line, column));
+		assert!(details.contains("field named `colour`"), "{}", details);
+
+		let (line, column, details) =
+			error_at(&scene("    objects: [(name: \"o\", mesh: \"m\", transform: (scale: (1, 2)))],"));
+		assert_eq!((4, 62), (line, column));
+		assert!(details.contains("tuple of size 3"), "{}", details);
+
+		let (line, column, details) = error_at(&scene("    objects: [], camera: (projection: Fisheye),"));
+		assert_eq!((4, 46), (line, column));
+		assert!(details.contains("`Perspective` or `Orthographic`"), "{}", details);
+
+		let (line, column, details) = error_at(&scene(
+			"    objects: [], animations: [(name: \"a\", source: Spin(axis: (0, 0, 0), degrees_per_second: 1))],",
+		));
+		assert_eq!((4, 71), (line, column));
+		assert!(details.contains("a spin needs an axis"), "{}", details);
+
+		// Syntax errors come through with the location the RON parser found.
+		let (line, column, _) = error_at("Scene(\n    version: 1,\n    meshes: [}");
+		assert_eq!((3, 14), (line, column));
+	}
+
+	#[test]
+	fn test_names_are_checked()
+	{
+		let scene = |objects : &str| {
+			format!("Scene(version: 1, meshes: [Mesh(name: \"m\", source: Tetrahedron(radius: 1.0))], {})", objects)
+		};
+		let names_error = |source : &str| match parse_scene("test.ron", source)
+		{
+			Err(SceneFileError::Names {
+				details,
+				..
+			}) => details,
+			other => panic!("expected a names error, got {:?}", other),
+		};
+
+		let details = names_error(&scene("objects: [Object(name: \"o\", mesh: \"n\")]"));
+		assert!(details.contains("uses mesh `n`"), "{}", details);
+
+		let details = names_error(&scene(
+			"objects: [(name: \"o\", mesh: \"m\", children: [(name: \"c\", mesh: \"m\", animation: Some(\"spin\"))])]",
+		));
+		assert!(details.contains("object `c` uses animation `spin`"), "{}", details);
+
+		let details = names_error(
+			"Scene(version: 1, objects: [], meshes: [Mesh(name: \"m\", source: Cube(size: 1)), Mesh(name: \"m\", \
+			 source: Cube(size: 2))])",
+		);
+		assert!(details.contains("already is a mesh named `m`"), "{}", details);
+	}
+
+	#[test]
+	fn test_load_mesh_errors()
+	{
+		let directory = std::env::temp_dir().join(format!("scene_file_tests_{}_mesh_errors", std::process::id()));
+		fs::create_dir_all(&directory).unwrap();
+		fs::write(directory.join("empty.obj"), "# saved halfway\n").unwrap();
+
+		for file in ["empty.obj", "missing.obj", "mesh.fbx"].iter()
+		{
+			match load_mesh(&MeshSource::File(file.to_string()), &directory)
+			{
+				Err(SceneFileError::Asset {
+					path,
+					..
+				}) => assert_eq!(directory.join(file), path),
+				other =>
+				{
+					panic!("expected an asset error for {}, got {:?}", file, other.map(|mesh| mesh.vertices.len()))
+				}
+			}
+		}
+		fs::remove_dir_all(&directory).unwrap();
+	}
+
+	#[test]
+	fn test_build_scene()
+	{
+		let description = test_description();
+		let meshes = [MeshId(4), MeshId(5), MeshId(6)];
+		let mut scene = build_scene(&description, &meshes, &scenes_directory()).unwrap();
+		assert_eq!(4, scene.len());
+
+		let root = scene.find("root").unwrap();
+		let moon = scene.find("moon \"small\"").unwrap();
+		assert_eq!(Ok(Some(root)), scene.parent(moon));
+		assert_eq!(MeshId(5), scene.object(moon).unwrap().mesh);
+		assert!(scene.object(moon).unwrap().animation.is_some());
+		assert_eq!(None, scene.parent(scene.find("lonely").unwrap()).unwrap());
+		assert_eq!(4, scene.draws(0.0).len());
+
+		let mut broken = description.clone();
+		broken.objects[1].mesh = "missing".to_string();
+		match build_scene(&broken, &meshes, &scenes_directory())
+		{
+			Err(SceneFileError::UnknownReference {
+				object,
+				reference,
+			}) => assert_eq!(("lonely".to_string(), "missing".to_string()), (object, reference)),
+			other => panic!("unexpected {:?}", other.map(|scene| scene.len())),
+		}
+	}
+}
@@ -1,6 +1,8 @@
 cbuffer Projection : register(b0)
 {
     float4x4 mvp_transform;
+    float4 tint; // multiplies the vertex colors
+    int highlighted_triangle; // -1 for none
 };
 
 struct PSInput
@@ -14,12 +16,31 @@ PSInput VSMain(float4 position : POSITION, float4 color : COLOR)
     PSInput result;
 
     result.position = mul(mvp_transform, position);
-    result.color = color;
+    result.color = color * tint;
 
     return result;
 }
 
-float4 PSMain(PSInput input) : SV_TARGET
+// Instanced draws place each copy with the transform and tint of the second
+// vertex stream, and mvp_transform is the view projection alone.
+PSInput VSInstanced(float4 position : POSITION, float4 color : COLOR,
+                    float4x4 transform : TRANSFORM, float4 instance_tint : COLOR1)
 {
+    PSInput result;
+
+    result.position = mul(mvp_transform, mul(transform, position));
+    result.color = color * tint * instance_tint;
+
+    return result;
+}
+
+float4 PSMain(PSInput input, uint primitive_id : SV_PrimitiveID) : SV_TARGET
+{
+    // Primitive ids count triangles from the start of the draw, the same
+    // numbering picking uses.
+    if ((int)primitive_id == highlighted_triangle)
+    {
+        return lerp(input.color, float4(1.0, 1.0, 1.0, 1.0), 0.6);
+    }
     return input.color;
 }
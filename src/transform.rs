@@ -0,0 +1,631 @@
+// Object transforms as translation, rotation and scale, and hierarchies of
+// them.
+//
+// A Transform applies scale first, then rotation, then translation, so its
+// matrix is T * R * S. Non-uniform scale under a rotation shears the child's
+// axes, which a single TRS cannot express. Composition and inversion are exact
+// for uniform scale, or when no rotation comes before a non-uniform scale, and
+// otherwise keep the rotation and multiply the scales per axis. Hierarchies
+// compose matrices instead, so world matrices are always exact.
+use cgmath::*;
+
+// How far from zero a scale has to be for a transform to be invertible.
+const MIN_SCALE : f32 = 1.0e-12;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform
+{
+	pub translation : Vector3<f32>,
+	// Kept normalized, to be a pure rotation.
+	pub rotation :    Quaternion<f32>,
+	pub scale :       Vector3<f32>,
+}
+
+impl Default for Transform
+{
+	fn default() -> Self
+	{
+		Transform::identity()
+	}
+}
+
+#[allow(dead_code)]
+impl Transform
+{
+	pub fn identity() -> Self
+	{
+		Transform {
+			translation : Vector3::zero(),
+			rotation :    Quaternion::one(),
+			scale :       Vector3::new(1.0, 1.0, 1.0),
+		}
+	}
+
+	pub fn new(translation : Vector3<f32>, rotation : Quaternion<f32>, scale : Vector3<f32>) -> Self
+	{
+		Transform {
+			translation : translation,
+			rotation :    rotation.normalize(),
+			scale :       scale,
+		}
+	}
+
+	pub fn from_translation(translation : Vector3<f32>) -> Self
+	{
+		Transform {
+			translation : translation,
+			..Transform::identity()
+		}
+	}
+
+	pub fn from_rotation(rotation : Quaternion<f32>) -> Self
+	{
+		Transform {
+			rotation : rotation.normalize(),
+			..Transform::identity()
+		}
+	}
+
+	pub fn from_scale(scale : Vector3<f32>) -> Self
+	{
+		Transform {
+			scale : scale,
+			..Transform::identity()
+		}
+	}
+
+	pub fn matrix(&self) -> Matrix4<f32>
+	{
+		Matrix4::from_translation(self.translation)
+			* Matrix4::from(self.rotation)
+			* Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+	}
+
+	pub fn transform_point(&self, point : Point3<f32>) -> Point3<f32>
+	{
+		Point3::from_vec(self.rotation.rotate_vector(point.to_vec().mul_element_wise(self.scale)) + self.translation)
+	}
+
+	pub fn transform_vector(&self, vector : Vector3<f32>) -> Vector3<f32>
+	{
+		self.rotation.rotate_vector(vector.mul_element_wise(self.scale))
+	}
+
+	// The transform that applies `child` first, then `self`, as in a parent and
+	// child relationship.
+	pub fn compose(&self, child : &Transform) -> Transform
+	{
+		Transform {
+			translation : self.transform_point(Point3::from_vec(child.translation)).to_vec(),
+			rotation :    (self.rotation * child.rotation).normalize(),
+			scale :       self.scale.mul_element_wise(child.scale),
+		}
+	}
+
+	// None when the scale is zero along some axis.
+	pub fn inverse(&self) -> Option<Transform>
+	{
+		if self.scale.x.abs() < MIN_SCALE || self.scale.y.abs() < MIN_SCALE || self.scale.z.abs() < MIN_SCALE
+		{
+			return None;
+		}
+
+		let rotation = self.rotation.conjugate();
+		let scale = Vector3::new(1.0 / self.scale.x, 1.0 / self.scale.y, 1.0 / self.scale.z);
+		Some(Transform {
+			translation : -rotation.rotate_vector(self.translation).mul_element_wise(scale),
+			rotation :    rotation,
+			scale :       scale,
+		})
+	}
+
+	// Splits an affine matrix back into translation, rotation and scale. A
+	// mirroring matrix gets a negative x scale. Shear is lost, and None is
+	// returned for projections and matrices that flatten an axis.
+	pub fn from_matrix(m : Matrix4<f32>) -> Option<Transform>
+	{
+		if m.x.w != 0.0 || m.y.w != 0.0 || m.z.w != 0.0 || m.w.w != 1.0
+		{
+			return None;
+		}
+
+		let mut scale =
+			Vector3::new(m.x.truncate().magnitude(), m.y.truncate().magnitude(), m.z.truncate().magnitude());
+		if scale.x < MIN_SCALE || scale.y < MIN_SCALE || scale.z < MIN_SCALE
+		{
+			return None;
+		}
+
+		let mut x_axis = m.x.truncate() / scale.x;
+		let y_axis = m.y.truncate() / scale.y;
+		let z_axis = m.z.truncate() / scale.z;
+		if x_axis.cross(y_axis).dot(z_axis) < 0.0
+		{
+			scale.x = -scale.x;
+			x_axis = -x_axis;
+		}
+
+		Some(Transform {
+			translation : m.w.truncate(),
+			rotation :    Quaternion::from(Matrix3::from_cols(x_axis, y_axis, z_axis)).normalize(),
+			scale :       scale,
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+struct HierarchyNode
+{
+	local :    Transform,
+	parent :   Option<usize>,
+	children : Vec<usize>,
+	// Cached parent world matrix times the local matrix. A dirty node's
+	// descendants are all dirty too.
+	world :    Matrix4<f32>,
+	dirty :    bool,
+}
+
+// Parent and child transforms, addressed by the index `add` returns. World
+// matrices are only recomputed for nodes whose local transform, or that of an
+// ancestor, changed since they were last asked for. Indices of removed nodes
+// are handed out again by later adds.
+#[derive(Debug, Clone, Default)]
+pub struct TransformHierarchy
+{
+	nodes : Vec<Option<HierarchyNode>>,
+	free :  Vec<usize>,
+}
+
+#[allow(dead_code)]
+impl TransformHierarchy
+{
+	pub fn new() -> Self
+	{
+		TransformHierarchy {
+			nodes : Vec::new(),
+			free :  Vec::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize
+	{
+		self.nodes.len() - self.free.len()
+	}
+
+	pub fn is_empty(&self) -> bool
+	{
+		self.len() == 0
+	}
+
+	pub fn contains(&self, node : usize) -> bool
+	{
+		matches!(self.nodes.get(node), Some(Some(_)))
+	}
+
+	// Indices of every node, parents before their children.
+	pub fn nodes(&self) -> Vec<usize>
+	{
+		let mut order = Vec::with_capacity(self.len());
+		let mut stack : Vec<usize> = self.roots().into_iter().rev().collect();
+		while let Some(node) = stack.pop()
+		{
+			order.push(node);
+			stack.extend(self.children(node).iter().rev());
+		}
+		order
+	}
+
+	pub fn roots(&self) -> Vec<usize>
+	{
+		(0..self.nodes.len()).filter(|&node| self.contains(node) && self.parent(node).is_none()).collect()
+	}
+
+	fn node(&self, node : usize) -> &HierarchyNode
+	{
+		self.nodes[node].as_ref().expect("The node was removed.")
+	}
+
+	fn node_mut(&mut self, node : usize) -> &mut HierarchyNode
+	{
+		self.nodes[node].as_mut().expect("The node was removed.")
+	}
+
+	// Adds a node under `parent`, or as a root, and returns its index.
+	pub fn add(&mut self, local : Transform, parent : Option<usize>) -> usize
+	{
+		let node = self.free.pop().unwrap_or(self.nodes.len());
+		if let Some(parent) = parent
+		{
+			self.node_mut(parent).children.push(node);
+		}
+		let new_node = Some(HierarchyNode {
+			local :    local,
+			parent :   parent,
+			children : Vec::new(),
+			world :    Matrix4::identity(),
+			dirty :    true,
+		});
+		if node == self.nodes.len()
+		{
+			self.nodes.push(new_node);
+		}
+		else
+		{
+			self.nodes[node] = new_node;
+		}
+		node
+	}
+
+	// Removes `node` and everything below it, and returns the removed indices,
+	// parents before their children.
+	pub fn remove(&mut self, node : usize) -> Vec<usize>
+	{
+		self.detach(node);
+		let mut removed = Vec::new();
+		let mut stack = vec![node];
+		while let Some(n) = stack.pop()
+		{
+			let removed_node = self.nodes[n].take().expect("The node was removed.");
+			stack.extend(removed_node.children.iter().rev());
+			self.free.push(n);
+			removed.push(n);
+		}
+		removed
+	}
+
+	// Moves `node` under `parent`, or to the roots. The local transform is kept,
+	// so the subtree follows its new parent. Panics if `parent` is `node` or
+	// one of its descendants.
+	pub fn reparent(&mut self, node : usize, parent : Option<usize>)
+	{
+		if let Some(parent) = parent
+		{
+			assert!(!self.is_ancestor(node, parent), "Reparenting node {} under {} makes a cycle.", node, parent);
+		}
+		self.detach(node);
+		if let Some(parent) = parent
+		{
+			self.node_mut(parent).children.push(node);
+		}
+		self.node_mut(node).parent = parent;
+		self.mark_dirty(node);
+	}
+
+	// Whether `node` is `ancestor` or below it.
+	pub fn is_ancestor(&self, ancestor : usize, node : usize) -> bool
+	{
+		let mut current = Some(node);
+		while let Some(n) = current
+		{
+			if n == ancestor
+			{
+				return true;
+			}
+			current = self.parent(n);
+		}
+		false
+	}
+
+	fn detach(&mut self, node : usize)
+	{
+		if let Some(parent) = self.node(node).parent
+		{
+			self.node_mut(parent).children.retain(|&child| child != node);
+		}
+	}
+
+	pub fn parent(&self, node : usize) -> Option<usize>
+	{
+		self.node(node).parent
+	}
+
+	pub fn children(&self, node : usize) -> &[usize]
+	{
+		&self.node(node).children
+	}
+
+	pub fn local(&self, node : usize) -> &Transform
+	{
+		&self.node(node).local
+	}
+
+	pub fn set_local(&mut self, node : usize, local : Transform)
+	{
+		self.node_mut(node).local = local;
+		self.mark_dirty(node);
+	}
+
+	pub fn is_dirty(&self, node : usize) -> bool
+	{
+		self.node(node).dirty
+	}
+
+	// Marks the subtree under `node`, stopping at subtrees that are already
+	// dirty.
+	fn mark_dirty(&mut self, node : usize)
+	{
+		let mut stack = vec![node];
+		while let Some(n) = stack.pop()
+		{
+			if !self.node(n).dirty
+			{
+				self.node_mut(n).dirty = true;
+				stack.extend_from_slice(&self.node(n).children);
+			}
+		}
+	}
+
+	pub fn world_matrix(&mut self, node : usize) -> Matrix4<f32>
+	{
+		if self.node(node).dirty
+		{
+			let parent_world = match self.node(node).parent
+			{
+				Some(parent) => self.world_matrix(parent),
+				None => Matrix4::identity(),
+			};
+			let world = parent_world * self.node(node).local.matrix();
+			let n = self.node_mut(node);
+			n.world = world;
+			n.dirty = false;
+		}
+		self.node(node).world
+	}
+
+	// Brings every world matrix up to date.
+	pub fn update(&mut self)
+	{
+		for node in 0..self.nodes.len()
+		{
+			if self.contains(node)
+			{
+				self.world_matrix(node);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod trs_tests
+{
+	use crate::transform::{
+		Transform,
+		TransformHierarchy,
+	};
+	use cgmath::*;
+
+	#[allow(non_upper_case_globals)]
+	const _test_translation : Vector3<f32> = Vector3::new(1.0, 2.0, 3.0);
+	#[allow(non_upper_case_globals)]
+	const _test_scale : Vector3<f32> = Vector3::new(2.0, 3.0, 4.0);
+
+	// Scale by (2, 3, 4), rotate 90 degrees about y, then translate by (1, 2, 3).
+	#[allow(non_upper_case_globals)]
+	const _expected_trs : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.0, 0.0, -2.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 3.0, 0.0, 0.0),
+		z : Vector4::<f32>::new(4.0, 0.0, 0.0, 0.0),
+		w : Vector4::<f32>::new(1.0, 2.0, 3.0, 1.0),
+	};
+
+	fn test_transform() -> Transform
+	{
+		Transform::new(_test_translation, Quaternion::from_angle_y(Deg(90.0)), _test_scale)
+	}
+
+	fn assert_matrix_near(expected : Matrix4<f32>, actual : Matrix4<f32>)
+	{
+		let expected : &[f32; 16] = expected.as_ref();
+		let actual : &[f32; 16] = actual.as_ref();
+		for (e, a) in expected.iter().zip(actual.iter())
+		{
+			assert!((e - a).abs() <= 1.0e-5 * e.abs().max(1.0), "expected {:?}, got {:?}", expected, actual);
+		}
+	}
+
+	fn assert_transform_near(expected : &Transform, actual : &Transform)
+	{
+		assert!((expected.translation - actual.translation).magnitude() < 1.0e-5, "{:?} {:?}", expected, actual);
+		assert!((expected.scale - actual.scale).magnitude() < 1.0e-5, "{:?} {:?}", expected, actual);
+		// q and -q are the same rotation.
+		assert!(expected.rotation.dot(actual.rotation).abs() > 1.0 - 1.0e-5, "{:?} {:?}", expected, actual);
+	}
+
+	#[test]
+	fn test_matrix()
+	{
+		assert_matrix_near(Matrix4::identity(), Transform::identity().matrix());
+		assert_matrix_near(_expected_trs, test_transform().matrix());
+
+		// Points and vectors agree with the matrix.
+		let transform = test_transform();
+		let point = Point3::new(0.5, -1.0, 2.0);
+		let expected = transform.matrix() * point.to_homogeneous();
+		assert!((Point3::from_homogeneous(expected) - transform.transform_point(point)).magnitude() < 1.0e-5);
+		let expected = transform.matrix() * point.to_vec().extend(0.0);
+		assert!((expected.truncate() - transform.transform_vector(point.to_vec())).magnitude() < 1.0e-5);
+	}
+
+	#[test]
+	fn test_compose()
+	{
+		let parent = Transform::new(
+			Vector3::new(0.0, 1.0, 0.0),
+			Quaternion::from_angle_z(Deg(30.0)),
+			Vector3::new(2.0, 2.0, 2.0),
+		);
+		let child = test_transform();
+		let composed = parent.compose(&child);
+		assert_matrix_near(parent.matrix() * child.matrix(), composed.matrix());
+
+		// Non-uniform scales compose exactly when the child is not rotated.
+		let parent = test_transform();
+		let child = Transform::new(Vector3::new(5.0, 0.0, 0.0), Quaternion::one(), Vector3::new(1.0, 0.5, 2.0));
+		assert_matrix_near(parent.matrix() * child.matrix(), parent.compose(&child).matrix());
+
+		assert_transform_near(&child, &Transform::identity().compose(&child));
+		assert_transform_near(&child, &child.compose(&Transform::identity()));
+	}
+
+	#[test]
+	fn test_inverse()
+	{
+		let uniform =
+			Transform::new(_test_translation, Quaternion::from_angle_x(Deg(-60.0)), Vector3::new(3.0, 3.0, 3.0));
+		let inverse = uniform.inverse().unwrap();
+		assert_matrix_near(uniform.matrix().invert().unwrap(), inverse.matrix());
+		assert_transform_near(&Transform::identity(), &uniform.compose(&inverse));
+		assert_transform_near(&Transform::identity(), &inverse.compose(&uniform));
+
+		// Non-uniform scale inverts exactly without a rotation.
+		let transform = Transform::new(_test_translation, Quaternion::one(), _test_scale);
+		let point = Point3::new(-2.0, 0.5, 7.0);
+		let inverse = transform.inverse().unwrap();
+		assert_matrix_near(transform.matrix().invert().unwrap(), inverse.matrix());
+		assert!((inverse.transform_point(transform.transform_point(point)) - point).magnitude() < 1.0e-5);
+
+		// With one, only the translation is exact.
+		let transform = test_transform();
+		let inverse = transform.inverse().unwrap();
+		assert!((transform.matrix().invert().unwrap().w - inverse.matrix().w).magnitude() < 1.0e-5);
+
+		assert_eq!(None, Transform::from_scale(Vector3::new(1.0, 0.0, 1.0)).inverse());
+	}
+
+	#[test]
+	fn test_from_matrix()
+	{
+		assert_transform_near(&test_transform(), &Transform::from_matrix(_expected_trs).unwrap());
+		assert_transform_near(&Transform::identity(), &Transform::from_matrix(Matrix4::identity()).unwrap());
+
+		// Mirroring comes back as a negative scale that rebuilds the same matrix.
+		let mirrored =
+			Transform::new(_test_translation, Quaternion::from_angle_z(Deg(45.0)), Vector3::new(1.0, 2.0, -1.0));
+		let decomposed = Transform::from_matrix(mirrored.matrix()).unwrap();
+		assert!(decomposed.scale.x < 0.0);
+		assert_matrix_near(mirrored.matrix(), decomposed.matrix());
+
+		assert_eq!(None, Transform::from_matrix(Matrix4::from_nonuniform_scale(1.0, 0.0, 1.0)));
+		assert_eq!(
+			None,
+			Transform::from_matrix(crate::transforms::perspective_lh(PerspectiveFov {
+				fovy :   Rad(1.0),
+				aspect : 1.0,
+				near :   0.1,
+				far :    10.0,
+			}))
+		);
+	}
+
+	#[test]
+	fn test_hierarchy_world_matrices()
+	{
+		let mut hierarchy = TransformHierarchy::new();
+		let root = hierarchy.add(Transform::from_translation(Vector3::new(0.0, 0.0, 10.0)), None);
+		let arm = hierarchy.add(test_transform(), Some(root));
+		let hand = hierarchy.add(Transform::from_rotation(Quaternion::from_angle_x(Deg(45.0))), Some(arm));
+		let other = hierarchy.add(Transform::identity(), None);
+
+		assert_eq!(4, hierarchy.len());
+		assert_eq!(Some(arm), hierarchy.parent(hand));
+		assert_eq!(&[arm], hierarchy.children(root));
+		assert_eq!(None, hierarchy.parent(other));
+
+		let expected = hierarchy.local(root).matrix() * hierarchy.local(arm).matrix() * hierarchy.local(hand).matrix();
+		assert_matrix_near(expected, hierarchy.world_matrix(hand));
+		assert_matrix_near(Matrix4::identity(), hierarchy.world_matrix(other));
+	}
+
+	#[test]
+	fn test_hierarchy_dirty_flags()
+	{
+		let mut hierarchy = TransformHierarchy::new();
+		let root = hierarchy.add(Transform::identity(), None);
+		let child = hierarchy.add(test_transform(), Some(root));
+		let grandchild = hierarchy.add(Transform::from_translation(Vector3::unit_x()), Some(child));
+		let sibling = hierarchy.add(Transform::identity(), Some(root));
+
+		// Asking for one node computes its ancestors, but nothing else.
+		hierarchy.world_matrix(grandchild);
+		assert!(!hierarchy.is_dirty(root) && !hierarchy.is_dirty(child) && !hierarchy.is_dirty(grandchild));
+		assert!(hierarchy.is_dirty(sibling));
+		hierarchy.update();
+		assert!(!hierarchy.is_dirty(sibling));
+
+		// Changing a node invalidates its subtree only.
+		let moved = Transform::from_translation(Vector3::new(0.0, 5.0, 0.0));
+		hierarchy.set_local(child, moved);
+		assert!(!hierarchy.is_dirty(root) && !hierarchy.is_dirty(sibling));
+		assert!(hierarchy.is_dirty(child) && hierarchy.is_dirty(grandchild));
+		assert_matrix_near(Matrix4::from_translation(Vector3::new(1.0, 5.0, 0.0)), hierarchy.world_matrix(grandchild));
+
+		// Changing the root reaches everything, even below clean nodes.
+		hierarchy.set_local(root, Transform::from_scale(Vector3::new(2.0, 2.0, 2.0)));
+		for &node in &[root, child, grandchild, sibling]
+		{
+			assert!(hierarchy.is_dirty(node));
+		}
+		assert_matrix_near(
+			Matrix4::from_scale(2.0) * Matrix4::from_translation(Vector3::new(1.0, 5.0, 0.0)),
+			hierarchy.world_matrix(grandchild),
+		);
+	}
+
+	#[test]
+	fn test_hierarchy_remove()
+	{
+		let mut hierarchy = TransformHierarchy::new();
+		let root = hierarchy.add(Transform::identity(), None);
+		let child = hierarchy.add(test_transform(), Some(root));
+		let grandchild = hierarchy.add(Transform::identity(), Some(child));
+		let sibling = hierarchy.add(Transform::identity(), Some(root));
+
+		assert_eq!(vec![child, grandchild], hierarchy.remove(child));
+		assert_eq!(2, hierarchy.len());
+		assert!(!hierarchy.contains(child) && !hierarchy.contains(grandchild));
+		assert_eq!(&[sibling], hierarchy.children(root));
+		assert_eq!(vec![root, sibling], hierarchy.nodes());
+		hierarchy.update();
+
+		// Removed indices are reused.
+		let added = hierarchy.add(Transform::identity(), None);
+		assert!(added == child || added == grandchild);
+		assert_eq!(3, hierarchy.len());
+		assert_eq!(vec![root, added], hierarchy.roots());
+	}
+
+	#[test]
+	fn test_hierarchy_reparent()
+	{
+		let mut hierarchy = TransformHierarchy::new();
+		let a = hierarchy.add(Transform::from_translation(Vector3::unit_x()), None);
+		let b = hierarchy.add(Transform::from_translation(Vector3::unit_y()), None);
+		let child = hierarchy.add(Transform::from_translation(Vector3::unit_z()), Some(a));
+		assert_matrix_near(Matrix4::from_translation(Vector3::new(1.0, 0.0, 1.0)), hierarchy.world_matrix(child));
+
+		hierarchy.reparent(child, Some(b));
+		assert_eq!(Some(b), hierarchy.parent(child));
+		assert!(hierarchy.children(a).is_empty());
+		assert_eq!(&[child], hierarchy.children(b));
+		assert!(hierarchy.is_dirty(child));
+		assert_matrix_near(Matrix4::from_translation(Vector3::new(0.0, 1.0, 1.0)), hierarchy.world_matrix(child));
+
+		hierarchy.reparent(child, None);
+		assert_eq!(vec![a, b, child], hierarchy.roots());
+		assert_matrix_near(Matrix4::from_translation(Vector3::unit_z()), hierarchy.world_matrix(child));
+
+		assert!(hierarchy.is_ancestor(b, b));
+		hierarchy.reparent(b, Some(a));
+		assert!(hierarchy.is_ancestor(a, b) && !hierarchy.is_ancestor(b, a));
+	}
+
+	#[test]
+	#[should_panic]
+	fn test_hierarchy_reparent_cycle()
+	{
+		let mut hierarchy = TransformHierarchy::new();
+		let root = hierarchy.add(Transform::identity(), None);
+		let child = hierarchy.add(Transform::identity(), Some(root));
+		hierarchy.reparent(root, Some(child));
+	}
+}
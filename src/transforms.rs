@@ -39,117 +39,329 @@ pub fn look_at_lh(eye_p : Point3<f32>, target : Point3<f32>, up : Vector3<f32>)
 	)
 }
 
-// This outputs a right-handed erspective matrix that ranges in depth from -1 to
-// 1
-#[allow(dead_code)]
-pub fn _glm_perspective_rh<S : BaseFloat>(persp : PerspectiveFov<S>) -> Matrix4<S>
+// Projections.
+//
+// Every projection takes a ClipSpaceConvention: the handedness of view space,
+// which decides whether the camera looks down +z or -z, and the clip space
+// depth range the near and far planes map to. D3D12 clips depth to 0..1, so
+// that is the default, and the _lh/_rh shorthands use it. The OpenGL range of
+// -1..1 is there to compare against GLM. Reverse-Z maps the near plane to 1 and
+// the far plane to 0, so the precision of floating point depth, which is
+// densest near 0, goes to the distant geometry that needs it most; it needs a
+// GREATER depth test and a clear value of 0.
+//
+// Matrices are column-major for column vectors. The columns equal the rows of
+// the DirectXMath matrices for the same projection.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Handedness
 {
-	let mut m = Matrix4::<S>::zero();
+	// +z points away from the camera, as in DirectXMath's LH functions.
+	Left,
+	// -z points away from the camera, as in OpenGL.
+	Right,
+}
 
-	let two : S = cast(2).unwrap();
-	let f = Rad::tan(persp.fovy / two);
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DepthRange
+{
+	// D3D and Vulkan.
+	ZeroToOne,
+	// OpenGL.
+	MinusOneToOne,
+	// Reverse-Z, from 1 at the near plane to 0 at the far plane.
+	OneToZero,
+}
 
-	let a = S::one() / (persp.aspect * f);
-	let b = S::one() / f;
-	let c = -(persp.far + persp.near) / (persp.far - persp.near);
-	let d = -S::one();
-	let e = -(two * persp.far * persp.near) / (persp.far - persp.near);
-	// glm::perspectiveRH_NO and glm::perspectiveLH_NO both Negate the e term.
+impl DepthRange
+{
+	// Clip space depth at the near and far planes.
+	pub fn near_far<S : BaseFloat>(self) -> (S, S)
+	{
+		match self
+		{
+			DepthRange::ZeroToOne => (S::zero(), S::one()),
+			DepthRange::MinusOneToOne => (-S::one(), S::one()),
+			DepthRange::OneToZero => (S::one(), S::zero()),
+		}
+	}
+}
 
-	m[0][0] = a;
-	m[1][1] = b;
-	m[2][2] = c;
-	m[2][3] = d;
-	m[3][2] = e;
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClipSpaceConvention
+{
+	pub handedness :  Handedness,
+	pub depth_range : DepthRange,
+}
 
-	return m;
+impl ClipSpaceConvention
+{
+	#[allow(dead_code)]
+	pub const D3D12 : ClipSpaceConvention = ClipSpaceConvention {
+		handedness :  Handedness::Left,
+		depth_range : DepthRange::ZeroToOne,
+	};
+	#[allow(dead_code)]
+	pub const D3D12_REVERSE_Z : ClipSpaceConvention = ClipSpaceConvention {
+		handedness :  Handedness::Left,
+		depth_range : DepthRange::OneToZero,
+	};
+	// GLM's defaults.
+	#[allow(dead_code)]
+	pub const OPENGL : ClipSpaceConvention = ClipSpaceConvention {
+		handedness :  Handedness::Right,
+		depth_range : DepthRange::MinusOneToOne,
+	};
+
+	pub fn new(handedness : Handedness, depth_range : DepthRange) -> Self
+	{
+		ClipSpaceConvention {
+			handedness :  handedness,
+			depth_range : depth_range,
+		}
+	}
+
+	// View space z of a point one unit in front of the camera.
+	fn forward<S : BaseFloat>(self) -> S
+	{
+		match self.handedness
+		{
+			Handedness::Left => S::one(),
+			Handedness::Right => -S::one(),
+		}
+	}
 }
 
-// This outputs a left-handed erspective matrix that ranges in depth from -1 to
-// 1
-#[allow(dead_code)]
-pub fn _glm_perspective_lh<S : BaseFloat>(persp : PerspectiveFov<S>) -> Matrix4<S>
+impl Default for ClipSpaceConvention
 {
-	let mut m = Matrix4::<S>::zero();
+	fn default() -> Self
+	{
+		ClipSpaceConvention::D3D12
+	}
+}
 
+fn perspective_scale<S : BaseFloat>(fovy : Rad<S>, aspect : S) -> (S, S)
+{
 	let two : S = cast(2).unwrap();
-	let f = Rad::tan(persp.fovy / two);
+	let y_scale = S::one() / Rad::tan(fovy / two);
+	(y_scale / aspect, y_scale)
+}
 
-	let a = S::one() / (persp.aspect * f);
-	let b = S::one() / f;
-	let c = (persp.far + persp.near) / (persp.far - persp.near);
-	let d = S::one();
-	let e = -(two * persp.far * persp.near) / (persp.far - persp.near);
-	// glm::perspectiveRH_NO and glm::perspectiveLH_NO both Negate the e term.
+// Clip space z is `a * w + b`, with w the distance in front of the camera, so
+// depth after the divide is `a + b / distance`. The far plane may be infinite.
+fn perspective_from_depth<S : BaseFloat>(
+	fovy : Rad<S>, aspect : S, a : S, b : S, convention : ClipSpaceConvention,
+) -> Matrix4<S>
+{
+	let mut m = Matrix4::<S>::zero();
 
-	m[0][0] = a;
-	m[1][1] = b;
-	m[2][2] = c;
-	m[2][3] = d;
-	m[3][2] = e;
+	let (x_scale, y_scale) = perspective_scale(fovy, aspect);
+	let forward = convention.forward::<S>();
+
+	m[0][0] = x_scale;
+	m[1][1] = y_scale;
+	m[2][2] = a * forward;
+	m[2][3] = forward;
+	m[3][2] = b;
 
 	return m;
 }
 
-// This outputs a right-handed erspective matrix that ranges in depth from 0 to
-// 1
+// Maps the near and far planes of `persp` to the depth range of `convention`.
 #[allow(dead_code)]
-pub fn _dx_perspective_rh<S : BaseFloat>(persp : PerspectiveFov<S>) -> Matrix4<S>
+pub fn perspective<S : BaseFloat>(persp : PerspectiveFov<S>, convention : ClipSpaceConvention) -> Matrix4<S>
 {
-	let mut m = Matrix4::<S>::zero();
+	let (near_depth, far_depth) = convention.depth_range.near_far::<S>();
+	let (near, far) = (persp.near, persp.far);
 
-	let two : S = cast(2).unwrap();
-	let f = Rad::tan(persp.fovy / two);
-	let height = f;
-	let width = height / persp.aspect;
-	let f_range = persp.far / (persp.far - persp.near);
-
-	m[0][0] = width;
-	m[1][1] = height;
-	m[2][2] = f_range;
-	m[2][3] = -S::one();
-	m[3][2] = f_range * persp.near;
+	// Solves a + b / near = near_depth and a + b / far = far_depth.
+	let a = (far_depth * far - near_depth * near) / (far - near);
+	let b = (near_depth - far_depth) * near * far / (far - near);
+	perspective_from_depth(persp.fovy, persp.aspect, a, b, convention)
+}
 
-	return m;
+// `perspective` in the limit of an infinitely distant far plane. Nothing is
+// clipped for being too far away, at almost no cost in precision.
+#[allow(dead_code)]
+pub fn perspective_infinite<S : BaseFloat>(
+	fovy : Rad<S>, aspect : S, near : S, convention : ClipSpaceConvention,
+) -> Matrix4<S>
+{
+	let (near_depth, far_depth) = convention.depth_range.near_far::<S>();
+	perspective_from_depth(fovy, aspect, far_depth, (near_depth - far_depth) * near, convention)
 }
 
-// This outputs a left-handed erspective matrix that ranges in depth from 0 to 1
+// Maps the box from (left, bottom, near) to (right, top, far) in view space,
+// with near and far as distances in front of the camera, onto clip space.
 #[allow(dead_code)]
-pub fn _dx_perspective_lh<S : BaseFloat>(persp : PerspectiveFov<S>) -> Matrix4<S>
+pub fn ortho_off_center<S : BaseFloat>(ortho : Ortho<S>, convention : ClipSpaceConvention) -> Matrix4<S>
 {
-	let mut m = Matrix4::<S>::zero();
+	let mut m = Matrix4::<S>::identity();
 
 	let two : S = cast(2).unwrap();
-	let f = Rad::tan(persp.fovy / two);
-	let height = f;
-	let width = height / persp.aspect;
-	let f_range = persp.far / (persp.far - persp.near);
+	let (near_depth, far_depth) = convention.depth_range.near_far::<S>();
+	let (near, far) = (ortho.near, ortho.far);
+	let depth_scale = (far_depth - near_depth) / (far - near);
 
-	m[0][0] = width;
-	m[1][1] = height;
-	m[2][2] = f_range;
-	m[2][3] = S::one();
-	m[3][2] = -f_range * persp.near;
+	m[0][0] = two / (ortho.right - ortho.left);
+	m[1][1] = two / (ortho.top - ortho.bottom);
+	m[2][2] = depth_scale * convention.forward::<S>();
+	m[3][0] = -(ortho.right + ortho.left) / (ortho.right - ortho.left);
+	m[3][1] = -(ortho.top + ortho.bottom) / (ortho.top - ortho.bottom);
+	m[3][2] = (near_depth * far - far_depth * near) / (far - near);
 
 	return m;
 }
 
+// A view of `width` by `height` centered on the view axis, like
+// XMMatrixOrthographicLH/RH.
+#[allow(dead_code)]
+pub fn ortho<S : BaseFloat>(width : S, height : S, near : S, far : S, convention : ClipSpaceConvention) -> Matrix4<S>
+{
+	let two : S = cast(2).unwrap();
+	let view_box = Ortho {
+		left :   -width / two,
+		right :  width / two,
+		bottom : -height / two,
+		top :    height / two,
+		near :   near,
+		far :    far,
+	};
+	ortho_off_center(view_box, convention)
+}
+
+// Shorthands for the D3D12 depth range.
+
 #[allow(dead_code)]
 pub fn perspective_lh<S : BaseFloat>(persp : PerspectiveFov<S>) -> Matrix4<S>
 {
-	_glm_perspective_lh(persp)
+	perspective(persp, ClipSpaceConvention::new(Handedness::Left, DepthRange::ZeroToOne))
 }
 
 #[allow(dead_code)]
 pub fn perspective_rh<S : BaseFloat>(persp : PerspectiveFov<S>) -> Matrix4<S>
 {
-	_glm_perspective_rh(persp)
+	perspective(persp, ClipSpaceConvention::new(Handedness::Right, DepthRange::ZeroToOne))
+}
+
+#[allow(dead_code)]
+pub fn ortho_lh<S : BaseFloat>(width : S, height : S, near : S, far : S) -> Matrix4<S>
+{
+	ortho(width, height, near, far, ClipSpaceConvention::new(Handedness::Left, DepthRange::ZeroToOne))
+}
+
+#[allow(dead_code)]
+pub fn ortho_rh<S : BaseFloat>(width : S, height : S, near : S, far : S) -> Matrix4<S>
+{
+	ortho(width, height, near, far, ClipSpaceConvention::new(Handedness::Right, DepthRange::ZeroToOne))
+}
+
+// Viewport mapping.
+//
+// The viewport takes normalized device coordinates to pixels the way the
+// rasterizer does: x from -1 to 1 goes left to right, y from 1 to -1 goes top
+// to bottom, and clip space depth goes from min_depth to max_depth. GL style
+// -1..1 depth is first brought to 0..1, and reversed depth is stored as is.
+
+// A viewport in pixels, with the same fields as D3D12_VIEWPORT.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport
+{
+	pub x :         f32,
+	pub y :         f32,
+	pub width :     f32,
+	pub height :    f32,
+	pub min_depth : f32,
+	pub max_depth : f32,
+}
+
+impl Viewport
+{
+	// A full depth range viewport from the top left corner.
+	#[allow(dead_code)]
+	pub fn new(width : f32, height : f32) -> Self
+	{
+		Viewport {
+			x :         0.0,
+			y :         0.0,
+			width :     width,
+			height :    height,
+			min_depth : 0.0,
+			max_depth : 1.0,
+		}
+	}
+
+	pub fn from_ndc(&self, ndc : Point3<f32>, depth_range : DepthRange) -> Point3<f32>
+	{
+		let depth = match depth_range
+		{
+			DepthRange::MinusOneToOne => (ndc.z + 1.0) * 0.5,
+			DepthRange::ZeroToOne | DepthRange::OneToZero => ndc.z,
+		};
+		Point3::new(
+			self.x + (ndc.x + 1.0) * 0.5 * self.width,
+			self.y + (1.0 - ndc.y) * 0.5 * self.height,
+			self.min_depth + depth * (self.max_depth - self.min_depth),
+		)
+	}
+
+	pub fn to_ndc(&self, pixel : Point3<f32>, depth_range : DepthRange) -> Point3<f32>
+	{
+		let depth = (pixel.z - self.min_depth) / (self.max_depth - self.min_depth);
+		Point3::new(
+			(pixel.x - self.x) / self.width * 2.0 - 1.0,
+			1.0 - (pixel.y - self.y) / self.height * 2.0,
+			match depth_range
+			{
+				DepthRange::MinusOneToOne => depth * 2.0 - 1.0,
+				DepthRange::ZeroToOne | DepthRange::OneToZero => depth,
+			},
+		)
+	}
+}
+
+// Where `point` lands on screen: pixel x and y, and the depth the depth
+// buffer would store. None for points at or behind the camera plane, which
+// have no meaningful position on screen.
+#[allow(dead_code)]
+pub fn project(
+	point : Point3<f32>, view : Matrix4<f32>, projection : Matrix4<f32>, viewport : &Viewport, depth_range : DepthRange,
+) -> Option<Point3<f32>>
+{
+	let clip = projection * view * point.to_homogeneous();
+	if clip.w <= 0.0
+	{
+		return None;
+	}
+	Some(viewport.from_ndc(Point3::from_homogeneous(clip), depth_range))
+}
+
+// The world space point that `project` maps to `pixel`, with depth as stored
+// in the depth buffer. None if the matrices can not be inverted, or for the
+// far plane of an infinite projection, which is at infinity.
+#[allow(dead_code)]
+pub fn unproject(
+	pixel : Point3<f32>, view : Matrix4<f32>, projection : Matrix4<f32>, viewport : &Viewport, depth_range : DepthRange,
+) -> Option<Point3<f32>>
+{
+	let inverse = (projection * view).invert()?;
+	let world = inverse * viewport.to_ndc(pixel, depth_range).to_homogeneous();
+	if world.w == 0.0
+	{
+		return None;
+	}
+	Some(Point3::from_homogeneous(world))
 }
 
 #[cfg(test)]
 mod transform_tests
 {
-	use crate::transforms;
+	use crate::transforms::{
+		self,
+		ClipSpaceConvention,
+		DepthRange,
+		Handedness,
+	};
 	use cgmath::*;
 	use std::f32::consts::PI;
 
@@ -172,6 +384,22 @@ mod transform_tests
 		far :    TEST_FAR_PLANE,
 	};
 
+	const DX_LH : ClipSpaceConvention = ClipSpaceConvention::D3D12;
+	const DX_RH : ClipSpaceConvention = ClipSpaceConvention {
+		handedness :  Handedness::Right,
+		depth_range : DepthRange::ZeroToOne,
+	};
+	const GLM_LH : ClipSpaceConvention = ClipSpaceConvention {
+		handedness :  Handedness::Left,
+		depth_range : DepthRange::MinusOneToOne,
+	};
+	const GLM_RH : ClipSpaceConvention = ClipSpaceConvention::OPENGL;
+	const REVERSE_Z_LH : ClipSpaceConvention = ClipSpaceConvention::D3D12_REVERSE_Z;
+	const REVERSE_Z_RH : ClipSpaceConvention = ClipSpaceConvention {
+		handedness :  Handedness::Right,
+		depth_range : DepthRange::OneToZero,
+	};
+
 	// calculated using floats
 	#[allow(dead_code)]
 	const EXPECTED_A : f32 = 0.803333223;
@@ -254,6 +482,148 @@ mod transform_tests
 	#[allow(non_upper_case_globals)]
 	const _expected_glm_perspective_default : Matrix4<f32> = _expected_glm_perspective_rh;
 
+	// Orthographic references, from glm::orthoLH_NO/orthoRH_NO and
+	// XMMatrixOrthographicOffCenterLH/RH.
+
+	const TEST_ORTHO_OFF_CENTER : Ortho<f32> = Ortho {
+		left :   -2.0,
+		right :  6.0,
+		bottom : -1.0,
+		top :    3.0,
+		near :   TEST_NEAR_PLANE,
+		far :    TEST_FAR_PLANE,
+	};
+	const TEST_ORTHO_WIDTH : f32 = 16.0;
+	const TEST_ORTHO_HEIGHT : f32 = 9.0;
+
+	#[allow(non_upper_case_globals)]
+	const _expected_glm_ortho_off_center_lh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.25, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 0.5, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, 0.0200200200, 0.0),
+		w : Vector4::<f32>::new(-0.5, -0.5, -1.00200200, 1.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_glm_ortho_off_center_rh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.25, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 0.5, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, -0.0200200200, 0.0),
+		w : Vector4::<f32>::new(-0.5, -0.5, -1.00200200, 1.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_dx_ortho_off_center_lh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.25, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 0.5, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, 0.0100100100, 0.0),
+		w : Vector4::<f32>::new(-0.5, -0.5, -0.00100100100, 1.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_dx_ortho_off_center_rh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.25, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 0.5, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, -0.0100100100, 0.0),
+		w : Vector4::<f32>::new(-0.5, -0.5, -0.00100100100, 1.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_glm_ortho_lh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.125, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 0.222222222, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, 0.0200200200, 0.0),
+		w : Vector4::<f32>::new(0.0, 0.0, -1.00200200, 1.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_glm_ortho_rh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.125, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 0.222222222, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, -0.0200200200, 0.0),
+		w : Vector4::<f32>::new(0.0, 0.0, -1.00200200, 1.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_dx_ortho_lh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.125, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 0.222222222, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, 0.0100100100, 0.0),
+		w : Vector4::<f32>::new(0.0, 0.0, -0.00100100100, 1.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_dx_ortho_rh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.125, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 0.222222222, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, -0.0100100100, 0.0),
+		w : Vector4::<f32>::new(0.0, 0.0, -0.00100100100, 1.0),
+	};
+
+	// Reverse-Z and infinite far plane references, from XMMatrixPerspectiveFovLH
+	// and RH with near and far swapped, and their limits as far goes to infinity.
+
+	#[allow(non_upper_case_globals)]
+	const _expected_dx_perspective_reverse_z_lh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.803333104, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 1.42814779, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, -0.00100100100, 1.0),
+		w : Vector4::<f32>::new(0.0, 0.0, 0.100100100, 0.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_dx_perspective_reverse_z_rh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.803333104, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 1.42814779, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, 0.00100100100, -1.0),
+		w : Vector4::<f32>::new(0.0, 0.0, 0.100100100, 0.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_dx_perspective_infinite_lh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.803333104, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 1.42814779, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, 1.0, 1.0),
+		w : Vector4::<f32>::new(0.0, 0.0, -0.1, 0.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_dx_perspective_infinite_rh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.803333104, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 1.42814779, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, -1.0, -1.0),
+		w : Vector4::<f32>::new(0.0, 0.0, -0.1, 0.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_dx_perspective_infinite_reverse_z_lh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.803333104, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 1.42814779, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, 0.0, 1.0),
+		w : Vector4::<f32>::new(0.0, 0.0, 0.1, 0.0),
+	};
+
+	#[allow(non_upper_case_globals)]
+	const _expected_dx_perspective_infinite_reverse_z_rh : Matrix4<f32> = Matrix4 {
+		x : Vector4::<f32>::new(0.803333104, 0.0, 0.0, 0.0),
+		y : Vector4::<f32>::new(0.0, 1.42814779, 0.0, 0.0),
+		z : Vector4::<f32>::new(0.0, 0.0, 0.0, -1.0),
+		w : Vector4::<f32>::new(0.0, 0.0, 0.1, 0.0),
+	};
+
+	// The references are rounded to float, and the order of operations differs
+	// between libraries, so newer results are compared within a few ulps
+	// instead of exactly.
+	fn assert_matrix_near(expected : Matrix4<f32>, actual : Matrix4<f32>)
+	{
+		let expected : &[f32; 16] = expected.as_ref();
+		let actual : &[f32; 16] = actual.as_ref();
+		for (e, a) in expected.iter().zip(actual.iter())
+		{
+			assert!((e - a).abs() <= 1.0e-6 * e.abs().max(1.0), "expected {:?}, got {:?}", expected, actual);
+		}
+	}
+
 	#[test]
 	fn it_works()
 	{
@@ -274,17 +644,515 @@ mod transform_tests
 		assert_eq!(_expected_lookat_rh, result);
 	}
 
+	// D3D12 clips depth to 0..1, so the shorthands match DirectXMath.
 	#[test]
 	fn test_perspective_lh()
 	{
 		let result = transforms::perspective_lh(TEST_PERSPECTIVE_FOV);
-		assert_eq!(_expected_glm_perspective_lh, result);
+		assert_matrix_near(_expected_dx_perspective_lh, result);
 	}
 
 	#[test]
 	fn test_perspective_rh()
 	{
 		let result = transforms::perspective_rh(TEST_PERSPECTIVE_FOV);
-		assert_eq!(_expected_glm_perspective_rh, result);
+		assert_matrix_near(_expected_dx_perspective_rh, result);
+	}
+
+	#[test]
+	fn test_perspective_conventions()
+	{
+		assert_matrix_near(_expected_dx_perspective_lh, transforms::perspective(TEST_PERSPECTIVE_FOV, DX_LH));
+		assert_matrix_near(_expected_dx_perspective_rh, transforms::perspective(TEST_PERSPECTIVE_FOV, DX_RH));
+		assert_matrix_near(_expected_glm_perspective_lh, transforms::perspective(TEST_PERSPECTIVE_FOV, GLM_LH));
+		assert_matrix_near(_expected_glm_perspective_rh, transforms::perspective(TEST_PERSPECTIVE_FOV, GLM_RH));
+		assert_matrix_near(
+			_expected_glm_perspective_default,
+			transforms::perspective(TEST_PERSPECTIVE_FOV, ClipSpaceConvention::OPENGL),
+		);
+		assert_eq!(DX_LH, ClipSpaceConvention::default());
+	}
+
+	#[test]
+	fn test_ortho_off_center()
+	{
+		assert_matrix_near(
+			_expected_glm_ortho_off_center_lh,
+			transforms::ortho_off_center(TEST_ORTHO_OFF_CENTER, GLM_LH),
+		);
+		assert_matrix_near(
+			_expected_glm_ortho_off_center_rh,
+			transforms::ortho_off_center(TEST_ORTHO_OFF_CENTER, GLM_RH),
+		);
+		assert_matrix_near(
+			_expected_dx_ortho_off_center_lh,
+			transforms::ortho_off_center(TEST_ORTHO_OFF_CENTER, DX_LH),
+		);
+		assert_matrix_near(
+			_expected_dx_ortho_off_center_rh,
+			transforms::ortho_off_center(TEST_ORTHO_OFF_CENTER, DX_RH),
+		);
+	}
+
+	#[test]
+	fn test_ortho()
+	{
+		let (width, height) = (TEST_ORTHO_WIDTH, TEST_ORTHO_HEIGHT);
+		let (near, far) = (TEST_NEAR_PLANE, TEST_FAR_PLANE);
+		assert_matrix_near(_expected_glm_ortho_lh, transforms::ortho(width, height, near, far, GLM_LH));
+		assert_matrix_near(_expected_glm_ortho_rh, transforms::ortho(width, height, near, far, GLM_RH));
+		assert_matrix_near(_expected_dx_ortho_lh, transforms::ortho(width, height, near, far, DX_LH));
+		assert_matrix_near(_expected_dx_ortho_rh, transforms::ortho(width, height, near, far, DX_RH));
+		assert_eq!(transforms::ortho(width, height, near, far, DX_LH), transforms::ortho_lh(width, height, near, far));
+		assert_eq!(transforms::ortho(width, height, near, far, DX_RH), transforms::ortho_rh(width, height, near, far));
+	}
+
+	#[test]
+	fn test_ortho_maps_box_corners()
+	{
+		// The corners of the box land on the corners of clip space, with depth
+		// in the range of each convention. View space z grows away from the
+		// camera for LH and towards it for RH.
+		let o = TEST_ORTHO_OFF_CENTER;
+		let cases = [
+			(transforms::ortho_off_center(o, GLM_LH), 1.0, -1.0),
+			(transforms::ortho_off_center(o, GLM_RH), -1.0, -1.0),
+			(transforms::ortho_off_center(o, DX_LH), 1.0, 0.0),
+			(transforms::ortho_off_center(o, DX_RH), -1.0, 0.0),
+		];
+		for &(m, forward, near_depth) in cases.iter()
+		{
+			let near = m * Vector4::new(o.left, o.bottom, forward * o.near, 1.0);
+			let far = m * Vector4::new(o.right, o.top, forward * o.far, 1.0);
+			assert!((near - Vector4::new(-1.0, -1.0, near_depth, 1.0)).magnitude() < 1.0e-5);
+			assert!((far - Vector4::new(1.0, 1.0, 1.0, 1.0)).magnitude() < 1.0e-5);
+		}
+	}
+
+	#[test]
+	fn test_perspective_reverse_z()
+	{
+		assert_matrix_near(
+			_expected_dx_perspective_reverse_z_lh,
+			transforms::perspective(TEST_PERSPECTIVE_FOV, REVERSE_Z_LH),
+		);
+		assert_matrix_near(
+			_expected_dx_perspective_reverse_z_rh,
+			transforms::perspective(TEST_PERSPECTIVE_FOV, REVERSE_Z_RH),
+		);
+	}
+
+	#[test]
+	fn test_perspective_infinite()
+	{
+		let (fovy, aspect, near) = (TEST_PERSPECTIVE_FOV.fovy, TEST_ASPECT_RATIO, TEST_NEAR_PLANE);
+		assert_matrix_near(
+			_expected_dx_perspective_infinite_lh,
+			transforms::perspective_infinite(fovy, aspect, near, DX_LH),
+		);
+		assert_matrix_near(
+			_expected_dx_perspective_infinite_rh,
+			transforms::perspective_infinite(fovy, aspect, near, DX_RH),
+		);
+		assert_matrix_near(
+			_expected_dx_perspective_infinite_reverse_z_lh,
+			transforms::perspective_infinite(fovy, aspect, near, REVERSE_Z_LH),
+		);
+		assert_matrix_near(
+			_expected_dx_perspective_infinite_reverse_z_rh,
+			transforms::perspective_infinite(fovy, aspect, near, REVERSE_Z_RH),
+		);
+	}
+
+	// Depth after the perspective divide of a point `distance` in front of the
+	// camera, for a left-handed projection.
+	fn depth_at(projection : Matrix4<f32>, distance : f32) -> f32
+	{
+		let clip = projection * Vector4::new(0.0, 0.0, distance, 1.0);
+		clip.z / clip.w
+	}
+
+	// The smallest change in view distance that changes the stored depth at
+	// `distance`, relative to that distance. `depth_step` is the spacing of the
+	// depth buffer format around a depth value.
+	fn relative_resolution(projection : Matrix4<f32>, distance : f32, depth_step : fn(f32) -> f32) -> f64
+	{
+		// Depth is m22 + m32 / z for a left-handed projection with w = z.
+		let slope = projection[3][2].abs() as f64 / (distance as f64 * distance as f64);
+		depth_step(depth_at(projection, distance)) as f64 / slope / distance as f64
+	}
+
+	fn float_step(depth : f32) -> f32
+	{
+		f32::from_bits(depth.abs().to_bits() + 1) - depth.abs()
+	}
+
+	fn unorm24_step(_depth : f32) -> f32
+	{
+		1.0 / ((1 << 24) - 1) as f32
+	}
+
+	#[test]
+	fn test_depth_ranges()
+	{
+		let (near, far) = (TEST_NEAR_PLANE, TEST_FAR_PLANE);
+		let persp = TEST_PERSPECTIVE_FOV;
+		let (fovy, aspect) = (persp.fovy, persp.aspect);
+		let cases = [
+			(transforms::perspective(persp, REVERSE_Z_LH), 1.0, 0.0),
+			(transforms::perspective_infinite(fovy, aspect, near, DX_LH), 0.0, (far - near) / far),
+			(transforms::perspective_infinite(fovy, aspect, near, REVERSE_Z_LH), 1.0, near / far),
+		];
+		for &(projection, near_depth, far_depth) in cases.iter()
+		{
+			assert!((depth_at(projection, near) - near_depth).abs() < 1.0e-6);
+			assert!((depth_at(projection, far) - far_depth).abs() < 1.0e-6);
+			// Farther is always closer to the far end of the range.
+			let toward_far = (far_depth - near_depth).signum();
+			assert!((depth_at(projection, 1.0e6) - depth_at(projection, far)) * toward_far > 0.0);
+		}
+
+		// The RH variants put the same depths at negative z.
+		let rh = [
+			(transforms::perspective(persp, REVERSE_Z_LH), transforms::perspective(persp, REVERSE_Z_RH)),
+			(
+				transforms::perspective_infinite(fovy, aspect, near, DX_LH),
+				transforms::perspective_infinite(fovy, aspect, near, DX_RH),
+			),
+			(
+				transforms::perspective_infinite(fovy, aspect, near, REVERSE_Z_LH),
+				transforms::perspective_infinite(fovy, aspect, near, REVERSE_Z_RH),
+			),
+		];
+		for &(lh, rh) in rh.iter()
+		{
+			for &distance in &[near, 1.0, 30.0, far]
+			{
+				assert!((depth_at(lh, distance) - depth_at(rh, -distance)).abs() < 1.0e-6);
+			}
+		}
+	}
+
+	#[test]
+	fn test_depth_precision()
+	{
+		// A far plane ten thousand times the near plane, where standard depth
+		// starts to fight.
+		let persp = PerspectiveFov {
+			far : 1000.0,
+			..TEST_PERSPECTIVE_FOV
+		};
+		let (fovy, aspect, near) = (persp.fovy, persp.aspect, persp.near);
+		let standard = transforms::perspective(persp, DX_LH);
+		let reverse_z = transforms::perspective(persp, REVERSE_Z_LH);
+		let infinite = transforms::perspective_infinite(fovy, aspect, near, DX_LH);
+		let infinite_reverse_z = transforms::perspective_infinite(fovy, aspect, near, REVERSE_Z_LH);
+
+		for &distance in &[1.0, 10.0, 100.0, 500.0, 999.0]
+		{
+			// With float depth, standard depth loses resolution in proportion to
+			// the distance, about 6 parts in ten million per unit here, while
+			// reversed depth stays near the 24 bit mantissa everywhere.
+			let standard_float = relative_resolution(standard, distance, float_step);
+			assert!(standard_float > 5.0e-7 * distance as f64, "{} {}", distance, standard_float);
+			for &projection in &[reverse_z, infinite_reverse_z]
+			{
+				let reversed_float = relative_resolution(projection, distance, float_step);
+				assert!(reversed_float < 2.0e-7, "{} {}", distance, reversed_float);
+			}
+
+			// Dropping the far plane costs almost nothing.
+			let infinite_float = relative_resolution(infinite, distance, float_step);
+			assert!(infinite_float < standard_float * 1.01, "{} {}", distance, infinite_float);
+
+			// Fixed point depth is evenly spaced, so reversing it does not help.
+			let standard_unorm = relative_resolution(standard, distance, unorm24_step);
+			let reversed_unorm = relative_resolution(reverse_z, distance, unorm24_step);
+			assert!((standard_unorm - reversed_unorm).abs() < standard_unorm * 1.0e-3, "{}", distance);
+		}
+
+		// At the far end, standard float depth resolves about half a percent of
+		// the distance, reversed depth under a millionth of a percent.
+		assert!(relative_resolution(standard, 999.0, float_step) > 5.0e-4);
+		assert!(relative_resolution(reverse_z, 999.0, float_step) < 1.0e-8);
+	}
+
+	// The viewport of the sample window, and one offset into a larger target
+	// with a partial depth range.
+	const TEST_VIEWPORTS : [transforms::Viewport; 2] = [
+		transforms::Viewport {
+			x :         0.0,
+			y :         0.0,
+			width :     1280.0,
+			height :    720.0,
+			min_depth : 0.0,
+			max_depth : 1.0,
+		},
+		transforms::Viewport {
+			x :         100.0,
+			y :         50.0,
+			width :     640.0,
+			height :    360.0,
+			min_depth : 0.25,
+			max_depth : 0.75,
+		},
+	];
+
+	#[test]
+	fn test_project()
+	{
+		let view = transforms::look_at_lh(TEST_EYE, TEST_TARGET, UP);
+		let projection = transforms::perspective_lh(TEST_PERSPECTIVE_FOV);
+		let viewport = TEST_VIEWPORTS[0];
+		let project = |point| transforms::project(point, view, projection, &viewport, DepthRange::ZeroToOne);
+
+		// The target is in the middle of the screen, and the near plane at depth 0.
+		let center = project(TEST_TARGET).unwrap();
+		assert!((center - Point3::new(640.0, 360.0, center.z)).magnitude() < 1.0e-3);
+		let near = project(TEST_EYE + Vector3::new(0.0, 0.0, TEST_NEAR_PLANE)).unwrap();
+		assert!(near.z.abs() < 1.0e-5);
+
+		// Up is toward the top of the screen, right toward the right.
+		let up_right = project(Point3::new(0.5, 0.5, 0.0)).unwrap();
+		assert!(up_right.x > 640.0 && up_right.y < 360.0);
+
+		// Nothing sensible for points behind the camera.
+		assert_eq!(None, project(TEST_EYE - Vector3::new(0.0, 0.0, 1.0)));
+	}
+
+	#[test]
+	fn test_project_unproject_round_trip()
+	{
+		let view = transforms::look_at_lh(Point3::new(1.0, 2.0, -5.0), Point3::new(0.0, 0.5, 0.0), UP);
+		let points = [
+			Point3::new(0.0, 0.0, 0.0),
+			Point3::new(1.5, -0.5, 2.0),
+			Point3::new(-3.0, 2.0, 20.0),
+			Point3::new(0.3, 0.2, -4.0),
+		];
+		let conventions = [DX_LH, GLM_LH, REVERSE_Z_LH];
+		for viewport in TEST_VIEWPORTS.iter()
+		{
+			for &convention in conventions.iter()
+			{
+				let projections = [
+					transforms::perspective(TEST_PERSPECTIVE_FOV, convention),
+					transforms::ortho(16.0, 9.0, TEST_NEAR_PLANE, TEST_FAR_PLANE, convention),
+				];
+				for &projection in projections.iter()
+				{
+					let depth_range = convention.depth_range;
+					for &point in points.iter()
+					{
+						let pixel = transforms::project(point, view, projection, viewport, depth_range).unwrap();
+						assert!(pixel.z >= viewport.min_depth && pixel.z <= viewport.max_depth);
+						let back = transforms::unproject(pixel, view, projection, viewport, depth_range).unwrap();
+						assert!(
+							(back - point).magnitude() < 1.0e-3 * (point - TEST_EYE).magnitude(),
+							"{:?}",
+							convention
+						);
+					}
+
+					// And the other way around, from pixels at a few depths.
+					for &(x, y) in &[(0.25, 0.75), (0.5, 0.5), (0.9, 0.1)]
+					{
+						for &depth in &[0.1, 0.5, 0.9]
+						{
+							let pixel = Point3::new(
+								viewport.x + x * viewport.width,
+								viewport.y + y * viewport.height,
+								viewport.min_depth + depth * (viewport.max_depth - viewport.min_depth),
+							);
+							let world = transforms::unproject(pixel, view, projection, viewport, depth_range).unwrap();
+							let again = transforms::project(world, view, projection, viewport, depth_range).unwrap();
+							assert!((again - pixel).magnitude() < 1.0e-2, "{:?} {:?}", pixel, again);
+						}
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_unproject_infinite_far_plane()
+	{
+		let view = transforms::look_at_lh(TEST_EYE, TEST_TARGET, UP);
+		let projection = transforms::perspective_infinite(TEST_PERSPECTIVE_FOV.fovy, TEST_ASPECT_RATIO, 0.1, DX_LH);
+		let viewport = TEST_VIEWPORTS[0];
+		let far = Point3::new(640.0, 360.0, 1.0);
+		assert_eq!(None, transforms::unproject(far, view, projection, &viewport, DepthRange::ZeroToOne));
+	}
+
+	// Property-based checks over random projections in every convention, in
+	// double precision so the properties are not lost to rounding.
+	mod properties
+	{
+		use crate::transforms::{
+			self,
+			ClipSpaceConvention,
+			DepthRange,
+			Handedness,
+		};
+		use cgmath::*;
+		use proptest::prelude::*;
+
+		fn conventions() -> impl Strategy<Value = ClipSpaceConvention>
+		{
+			let handedness = prop::sample::select(vec![Handedness::Left, Handedness::Right]);
+			let depth_range =
+				prop::sample::select(vec![DepthRange::ZeroToOne, DepthRange::MinusOneToOne, DepthRange::OneToZero]);
+			(handedness, depth_range)
+				.prop_map(|(handedness, depth_range)| ClipSpaceConvention::new(handedness, depth_range))
+		}
+
+		// A point `distance` in front of the camera, offset by x and y.
+		fn view_point(convention : ClipSpaceConvention, x : f64, y : f64, distance : f64) -> Vector4<f64>
+		{
+			match convention.handedness
+			{
+				Handedness::Left => Vector4::new(x, y, distance, 1.0),
+				Handedness::Right => Vector4::new(x, y, -distance, 1.0),
+			}
+		}
+
+		fn to_ndc(projection : Matrix4<f64>, point : Vector4<f64>) -> Vector3<f64>
+		{
+			let clip = projection * point;
+			clip.truncate() / clip.w
+		}
+
+		proptest! {
+			#[test]
+			fn perspective_maps_near_and_far(
+				convention in conventions(),
+				fovy_degrees in 10.0..170.0_f64,
+				aspect in 0.25..4.0_f64,
+				near in 0.01..10.0_f64,
+				far_ratio in 1.5..1.0e4_f64,
+			)
+			{
+				let far = near * far_ratio;
+				let persp = PerspectiveFov { fovy : Rad(fovy_degrees.to_radians()), aspect : aspect, near : near, far : far };
+				let projection = transforms::perspective(persp, convention);
+				let (near_depth, far_depth) = convention.depth_range.near_far::<f64>();
+
+				let depth = |distance : f64| to_ndc(projection, view_point(convention, 0.0, 0.0, distance)).z;
+				prop_assert!((depth(near) - near_depth).abs() < 1.0e-9);
+				prop_assert!((depth(far) - far_depth).abs() < 1.0e-9);
+
+				// Depth moves monotonically from the near value to the far value.
+				let toward_far = (far_depth - near_depth).signum();
+				let samples : Vec<f64> = (0..=8).map(|n| depth(near + (far - near) * n as f64 / 8.0)).collect();
+				for pair in samples.windows(2)
+				{
+					prop_assert!((pair[1] - pair[0]) * toward_far > 0.0);
+				}
+
+				// The field of view reaches the edges of clip space at any distance.
+				let half_height = (fovy_degrees.to_radians() / 2.0).tan();
+				for &distance in &[near, (near + far) / 2.0, far]
+				{
+					let corner = view_point(convention, half_height * aspect * distance, half_height * distance, distance);
+					let ndc = to_ndc(projection, corner);
+					prop_assert!((ndc.x - 1.0).abs() < 1.0e-9 && (ndc.y - 1.0).abs() < 1.0e-9);
+				}
+			}
+
+			#[test]
+			fn infinite_perspective_is_the_limit(
+				convention in conventions(),
+				fovy_degrees in 10.0..170.0_f64,
+				near in 0.01..10.0_f64,
+			)
+			{
+				let fovy = Rad(fovy_degrees.to_radians());
+				let projection = transforms::perspective_infinite(fovy, 1.0, near, convention);
+				let (near_depth, far_depth) = convention.depth_range.near_far::<f64>();
+				let depth = |distance : f64| to_ndc(projection, view_point(convention, 0.0, 0.0, distance)).z;
+				prop_assert!((depth(near) - near_depth).abs() < 1.0e-9);
+				prop_assert!((depth(near * 1.0e9) - far_depth).abs() < 1.0e-6);
+
+				// Close to a finite projection with a distant far plane.
+				let persp = PerspectiveFov { fovy : fovy, aspect : 1.0, near : near, far : near * 1.0e9 };
+				let finite = transforms::perspective(persp, convention);
+				let (finite, infinite) : (&[f64; 16], &[f64; 16]) = (finite.as_ref(), projection.as_ref());
+				for (a, b) in finite.iter().zip(infinite.iter())
+				{
+					prop_assert!((a - b).abs() < 1.0e-6 * a.abs().max(1.0));
+				}
+			}
+
+			#[test]
+			fn ortho_maps_the_box(
+				convention in conventions(),
+				left in -10.0..10.0_f64,
+				width in 0.1..20.0_f64,
+				bottom in -10.0..10.0_f64,
+				height in 0.1..20.0_f64,
+				near in 0.0..10.0_f64,
+				depth in 0.1..1.0e3_f64,
+			)
+			{
+				let o = Ortho {
+					left :   left,
+					right :  left + width,
+					bottom : bottom,
+					top :    bottom + height,
+					near :   near,
+					far :    near + depth,
+				};
+				let projection = transforms::ortho_off_center(o, convention);
+				let (near_depth, far_depth) = convention.depth_range.near_far::<f64>();
+
+				let low = to_ndc(projection, view_point(convention, o.left, o.bottom, o.near));
+				let high = to_ndc(projection, view_point(convention, o.right, o.top, o.far));
+				prop_assert!((low - Vector3::new(-1.0, -1.0, near_depth)).magnitude() < 1.0e-9);
+				prop_assert!((high - Vector3::new(1.0, 1.0, far_depth)).magnitude() < 1.0e-9);
+			}
+
+			#[test]
+			fn project_unproject_round_trip(
+				convention in conventions(),
+				x in -1.0..1.0_f64,
+				y in -1.0..1.0_f64,
+				t in 0.0..1.0_f64,
+			)
+			{
+				// Points anywhere inside the frustum come back where they were.
+				let persp = PerspectiveFov { fovy : Rad(1.2_f32), aspect : 1.5, near : 0.5, far : 50.0 };
+				let projection = transforms::perspective(persp, convention);
+				let view = Matrix4::identity();
+				let viewport = transforms::Viewport::new(800.0, 600.0);
+
+				let distance = 0.5 + t * 49.5;
+				let half_height = (0.6_f64).tan() * distance;
+				let point = view_point(convention, x * half_height * 1.5, y * half_height, distance).cast::<f32>().unwrap();
+				let point = Point3::from_homogeneous(point);
+
+				let pixel = transforms::project(point, view, projection, &viewport, convention.depth_range).unwrap();
+				let back = transforms::unproject(pixel, view, projection, &viewport, convention.depth_range).unwrap();
+				prop_assert!((back - point).magnitude() < 1.0e-3 * distance as f32);
+			}
+
+			#[test]
+			fn handedness_mirrors_z(
+				depth_range in prop::sample::select(vec![DepthRange::ZeroToOne, DepthRange::MinusOneToOne, DepthRange::OneToZero]),
+				fovy_degrees in 10.0..170.0_f64,
+				x in -5.0..5.0_f64,
+				y in -5.0..5.0_f64,
+				distance in 0.1..100.0_f64,
+			)
+			{
+				// A right-handed view of (x, y, -z) looks the same as a
+				// left-handed view of (x, y, z).
+				let persp = PerspectiveFov { fovy : Rad(fovy_degrees.to_radians()), aspect : 1.5, near : 0.1, far : 100.0 };
+				let lh = transforms::perspective(persp, ClipSpaceConvention::new(Handedness::Left, depth_range));
+				let rh = transforms::perspective(persp, ClipSpaceConvention::new(Handedness::Right, depth_range));
+				let ndc_lh = to_ndc(lh, Vector4::new(x, y, distance, 1.0));
+				let ndc_rh = to_ndc(rh, Vector4::new(x, y, -distance, 1.0));
+				prop_assert!((ndc_lh - ndc_rh).magnitude() < 1.0e-9);
+			}
+		}
 	}
 }
@@ -0,0 +1,246 @@
+// Vertex layouts described by the vertex structs themselves.
+//
+// A vertex struct lists its fields and shader semantics once, through
+// `impl_vertex_layout!`, and the formats and byte offsets are derived from the
+// field types and the struct's actual layout. The renderer turns the result
+// into its input layout, so the struct and the shader input can not drift
+// apart. Vertex structs must be `#[repr(C)]`.
+use cgmath::*;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum VertexFormat
+{
+	Float,
+	Float2,
+	Float3,
+	Float4,
+}
+
+impl VertexFormat
+{
+	pub fn size(self) -> usize
+	{
+		match self
+		{
+			VertexFormat::Float => 4,
+			VertexFormat::Float2 => 8,
+			VertexFormat::Float3 => 12,
+			VertexFormat::Float4 => 16,
+		}
+	}
+}
+
+// One shader input, as it appears in an input layout.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VertexElement
+{
+	pub semantic_name :  &'static str,
+	pub semantic_index : u32,
+	pub format :         VertexFormat,
+	pub offset :         usize,
+}
+
+// Field types that can be fed to a shader. Types wider than one register,
+// like matrices, take `COUNT` consecutive semantic indices.
+pub trait VertexAttribute
+{
+	const FORMAT : VertexFormat;
+	const COUNT : u32 = 1;
+}
+
+impl VertexAttribute for f32
+{
+	const FORMAT : VertexFormat = VertexFormat::Float;
+}
+
+impl VertexAttribute for Vector2<f32>
+{
+	const FORMAT : VertexFormat = VertexFormat::Float2;
+}
+
+impl VertexAttribute for Vector3<f32>
+{
+	const FORMAT : VertexFormat = VertexFormat::Float3;
+}
+
+impl VertexAttribute for Vector4<f32>
+{
+	const FORMAT : VertexFormat = VertexFormat::Float4;
+}
+
+// One float4 per column, which is how HLSL receives a float4x4 from a vertex
+// stream.
+impl VertexAttribute for Matrix4<f32>
+{
+	const COUNT : u32 = 4;
+	const FORMAT : VertexFormat = VertexFormat::Float4;
+}
+
+pub trait VertexLayout: Sized
+{
+	fn elements() -> Vec<VertexElement>;
+
+	fn stride() -> usize
+	{
+		std::mem::size_of::<Self>()
+	}
+}
+
+// Used by `impl_vertex_layout!`, where `field` points into the vertex at
+// `base`, to let the field's type pick the format.
+#[doc(hidden)]
+pub fn push_attribute_elements<T : VertexAttribute>(
+	elements : &mut Vec<VertexElement>, base : *const u8, field : *const T, semantic_name : &'static str,
+	semantic_index : u32,
+)
+{
+	let offset = field as usize - base as usize;
+	for n in 0..T::COUNT
+	{
+		elements.push(VertexElement {
+			semantic_name :  semantic_name,
+			semantic_index : semantic_index + n,
+			format :         T::FORMAT,
+			offset :         offset + n as usize * T::FORMAT.size(),
+		});
+	}
+}
+
+// Implements VertexLayout for a `#[repr(C)]` struct:
+//
+//     impl_vertex_layout!(ColoredVertex {
+//         position => "POSITION",
+//         color => "COLOR",
+//         uv1 => "TEXCOORD" 1,
+//     });
+//
+// The semantic index defaults to 0. Fields with types that do not implement
+// VertexAttribute fail to compile.
+#[macro_export]
+macro_rules! impl_vertex_layout {
+	($vertex:ty { $($field:ident => $semantic:literal $($index:literal)?),* $(,)? }) => {
+		impl $crate::vertex_layout::VertexLayout for $vertex
+		{
+			fn elements() -> Vec<$crate::vertex_layout::VertexElement>
+			{
+				let vertex = std::mem::MaybeUninit::<$vertex>::uninit();
+				let base = vertex.as_ptr();
+				let mut elements = Vec::new();
+				$(
+					// Only the address is taken, the uninitialized field is
+					// never read.
+					let field = unsafe { std::ptr::addr_of!((*base).$field) };
+					$crate::vertex_layout::push_attribute_elements(
+						&mut elements,
+						base as *const u8,
+						field,
+						$semantic,
+						0 $(+ $index)?,
+					);
+				)*
+				elements
+			}
+		}
+	};
+}
+
+#[cfg(test)]
+mod vertex_layout_tests
+{
+	use crate::{
+		geometry::{
+			ColoredVertex,
+			MeshVertex,
+		},
+		vertex_layout::{
+			VertexElement,
+			VertexFormat,
+			VertexLayout,
+		},
+	};
+	use cgmath::*;
+	use std::mem;
+
+	#[repr(C)]
+	#[derive(Copy, Clone)]
+	struct InstanceData
+	{
+		tint :      Vector4<f32>,
+		transform : Matrix4<f32>,
+		scale :     f32,
+		uv1 :       Vector2<f32>,
+	}
+
+	crate::impl_vertex_layout!(InstanceData {
+		tint => "COLOR" 1,
+		transform => "TRANSFORM",
+		scale => "SCALE",
+		uv1 => "TEXCOORD" 1,
+	});
+
+	// Elements are in increasing order, do not overlap, and cover the whole
+	// stride, so no field is missing from the layout.
+	fn assert_covers_vertex<V : VertexLayout>()
+	{
+		let elements = V::elements();
+		let mut end = 0;
+		for element in &elements
+		{
+			assert_eq!(end, element.offset, "gap or overlap before {:?}", element);
+			end = element.offset + element.format.size();
+		}
+		assert_eq!(V::stride(), end);
+		assert_eq!(mem::size_of::<V>(), V::stride());
+	}
+
+	fn element(
+		semantic_name : &'static str, semantic_index : u32, format : VertexFormat, offset : usize,
+	) -> VertexElement
+	{
+		VertexElement {
+			semantic_name :  semantic_name,
+			semantic_index : semantic_index,
+			format :         format,
+			offset :         offset,
+		}
+	}
+
+	#[test]
+	fn test_colored_vertex_layout()
+	{
+		assert_eq!(
+			vec![element("POSITION", 0, VertexFormat::Float3, 0), element("COLOR", 0, VertexFormat::Float4, 12)],
+			ColoredVertex::elements()
+		);
+		assert_eq!(28, ColoredVertex::stride());
+		assert_covers_vertex::<ColoredVertex>();
+	}
+
+	#[test]
+	fn test_mesh_vertex_layout()
+	{
+		let elements = MeshVertex::elements();
+		let semantics : Vec<&str> = elements.iter().map(|e| e.semantic_name).collect();
+		assert_eq!(vec!["POSITION", "NORMAL", "TEXCOORD", "COLOR"], semantics);
+		assert_eq!(VertexFormat::Float2, elements[2].format);
+		assert_covers_vertex::<MeshVertex>();
+	}
+
+	#[test]
+	fn test_matrix_and_semantic_indices()
+	{
+		let elements = InstanceData::elements();
+		assert_eq!(7, elements.len());
+		assert_eq!(element("COLOR", 1, VertexFormat::Float4, 0), elements[0]);
+		for column in 0..4
+		{
+			assert_eq!(
+				element("TRANSFORM", column, VertexFormat::Float4, 16 + 16 * column as usize),
+				elements[1 + column as usize]
+			);
+		}
+		assert_eq!(element("SCALE", 0, VertexFormat::Float, 80), elements[5]);
+		assert_eq!(element("TEXCOORD", 1, VertexFormat::Float2, 84), elements[6]);
+		assert_covers_vertex::<InstanceData>();
+	}
+}
@@ -9,16 +9,22 @@ use std::{
 
 use winapi::um::winuser::{
 	DispatchMessageW,
+	GetClientRect,
 	PeekMessageW,
 	TranslateMessage,
 	MSG,
 	PM_REMOVE,
 	WM_CLOSE,
 	WM_DESTROY,
+	WM_KEYDOWN,
+	WM_LBUTTONDOWN,
 	WM_QUIT,
 };
 
-use winapi::shared::windef::HWND;
+use winapi::shared::windef::{
+	HWND,
+	RECT,
+};
 
 use winapi::shared::minwindef::{
 	LPARAM,
@@ -59,6 +65,61 @@ impl Error for PlatformError
 
 pub type ExitResult = Result<ExitCode, PlatformError>;
 
+// Input forwarded from the platform thread.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InputEvent
+{
+	// Cursor position relative to the client area, from (0, 0) at the top left
+	// to (1, 1) at the bottom right, so it does not depend on the window size.
+	LeftButtonDown
+	{
+		x : f32,
+		y : f32,
+	},
+	// Windows virtual-key code, which is the upper case ASCII code for letters
+	// and digits. Not repeated while the key is held.
+	KeyDown
+	{
+		virtual_key : u32,
+	},
+}
+
+// Translates the window messages that are forwarded as input.
+fn input_event(message : &MSG) -> Option<InputEvent>
+{
+	match message.message
+	{
+		WM_LBUTTONDOWN =>
+		{
+			let mut client_rect = RECT {
+				left :   0,
+				top :    0,
+				right :  0,
+				bottom : 0,
+			};
+			if unsafe { GetClientRect(message.hwnd, &mut client_rect) } == 0
+			{
+				return None;
+			}
+			let width = (client_rect.right - client_rect.left).max(1) as f32;
+			let height = (client_rect.bottom - client_rect.top).max(1) as f32;
+
+			// Signed 16-bit client coordinates in the low and high words.
+			let x = (message.lParam & 0xffff) as u16 as i16 as f32;
+			let y = ((message.lParam >> 16) & 0xffff) as u16 as i16 as f32;
+			Some(InputEvent::LeftButtonDown {
+				x : x / width,
+				y : y / height,
+			})
+		}
+		// Bit 30 of lParam is set for auto-repeat.
+		WM_KEYDOWN if message.lParam & (1 << 30) == 0 => Some(InputEvent::KeyDown {
+			virtual_key : message.wParam as u32,
+		}),
+		_ => None,
+	}
+}
+
 pub unsafe extern "system" fn window_proc(hwnd : HWND, u_msg : UINT, w_param : WPARAM, l_param : LPARAM) -> LRESULT
 {
 	match u_msg
@@ -79,7 +140,7 @@ pub unsafe extern "system" fn window_proc(hwnd : HWND, u_msg : UINT, w_param : W
 
 pub fn platform_thread_run(
 	window_sender : mpsc::Sender<win_window::Window>, _exit_sender : mpsc::Sender<ExitResult>,
-	_input_sender : mpsc::Sender<u32>,
+	input_sender : mpsc::Sender<InputEvent>,
 )
 {
 	let window = win_window::create_window().unwrap();
@@ -103,6 +164,12 @@ pub fn platform_thread_run(
 				break;
 			}
 
+			if let Some(event) = input_event(unsafe { &*message.as_ptr() })
+			{
+				// The render thread may have stopped listening, which is fine.
+				let _ = input_sender.send(event);
+			}
+
 			unsafe {
 				TranslateMessage(message.as_ptr());
 				DispatchMessageW(message.as_ptr());